@@ -0,0 +1,126 @@
+//! Measures how many instructions per second the CPU core executes.
+//!
+//! The Klaus Dormann functional test isn't part of the repository. To include
+//! it, point `KLAUS_FUNCTIONAL_TEST` at the assembled 64K image
+//! (`6502_functional_test.bin`, built to start at $0400).
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use butterflyrs::bus::flat::FlatBus;
+use butterflyrs::bus::ram::Ram;
+use butterflyrs::bus::{MainBus, SharedBus};
+use butterflyrs::cpu::{Cpu, CpuBuilder};
+use butterflyrs::emulator::Emulator;
+
+// TODO: Make the hot path an order of magnitude faster than it was before
+// these benchmarks were added. So far clock() is about 2.5 times faster,
+// tick() 1.8 times and step() 1.6 times. Ticking the devices after every
+// instruction is nearly a third of what clock() costs on the tight loop: each
+// device's clock rate, tick and bus request are three calls through a
+// `dyn BusDevice`. The rest hasn't been broken down. Each access still borrows
+// the bus unless it is held, looks the device up in the page table and calls
+// it through a `dyn BusDevice`, then checks for the recorder and watchpoints.
+
+/// The number of instructions executed per benchmark iteration.
+const INSTRUCTIONS: u64 = 100_000;
+
+/// The number of cycles run per benchmark iteration, where whole runs are measured.
+const CYCLES: u64 = 400_000;
+
+/// Creates a machine with 64K of RAM holding `image` at `address`, starting at `start`.
+fn machine(address: u16, image: &[u8], start: u16) -> Emulator {
+    let mut emulator = Emulator::from_cpu(CpuBuilder::new().build());
+    emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    emulator.load(address, image);
+    emulator.cpu.pc.set(start);
+    emulator
+}
+
+/// Creates the same machine as `machine()`, but with a `FlatBus` for its memory.
+fn flat_machine(address: u16, image: &[u8], start: u16) -> Emulator {
+    let mut memory = FlatBus::new();
+    memory.load(address, image);
+    let mut emulator = Emulator::from_cpu(CpuBuilder::new().bus(SharedBus::new(MainBus::from(memory))).build());
+    emulator.cpu.pc.set(start);
+    emulator
+}
+
+/// Executes exactly `count` instructions with `clock()`.
+fn run_clocked(cpu: &mut Cpu, count: u64) {
+    for _ in 0..count {
+        cpu.clock();
+        while cpu.cycles > 0 {
+            cpu.clock();
+        }
+    }
+}
+
+/// Executes exactly `count` instructions with `tick()`.
+fn run_ticked(cpu: &mut Cpu, count: u64) {
+    for _ in 0..count {
+        cpu.tick();
+        while cpu.cycles > 0 {
+            cpu.tick();
+        }
+    }
+}
+
+fn tight_loop(c: &mut Criterion) {
+    // LDX #$00, loop: INX, STX $10, LDA ($10),Y, BNE loop, JMP $0200
+    let program = [0xA2, 0x00, 0xE8, 0x86, 0x10, 0xB1, 0x10, 0xD0, 0xF9, 0x4C, 0x00, 0x02];
+
+    let mut group = c.benchmark_group("tight loop");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.bench_function("clock", |b| {
+        let mut emulator = machine(0x0200, &program, 0x0200);
+        b.iter(|| run_clocked(&mut emulator.cpu, INSTRUCTIONS));
+    });
+    group.bench_function("clock, flat memory", |b| {
+        let mut emulator = flat_machine(0x0200, &program, 0x0200);
+        b.iter(|| run_clocked(&mut emulator.cpu, INSTRUCTIONS));
+    });
+    group.bench_function("tick", |b| {
+        let mut emulator = machine(0x0200, &program, 0x0200);
+        b.iter(|| run_ticked(&mut emulator.cpu, INSTRUCTIONS));
+    });
+    group.bench_function("step", |b| {
+        let mut emulator = machine(0x0200, &program, 0x0200);
+        b.iter(|| {
+            for _ in 0..INSTRUCTIONS {
+                emulator.step();
+            }
+        });
+    });
+    group.finish();
+
+    // Runs hold the bus throughout, so they are measured in cycles
+    let mut group = c.benchmark_group("tight loop, run");
+    group.throughput(Throughput::Elements(CYCLES));
+    group.bench_function("run_for_cycles", |b| {
+        let mut emulator = machine(0x0200, &program, 0x0200);
+        b.iter(|| emulator.run_for_cycles(CYCLES));
+    });
+    group.finish();
+}
+
+fn klaus_functional_test(c: &mut Criterion) {
+    let Some(path) = std::env::var_os("KLAUS_FUNCTIONAL_TEST") else {
+        return;
+    };
+    let image = std::fs::read(path).expect("the functional test image should be readable");
+
+    // Start from the top of the test each time, so every iteration runs the same code
+    let mut group = c.benchmark_group("klaus functional test");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.bench_function("clock", |b| {
+        b.iter_batched_ref(
+            || machine(0x0000, &image, 0x0400),
+            |emulator| run_clocked(&mut emulator.cpu, INSTRUCTIONS),
+            BatchSize::LargeInput,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tight_loop, klaus_functional_test);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+fn main() {
+    // Generate the C header whenever the bindings change. It goes in OUT_DIR,
+    // since a build mustn't write to the source tree; the copy checked in at
+    // include/butterfly.h is regenerated with the cbindgen command line tool,
+    // and a test checks that it matches this one.
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("Cargo sets CARGO_MANIFEST_DIR for build scripts");
+        let out_dir = std::env::var("OUT_DIR").expect("Cargo sets OUT_DIR for build scripts");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("Unable to read the cbindgen configuration in cbindgen.toml");
+        cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .generate()
+            .expect("Unable to generate the C bindings")
+            .write_to_file(format!("{}/butterfly.h", out_dir));
+    }
+}
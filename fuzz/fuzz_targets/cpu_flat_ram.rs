@@ -0,0 +1,84 @@
+//! Runs arbitrary code on every CPU model with 64K of RAM.
+//!
+//! The first input byte picks the model and options; the rest is loaded at
+//! $0200, which all three vectors point to.
+//!
+//! After every instruction the program counter must have moved past it,
+//! unless it is one that jumps, and the stack pointer must be where it was,
+//! unless it is one that uses the stack or an interrupt was taken first.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use butterflyrs::bus::ram::Ram;
+use butterflyrs::cpu::{CpuBuilder, CpuModel, StatusFlags};
+use butterflyrs::emulator::Emulator;
+
+/// The instructions that may leave the program counter anywhere but the next instruction.
+const JUMPS: &[&str] = &[
+    "BCC", "BCS", "BEQ", "BMI", "BNE", "BPL", "BRA", "BVC", "BVS", "BRK", "JMP", "JSR", "RTS", "RTI", "KIL", "STP",
+    "WAI",
+];
+
+/// The instructions that may change the stack pointer.
+const STACK: &[&str] = &[
+    "PHA", "PHP", "PHX", "PHY", "PLA", "PLP", "PLX", "PLY", "BRK", "JSR", "RTS", "RTI", "TXS", "TAS",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&config, program)) = data.split_first() else {
+        return;
+    };
+
+    let model = match config & 0x03 {
+        0 => CpuModel::Nmos6502,
+        1 => CpuModel::Wdc65C02,
+        2 => CpuModel::Ricoh2A03,
+        _ => CpuModel::Wdc65C816,
+    };
+    let cpu = CpuBuilder::new()
+        .model(model)
+        .illegal_opcodes(config & 0x04 != 0)
+        .rmw_dummy_write(config & 0x08 != 0)
+        .build();
+
+    let mut emulator = Emulator::from_cpu(cpu);
+    emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    emulator.load(0x0200, program);
+    emulator.load(0xFFFA, &[0x00, 0x02, 0x00, 0x02, 0x00, 0x02]);
+    emulator.reset();
+
+    for _ in 0..10_000 {
+        let sp = emulator.cpu.sp.get();
+        let record = emulator.step();
+
+        // Bit 5 of P has no flip-flop outside 65C816 native mode, so it always reads as set
+        if emulator.cpu.emulation_mode() {
+            assert!(emulator.cpu.p.get() & StatusFlags::Unused.bits() != 0, "bit 5 cleared by {}", record.mnemonic);
+        }
+
+        // A waiting, stopped or jammed CPU won't do anything more
+        if record.cycles == 0 {
+            break;
+        }
+
+        let pc = emulator.cpu.pc.get();
+        let jumped = JUMPS.contains(&record.mnemonic) || record.mnemonic.starts_with("BB");
+        let next = record.pc.wrapping_add(1 + record.operands.len() as u16);
+        assert!(jumped || pc == next, "{} at ${:04X} left PC at ${:04X}", record.mnemonic, record.pc, pc);
+
+        // An interrupt taken before the instruction pushed three bytes
+        let sp = if record.interrupt { sp.wrapping_sub(3) } else { sp };
+        let stacked = STACK.contains(&record.mnemonic);
+        assert!(stacked || emulator.cpu.sp.get() == sp, "{} at ${:04X} moved SP", record.mnemonic, record.pc);
+
+        // Exercise the interrupt paths along the way
+        if config & 0x10 != 0 {
+            emulator.cpu.set_irq_line(record.pc & 0x07 == 0);
+        }
+        if config & 0x20 != 0 && record.pc & 0x0F == 0x03 {
+            emulator.cpu.trigger_nmi();
+        }
+    }
+});
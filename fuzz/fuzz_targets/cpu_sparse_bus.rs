@@ -0,0 +1,50 @@
+//! Runs arbitrary code on a bus with holes in it, so that unmapped reads and
+//! writes, ROM writes and device registers all get hit.
+//!
+//! The first input byte picks the unmapped policy and model; the rest is both
+//! the ROM image at $C000 and the program copied into RAM at $0200.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use butterflyrs::bus::blink8::Blink8;
+use butterflyrs::bus::ram::Ram;
+use butterflyrs::bus::rom::Rom;
+use butterflyrs::bus::UnmappedPolicy;
+use butterflyrs::cpu::{CpuBuilder, CpuModel};
+use butterflyrs::emulator::Emulator;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&config, program)) = data.split_first() else {
+        return;
+    };
+
+    let model = if config & 0x01 != 0 { CpuModel::Wdc65C02 } else { CpuModel::Nmos6502 };
+    let mut emulator = Emulator::from_cpu(CpuBuilder::new().model(model).illegal_opcodes(true).build());
+
+    // RAM, a gap, the Blink8 registers, another gap, then a ROM that may be
+    // shorter than its address range
+    let mut blink8 = Blink8::new();
+    blink8.set_output(Box::new(|_| {}));
+    let mut rom = Rom::new(0xC000, 0xFFFF);
+    rom.data = program.to_vec();
+    emulator.add_device(Box::new(Ram::new(0x0000, 0x1FFF)));
+    emulator.add_device(Box::new(blink8));
+    emulator.add_device(Box::new(rom));
+    emulator.bus().borrow_mut().unmapped_policy = match (config >> 1) & 0x03 {
+        0 => UnmappedPolicy::Ignore,
+        1 => UnmappedPolicy::OpenBus,
+        _ => UnmappedPolicy::Error,
+    };
+
+    emulator.load(0x0200, program);
+    emulator.reset();
+
+    // Alternate between the two clocking modes, which handle writes differently
+    for _ in 0..10_000 {
+        let stop = if config & 0x08 != 0 { emulator.cpu.tick() } else { emulator.cpu.clock() };
+        assert!(stop.is_none());
+        emulator.bus().borrow().take_error();
+    }
+});
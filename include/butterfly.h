@@ -0,0 +1,230 @@
+#ifndef BUTTERFLY_H
+#define BUTTERFLY_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The CPU variants that can be created through the C bindings.
+ */
+typedef enum ButterflyModel {
+  /**
+   * The original NMOS 6502.
+   */
+  ButterflyModel_Nmos6502,
+  /**
+   * The WDC 65C02.
+   */
+  ButterflyModel_Wdc65C02,
+  /**
+   * The Ricoh 2A03 used in the NES.
+   */
+  ButterflyModel_Ricoh2A03,
+  /**
+   * The WDC 65C816.
+   */
+  ButterflyModel_Wdc65C816,
+} ButterflyModel;
+
+/**
+ * The registers that can be read and written through the C bindings.
+ */
+typedef enum ButterflyRegister {
+  /**
+   * The accumulator.
+   */
+  ButterflyRegister_A,
+  /**
+   * The X index register.
+   */
+  ButterflyRegister_X,
+  /**
+   * The Y index register.
+   */
+  ButterflyRegister_Y,
+  /**
+   * The processor status register.
+   */
+  ButterflyRegister_P,
+  /**
+   * The stack pointer.
+   */
+  ButterflyRegister_Sp,
+  /**
+   * The program counter.
+   */
+  ButterflyRegister_Pc,
+} ButterflyRegister;
+
+/**
+ * An opaque handle to a CPU and its bus.
+ */
+typedef struct ButterflyCpu ButterflyCpu;
+
+/**
+ * How fast a device is clocked, as a ratio of the CPU clock.
+ *
+ * A device reports its rate with `BusDevice::clock_rate()`, and the bus
+ * scales the CPU cycles it ticks the device by to the device's own clock.
+ * A rate that doesn't divide the cycles evenly carries the fraction over to
+ * the next tick, so a device at a third of the CPU clock sees exactly one
+ * cycle for every three, however the CPU's cycles are split up.
+ *
+ * # Example
+ *
+ * ```
+ * use butterflyrs::bus::ClockRate;
+ *
+ * // A peripheral at CPU/16 and a video chip at three times the CPU clock
+ * let slow = ClockRate::divided(16);
+ * let video = ClockRate::multiplied(3);
+ *
+ * let mut phase = 0;
+ * assert_eq!(slow.scale(10, &mut phase), 0);
+ * assert_eq!(slow.scale(10, &mut phase), 1);
+ * assert_eq!(video.scale(10, &mut 0), 30);
+ * ```
+ */
+typedef struct ClockRate ClockRate;
+
+/**
+ * Called to read a byte from a host-provided bus.
+ */
+typedef uint8_t (*ButterflyReadFn)(void *user_data, uint16_t address);
+
+/**
+ * Called to write a byte to a host-provided bus.
+ */
+typedef void (*ButterflyWriteFn)(void *user_data, uint16_t address, uint8_t value);
+
+/**
+ * Creates a CPU backed by 64K of RAM.
+ *
+ * The CPU isn't reset; load a program and call `butterfly_cpu_reset` first.
+ *
+ * # Arguments
+ *
+ * * `model` - The CPU variant to emulate.
+ *
+ * # Returns
+ *
+ * A handle to release with `butterfly_cpu_free`.
+ */
+struct ButterflyCpu *butterfly_cpu_new(enum ButterflyModel model);
+
+/**
+ * Creates a CPU whose whole address space is handled by host callbacks.
+ *
+ * # Arguments
+ *
+ * * `model` - The CPU variant to emulate.
+ * * `read` - Called for every read, with `user_data` and the address.
+ * * `write` - Called for every write, with `user_data`, the address and the value.
+ * * `user_data` - Passed through to the callbacks untouched.
+ *
+ * # Returns
+ *
+ * A handle to release with `butterfly_cpu_free`.
+ *
+ * # Safety
+ *
+ * `user_data` must remain valid for as long as the handle exists, and the
+ * callbacks must be safe to call from whichever thread drives the CPU.
+ */
+struct ButterflyCpu *butterfly_cpu_new_with_bus(enum ButterflyModel model,
+                                                ButterflyReadFn read,
+                                                ButterflyWriteFn write,
+                                                void *user_data);
+
+/**
+ * Releases a CPU handle. Passing null does nothing.
+ *
+ * # Safety
+ *
+ * `cpu` must be null or a handle returned by a `butterfly_cpu_new` function
+ * that hasn't been freed yet.
+ */
+void butterfly_cpu_free(struct ButterflyCpu *cpu);
+
+/**
+ * Resets the CPU, loading the program counter from the reset vector.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+ */
+void butterfly_cpu_reset(struct ButterflyCpu *cpu);
+
+/**
+ * Executes one instruction, servicing a pending interrupt first.
+ *
+ * # Returns
+ *
+ * The number of cycles taken, which is 0 if the CPU is waiting, stopped or jammed.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+ */
+uint32_t butterfly_cpu_step(struct ButterflyCpu *cpu);
+
+/**
+ * Reads a byte from the bus for a debugger, without side effects.
+ *
+ * The device's `peek()` is used, so reading an I/O register doesn't change it.
+ *
+ * # Returns
+ *
+ * The byte at `address`, or 0 if nothing is mapped there.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+ */
+uint8_t butterfly_cpu_read(const struct ButterflyCpu *cpu, uint16_t address);
+
+/**
+ * Writes a byte to the bus. Writes to unmapped addresses are dropped.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+ */
+void butterfly_cpu_write(struct ButterflyCpu *cpu, uint16_t address, uint8_t value);
+
+/**
+ * Reads a register.
+ *
+ * # Returns
+ *
+ * The register's value, widened to 16 bits for the 8-bit registers.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+ */
+uint16_t butterfly_cpu_get_register(const struct ButterflyCpu *cpu,
+                                    enum ButterflyRegister register_);
+
+/**
+ * Sets a register.
+ *
+ * # Returns
+ *
+ * `false` if `value` doesn't fit in an 8-bit register, in which case the
+ * register is left alone.
+ *
+ * # Safety
+ *
+ * `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+ */
+bool butterfly_cpu_set_register(struct ButterflyCpu *cpu,
+                                enum ButterflyRegister register_,
+                                uint16_t value);
+
+#endif  /* BUTTERFLY_H */
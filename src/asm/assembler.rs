@@ -0,0 +1,723 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::asm::expression::{evaluate, is_symbol_char};
+use crate::asm::{AsmError, AsmErrorKind, Assembly, Listing, ListingLine, Segment};
+use crate::cpu::instructions::{self, Instruction};
+use crate::cpu::{AddressingMode, CpuModel};
+use crate::disasm::SymbolTable;
+
+/// How deeply macros can invoke other macros before assembly gives up.
+const MAX_MACRO_DEPTH: usize = 16;
+
+/// An addressing mode an operand could be assembled with, and the values its
+/// operand bytes are made from. A value is `None` if it isn't known yet.
+type Candidate = (AddressingMode, Vec<Option<i64>>);
+
+/// A macro defined with `.macro`.
+struct Macro {
+    /// The names of the parameters, in order.
+    parameters: Vec<String>,
+
+    /// The lines between `.macro` and `.endmacro`, as written.
+    body: Vec<String>,
+}
+
+/// A macro whose body is still being read.
+struct Recording {
+    /// The line the `.macro` directive is on.
+    line: usize,
+    name: String,
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+/// Assembles source in two passes.
+///
+/// The first pass works out the address of every label. Symbols that aren't
+/// defined yet are unresolved rather than errors, and the bytes they would make
+/// up are left as zero. The second pass assembles everything again with every
+/// symbol known, and reports anything still undefined.
+///
+/// An instruction must be the same size in both passes, or the labels after it
+/// would move. So the second pass uses the addressing mode chosen in the first,
+/// which means an operand that was unresolved in the first pass is always
+/// assembled as an absolute address, even if it turns out to be in page zero.
+pub(crate) struct Assembler {
+    model: CpuModel,
+
+    /// Whether this is the second pass, which produces the output.
+    final_pass: bool,
+
+    /// The address the next byte is assembled to.
+    pc: u16,
+
+    /// Whether the next byte starts a new segment, which it does after `.org`.
+    new_segment: bool,
+
+    segments: Vec<Segment>,
+
+    /// The values of every label and constant. These are kept from the first
+    /// pass, so the second one can look ahead.
+    values: BTreeMap<String, i64>,
+
+    /// The symbols defined so far in this pass, to catch duplicates.
+    defined: BTreeSet<String>,
+
+    /// The labels alone, which end up in the assembly.
+    labels: SymbolTable,
+
+    macros: BTreeMap<String, Macro>,
+
+    /// The macro being defined, if the assembler is between `.macro` and `.endmacro`.
+    recording: Option<Recording>,
+
+    /// The number of macros expanded so far, which `\@` is replaced with.
+    expansions: usize,
+
+    /// The addressing mode of every instruction assembled in the first pass, in order.
+    modes: Vec<AddressingMode>,
+
+    /// The number of instructions assembled so far in this pass.
+    instructions: usize,
+
+    /// The lines assembled so far in the second pass.
+    listing: Listing,
+
+    /// The address of the first byte the current line assembled to.
+    line_address: Option<u16>,
+
+    /// The bytes the current line has assembled to so far.
+    line_bytes: Vec<u8>,
+}
+
+impl Assembler {
+    /// Creates an assembler for a CPU model, starting at address zero.
+    pub(crate) fn new(model: CpuModel) -> Assembler {
+        Assembler {
+            model,
+            final_pass: false,
+            pc: 0,
+            new_segment: true,
+            segments: Vec::new(),
+            values: BTreeMap::new(),
+            defined: BTreeSet::new(),
+            labels: SymbolTable::new(),
+            macros: BTreeMap::new(),
+            recording: None,
+            expansions: 0,
+            modes: Vec::new(),
+            instructions: 0,
+            listing: Listing::default(),
+            line_address: None,
+            line_bytes: Vec::new(),
+        }
+    }
+
+    /// Assembles a whole source file.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The assembly source.
+    ///
+    /// # Returns
+    ///
+    /// The assembled code and labels, or the first error found.
+    pub(crate) fn assemble(mut self, source: &str) -> Result<Assembly, AsmError> {
+        for final_pass in [false, true] {
+            self.start_pass(final_pass);
+
+            for (index, text) in source.lines().enumerate() {
+                let line = index + 1;
+                self.line(text, line, 0).map_err(|kind| AsmError { line, kind })?;
+
+                if final_pass {
+                    self.listing.lines.push(ListingLine {
+                        line,
+                        address: self.line_address.take().unwrap_or(self.pc),
+                        bytes: core::mem::take(&mut self.line_bytes),
+                        source: String::from(text),
+                    });
+                }
+            }
+
+            if let Some(recording) = self.recording.take() {
+                return Err(AsmError {
+                    line: recording.line,
+                    kind: AsmErrorKind::UnterminatedMacro(recording.name),
+                });
+            }
+        }
+
+        Ok(Assembly {
+            segments: self.segments,
+            symbols: self.labels,
+            listing: self.listing,
+        })
+    }
+
+    /// Resets everything that a pass builds up, keeping the symbol values and
+    /// the addressing modes from the first pass.
+    fn start_pass(&mut self, final_pass: bool) {
+        self.final_pass = final_pass;
+        self.pc = 0;
+        self.new_segment = true;
+        self.segments.clear();
+        self.defined.clear();
+        self.labels = SymbolTable::new();
+        self.macros.clear();
+        self.expansions = 0;
+        self.instructions = 0;
+    }
+
+    /// Assembles a single line.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The line, which may have a label and a comment.
+    /// * `line` - The line number, used by `.macro` to report a missing `.endmacro`.
+    /// * `depth` - How many macros deep the line is.
+    fn line(&mut self, text: &str, line: usize, depth: usize) -> Result<(), AsmErrorKind> {
+        let text = strip_comment(text);
+
+        // Lines inside a macro definition are kept as written until .endmacro
+        if let Some(mut recording) = self.recording.take() {
+            let first = text.split_whitespace().next().unwrap_or_default();
+            if first.eq_ignore_ascii_case(".endmacro") || first.eq_ignore_ascii_case(".endm") {
+                self.macros.insert(
+                    recording.name,
+                    Macro {
+                        parameters: recording.parameters,
+                        body: recording.body,
+                    },
+                );
+            } else {
+                recording.body.push(String::from(text));
+                self.recording = Some(recording);
+            }
+            return Ok(());
+        }
+
+        // A label takes the address of whatever follows it
+        let mut text = text.trim();
+        if let Some((name, rest)) = split_label(text) {
+            self.define(name, Some(self.pc as i64), true)?;
+            text = rest.trim_start();
+        }
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let (word, rest) = split_word(text);
+
+        // NAME = value and NAME .equ value define constants
+        if let Some(value) = rest.strip_prefix('=') {
+            return self.constant(word, value);
+        }
+        let (second, value) = split_word(rest);
+        if second.eq_ignore_ascii_case(".equ") {
+            return self.constant(word, value);
+        }
+
+        if word.starts_with('.') {
+            self.directive(word, rest, line)
+        } else if self.macros.contains_key(word) {
+            self.expand(word, rest, line, depth)
+        } else {
+            self.instruction(word, rest)
+        }
+    }
+
+    /// Defines a label or constant.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The symbol's name.
+    /// * `value` - Its value, or `None` if it can't be worked out until the second pass.
+    /// * `label` - Whether the symbol is a label rather than a constant.
+    fn define(&mut self, name: &str, value: Option<i64>, label: bool) -> Result<(), AsmErrorKind> {
+        if !is_symbol_name(name) {
+            return Err(AsmErrorKind::InvalidOperand(String::from(name)));
+        }
+        if !self.defined.insert(String::from(name)) {
+            return Err(AsmErrorKind::DuplicateSymbol(String::from(name)));
+        }
+
+        if let Some(value) = value {
+            self.values.insert(String::from(name), value);
+            if label {
+                self.labels.insert(value as u16, name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Defines a constant from an expression.
+    fn constant(&mut self, name: &str, value: &str) -> Result<(), AsmErrorKind> {
+        let value = self.evaluate(value)?;
+        self.define(name, value, false)
+    }
+
+    /// Evaluates an expression.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if it uses a symbol that is only defined later in
+    /// the source. That can only happen in the first pass; in the second, an
+    /// undefined symbol is an error.
+    fn evaluate(&self, text: &str) -> Result<Option<i64>, AsmErrorKind> {
+        let values = &self.values;
+        match evaluate(text, self.pc, &|name| values.get(name).copied()) {
+            Ok(value) => Ok(Some(value)),
+            Err(AsmErrorKind::UndefinedSymbol(_)) if !self.final_pass => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Evaluates an expression whose value is needed in the first pass, such as
+    /// the address given to `.org`.
+    fn resolve(&self, text: &str) -> Result<i64, AsmErrorKind> {
+        let values = &self.values;
+        evaluate(text, self.pc, &|name| values.get(name).copied())
+    }
+
+    /// Appends bytes at the current address.
+    fn emit(&mut self, bytes: &[u8]) {
+        if self.new_segment {
+            self.segments.push(Segment {
+                origin: self.pc,
+                bytes: Vec::new(),
+            });
+            self.new_segment = false;
+        }
+
+        let segment = self.segments.last_mut().expect("a segment has been started");
+        segment.bytes.extend_from_slice(bytes);
+
+        if self.final_pass {
+            self.line_address.get_or_insert(self.pc);
+            self.line_bytes.extend_from_slice(bytes);
+        }
+        self.pc = self.pc.wrapping_add(bytes.len() as u16);
+    }
+
+    /// Handles a directive.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The directive, including its leading dot.
+    /// * `arguments` - The rest of the line.
+    /// * `line` - The line number, kept with a macro definition.
+    fn directive(&mut self, name: &str, arguments: &str, line: usize) -> Result<(), AsmErrorKind> {
+        match name.to_ascii_lowercase().as_str() {
+            ".org" => {
+                let origin = self.resolve(arguments)?;
+                self.pc = word(Some(origin))?;
+                self.new_segment = true;
+            }
+            ".byte" | ".db" => {
+                for argument in split_arguments(arguments) {
+                    if let Some(string) = argument.strip_prefix('"') {
+                        let string = string
+                            .strip_suffix('"')
+                            .ok_or_else(|| AsmErrorKind::InvalidOperand(String::from(argument)))?;
+                        self.emit(string.as_bytes());
+                    } else {
+                        let value = self.evaluate(argument)?;
+                        self.emit(&[byte(value)?]);
+                    }
+                }
+            }
+            ".word" | ".dw" => {
+                for argument in split_arguments(arguments) {
+                    let value = word(self.evaluate(argument)?)?;
+                    self.emit(&value.to_le_bytes());
+                }
+            }
+            ".equ" => {
+                let arguments = split_arguments(arguments);
+                let [name, value] = arguments[..] else {
+                    return Err(AsmErrorKind::InvalidOperand(arguments.join(",")));
+                };
+                self.constant(name, value)?;
+            }
+            ".macro" => {
+                let (name, parameters) = split_word(arguments);
+                if !is_symbol_name(name) {
+                    return Err(AsmErrorKind::InvalidOperand(String::from(name)));
+                }
+                if self.macros.contains_key(name) {
+                    return Err(AsmErrorKind::DuplicateSymbol(String::from(name)));
+                }
+                self.recording = Some(Recording {
+                    line,
+                    name: String::from(name),
+                    parameters: split_arguments(parameters).into_iter().map(String::from).collect(),
+                    body: Vec::new(),
+                });
+            }
+            _ => return Err(AsmErrorKind::UnknownDirective(String::from(name))),
+        }
+
+        Ok(())
+    }
+
+    /// Expands a macro and assembles its body.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the macro.
+    /// * `arguments` - The rest of the line, which holds the macro's arguments.
+    /// * `line` - The line number of the invocation.
+    /// * `depth` - How many macros deep the invocation is.
+    fn expand(&mut self, name: &str, arguments: &str, line: usize, depth: usize) -> Result<(), AsmErrorKind> {
+        if depth >= MAX_MACRO_DEPTH {
+            return Err(AsmErrorKind::MacroDepth(String::from(name)));
+        }
+
+        let definition = &self.macros[name];
+        let arguments = split_arguments(arguments);
+        if arguments.len() != definition.parameters.len() {
+            return Err(AsmErrorKind::MacroArguments {
+                name: String::from(name),
+                expected: definition.parameters.len(),
+                found: arguments.len(),
+            });
+        }
+
+        // Each expansion gets its own number, so labels ending in \@ are unique
+        self.expansions += 1;
+        let unique = self.expansions.to_string();
+        let body: Vec<String> = definition
+            .body
+            .iter()
+            .map(|text| substitute(text, &definition.parameters, &arguments, &unique))
+            .collect();
+
+        for text in body {
+            self.line(&text, line, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Assembles an instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - The instruction's name, in any case.
+    /// * `operand` - The operand as written, which may be empty.
+    fn instruction(&mut self, mnemonic: &str, operand: &str) -> Result<(), AsmErrorKind> {
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        let candidates: Vec<&Instruction> = (0..=255u8)
+            .map(|opcode| instructions::lookup(self.model, opcode))
+            .filter(|instruction| instruction.name == mnemonic)
+            .collect();
+        if candidates.is_empty() {
+            return Err(AsmErrorKind::UnknownMnemonic(mnemonic));
+        }
+
+        // BRK is a single byte, though the CPU skips the byte after it
+        let operand = operand.trim();
+        if mnemonic == "BRK" && operand.is_empty() {
+            self.emit(&[0x00]);
+            return Ok(());
+        }
+
+        let relative = candidates.iter().any(|instruction| instruction.mode == AddressingMode::Relative);
+        let mut modes = self.modes(operand, relative)?;
+
+        // The second pass uses the mode the first chose, so the instruction stays the same size
+        if self.final_pass {
+            if let Some(&chosen) = self.modes.get(self.instructions) {
+                modes.retain(|&(mode, _)| mode == chosen);
+            }
+        }
+
+        for (mode, values) in modes {
+            // Prefer the documented opcode where an illegal one does the same thing
+            let Some(instruction) = candidates
+                .iter()
+                .filter(|instruction| instruction.mode == mode)
+                .min_by_key(|instruction| instruction.illegal)
+            else {
+                continue;
+            };
+
+            let mut bytes = vec![instruction.opcode];
+            match mode {
+                AddressingMode::Relative => bytes.push(self.branch_offset(values[0], 2)?),
+                AddressingMode::ZeroPageRelative => {
+                    bytes.push(byte(values[0])?);
+                    bytes.push(self.branch_offset(values[1], 3)?);
+                }
+                _ => match mode.operand_length() {
+                    1 => bytes.push(byte(values[0])?),
+                    2 => bytes.extend_from_slice(&word(values[0])?.to_le_bytes()),
+                    _ => {}
+                },
+            }
+            self.emit(&bytes);
+
+            if !self.final_pass {
+                self.modes.push(mode);
+            }
+            self.instructions += 1;
+            return Ok(());
+        }
+
+        Err(AsmErrorKind::InvalidAddressingMode(mnemonic))
+    }
+
+    /// Works out which addressing modes an operand could be assembled with.
+    ///
+    /// An address that fits in page zero can use a zero page mode, which is
+    /// tried first since it is shorter. Writing `a:` in front of an address
+    /// forces the absolute mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `operand` - The operand as written.
+    /// * `relative` - Whether the instruction is a branch.
+    ///
+    /// # Returns
+    ///
+    /// The modes in the order they should be tried, each with the values its operand bytes are made from.
+    fn modes(&self, operand: &str, relative: bool) -> Result<Vec<Candidate>, AsmErrorKind> {
+        use AddressingMode::*;
+
+        if operand.is_empty() {
+            return Ok(vec![(Implied, vec![]), (Accumulator, vec![])]);
+        }
+        if operand.eq_ignore_ascii_case("A") {
+            return Ok(vec![(Accumulator, vec![])]);
+        }
+        if let Some(value) = operand.strip_prefix('#') {
+            return Ok(vec![(Immediate, vec![self.evaluate(value)?])]);
+        }
+
+        // Indirect modes are wrapped in parentheses, which also have to be
+        // told apart from an expression that starts with one
+        if let Some(inner) = enclosed(operand) {
+            let parts = split_arguments(inner);
+            return match parts[..] {
+                [address, index] if index.eq_ignore_ascii_case("X") => {
+                    self.sized(address, IndexedIndirect, AbsoluteIndexedIndirect)
+                }
+                [address] => self.sized(address, ZeroPageIndirect, Indirect),
+                _ => Err(AsmErrorKind::InvalidOperand(String::from(operand))),
+            };
+        }
+        if let Some(pointer) = operand
+            .strip_suffix(['Y', 'y'])
+            .and_then(|rest| rest.trim_end().strip_suffix(','))
+            .and_then(|rest| enclosed(rest.trim_end()))
+        {
+            return Ok(vec![(IndirectIndexed, vec![self.evaluate(pointer)?])]);
+        }
+
+        let parts = split_arguments(operand);
+        match parts[..] {
+            [address, index] if index.eq_ignore_ascii_case("X") => self.sized(address, ZeroPageX, AbsoluteX),
+            [address, index] if index.eq_ignore_ascii_case("Y") => self.sized(address, ZeroPageY, AbsoluteY),
+            [address, target] => {
+                Ok(vec![(ZeroPageRelative, vec![self.evaluate(address)?, self.evaluate(target)?])])
+            }
+            [target] if relative => Ok(vec![(Relative, vec![self.evaluate(target)?])]),
+            [address] => self.sized(address, ZeroPage, Absolute),
+            _ => Err(AsmErrorKind::InvalidOperand(String::from(operand))),
+        }
+    }
+
+    /// Chooses between the zero page and absolute form of a mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address expression, which may start with `a:`.
+    /// * `zero_page` - The mode to use if the address is in page zero.
+    /// * `absolute` - The mode to use otherwise.
+    fn sized(
+        &self,
+        address: &str,
+        zero_page: AddressingMode,
+        absolute: AddressingMode,
+    ) -> Result<Vec<Candidate>, AsmErrorKind> {
+        let (forced, address) = match address.get(..2) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("a:") => (true, &address[2..]),
+            _ => (false, address),
+        };
+
+        // An address that isn't known yet might not be in page zero
+        let value = self.evaluate(address)?;
+        let mut modes = Vec::new();
+        if !forced && matches!(value, Some(0..=0xFF)) {
+            modes.push((zero_page, vec![value]));
+        }
+        modes.push((absolute, vec![value]));
+        Ok(modes)
+    }
+
+    /// Works out the offset of a branch.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The address to branch to, or `None` if it isn't known yet.
+    /// * `length` - The length of the branch instruction, since offsets are from the instruction after it.
+    fn branch_offset(&self, target: Option<i64>, length: i64) -> Result<u8, AsmErrorKind> {
+        let Some(target) = target else {
+            return Ok(0);
+        };
+
+        let offset = target - (self.pc as i64 + length);
+        match offset {
+            -128..=127 => Ok(offset as u8),
+            _ if offset > 127 => Err(AsmErrorKind::BranchOutOfRange(offset - 127)),
+            _ => Err(AsmErrorKind::BranchOutOfRange(-128 - offset)),
+        }
+    }
+}
+
+/// Checks that a value fits in a byte, either signed or unsigned. A value
+/// that isn't known yet is left as zero until the second pass.
+fn byte(value: Option<i64>) -> Result<u8, AsmErrorKind> {
+    match value.unwrap_or(0) {
+        value @ -0x80..=0xFF => Ok(value as u8),
+        value => Err(AsmErrorKind::ValueOutOfRange(value)),
+    }
+}
+
+/// Checks that a value fits in a word, either signed or unsigned. A value
+/// that isn't known yet is left as zero until the second pass.
+fn word(value: Option<i64>) -> Result<u16, AsmErrorKind> {
+    match value.unwrap_or(0) {
+        value @ -0x8000..=0xFFFF => Ok(value as u16),
+        value => Err(AsmErrorKind::ValueOutOfRange(value)),
+    }
+}
+
+/// Returns whether a string is a valid label, constant or macro name.
+fn is_symbol_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_') && chars.all(is_symbol_char)
+}
+
+/// Removes a comment, which starts at a semicolon outside of quotes.
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, ';') => return &text[..index],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Splits a label off the start of a line.
+///
+/// # Returns
+///
+/// The label's name and the rest of the line, or `None` if the line doesn't start with a label.
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let end = text.find(|c| !is_symbol_char(c))?;
+    let (name, rest) = text.split_at(end);
+    let rest = rest.strip_prefix(':')?;
+    is_symbol_name(name).then_some((name, rest))
+}
+
+/// Splits the first word off a line.
+///
+/// # Returns
+///
+/// The first word and the rest of the line, both without surrounding whitespace.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    let end = text.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(text.len());
+    (&text[..end], text[end..].trim_start())
+}
+
+/// Splits a list of arguments at the commas that aren't inside parentheses or quotes.
+///
+/// # Returns
+///
+/// The arguments without surrounding whitespace. An empty list has no arguments.
+fn split_arguments(text: &str) -> Vec<&str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), _) if open == c => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                arguments.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    arguments.push(text[start..].trim());
+    arguments
+}
+
+/// Returns what is inside a pair of parentheses that wrap the whole of a string.
+///
+/// `($12),Y` and `(1+2)*3` both start with a parenthesis, but it doesn't close at
+/// the end of the string, so neither is enclosed.
+fn enclosed(text: &str) -> Option<&str> {
+    let inner = text.strip_prefix('(')?.strip_suffix(')')?;
+
+    // The opening parenthesis must not close before the end
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    Some(inner)
+}
+
+/// Replaces a macro's parameters with its arguments in a line of its body.
+///
+/// Parameters are only replaced where they make up a whole symbol, so a
+/// parameter called `x` doesn't change `inx`. `\@` is replaced with a number
+/// that is different for every expansion.
+///
+/// # Arguments
+///
+/// * `text` - A line of the macro's body.
+/// * `parameters` - The macro's parameter names.
+/// * `arguments` - The arguments it was invoked with, in the same order.
+/// * `unique` - The number for this expansion.
+fn substitute(text: &str, parameters: &[String], arguments: &[&str], unique: &str) -> String {
+    let text = text.replace("\\@", unique);
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find(is_symbol_char) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_symbol_char(c)).unwrap_or(rest.len());
+        let symbol = &rest[..end];
+        match parameters.iter().position(|parameter| parameter == symbol) {
+            Some(index) => result.push_str(arguments[index]),
+            None => result.push_str(symbol),
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,61 @@
+use alloc::vec::Vec;
+
+use crate::asm::Listing;
+use crate::disasm::SymbolTable;
+
+/// A run of bytes assembled to consecutive addresses.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    /// The address of the first byte.
+    pub origin: u16,
+
+    /// The assembled bytes.
+    pub bytes: Vec<u8>,
+}
+
+/// The output of the assembler.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Assembly {
+    /// The assembled code and data, one segment per `.org`, in source order.
+    pub segments: Vec<Segment>,
+
+    /// The labels defined in the source. Constants aren't included, since they
+    /// don't name addresses.
+    pub symbols: SymbolTable,
+
+    /// Every source line with the address and bytes it assembled to.
+    pub listing: Listing,
+}
+
+impl Assembly {
+    /// Lays all the segments out in a single image.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill` - The value for addresses between segments that nothing was assembled to.
+    ///
+    /// # Returns
+    ///
+    /// The address of the first byte and the image, which runs up to the last
+    /// assembled byte. The image is empty if nothing was assembled.
+    pub fn image(&self, fill: u8) -> (u16, Vec<u8>) {
+        let start = self.segments.iter().map(|segment| segment.origin as usize).min();
+        let end = self
+            .segments
+            .iter()
+            .map(|segment| segment.origin as usize + segment.bytes.len())
+            .max();
+        let (Some(start), Some(end)) = (start, end) else {
+            return (0, Vec::new());
+        };
+
+        // Later segments win where they overlap, as they would when loaded in order
+        let mut image = vec![fill; end - start];
+        for segment in &self.segments {
+            let offset = segment.origin as usize - start;
+            image[offset..offset + segment.bytes.len()].copy_from_slice(&segment.bytes);
+        }
+
+        (start as u16, image)
+    }
+}
@@ -0,0 +1,95 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+/// What went wrong while assembling a line.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsmErrorKind {
+    /// The first word of the line isn't an instruction, directive or macro.
+    UnknownMnemonic(String),
+
+    /// The instruction doesn't have the addressing mode the operand asks for.
+    InvalidAddressingMode(String),
+
+    /// The operand couldn't be parsed.
+    InvalidOperand(String),
+
+    /// An expression couldn't be parsed.
+    InvalidExpression(String),
+
+    /// A symbol was used that hasn't been defined.
+    UndefinedSymbol(String),
+
+    /// A label or constant was defined twice.
+    DuplicateSymbol(String),
+
+    /// A value doesn't fit in the byte or word it is stored in.
+    ValueOutOfRange(i64),
+
+    /// A branch target is further away than a relative branch can reach.
+    BranchOutOfRange(i64),
+
+    /// A directive that the assembler doesn't know.
+    UnknownDirective(String),
+
+    /// A `.macro` without a matching `.endmacro`.
+    UnterminatedMacro(String),
+
+    /// A macro was invoked with the wrong number of arguments.
+    MacroArguments {
+        /// The name of the macro.
+        name: String,
+
+        /// The number of parameters the macro has.
+        expected: usize,
+
+        /// The number of arguments it was given.
+        found: usize,
+    },
+
+    /// Macros were nested too deeply, which usually means a macro invokes itself.
+    MacroDepth(String),
+
+    /// An expression divided by zero.
+    DivisionByZero,
+}
+
+impl Display for AsmErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(name) => write!(f, "Unknown instruction or macro: {}", name),
+            AsmErrorKind::InvalidAddressingMode(name) => write!(f, "Invalid addressing mode for {}", name),
+            AsmErrorKind::InvalidOperand(operand) => write!(f, "Invalid operand: {}", operand),
+            AsmErrorKind::InvalidExpression(expression) => write!(f, "Invalid expression: {}", expression),
+            AsmErrorKind::UndefinedSymbol(name) => write!(f, "Undefined symbol: {}", name),
+            AsmErrorKind::DuplicateSymbol(name) => write!(f, "Symbol defined twice: {}", name),
+            AsmErrorKind::ValueOutOfRange(value) => write!(f, "Value out of range: {}", value),
+            AsmErrorKind::BranchOutOfRange(offset) => write!(f, "Branch out of range by {} bytes", offset),
+            AsmErrorKind::UnknownDirective(name) => write!(f, "Unknown directive: {}", name),
+            AsmErrorKind::UnterminatedMacro(name) => write!(f, "Macro {} has no .endmacro", name),
+            AsmErrorKind::MacroArguments { name, expected, found } => {
+                write!(f, "Macro {} takes {} arguments, found {}", name, expected, found)
+            }
+            AsmErrorKind::MacroDepth(name) => write!(f, "Macros nested too deeply in {}", name),
+            AsmErrorKind::DivisionByZero => write!(f, "Division by zero"),
+        }
+    }
+}
+
+/// An error in assembly source, with the line it was found on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AsmError {
+    /// The line of the source the error is on, counting from 1. Errors inside
+    /// a macro are reported on the line that invoked it.
+    pub line: usize,
+
+    /// What went wrong.
+    pub kind: AsmErrorKind,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.kind)
+    }
+}
+
+impl core::error::Error for AsmError {}
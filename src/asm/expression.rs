@@ -0,0 +1,228 @@
+use alloc::string::String;
+
+use crate::asm::AsmErrorKind;
+
+/// Evaluates an expression in an operand or directive.
+///
+/// Numbers can be decimal, hex with `$`, binary with `%` or a character in
+/// single quotes. `*` on its own stands for the current address. The operators
+/// are, from loosest to tightest binding: `|`, `^`, `&`, `+` and `-`, `*` and
+/// `/`, then the unary `-`, `~`, `<` (low byte) and `>` (high byte). Parentheses
+/// group as usual.
+///
+/// # Arguments
+///
+/// * `text` - The expression.
+/// * `pc` - The address of the current instruction.
+/// * `lookup` - Returns the value of a symbol, or `None` if it isn't defined.
+///
+/// # Returns
+///
+/// The value of the expression, or the reason it couldn't be evaluated.
+pub(crate) fn evaluate(text: &str, pc: u16, lookup: &dyn Fn(&str) -> Option<i64>) -> Result<i64, AsmErrorKind> {
+    let mut parser = Parser { text, position: 0, pc, lookup };
+    let value = parser.or()?;
+
+    // Anything left over means the expression was malformed
+    parser.skip_whitespace();
+    if parser.position < text.len() {
+        return Err(AsmErrorKind::InvalidExpression(String::from(text.trim())));
+    }
+
+    Ok(value)
+}
+
+/// Returns whether a character can appear in a symbol name.
+pub(crate) fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// A recursive descent parser that evaluates as it goes.
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    pc: u16,
+    lookup: &'a dyn Fn(&str) -> Option<i64>,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the text that hasn't been parsed yet.
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.position = self.text.len() - trimmed.len();
+    }
+
+    /// Consumes the next character if it is `c`.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The error for a malformed expression.
+    fn invalid(&self) -> AsmErrorKind {
+        AsmErrorKind::InvalidExpression(String::from(self.text.trim()))
+    }
+
+    fn or(&mut self) -> Result<i64, AsmErrorKind> {
+        let mut value = self.xor()?;
+        while self.eat('|') {
+            value |= self.xor()?;
+        }
+        Ok(value)
+    }
+
+    fn xor(&mut self) -> Result<i64, AsmErrorKind> {
+        let mut value = self.and()?;
+        while self.eat('^') {
+            value ^= self.and()?;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i64, AsmErrorKind> {
+        let mut value = self.additive()?;
+        while self.eat('&') {
+            value &= self.additive()?;
+        }
+        Ok(value)
+    }
+
+    fn additive(&mut self) -> Result<i64, AsmErrorKind> {
+        let mut value = self.multiplicative()?;
+        loop {
+            if self.eat('+') {
+                value = value.wrapping_add(self.multiplicative()?);
+            } else if self.eat('-') {
+                value = value.wrapping_sub(self.multiplicative()?);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<i64, AsmErrorKind> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value = value.wrapping_mul(self.unary()?);
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0 {
+                    return Err(AsmErrorKind::DivisionByZero);
+                }
+                value /= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64, AsmErrorKind> {
+        if self.eat('-') {
+            Ok(-self.unary()?)
+        } else if self.eat('~') {
+            Ok(!self.unary()?)
+        } else if self.eat('<') {
+            Ok(self.unary()? & 0xFF)
+        } else if self.eat('>') {
+            Ok((self.unary()? >> 8) & 0xFF)
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, AsmErrorKind> {
+        self.skip_whitespace();
+
+        // A parenthesised expression
+        if self.eat('(') {
+            let value = self.or()?;
+            if !self.eat(')') {
+                return Err(self.invalid());
+            }
+            return Ok(value);
+        }
+
+        // The current address
+        if self.eat('*') {
+            return Ok(self.pc as i64);
+        }
+
+        // A character, such as 'A'
+        if self.eat('\'') {
+            let c = self.rest().chars().next().ok_or_else(|| self.invalid())?;
+            self.position += c.len_utf8();
+            if !self.eat('\'') {
+                return Err(self.invalid());
+            }
+            return Ok(c as i64);
+        }
+
+        // A number in hex, binary or decimal
+        let (radix, prefix) = match self.rest().chars().next() {
+            Some('$') => (16, 1),
+            Some('%') => (2, 1),
+            Some(c) if c.is_ascii_digit() => (10, 0),
+            _ => (0, 0),
+        };
+        if radix != 0 {
+            self.position += prefix;
+            let digits = self.take_while(|c| c.is_digit(radix));
+            return i64::from_str_radix(digits, radix).map_err(|_| self.invalid());
+        }
+
+        // A symbol
+        let name = self.take_while(is_symbol_char);
+        if name.is_empty() {
+            return Err(self.invalid());
+        }
+        (self.lookup)(name).ok_or_else(|| AsmErrorKind::UndefinedSymbol(String::from(name)))
+    }
+
+    /// Consumes the characters that match a predicate.
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.position;
+        let length = self.rest().find(|c| !predicate(c)).unwrap_or(self.rest().len());
+        self.position += length;
+        &self.text[start..self.position]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asm::expression::evaluate;
+    use crate::asm::AsmErrorKind;
+
+    fn lookup(name: &str) -> Option<i64> {
+        match name {
+            "screen" => Some(0x0400),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn evaluates_numbers_and_operators() {
+        assert_eq!(evaluate("$10 + %101 * 2", 0, &lookup), Ok(0x1A));
+        assert_eq!(evaluate("(1 + 2) * 3", 0, &lookup), Ok(9));
+        assert_eq!(evaluate("'A' | $80", 0, &lookup), Ok(0xC1));
+        assert_eq!(evaluate(">screen + <screen", 0, &lookup), Ok(0x04));
+        assert_eq!(evaluate("* + 2", 0x0200, &lookup), Ok(0x0202));
+    }
+
+    #[test]
+    fn reports_bad_expressions() {
+        assert_eq!(evaluate("nowhere", 0, &lookup), Err(AsmErrorKind::UndefinedSymbol("nowhere".into())));
+        assert_eq!(evaluate("1 / 0", 0, &lookup), Err(AsmErrorKind::DivisionByZero));
+        assert!(matches!(evaluate("1 +", 0, &lookup), Err(AsmErrorKind::InvalidExpression(_))));
+        assert!(matches!(evaluate("2 3", 0, &lookup), Err(AsmErrorKind::InvalidExpression(_))));
+    }
+}
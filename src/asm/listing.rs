@@ -0,0 +1,76 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// The number of bytes shown on each row of a listing. Longer data carries on
+/// over the rows below.
+const BYTES_PER_ROW: usize = 3;
+
+/// A line of source and what it assembled to.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListingLine {
+    /// The line number in the source, counting from 1.
+    pub line: usize,
+
+    /// The address of the first byte the line assembled to, or the current
+    /// address if it didn't assemble to anything.
+    pub address: u16,
+
+    /// The bytes the line assembled to, including any from macros it invokes.
+    pub bytes: Vec<u8>,
+
+    /// The line as written.
+    pub source: String,
+}
+
+/// Every line of the source, with its address and bytes.
+///
+/// A listing ties addresses back to source lines, so an address from a trace
+/// or a breakpoint can be looked up with [`Listing::find`].
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Listing {
+    /// The lines in source order.
+    pub lines: Vec<ListingLine>,
+}
+
+impl Listing {
+    /// Finds the line that assembled to an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to look up.
+    ///
+    /// # Returns
+    ///
+    /// The line whose bytes include the address, or `None` if nothing was assembled there.
+    pub fn find(&self, address: u16) -> Option<&ListingLine> {
+        self.lines.iter().find(|line| {
+            let offset = address.wrapping_sub(line.address) as usize;
+            offset < line.bytes.len()
+        })
+    }
+}
+
+impl Display for Listing {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for line in &self.lines {
+            let mut rows = line.bytes.chunks(BYTES_PER_ROW);
+
+            // The first row has the source, the rest just carry on the bytes
+            let first = rows.next().unwrap_or_default();
+            let row = format!("{:04X}  {:<8}  {}", line.address, hex(first), line.source);
+            writeln!(f, "{}", row.trim_end())?;
+            for (index, row) in rows.enumerate() {
+                let address = line.address.wrapping_add(((index + 1) * BYTES_PER_ROW) as u16);
+                writeln!(f, "{:04X}  {}", address, hex(row))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats bytes as hex pairs separated by spaces.
+fn hex(bytes: &[u8]) -> String {
+    let pairs: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    pairs.join(" ")
+}
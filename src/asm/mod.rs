@@ -0,0 +1,364 @@
+#![warn(missing_docs)]
+//! A small assembler for writing 6502 programs and firmware inside the repo.
+//!
+//! Each line can hold a label, then an instruction, directive or macro
+//! invocation, then a comment after a `;`:
+//!
+//! ```text
+//! ACIA_DATA = $8000           ; a constant
+//! ACIA_STATUS .equ $8001      ; another way to write one
+//!
+//! .macro putc char            ; a macro with one parameter
+//!         lda #char
+//!         sta ACIA_DATA
+//! .endmacro
+//!
+//!         .org $0200
+//! start:  putc 'H'
+//!         putc 'I'
+//!         brk
+//! message: .byte "HI", 0
+//! vectors: .word start, start
+//! ```
+//!
+//! Mnemonics and directives can be written in any case, but symbols and
+//! macro names are case sensitive. Numbers are decimal, `$` hex, `%` binary or
+//! a character in single quotes, and `*` is the address of the current line.
+//! Expressions can use `+ - * / & | ^ ~`, with `<` and `>` for the low and high
+//! byte of a word.
+//!
+//! The directives are:
+//!
+//! * `.org address` - assembles what follows at a new address.
+//! * `.byte value, "text", ...` - assembles bytes and strings. `.db` is the same.
+//! * `.word value, ...` - assembles little-endian words. `.dw` is the same.
+//! * `.equ NAME, value` - defines a constant, as do `NAME = value` and `NAME .equ value`.
+//! * `.macro name param, ...` to `.endmacro` (or `.endm`) - defines a macro.
+//!
+//! A macro's parameters are replaced with its arguments wherever they appear
+//! as a whole symbol, and `\@` is replaced with a number that is different for
+//! every expansion, so a macro can define labels such as `loop\@`.
+//!
+//! Labels can be used before they are defined, since the source is assembled
+//! in two passes: the first works out where every label is and the second
+//! produces the bytes. The address given to `.org` is the exception, since it
+//! has to be known in the first pass.
+//!
+//! An address that fits in page zero uses the zero page form of an
+//! instruction, unless it is written with an `a:` prefix. That can only be
+//! decided for an address that is known in the first pass, so a reference to a
+//! symbol defined further down is always assembled as an absolute address.
+//! Branches are checked to be in range once their targets are known.
+//!
+//! Alongside the bytes, an [`Assembly`] has a [`Listing`] of every source line
+//! with its address and bytes, which can be printed or used to find the line an
+//! address in a trace came from.
+
+mod assembler;
+mod assembly;
+mod error;
+mod expression;
+mod listing;
+mod program;
+
+use crate::asm::assembler::Assembler;
+use crate::cpu::CpuModel;
+
+pub use crate::asm::assembly::{Assembly, Segment};
+pub use crate::asm::error::{AsmError, AsmErrorKind};
+pub use crate::asm::listing::{Listing, ListingLine};
+pub use crate::asm::program::Program;
+
+/// Assembles NMOS 6502 source.
+///
+/// # Arguments
+///
+/// * `source` - The assembly source.
+///
+/// # Returns
+///
+/// The assembled code and labels, or the first error in the source.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::asm::assemble;
+///
+/// let assembly = assemble(".org $0200\nstart: lda #$01\n jmp start").unwrap();
+/// assert_eq!(assembly.segments[0].origin, 0x0200);
+/// assert_eq!(assembly.segments[0].bytes, [0xA9, 0x01, 0x4C, 0x00, 0x02]);
+/// ```
+pub fn assemble(source: &str) -> Result<Assembly, AsmError> {
+    assemble_model(CpuModel::Nmos6502, source)
+}
+
+/// Assembles source for a specific CPU model.
+///
+/// # Arguments
+///
+/// * `model` - The CPU model whose instruction set is used.
+/// * `source` - The assembly source.
+///
+/// # Returns
+///
+/// The assembled code and labels, or the first error in the source.
+pub fn assemble_model(model: CpuModel, source: &str) -> Result<Assembly, AsmError> {
+    Assembler::new(model).assemble(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use crate::asm::{assemble, assemble_model, AsmError, AsmErrorKind, Segment};
+    use crate::cpu::CpuModel;
+
+    /// Assembles source that is expected to be valid and returns the first segment.
+    fn bytes(source: &str) -> alloc::vec::Vec<u8> {
+        let assembly = assemble(source).unwrap();
+        assembly.segments[0].bytes.clone()
+    }
+
+    #[test]
+    fn assembles_every_addressing_mode() {
+        let source = "
+            lda #$10
+            lda $10
+            lda $10,x
+            ldx $10,y
+            lda $1234
+            lda $1234,X
+            lda $1234,Y
+            lda ($10,x)
+            lda ($10),y
+            asl a
+            asl
+            jmp ($1234)
+            inx
+        ";
+
+        assert_eq!(
+            bytes(source),
+            [
+                0xA9, 0x10, 0xA5, 0x10, 0xB5, 0x10, 0xB6, 0x10, 0xAD, 0x34, 0x12, 0xBD, 0x34, 0x12, 0xB9, 0x34,
+                0x12, 0xA1, 0x10, 0xB1, 0x10, 0x0A, 0x0A, 0x6C, 0x34, 0x12, 0xE8
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_absolute_form_when_asked_or_when_needed() {
+        // LDA has no zero page,Y form, and a: forces the absolute one
+        assert_eq!(bytes("lda $10,y\nlda a:$10"), [0xB9, 0x10, 0x00, 0xAD, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn assembles_65c02_instructions() {
+        let assembly = assemble_model(CpuModel::Wdc65C02, "stz $10\nlda ($10)\nbbr0 $10,*").unwrap();
+
+        assert_eq!(assembly.segments[0].bytes, [0x64, 0x10, 0xB2, 0x10, 0x0F, 0x10, 0xFD]);
+    }
+
+    #[test]
+    fn defines_constants() {
+        let source = "
+            ACIA_DATA = $8000
+            ACIA_STATUS .equ ACIA_DATA + 1
+            .equ ZP_PTR, $20
+            lda ACIA_STATUS
+            sta ZP_PTR
+            lda #>ACIA_DATA
+        ";
+
+        let assembly = assemble(source).unwrap();
+
+        assert_eq!(assembly.segments[0].bytes, [0xAD, 0x01, 0x80, 0x85, 0x20, 0xA9, 0x80]);
+        assert!(assembly.symbols.is_empty());
+    }
+
+    #[test]
+    fn expands_macros_with_parameters() {
+        let source = "
+            .macro store value, address
+                lda #value
+                sta address
+            .endmacro
+            .macro wait count
+                ldx #count
+            loop\\@: dex
+                bne loop\\@
+            .endm
+            .org $0300
+            store 1, $10
+            wait 5
+            wait 6
+        ";
+
+        let assembly = assemble(source).unwrap();
+
+        assert_eq!(
+            assembly.segments[0].bytes,
+            [0xA9, 0x01, 0x85, 0x10, 0xA2, 0x05, 0xCA, 0xD0, 0xFD, 0xA2, 0x06, 0xCA, 0xD0, 0xFD]
+        );
+        assert_eq!(assembly.symbols.address_of("loop2"), Some(0x0306));
+        assert_eq!(assembly.symbols.address_of("loop3"), Some(0x030B));
+    }
+
+    #[test]
+    fn assembles_data_into_segments() {
+        let source = "
+            .org $0200
+        start: jmp start
+        text:  .byte \"Hi\", 13, 'x' ; a comment with \"quotes\"
+            .org $FFFC
+            .word start, text
+        ";
+
+        let assembly = assemble(source).unwrap();
+
+        assert_eq!(
+            assembly.segments,
+            [
+                Segment {
+                    origin: 0x0200,
+                    bytes: vec![0x4C, 0x00, 0x02, b'H', b'i', 13, b'x'],
+                },
+                Segment {
+                    origin: 0xFFFC,
+                    bytes: vec![0x00, 0x02, 0x03, 0x02],
+                },
+            ]
+        );
+        assert_eq!(assembly.symbols.address_of("text"), Some(0x0203));
+
+        let (start, image) = assembly.image(0xEA);
+        assert_eq!(start, 0x0200);
+        assert_eq!(image.len(), 0x10000 - 0x0200);
+        assert_eq!(image[7], 0xEA);
+    }
+
+    #[test]
+    fn resolves_forward_references() {
+        let source = "
+            .org $0200
+        loop:   lda table,x
+                beq loop_end
+                jsr output
+                inx
+                bne loop
+        loop_end:
+                brk
+        output: sta $8000
+                rts
+        table:  .byte 1, 2, 0
+        pointer: .word table
+        ";
+
+        let assembly = assemble(source).unwrap();
+
+        assert_eq!(
+            assembly.segments[0].bytes,
+            [
+                0xBD, 0x10, 0x02, 0xF0, 0x06, 0x20, 0x0C, 0x02, 0xE8, 0xD0, 0xF5, 0x00, 0x8D, 0x00, 0x80, 0x60, 0x01,
+                0x02, 0x00, 0x10, 0x02
+            ]
+        );
+        assert_eq!(assembly.symbols.address_of("loop_end"), Some(0x020B));
+    }
+
+    #[test]
+    fn keeps_forward_zero_page_references_absolute() {
+        // The first pass doesn't know where counter is, so it has to leave room
+        // for a full address. Once it is defined, zero page is used.
+        let source = "
+            lda counter
+            counter = $10
+            lda counter
+        ";
+
+        assert_eq!(bytes(source), [0xAD, 0x10, 0x00, 0xA5, 0x10]);
+    }
+
+    #[test]
+    fn checks_forward_branch_range() {
+        let source = "
+            bne far
+            .org $0100
+        far: nop
+        ";
+
+        assert_eq!(
+            assemble(source).unwrap_err(),
+            AsmError {
+                line: 2,
+                kind: AsmErrorKind::BranchOutOfRange(0x100 - 2 - 127),
+            }
+        );
+    }
+
+    #[test]
+    fn lists_addresses_and_bytes() {
+        let source = "
+        .org $0200
+start:  lda #$01 ; load
+        .byte \"Hello\"
+";
+
+        let listing = assemble(source).unwrap().listing;
+
+        assert_eq!(
+            listing.to_string(),
+            "\
+0000
+0200                    .org $0200
+0200  A9 01     start:  lda #$01 ; load
+0202  48 65 6C          .byte \"Hello\"
+0205  6C 6F
+"
+        );
+        assert_eq!(listing.find(0x0201).map(|line| line.line), Some(3));
+        assert_eq!(listing.find(0x0206).map(|line| line.line), Some(4));
+        assert_eq!(listing.find(0x0207), None);
+    }
+
+    #[test]
+    fn assembles_the_blink_demo() {
+        let assembly = assemble(include_str!("../../demos/blink.asm")).unwrap();
+
+        assert_eq!(assembly.image(0x00), (0xC000, include_bytes!("../../demos/blink.bin").to_vec()));
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let error = |source| assemble(source).unwrap_err();
+
+        assert_eq!(
+            error("nop\nfoo $10"),
+            AsmError {
+                line: 2,
+                kind: AsmErrorKind::UnknownMnemonic("FOO".into()),
+            }
+        );
+        assert_eq!(error("jmp nowhere\nlater: nop").kind, AsmErrorKind::UndefinedSymbol("nowhere".into()));
+        assert_eq!(error("inx #1").kind, AsmErrorKind::InvalidAddressingMode("INX".into()));
+        assert_eq!(error("lda #256").kind, AsmErrorKind::ValueOutOfRange(256));
+        assert_eq!(error("a: nop\na: nop").kind, AsmErrorKind::DuplicateSymbol("a".into()));
+        assert_eq!(error(".org $0200\nbne $0300").kind, AsmErrorKind::BranchOutOfRange(127));
+        assert_eq!(error(".fill 3").kind, AsmErrorKind::UnknownDirective(".fill".into()));
+        assert_eq!(
+            error("nop\n.macro m\nnop").kind,
+            AsmErrorKind::UnterminatedMacro("m".into())
+        );
+        assert_eq!(error("nop\n.macro m\nnop").line, 2);
+        assert_eq!(
+            error(".macro m a\n.endm\nm 1, 2").kind,
+            AsmErrorKind::MacroArguments {
+                name: "m".into(),
+                expected: 1,
+                found: 2,
+            }
+        );
+        assert_eq!(error(".macro m\nm\n.endm\n\nm").kind, AsmErrorKind::MacroDepth("m".into()));
+        assert_eq!(error(".macro m\nm\n.endm\n\nm").line, 5);
+    }
+}
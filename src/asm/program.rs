@@ -0,0 +1,491 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::MainBus;
+use crate::cpu::instructions;
+use crate::cpu::{AddressingMode, CpuModel};
+
+/// How a label is written into an operand once its address is known.
+#[derive(Debug, Clone, Copy)]
+enum Fixup {
+    /// A branch offset, relative to the instruction after the branch.
+    Relative,
+
+    /// A full address.
+    Absolute,
+}
+
+/// Builds a program one instruction at a time, mostly for tests.
+///
+/// There is a method for every documented NMOS 6502 instruction and mode,
+/// named after the mnemonic and a suffix for the mode: `_imm`, `_zp`, `_zpx`,
+/// `_zpy`, `_abs`, `_absx`, `_absy`, `_ind`, `_indx`, `_indy` and `_acc`.
+/// Implied instructions have no suffix. Branches, and [`Program::jmp`] and
+/// [`Program::jsr`], take the name of a label, which can be placed before or
+/// after them. [`Program::instruction`] covers anything else, such as the
+/// 65C02 instructions.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::asm::Program;
+/// use butterflyrs::bus::MainBus;
+/// use butterflyrs::bus::ram::Ram;
+///
+/// let mut bus = MainBus::new();
+/// bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+///
+/// Program::at(0x0200).lda_imm(0x10).sta_abs(0x8000).brk().install(&mut bus);
+/// assert_eq!(bus.read(0x0200), 0xA9);
+/// assert_eq!(bus.read(0x0203), 0x00);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Program {
+    model: CpuModel,
+    origin: u16,
+    bytes: Vec<u8>,
+    labels: BTreeMap<String, u16>,
+
+    /// The operands waiting for a label: the offset of the operand, the label and how to write it.
+    fixups: Vec<(usize, String, Fixup)>,
+}
+
+impl Program {
+    /// Starts an NMOS 6502 program.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The address of the first instruction.
+    pub fn at(origin: u16) -> Program {
+        Program {
+            model: CpuModel::Nmos6502,
+            origin,
+            bytes: Vec::new(),
+            labels: BTreeMap::new(),
+            fixups: Vec::new(),
+        }
+    }
+
+    /// Sets the CPU model whose instruction set is used.
+    pub fn model(mut self, model: CpuModel) -> Program {
+        self.model = model;
+        self
+    }
+
+    /// Returns the address the next instruction will be added at.
+    pub fn pc(&self) -> u16 {
+        self.origin.wrapping_add(self.bytes.len() as u16)
+    }
+
+    /// Names the address of the next instruction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the label has already been placed.
+    pub fn label(mut self, name: &str) -> Program {
+        let pc = self.pc();
+        if self.labels.insert(String::from(name), pc).is_some() {
+            panic!("label {} placed twice", name);
+        }
+        self
+    }
+
+    /// Adds an instruction by mnemonic and addressing mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - The instruction's name, in upper case.
+    /// * `mode` - The addressing mode.
+    /// * `operand` - The operand. Only as many bytes as the mode needs are used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the CPU model has no such instruction.
+    pub fn instruction(mut self, mnemonic: &str, mode: AddressingMode, operand: u16) -> Program {
+        let opcode = self.opcode(mnemonic, mode);
+        self.bytes.push(opcode);
+        let operand = operand.to_le_bytes();
+        self.bytes.extend_from_slice(&operand[..mode.operand_length() as usize]);
+        self
+    }
+
+    /// Adds BRK. Like the assembler, this is a single byte, though the CPU
+    /// skips the byte after it.
+    pub fn brk(mut self) -> Program {
+        self.bytes.push(0x00);
+        self
+    }
+
+    /// Adds `JMP` to a label.
+    pub fn jmp(self, label: &str) -> Program {
+        self.with_label("JMP", AddressingMode::Absolute, label, Fixup::Absolute)
+    }
+
+    /// Adds `JSR` to a label.
+    pub fn jsr(self, label: &str) -> Program {
+        self.with_label("JSR", AddressingMode::Absolute, label, Fixup::Absolute)
+    }
+
+    /// Adds a byte of data.
+    pub fn byte(mut self, value: u8) -> Program {
+        self.bytes.push(value);
+        self
+    }
+
+    /// Adds bytes of data.
+    pub fn bytes(mut self, values: &[u8]) -> Program {
+        self.bytes.extend_from_slice(values);
+        self
+    }
+
+    /// Adds a little-endian word of data.
+    pub fn word(mut self, value: u16) -> Program {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Returns the assembled program, with every label filled in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label was used but never placed, or a branch can't reach its label.
+    pub fn assemble(&self) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        for (offset, label, fixup) in &self.fixups {
+            let target = *self
+                .labels
+                .get(label)
+                .unwrap_or_else(|| panic!("label {} is used but never placed", label));
+            match fixup {
+                Fixup::Relative => {
+                    let next = self.origin as i64 + *offset as i64 + 1;
+                    let distance = target as i64 - next;
+                    let distance = i8::try_from(distance)
+                        .unwrap_or_else(|_| panic!("branch to {} is {} bytes away", label, distance));
+                    bytes[*offset] = distance as u8;
+                }
+                Fixup::Absolute => bytes[*offset..*offset + 2].copy_from_slice(&target.to_le_bytes()),
+            }
+        }
+        bytes
+    }
+
+    /// Writes the program into memory on a bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus, which needs writable memory at the program's addresses.
+    pub fn install(&self, bus: &mut MainBus) {
+        for (offset, byte) in self.assemble().into_iter().enumerate() {
+            bus.write(self.origin.wrapping_add(offset as u16), byte);
+        }
+    }
+
+    /// Finds the opcode of an instruction.
+    fn opcode(&self, mnemonic: &str, mode: AddressingMode) -> u8 {
+        (0..=255u8)
+            .map(|opcode| instructions::lookup(self.model, opcode))
+            .filter(|instruction| instruction.name == mnemonic && instruction.mode == mode)
+            .min_by_key(|instruction| instruction.illegal)
+            .unwrap_or_else(|| panic!("the {} has no {} in {} mode", self.model, mnemonic, mode))
+            .opcode
+    }
+
+    /// Adds an instruction whose operand is a label.
+    fn with_label(mut self, mnemonic: &str, mode: AddressingMode, label: &str, fixup: Fixup) -> Program {
+        let opcode = self.opcode(mnemonic, mode);
+        self.bytes.push(opcode);
+        self.fixups.push((self.bytes.len(), String::from(label), fixup));
+        self.bytes.extend_from_slice(&[0; 2][..mode.operand_length() as usize]);
+        self
+    }
+}
+
+/// Defines methods for instructions without an operand.
+macro_rules! implied_instructions {
+    ($($method:ident => $mnemonic:literal, $mode:ident;)*) => {
+        impl Program {
+            $(
+                #[doc = concat!("Adds `", $mnemonic, "` in ", stringify!($mode), " mode.")]
+                pub fn $method(self) -> Program {
+                    self.instruction($mnemonic, AddressingMode::$mode, 0)
+                }
+            )*
+        }
+    };
+}
+
+/// Defines methods for instructions with a one-byte operand.
+macro_rules! byte_instructions {
+    ($($method:ident => $mnemonic:literal, $mode:ident;)*) => {
+        impl Program {
+            $(
+                #[doc = concat!("Adds `", $mnemonic, "` in ", stringify!($mode), " mode.")]
+                pub fn $method(self, operand: u8) -> Program {
+                    self.instruction($mnemonic, AddressingMode::$mode, operand as u16)
+                }
+            )*
+        }
+    };
+}
+
+/// Defines methods for instructions with a two-byte operand.
+macro_rules! word_instructions {
+    ($($method:ident => $mnemonic:literal, $mode:ident;)*) => {
+        impl Program {
+            $(
+                #[doc = concat!("Adds `", $mnemonic, "` in ", stringify!($mode), " mode.")]
+                pub fn $method(self, operand: u16) -> Program {
+                    self.instruction($mnemonic, AddressingMode::$mode, operand)
+                }
+            )*
+        }
+    };
+}
+
+/// Defines methods for branches, which take a label.
+macro_rules! branch_instructions {
+    ($($method:ident => $mnemonic:literal;)*) => {
+        impl Program {
+            $(
+                #[doc = concat!("Adds `", $mnemonic, "` to a label.")]
+                pub fn $method(self, label: &str) -> Program {
+                    self.with_label($mnemonic, AddressingMode::Relative, label, Fixup::Relative)
+                }
+            )*
+        }
+    };
+}
+
+implied_instructions! {
+    asl_acc => "ASL", Accumulator;
+    lsr_acc => "LSR", Accumulator;
+    rol_acc => "ROL", Accumulator;
+    ror_acc => "ROR", Accumulator;
+    clc => "CLC", Implied;
+    cld => "CLD", Implied;
+    cli => "CLI", Implied;
+    clv => "CLV", Implied;
+    dex => "DEX", Implied;
+    dey => "DEY", Implied;
+    inx => "INX", Implied;
+    iny => "INY", Implied;
+    nop => "NOP", Implied;
+    pha => "PHA", Implied;
+    php => "PHP", Implied;
+    pla => "PLA", Implied;
+    plp => "PLP", Implied;
+    rti => "RTI", Implied;
+    rts => "RTS", Implied;
+    sec => "SEC", Implied;
+    sed => "SED", Implied;
+    sei => "SEI", Implied;
+    tax => "TAX", Implied;
+    tay => "TAY", Implied;
+    tsx => "TSX", Implied;
+    txa => "TXA", Implied;
+    txs => "TXS", Implied;
+    tya => "TYA", Implied;
+}
+
+byte_instructions! {
+    adc_imm => "ADC", Immediate;
+    adc_zp => "ADC", ZeroPage;
+    adc_zpx => "ADC", ZeroPageX;
+    adc_indx => "ADC", IndexedIndirect;
+    adc_indy => "ADC", IndirectIndexed;
+    and_imm => "AND", Immediate;
+    and_zp => "AND", ZeroPage;
+    and_zpx => "AND", ZeroPageX;
+    and_indx => "AND", IndexedIndirect;
+    and_indy => "AND", IndirectIndexed;
+    asl_zp => "ASL", ZeroPage;
+    asl_zpx => "ASL", ZeroPageX;
+    bit_zp => "BIT", ZeroPage;
+    cmp_imm => "CMP", Immediate;
+    cmp_zp => "CMP", ZeroPage;
+    cmp_zpx => "CMP", ZeroPageX;
+    cmp_indx => "CMP", IndexedIndirect;
+    cmp_indy => "CMP", IndirectIndexed;
+    cpx_imm => "CPX", Immediate;
+    cpx_zp => "CPX", ZeroPage;
+    cpy_imm => "CPY", Immediate;
+    cpy_zp => "CPY", ZeroPage;
+    dec_zp => "DEC", ZeroPage;
+    dec_zpx => "DEC", ZeroPageX;
+    eor_imm => "EOR", Immediate;
+    eor_zp => "EOR", ZeroPage;
+    eor_zpx => "EOR", ZeroPageX;
+    eor_indx => "EOR", IndexedIndirect;
+    eor_indy => "EOR", IndirectIndexed;
+    inc_zp => "INC", ZeroPage;
+    inc_zpx => "INC", ZeroPageX;
+    lda_imm => "LDA", Immediate;
+    lda_zp => "LDA", ZeroPage;
+    lda_zpx => "LDA", ZeroPageX;
+    lda_indx => "LDA", IndexedIndirect;
+    lda_indy => "LDA", IndirectIndexed;
+    ldx_imm => "LDX", Immediate;
+    ldx_zp => "LDX", ZeroPage;
+    ldx_zpy => "LDX", ZeroPageY;
+    ldy_imm => "LDY", Immediate;
+    ldy_zp => "LDY", ZeroPage;
+    ldy_zpx => "LDY", ZeroPageX;
+    lsr_zp => "LSR", ZeroPage;
+    lsr_zpx => "LSR", ZeroPageX;
+    ora_imm => "ORA", Immediate;
+    ora_zp => "ORA", ZeroPage;
+    ora_zpx => "ORA", ZeroPageX;
+    ora_indx => "ORA", IndexedIndirect;
+    ora_indy => "ORA", IndirectIndexed;
+    rol_zp => "ROL", ZeroPage;
+    rol_zpx => "ROL", ZeroPageX;
+    ror_zp => "ROR", ZeroPage;
+    ror_zpx => "ROR", ZeroPageX;
+    sbc_imm => "SBC", Immediate;
+    sbc_zp => "SBC", ZeroPage;
+    sbc_zpx => "SBC", ZeroPageX;
+    sbc_indx => "SBC", IndexedIndirect;
+    sbc_indy => "SBC", IndirectIndexed;
+    sta_zp => "STA", ZeroPage;
+    sta_zpx => "STA", ZeroPageX;
+    sta_indx => "STA", IndexedIndirect;
+    sta_indy => "STA", IndirectIndexed;
+    stx_zp => "STX", ZeroPage;
+    stx_zpy => "STX", ZeroPageY;
+    sty_zp => "STY", ZeroPage;
+    sty_zpx => "STY", ZeroPageX;
+}
+
+word_instructions! {
+    adc_abs => "ADC", Absolute;
+    adc_absx => "ADC", AbsoluteX;
+    adc_absy => "ADC", AbsoluteY;
+    and_abs => "AND", Absolute;
+    and_absx => "AND", AbsoluteX;
+    and_absy => "AND", AbsoluteY;
+    asl_abs => "ASL", Absolute;
+    asl_absx => "ASL", AbsoluteX;
+    bit_abs => "BIT", Absolute;
+    cmp_abs => "CMP", Absolute;
+    cmp_absx => "CMP", AbsoluteX;
+    cmp_absy => "CMP", AbsoluteY;
+    cpx_abs => "CPX", Absolute;
+    cpy_abs => "CPY", Absolute;
+    dec_abs => "DEC", Absolute;
+    dec_absx => "DEC", AbsoluteX;
+    eor_abs => "EOR", Absolute;
+    eor_absx => "EOR", AbsoluteX;
+    eor_absy => "EOR", AbsoluteY;
+    inc_abs => "INC", Absolute;
+    inc_absx => "INC", AbsoluteX;
+    jmp_abs => "JMP", Absolute;
+    jmp_ind => "JMP", Indirect;
+    jsr_abs => "JSR", Absolute;
+    lda_abs => "LDA", Absolute;
+    lda_absx => "LDA", AbsoluteX;
+    lda_absy => "LDA", AbsoluteY;
+    ldx_abs => "LDX", Absolute;
+    ldx_absy => "LDX", AbsoluteY;
+    ldy_abs => "LDY", Absolute;
+    ldy_absx => "LDY", AbsoluteX;
+    lsr_abs => "LSR", Absolute;
+    lsr_absx => "LSR", AbsoluteX;
+    ora_abs => "ORA", Absolute;
+    ora_absx => "ORA", AbsoluteX;
+    ora_absy => "ORA", AbsoluteY;
+    rol_abs => "ROL", Absolute;
+    rol_absx => "ROL", AbsoluteX;
+    ror_abs => "ROR", Absolute;
+    ror_absx => "ROR", AbsoluteX;
+    sbc_abs => "SBC", Absolute;
+    sbc_absx => "SBC", AbsoluteX;
+    sbc_absy => "SBC", AbsoluteY;
+    sta_abs => "STA", Absolute;
+    sta_absx => "STA", AbsoluteX;
+    sta_absy => "STA", AbsoluteY;
+    stx_abs => "STX", Absolute;
+    sty_abs => "STY", Absolute;
+}
+
+branch_instructions! {
+    bcc => "BCC";
+    bcs => "BCS";
+    beq => "BEQ";
+    bmi => "BMI";
+    bne => "BNE";
+    bpl => "BPL";
+    bvc => "BVC";
+    bvs => "BVS";
+}
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::asm::Program;
+    use crate::bus::ram::Ram;
+    use crate::bus::{MainBus, SharedBus};
+    use crate::cpu::{AddressingMode, CpuBuilder, CpuModel};
+
+    #[test]
+    fn builds_the_same_bytes_as_the_assembler() {
+        let program = Program::at(0x0200)
+            .ldx_imm(0x03)
+            .label("loop")
+            .lda_absx(0x1000)
+            .sta_zpx(0x10)
+            .dex()
+            .bpl("loop")
+            .jsr("done")
+            .asl_acc()
+            .label("done")
+            .rts();
+
+        let assembly = crate::asm::assemble(
+            ".org $0200\nldx #3\nloop: lda $1000,x\nsta $10,x\ndex\nbpl loop\njsr done\nasl a\ndone: rts",
+        )
+        .unwrap();
+
+        assert_eq!(program.assemble(), assembly.segments[0].bytes);
+    }
+
+    #[test]
+    fn installs_and_runs() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        Program::at(0x0200)
+            .lda_imm(0x00)
+            .ldx_imm(0x05)
+            .label("loop")
+            .clc()
+            .adc_imm(0x02)
+            .dex()
+            .bne("loop")
+            .sta_abs(0x8000)
+            .brk()
+            .install(&mut bus);
+
+        let bus = SharedBus::new(bus);
+        let mut cpu = CpuBuilder::new().bus(bus.clone()).build();
+        cpu.pc.set(0x0200);
+        while cpu.pc.get() != 0x020D {
+            cpu.step();
+        }
+
+        assert_eq!(bus.borrow().read(0x8000), 10);
+    }
+
+    #[test]
+    fn uses_the_model_instruction_set() {
+        let program = Program::at(0).model(CpuModel::Wdc65C02).instruction("STZ", AddressingMode::ZeroPage, 0x10);
+
+        assert_eq!(program.assemble(), [0x64, 0x10]);
+    }
+
+    #[test]
+    #[should_panic(expected = "label nowhere is used but never placed")]
+    fn panics_on_missing_label() {
+        Program::at(0).jmp("nowhere").assemble();
+    }
+}
@@ -0,0 +1,104 @@
+//! Plays samples through the host's sound card.
+//!
+//! Sound devices such as `ToneGenerator` render samples as the CPU runs, and
+//! an [`AudioOutput`] plays them. The host moves samples from one to the
+//! other as it runs the machine:
+//!
+//! ```no_run
+//! use butterflyrs::audio::AudioOutput;
+//! use butterflyrs::bus::sound::ToneGenerator;
+//!
+//! let output = AudioOutput::new().unwrap();
+//! let mut tone = ToneGenerator::new(0xD400, 1_000_000, output.sample_rate());
+//! tone.advance(16_667);
+//! output.push(&tone.take_samples());
+//! ```
+
+use std::collections::VecDeque;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+/// A stream to the host's default sound output.
+///
+/// Samples are queued with `push()` and played in order. If the queue runs
+/// dry, silence is played until more samples arrive.
+pub struct AudioOutput {
+    /// The samples waiting to be played.
+    queue: Arc<Mutex<VecDeque<f32>>>,
+
+    /// The rate the output plays samples at, in Hz.
+    sample_rate: u32,
+
+    /// The stream, which stops playing when it is dropped.
+    _stream: cpal::Stream,
+}
+
+impl AudioOutput {
+    /// Opens the host's default sound output.
+    ///
+    /// # Returns
+    ///
+    /// The output, playing silence, or a message saying why it couldn't be
+    /// opened. Only outputs that take 32-bit float samples are supported.
+    pub fn new() -> Result<AudioOutput, String> {
+        let device = cpal::default_host().default_output_device().ok_or("There is no sound output")?;
+        let config = device.default_output_config().map_err(|error| error.to_string())?;
+        if config.sample_format() != cpal::SampleFormat::F32 {
+            return Err(format!("Unsupported sample format: {}", config.sample_format()));
+        }
+
+        let config: cpal::StreamConfig = config.into();
+        let channels = config.channels as usize;
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let source = queue.clone();
+
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut queue = source.lock().unwrap_or_else(PoisonError::into_inner);
+
+                    // Every channel plays the same sample
+                    for frame in data.chunks_mut(channels) {
+                        frame.fill(queue.pop_front().unwrap_or(0.0));
+                    }
+                },
+                |error| tracing::error!("Audio error: {}", error),
+                None,
+            )
+            .map_err(|error| error.to_string())?;
+        stream.play().map_err(|error| error.to_string())?;
+
+        Ok(AudioOutput {
+            queue,
+            sample_rate: config.sample_rate.0,
+            _stream: stream,
+        })
+    }
+
+    /// Returns the rate the output plays samples at, in Hz.
+    ///
+    /// Sound devices should render at this rate, or the sound plays at the wrong pitch.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Queues samples to be played.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - The samples, from -1.0 to 1.0.
+    pub fn push(&self, samples: &[f32]) {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner).extend(samples);
+    }
+
+    /// Returns the number of samples waiting to be played.
+    ///
+    /// A host running the machine faster than real time can use this to
+    /// wait for the queue to drain.
+    pub fn queued(&self) -> usize {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
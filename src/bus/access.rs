@@ -0,0 +1,57 @@
+use alloc::boxed::Box;
+use core::fmt::Display;
+
+/// Whether a bus access was a read or a write.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AccessKind {
+    /// A byte was read from the bus.
+    Read,
+
+    /// A byte was written to the bus.
+    Write,
+}
+
+/// Who made a bus access.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AccessOrigin {
+    /// The CPU, through `MainBus::read()` and `MainBus::write()`.
+    Cpu,
+
+    /// A device acting as a bus master, such as a DMA controller, through
+    /// `MainBus::device_read()` and `MainBus::device_write()`.
+    Device,
+}
+
+impl Display for AccessOrigin {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AccessOrigin::Cpu => write!(f, "CPU"),
+            AccessOrigin::Device => write!(f, "Device"),
+        }
+    }
+}
+
+/// A function called for every traced bus access.
+pub type BusTracer = Box<dyn FnMut(AccessOrigin, BusAccess) + Send>;
+
+/// A single read or write on the bus.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BusAccess {
+    /// Whether the access was a read or a write.
+    pub kind: AccessKind,
+
+    /// The address that was accessed.
+    pub address: u16,
+
+    /// The byte that was read or written.
+    pub value: u8,
+}
+
+impl Display for BusAccess {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.kind {
+            AccessKind::Read => write!(f, "R {:04X} -> {:02X}", self.address, self.value),
+            AccessKind::Write => write!(f, "W {:04X} <- {:02X}", self.address, self.value),
+        }
+    }
+}
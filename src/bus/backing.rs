@@ -0,0 +1,81 @@
+use std::io;
+use std::path::Path;
+use std::vec::Vec;
+
+/// Reads the contents of a device from its backing file.
+///
+/// # Arguments
+///
+/// * `path` - The backing file.
+/// * `size` - The size of the device in bytes.
+/// * `fill` - The value of any bytes the file doesn't cover.
+///
+/// # Returns
+///
+/// The contents, `size` bytes long. A missing file gives contents that are
+/// all `fill`, so a device can be created before its file exists. A file
+/// longer than the device is an `InvalidData` error.
+pub(crate) fn read_image(path: &Path, size: usize, fill: u8) -> io::Result<Vec<u8>> {
+    let mut data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(error) => return Err(error),
+    };
+
+    if data.len() > size {
+        let message = std::format!("{} is {} bytes, but the device holds {}", path.display(), data.len(), size);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    data.resize(size, fill);
+    Ok(data)
+}
+
+/// Writes the contents of a device to its backing file.
+///
+/// The contents are written to a temporary file that then replaces the
+/// backing file, so an interrupted write doesn't lose what was saved before.
+///
+/// # Arguments
+///
+/// * `path` - The backing file.
+/// * `data` - The contents of the device.
+pub(crate) fn write_image(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut temporary = std::ffi::OsString::from(path.as_os_str());
+    temporary.push(".tmp");
+    std::fs::write(&temporary, data)?;
+    std::fs::rename(&temporary, path)
+}
+
+/// Writes the contents of a device to its backing file, if they changed since
+/// they were last written.
+///
+/// # Arguments
+///
+/// * `path` - The backing file, if the device has one.
+/// * `data` - The contents of the device.
+/// * `dirty` - Whether the contents changed. It is cleared once they are written.
+///
+/// # Returns
+///
+/// `Ok(())` if the contents were written, or didn't need to be.
+pub(crate) fn flush(path: Option<&Path>, data: &[u8], dirty: &mut bool) -> io::Result<()> {
+    if let (true, Some(path)) = (*dirty, path) {
+        write_image(path, data)?;
+        *dirty = false;
+    }
+    Ok(())
+}
+
+/// Writes the contents of a device that is being dropped to its backing file.
+///
+/// There's no one to report an error to by then, so the contents are saved on
+/// a best-effort basis.
+///
+/// # Arguments
+///
+/// * `path` - The backing file, if the device has one.
+/// * `data` - The contents of the device.
+/// * `dirty` - Whether the contents changed since they were last written.
+pub(crate) fn flush_on_drop(path: Option<&Path>, data: &[u8], dirty: &mut bool) {
+    let _ = flush(path, data, dirty);
+}
@@ -1,4 +1,10 @@
-use crate::bus::BusDevice;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::bus::{BusDevice, SnapshotError};
+
+/// A function called with every value the Blink8 device displays.
+pub type Blink8Output = Box<dyn FnMut(u8) + Send>;
 
 /// Represents a Blink8 device.
 ///
@@ -19,6 +25,9 @@ pub struct Blink8 {
     ///
     /// This address is used to identify the device on the bus.
     pub end: u16,
+
+    /// Where displayed values go instead of `tracing` events, if anywhere.
+    output: Option<Blink8Output>,
 }
 
 impl Blink8 {
@@ -35,8 +44,36 @@ impl Blink8 {
             enabled: false,
             start: 0x8000,
             end: 0x8002,
+            output: None,
         }
     }
+
+    /// Sends displayed values to a function instead of reporting them as `tracing` events.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Called with every value written to the start address while
+    ///   the device is enabled.
+    pub fn set_output(&mut self, output: Blink8Output) {
+        self.output = Some(output);
+    }
+
+    /// Reports a displayed value as an info event, as a row of bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to report.
+    fn report(&self, value: u8) {
+        // The bit values go in reverse order, bit 0 first
+        let bits: String = (0..8).map(|i| if value & (1 << i) != 0 { '1' } else { '0' }).collect();
+        tracing::info!("{} {}", self.name(), bits);
+    }
+}
+
+impl Default for Blink8 {
+    fn default() -> Blink8 {
+        Blink8::new()
+    }
 }
 
 impl BusDevice for Blink8 {
@@ -49,6 +86,7 @@ impl BusDevice for Blink8 {
     /// # Returns
     ///
     /// The data read from the specified address.
+    #[allow(unused_variables)]
     fn read(&self, address: u16) -> u8 {
         // Blink8 is a write-only device, so we always return 0xFF
         0xFF
@@ -66,23 +104,13 @@ impl BusDevice for Blink8 {
             self.enabled = true;
         }
 
-        // If we wrote to 8000 and the blink8 device is enabled, print the value
-        if address == self.start && self.enabled {
-            // Print the Blink8 prefix
-            print!("{}", self.name() + " ");
-
-            // Print the bit values in reverse order
-            for i in 0..8 {
-                // Check if the i-th bit is set in the value
-                if value & (1 << i) != 0 {
-                    print!("1");
-                } else {
-                    print!("0");
-                }
-            }
-
-            // Print a newline character to end the line
-            println!();
+        // If we wrote to 8000 and the blink8 device is enabled, display the value
+        if address != self.start || !self.enabled {
+            return;
+        }
+        match self.output.as_mut() {
+            Some(output) => output(value),
+            None => self.report(value),
         }
     }
 
@@ -106,6 +134,8 @@ impl BusDevice for Blink8 {
     /// # Examples
     ///
     /// ```
+    /// # use butterflyrs::bus::BusDevice;
+    /// # use butterflyrs::bus::blink8::Blink8;
     /// let mut blink8 = Blink8::new();
     /// blink8.reset();
     /// ```
@@ -134,6 +164,8 @@ impl BusDevice for Blink8 {
     /// # Examples
     ///
     /// ```
+    /// # use butterflyrs::bus::BusDevice;
+    /// # use butterflyrs::bus::blink8::Blink8;
     /// let blink8 = Blink8::new();
     /// assert_eq!(blink8.start_address(), 0x8000);
     /// ```
@@ -151,6 +183,8 @@ impl BusDevice for Blink8 {
     /// # Examples
     ///
     /// ```
+    /// # use butterflyrs::bus::BusDevice;
+    /// # use butterflyrs::bus::blink8::Blink8;
     /// let blink8 = Blink8::new();
     /// assert_eq!(blink8.end_address(), 0x8002);
     /// ```
@@ -158,4 +192,32 @@ impl BusDevice for Blink8 {
         // Returns the end address of the Blink8 device.
         self.end
     }
+
+    /// Saves the enable flag of the Blink8 device.
+    ///
+    /// # Returns
+    ///
+    /// A single byte: 1 if the device is enabled, 0 otherwise.
+    fn save(&self) -> Vec<u8> {
+        vec![self.enabled as u8]
+    }
+
+    /// Restores the enable flag of the Blink8 device.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The state returned by `save()`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the state was restored, or an error if it isn't a single byte.
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        match data {
+            [enabled] => {
+                self.enabled = *enabled != 0;
+                Ok(())
+            }
+            _ => Err(SnapshotError::InvalidData(self.name())),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,440 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The size of a sector in bytes.
+pub const SECTOR_SIZE: usize = 512;
+
+/// The offset of the data register, which moves a transfer along one byte at a time.
+pub const DATA: u16 = 0;
+
+/// The offset of the sector count register.
+pub const COUNT: u16 = 1;
+
+/// The offset of the low byte of the four byte LBA, the number of the first
+/// sector to transfer. The other bytes follow it, from least significant.
+pub const LBA: u16 = 2;
+
+/// The offset of the command register when written, and the status register when read.
+pub const COMMAND: u16 = 6;
+
+/// The command that reads sectors into the data register.
+pub const READ_SECTORS: u8 = 0x20;
+
+/// The command that writes sectors from the data register.
+pub const WRITE_SECTORS: u8 = 0x30;
+
+/// The status bit that is set while the device has a disk image.
+pub const READY: u8 = 0x40;
+
+/// The status bit that is set while the data register has data to read or room to write.
+pub const DATA_REQUEST: u8 = 0x08;
+
+/// The status bit that is set when the last command failed.
+pub const ERROR: u8 = 0x01;
+
+/// Where the sectors of a disk are kept.
+enum Image {
+    /// In memory, lost when the device is dropped.
+    Memory(Vec<u8>),
+
+    /// In a disk image file on the host, read and written a sector at a time.
+    #[cfg(feature = "std")]
+    File(std::fs::File),
+}
+
+/// The transfer the data register is part of.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Transfer {
+    /// No transfer, so the data register reads 0 and ignores writes.
+    Idle,
+
+    /// Sectors were read into the buffer, and are being read from the data register.
+    Reading,
+
+    /// Sectors are being written to the buffer, and go to the disk once it is full.
+    Writing {
+        /// The first sector to write.
+        lba: u32,
+
+        /// The number of bytes to write.
+        length: usize,
+    },
+}
+
+/// A block storage device in the style of an SD card or CompactFlash card.
+///
+/// The disk is a run of 512 byte sectors, moved to and from the machine
+/// through a handful of registers modelled on the ATA task file:
+///
+/// * `DATA` - Reads or writes the next byte of a transfer.
+/// * `COUNT` - The number of sectors to transfer, with 0 meaning 256.
+/// * `LBA` to `LBA + 3` - The number of the first sector, least significant byte first.
+/// * `COMMAND` - Writing `READ_SECTORS` or `WRITE_SECTORS` starts a transfer.
+///   Reading it gives the status: `READY`, `DATA_REQUEST` while the transfer
+///   has bytes left, and `ERROR` if the last command failed.
+///
+/// Commands complete at once, so there is no busy bit to wait on. A read
+/// command fills the transfer with the sectors, and a write command writes
+/// them to the disk once the last byte has been written to `DATA`. A command
+/// for sectors past the end of the disk sets `ERROR` and transfers nothing.
+///
+/// Without the `std` feature the disk is kept in memory, created with
+/// `new()`. With it, `open()` uses a disk image file on the host instead.
+/// The disk's contents aren't part of a snapshot, only the registers.
+pub struct BlockStorage {
+    /// The first address of the device's registers.
+    pub start: u16,
+
+    /// Where the sectors are kept.
+    image: Image,
+
+    /// The number of sectors on the disk.
+    sectors: u32,
+
+    /// The sector count register.
+    count: u8,
+
+    /// The LBA registers, as one number.
+    lba: u32,
+
+    /// Whether the last command failed.
+    error: bool,
+
+    /// The transfer the data register is part of.
+    transfer: Transfer,
+
+    /// The bytes being transferred.
+    buffer: Vec<u8>,
+
+    /// The position of the next byte read from `buffer`. Reading `DATA`
+    /// moves it even though reads don't borrow the device mutably.
+    position: Cell<usize>,
+}
+
+impl BlockStorage {
+    /// Creates a disk in memory, with every byte 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the device's registers.
+    /// * `sectors` - The size of the disk in sectors.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `BlockStorage` struct.
+    pub fn new(start: u16, sectors: u32) -> BlockStorage {
+        BlockStorage::with_image(start, Image::Memory(vec![0x00; sectors as usize * SECTOR_SIZE]), sectors)
+    }
+
+    /// Creates a device using a disk image file on the host.
+    ///
+    /// Sectors are read from the file when the machine reads them, and
+    /// written to it as soon as the machine writes them.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the device's registers.
+    /// * `path` - The disk image, which must already exist.
+    ///
+    /// # Returns
+    ///
+    /// The device, or the error opening the file. A file whose size isn't a
+    /// whole number of sectors is an `InvalidData` error.
+    #[cfg(feature = "std")]
+    pub fn open(start: u16, path: impl AsRef<Path>) -> std::io::Result<BlockStorage> {
+        let path = path.as_ref();
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+
+        let size = file.metadata()?.len();
+        let sectors = size / SECTOR_SIZE as u64;
+        if size % SECTOR_SIZE as u64 != 0 || sectors > u32::MAX as u64 {
+            let message = std::format!("{} is {} bytes, which isn't a usable number of sectors", path.display(), size);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+        }
+        Ok(BlockStorage::with_image(start, Image::File(file), sectors as u32))
+    }
+
+    /// Creates a device with its registers cleared.
+    fn with_image(start: u16, image: Image, sectors: u32) -> BlockStorage {
+        BlockStorage {
+            start,
+            image,
+            sectors,
+            count: 0,
+            lba: 0,
+            error: false,
+            transfer: Transfer::Idle,
+            buffer: Vec::new(),
+            position: Cell::new(0),
+        }
+    }
+
+    /// Returns the size of the disk in sectors.
+    pub fn sectors(&self) -> u32 {
+        self.sectors
+    }
+
+    /// Returns the value of the status register.
+    fn status(&self) -> u8 {
+        let request = match self.transfer {
+            Transfer::Reading => self.position.get() < self.buffer.len(),
+            Transfer::Writing { .. } => true,
+            Transfer::Idle => false,
+        };
+        READY | if request { DATA_REQUEST } else { 0 } | if self.error { ERROR } else { 0 }
+    }
+
+    /// Returns the next byte of a read, if there is one.
+    fn next_byte(&self) -> Option<u8> {
+        match self.transfer {
+            Transfer::Reading => self.buffer.get(self.position.get()).copied(),
+            _ => None,
+        }
+    }
+
+    /// Starts the transfer for a command.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The value written to the command register.
+    fn command(&mut self, command: u8) {
+        let count = if self.count == 0 { 256 } else { self.count as u32 };
+        self.transfer = Transfer::Idle;
+        self.buffer.clear();
+        self.position.set(0);
+
+        // Commands for sectors past the end of the disk, or that we don't know, fail
+        let fits = self.lba.checked_add(count).is_some_and(|end| end <= self.sectors);
+        self.error = !fits || !matches!(command, READ_SECTORS | WRITE_SECTORS);
+        if self.error {
+            return;
+        }
+
+        if command == READ_SECTORS {
+            self.buffer = vec![0x00; count as usize * SECTOR_SIZE];
+            self.error = !self.read_sectors(self.lba);
+            if !self.error {
+                self.transfer = Transfer::Reading;
+            }
+        } else {
+            self.transfer = Transfer::Writing {
+                lba: self.lba,
+                length: count as usize * SECTOR_SIZE,
+            };
+        }
+    }
+
+    /// Reads sectors from the disk into the buffer, filling it.
+    ///
+    /// # Arguments
+    ///
+    /// * `lba` - The first sector to read.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the sectors were read.
+    fn read_sectors(&mut self, lba: u32) -> bool {
+        let offset = lba as usize * SECTOR_SIZE;
+        let length = self.buffer.len();
+        match &mut self.image {
+            Image::Memory(data) => {
+                self.buffer.copy_from_slice(&data[offset..offset + length]);
+                true
+            }
+            #[cfg(feature = "std")]
+            Image::File(file) => {
+                use std::io::{Read, Seek, SeekFrom};
+                file.seek(SeekFrom::Start(offset as u64)).and_then(|_| file.read_exact(&mut self.buffer)).is_ok()
+            }
+        }
+    }
+
+    /// Writes the buffer to the disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `lba` - The first sector to write.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the sectors were written.
+    fn write_sectors(&mut self, lba: u32) -> bool {
+        let offset = lba as usize * SECTOR_SIZE;
+        match &mut self.image {
+            Image::Memory(data) => {
+                data[offset..offset + self.buffer.len()].copy_from_slice(&self.buffer);
+                true
+            }
+            #[cfg(feature = "std")]
+            Image::File(file) => {
+                use std::io::{Seek, SeekFrom, Write};
+                file.seek(SeekFrom::Start(offset as u64)).and_then(|_| file.write_all(&self.buffer)).is_ok()
+            }
+        }
+    }
+}
+
+impl BusDevice for BlockStorage {
+    fn read(&self, address: u16) -> u8 {
+        match address - self.start {
+            DATA => {
+                let value = self.next_byte();
+                if value.is_some() {
+                    self.position.set(self.position.get() + 1);
+                }
+                value.unwrap_or(0x00)
+            }
+            COUNT => self.count,
+            COMMAND => self.status(),
+            offset => self.lba.to_le_bytes()[(offset - LBA) as usize],
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address - self.start {
+            DATA => {
+                if let Transfer::Writing { lba, length } = self.transfer {
+                    self.buffer.push(value);
+                    if self.buffer.len() == length {
+                        self.error = !self.write_sectors(lba);
+                        self.transfer = Transfer::Idle;
+                    }
+                }
+            }
+            COUNT => self.count = value,
+            COMMAND => self.command(value),
+            offset => {
+                let mut lba = self.lba.to_le_bytes();
+                lba[(offset - LBA) as usize] = value;
+                self.lba = u32::from_le_bytes(lba);
+            }
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        // Shows the next byte of a read without moving on
+        match address - self.start {
+            DATA => self.next_byte().unwrap_or(0x00),
+            _ => self.read(address),
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // Abandons any transfer, but the disk keeps what was written to it
+        self.count = 0;
+        self.lba = 0;
+        self.error = false;
+        self.transfer = Transfer::Idle;
+        self.buffer.clear();
+        self.position.set(0);
+    }
+
+    fn name(&self) -> String {
+        String::from("Block storage")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + COMMAND
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut data = vec![self.count];
+        data.extend_from_slice(&self.lba.to_le_bytes());
+        data.push(self.error as u8);
+        data
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let [count, lba @ .., error]: [u8; 6] = data.try_into().map_err(|_| SnapshotError::InvalidData(self.name()))?;
+
+        // A transfer in progress isn't saved, so it is abandoned
+        self.reset();
+        self.count = count;
+        self.lba = u32::from_le_bytes(lba);
+        self.error = error != 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::bus::block::{BlockStorage, COMMAND, COUNT, DATA_REQUEST, ERROR, LBA, READY, READ_SECTORS, SECTOR_SIZE, WRITE_SECTORS};
+    use crate::bus::BusDevice;
+
+    /// Starts a command for sectors on a device at $D100.
+    fn command(disk: &mut BlockStorage, command: u8, lba: u32, count: u8) {
+        for (offset, value) in lba.to_le_bytes().into_iter().enumerate() {
+            disk.write(0xD100 + LBA + offset as u16, value);
+        }
+        disk.write(0xD100 + COUNT, count);
+        disk.write(0xD100 + COMMAND, command);
+    }
+
+    /// Writes two sectors counting up from 0 at sector 1, then reads them back.
+    fn round_trip(disk: &mut BlockStorage) {
+        command(disk, WRITE_SECTORS, 1, 2);
+        assert_eq!(disk.read(0xD100 + COMMAND), READY | DATA_REQUEST);
+        for byte in 0..2 * SECTOR_SIZE {
+            disk.write(0xD100, byte as u8);
+        }
+        assert_eq!(disk.read(0xD100 + COMMAND), READY);
+
+        // Read from sector 2, the second one written
+        command(disk, READ_SECTORS, 2, 1);
+        assert_eq!(disk.peek(0xD100), 0x00);
+        let sector: Vec<u8> = (0..SECTOR_SIZE).map(|_| disk.read(0xD100)).collect();
+        assert!(sector.iter().enumerate().all(|(index, &byte)| byte == (SECTOR_SIZE + index) as u8));
+        assert_eq!(disk.read(0xD100 + COMMAND), READY);
+    }
+
+    #[test]
+    fn reads_and_writes_sectors() {
+        let mut disk = BlockStorage::new(0xD100, 4);
+        round_trip(&mut disk);
+
+        // Sector 0 wasn't touched
+        command(&mut disk, READ_SECTORS, 0, 1);
+        assert_eq!(disk.read(0xD100 + 2), 0x00);
+        assert!((0..SECTOR_SIZE).all(|_| disk.read(0xD100) == 0x00));
+
+        // Sectors 3 and 4 run off the end of the disk
+        command(&mut disk, READ_SECTORS, 3, 2);
+        assert_eq!(disk.read(0xD100 + COMMAND), READY | ERROR);
+        assert_eq!(disk.read(0xD100), 0x00);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn uses_a_disk_image_file() {
+        let path = std::env::temp_dir().join(std::format!("butterflyrs-disk-{}.img", std::process::id()));
+        std::fs::write(&path, [0xE5; 4 * SECTOR_SIZE]).unwrap();
+
+        let mut disk = BlockStorage::open(0xD100, &path).unwrap();
+        assert_eq!(disk.sectors(), 4);
+        round_trip(&mut disk);
+        drop(disk);
+
+        let image = std::fs::read(&path).unwrap();
+        assert_eq!((image[0], image[SECTOR_SIZE], image[3 * SECTOR_SIZE - 1]), (0xE5, 0x00, 0xFF));
+
+        // Images have to be made of whole sectors
+        std::fs::write(&path, [0x00; 100]).unwrap();
+        assert!(BlockStorage::open(0xD100, &path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
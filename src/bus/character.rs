@@ -0,0 +1,229 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::framebuffer::rgb32_to_rgba;
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The width and height of a character cell in pixels.
+pub const CELL_SIZE: usize = 8;
+
+/// The size of a full character generator ROM: 256 characters of 8 bytes each.
+pub const CHARSET_SIZE: usize = 256 * CELL_SIZE;
+
+/// A text screen drawn from screen RAM and a character generator ROM.
+///
+/// Each byte of screen RAM holds the code of the character in one cell, row
+/// by row, like the 40x25 screens of the Commodore PET. The picture of each
+/// code comes from the character generator: 8 bytes per character, one per
+/// row of pixels from the top, with the most significant bit on the left.
+///
+/// Only screen RAM is on the bus. The character generator is loaded by the
+/// host with `load_charset()`, and is blank until then.
+///
+/// The screen is rendered with `to_rgba()` or `to_rgb32()` at 8x8 pixels per
+/// cell, which suits displaying it or comparing it with a saved image in a
+/// test. `text()` gives the screen as text, for simpler checks.
+pub struct CharacterDisplay {
+    /// The character codes in screen RAM, row by row.
+    pub screen: Vec<u8>,
+
+    /// The address of the top left cell.
+    pub start: u16,
+
+    /// The colour of set pixels, as `0x00RRGGBB`.
+    pub foreground: u32,
+
+    /// The colour of clear pixels, as `0x00RRGGBB`.
+    pub background: u32,
+
+    /// The character generator ROM.
+    charset: Vec<u8>,
+
+    /// The number of cells in each row.
+    columns: usize,
+
+    /// The number of rows.
+    rows: usize,
+}
+
+impl CharacterDisplay {
+    /// Creates a screen full of spaces, green on black.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the top left cell.
+    /// * `columns` - The number of cells in each row.
+    /// * `rows` - The number of rows.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `CharacterDisplay` struct.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the screen is empty or doesn't fit between `start` and the top of memory.
+    pub fn new(start: u16, columns: usize, rows: usize) -> CharacterDisplay {
+        let size = columns * rows;
+        assert!(size > 0 && start as usize + size <= 0x10000, "a {}x{} screen doesn't fit at {:04X}", columns, rows, start);
+
+        CharacterDisplay {
+            screen: vec![b' '; size],
+            start,
+            foreground: 0x33FF33,
+            background: 0x000000,
+            charset: vec![0x00; CHARSET_SIZE],
+            columns,
+            rows,
+        }
+    }
+
+    /// Loads the character generator ROM.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - 8 bytes for each character, starting from character 0. A ROM
+    ///   with fewer than 256 characters leaves the rest blank, and anything
+    ///   after the 256th is ignored.
+    pub fn load_charset(&mut self, data: &[u8]) {
+        let length = data.len().min(CHARSET_SIZE);
+        self.charset.fill(0x00);
+        self.charset[..length].copy_from_slice(&data[..length]);
+    }
+
+    /// Returns the number of cells in each row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the width of the rendered screen in pixels.
+    pub fn pixel_width(&self) -> usize {
+        self.columns * CELL_SIZE
+    }
+
+    /// Returns the height of the rendered screen in pixels.
+    pub fn pixel_height(&self) -> usize {
+        self.rows * CELL_SIZE
+    }
+
+    /// Returns the screen as text, one line per row.
+    ///
+    /// Printable ASCII codes are shown as themselves and anything else as `.`.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity((self.columns + 1) * self.rows);
+        for row in self.screen.chunks(self.columns) {
+            text.extend(row.iter().map(|&code| if (0x20..0x7F).contains(&code) { code as char } else { '.' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Renders the screen, returning the colour of every pixel as `0x00RRGGBB`, row by row.
+    pub fn to_rgb32(&self) -> Vec<u32> {
+        let width = self.pixel_width();
+        let mut pixels = vec![self.background; width * self.pixel_height()];
+
+        for (cell, &code) in self.screen.iter().enumerate() {
+            let (column, row) = (cell % self.columns, cell / self.columns);
+            let glyph = &self.charset[code as usize * CELL_SIZE..][..CELL_SIZE];
+
+            for (line, bits) in glyph.iter().enumerate() {
+                let offset = (row * CELL_SIZE + line) * width + column * CELL_SIZE;
+                for (x, pixel) in pixels[offset..offset + CELL_SIZE].iter_mut().enumerate() {
+                    if bits & (0x80 >> x) != 0 {
+                        *pixel = self.foreground;
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Renders the screen, returning the colour of every pixel as four bytes
+    /// of red, green, blue and alpha, row by row.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        rgb32_to_rgba(&self.to_rgb32())
+    }
+}
+
+impl BusDevice for CharacterDisplay {
+    fn read(&self, address: u16) -> u8 {
+        self.screen[(address - self.start) as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.screen[(address - self.start) as usize] = value;
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        // The text stays on the screen through a reset
+    }
+
+    fn power_on(&mut self) {
+        self.screen.fill(b' ');
+    }
+
+    fn name(&self) -> String {
+        String::from("Character display")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + (self.screen.len() - 1) as u16
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.screen.clone()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        // The saved screen RAM must fill the screen exactly
+        if data.len() != self.screen.len() {
+            return Err(SnapshotError::InvalidData(self.name()));
+        }
+        self.screen.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::bus::character::{CharacterDisplay, CELL_SIZE};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn renders_cells_from_the_character_generator() {
+        let mut display = CharacterDisplay::new(0x8000, 2, 2);
+        display.foreground = 0xFFFFFF;
+
+        // Character 1 is a diagonal line from the top left
+        let mut charset = vec![0x00; 2 * CELL_SIZE];
+        for line in 0..CELL_SIZE {
+            charset[CELL_SIZE + line] = 0x80 >> line;
+        }
+        display.load_charset(&charset);
+        display.write(0x8003, 0x01);
+        display.write(0x8000, b'A');
+
+        assert_eq!(display.text(), "A \n .\n");
+
+        // The set pixels are exactly the diagonal of the bottom right cell
+        let set: Vec<usize> = (0..256).filter(|&pixel| display.to_rgb32()[pixel] == 0xFFFFFF).collect();
+        let diagonal: Vec<usize> = (0..8).map(|line| (8 + line) * 16 + 8 + line).collect();
+        assert_eq!(set, diagonal);
+        assert_eq!(&display.to_rgba()[..4], [0x00, 0x00, 0x00, 0xFF]);
+    }
+}
@@ -0,0 +1,107 @@
+/// How fast a device is clocked, as a ratio of the CPU clock.
+///
+/// A device reports its rate with `BusDevice::clock_rate()`, and the bus
+/// scales the CPU cycles it ticks the device by to the device's own clock.
+/// A rate that doesn't divide the cycles evenly carries the fraction over to
+/// the next tick, so a device at a third of the CPU clock sees exactly one
+/// cycle for every three, however the CPU's cycles are split up.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::ClockRate;
+///
+/// // A peripheral at CPU/16 and a video chip at three times the CPU clock
+/// let slow = ClockRate::divided(16);
+/// let video = ClockRate::multiplied(3);
+///
+/// let mut phase = 0;
+/// assert_eq!(slow.scale(10, &mut phase), 0);
+/// assert_eq!(slow.scale(10, &mut phase), 1);
+/// assert_eq!(video.scale(10, &mut 0), 30);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ClockRate {
+    /// The device cycles in every `divisor` CPU cycles.
+    multiplier: u32,
+
+    /// The CPU cycles in every `multiplier` device cycles.
+    divisor: u32,
+}
+
+impl ClockRate {
+    /// The CPU's own clock, which is what devices run at unless they say otherwise.
+    pub const CPU: ClockRate = ClockRate { multiplier: 1, divisor: 1 };
+
+    /// Creates a rate of `multiplier` device cycles for every `divisor` CPU cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `multiplier` - The device cycles.
+    /// * `divisor` - The CPU cycles they take.
+    ///
+    /// # Returns
+    ///
+    /// The rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either number is 0.
+    pub const fn ratio(multiplier: u32, divisor: u32) -> ClockRate {
+        assert!(multiplier != 0 && divisor != 0, "a clock rate must be a ratio of two non-zero numbers");
+        ClockRate { multiplier, divisor }
+    }
+
+    /// Creates a rate slower than the CPU clock, such as CPU/16.
+    ///
+    /// # Arguments
+    ///
+    /// * `divisor` - The CPU cycles in each device cycle.
+    ///
+    /// # Returns
+    ///
+    /// The rate.
+    pub const fn divided(divisor: u32) -> ClockRate {
+        ClockRate::ratio(1, divisor)
+    }
+
+    /// Creates a rate faster than the CPU clock, such as a video chip's dot clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `multiplier` - The device cycles in each CPU cycle.
+    ///
+    /// # Returns
+    ///
+    /// The rate.
+    pub const fn multiplied(multiplier: u32) -> ClockRate {
+        ClockRate::ratio(multiplier, 1)
+    }
+
+    /// Converts CPU cycles to device cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The CPU cycles that have elapsed.
+    /// * `phase` - How far the device is into its next cycle, in units of
+    ///   1/`divisor` of a device cycle. It starts at 0 and is kept between calls.
+    ///
+    /// # Returns
+    ///
+    /// The device cycles that have elapsed.
+    pub fn scale(&self, cycles: u64, phase: &mut u64) -> u64 {
+        if *self == ClockRate::CPU {
+            return cycles;
+        }
+
+        let total = cycles * self.multiplier as u64 + *phase;
+        *phase = total % self.divisor as u64;
+        total / self.divisor as u64
+    }
+}
+
+impl Default for ClockRate {
+    fn default() -> ClockRate {
+        ClockRate::CPU
+    }
+}
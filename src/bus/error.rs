@@ -0,0 +1,61 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+/// An access that the bus could not route to a device.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BusError {
+    /// A read from an address that no device decodes.
+    UnmappedRead(u16),
+
+    /// A write of the given value to an address that no device decodes.
+    UnmappedWrite(u16, u8),
+}
+
+impl Display for BusError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BusError::UnmappedRead(address) => write!(f, "Unmapped read: {:04X}", address),
+            BusError::UnmappedWrite(address, value) => write!(f, "Unmapped write: {:04X} = {:02X}", address, value),
+        }
+    }
+}
+
+impl core::error::Error for BusError {}
+
+/// A device that could not be added to the bus.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MapError {
+    /// The device's start address is after its end address.
+    InvalidRange {
+        /// The name of the device.
+        name: String,
+
+        /// The device's start address.
+        start: u16,
+
+        /// The device's end address.
+        end: u16,
+    },
+
+    /// The device overlaps one already on the bus, and the bus rejects overlaps.
+    Overlap {
+        /// The name of the device being added.
+        name: String,
+
+        /// The name of the device it overlaps.
+        other: String,
+    },
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MapError::InvalidRange { name, start, end } => {
+                write!(f, "{} has an invalid range: {:04X}-{:04X}", name, start, end)
+            }
+            MapError::Overlap { name, other } => write!(f, "{} overlaps {}", name, other),
+        }
+    }
+}
+
+impl core::error::Error for MapError {}
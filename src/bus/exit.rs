@@ -0,0 +1,124 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// A port a program writes to when it has finished.
+///
+/// Writing a byte to the port records it as the program's exit code, which
+/// the host reads with `exit_code()`. A host running programs without a
+/// user, such as a test runner, uses it to know when to stop and whether the
+/// program passed. Reading the port returns the exit code, or 0 before one
+/// is written.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::exit::ExitPort;
+/// use butterflyrs::bus::BusDevice;
+///
+/// let mut port = ExitPort::new(0xBFFF);
+/// assert_eq!(port.exit_code(), None);
+/// port.write(0xBFFF, 3);
+/// assert_eq!(port.exit_code(), Some(3));
+/// ```
+pub struct ExitPort {
+    /// The address of the port.
+    pub address: u16,
+
+    /// The exit code written by the program, if it has finished.
+    code: Option<u8>,
+}
+
+impl ExitPort {
+    /// Creates a port with no exit code written.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the port.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `ExitPort` struct.
+    pub fn new(address: u16) -> ExitPort {
+        ExitPort { address, code: None }
+    }
+
+    /// Returns the exit code the program wrote, or `None` if it hasn't finished.
+    pub fn exit_code(&self) -> Option<u8> {
+        self.code
+    }
+}
+
+impl BusDevice for ExitPort {
+    fn read(&self, _address: u16) -> u8 {
+        self.code.unwrap_or(0)
+    }
+
+    fn write(&mut self, _address: u16, value: u8) {
+        self.code = Some(value);
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // A reset starts the program again, so it hasn't finished
+        self.code = None;
+    }
+
+    fn name(&self) -> String {
+        String::from("Exit port")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.address
+    }
+
+    fn end_address(&self) -> u16 {
+        self.address
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.code.into_iter().collect()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        self.code = match data {
+            [] => None,
+            [code] => Some(*code),
+            _ => return Err(SnapshotError::InvalidData(self.name())),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::bus::exit::ExitPort;
+    use crate::bus::ram::Ram;
+    use crate::bus::{BusDevice, MainBus};
+
+    #[test]
+    fn records_the_exit_code_until_reset() {
+        // The port sits on top of RAM
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        bus.add_device_with_priority(Box::new(ExitPort::new(0x00FF)), 1);
+
+        bus.write(0x00FE, 0x01);
+        assert_eq!(bus.device_of_type::<ExitPort>().unwrap().exit_code(), None);
+        bus.write(0x00FF, 0x02);
+        assert_eq!(bus.device_of_type::<ExitPort>().unwrap().exit_code(), Some(0x02));
+
+        let port = bus.device_of_type_mut::<ExitPort>().unwrap();
+        let saved = port.save();
+        port.reset();
+        assert_eq!(port.read(0x00FF), 0x00);
+        port.load(&saved).unwrap();
+        assert_eq!(port.exit_code(), Some(0x02));
+    }
+}
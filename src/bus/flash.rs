@@ -0,0 +1,308 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The size of the block erased by a sector erase command.
+pub const SECTOR_SIZE: usize = 0x1000;
+
+/// Where in a command sequence the flash is.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum FlashState {
+    /// Waiting for the first unlock write.
+    Ready,
+
+    /// `$AA` was written to `$5555`.
+    Unlock1,
+
+    /// `$55` was written to `$2AAA`, so the next write is a command.
+    Unlock2,
+
+    /// The program command was given, so the next write is programmed.
+    Program,
+
+    /// The erase command was given, and must be unlocked again.
+    EraseSetup,
+
+    /// `$AA` was written to `$5555` after the erase command.
+    EraseUnlock1,
+
+    /// `$55` was written to `$2AAA` after the erase command, so the next write
+    /// says what to erase.
+    EraseUnlock2,
+}
+
+/// Flash memory programmed with the JEDEC command sequences.
+///
+/// Reads work like `Rom`, but software can change the contents with the
+/// same command sequences as common parallel flash and EEPROM chips such as
+/// the SST39SF010. Every command starts with the unlock sequence of `$AA`
+/// written to `$5555` then `$55` to `$2AAA`, followed by:
+///
+/// * `$A0` to `$5555`, then the byte to program to its address. Programming
+///   can only clear bits, so a byte must be erased before it is rewritten.
+/// * `$80` to `$5555`, the unlock sequence again, then `$30` to any address in
+///   a 4K sector to erase the sector to `$FF`, or `$10` to `$5555` to erase the
+///   whole chip.
+///
+/// Command addresses are offsets from the start of the device, with the
+/// address lines above A14 ignored. Writing `$F0` anywhere abandons a sequence,
+/// as does any write that doesn't fit one.
+///
+/// With the `std` feature the contents can be kept in a backing file with
+/// `Flash::open()`, which is written by `flush()` and when the device is dropped.
+pub struct Flash {
+    /// The contents of the flash.
+    pub data: Vec<u8>,
+
+    /// The start address of the flash.
+    pub start: u16,
+
+    /// The end address of the flash.
+    pub end: u16,
+
+    /// Where the flash is in a command sequence.
+    state: FlashState,
+
+    /// Whether the contents changed since they were last flushed.
+    dirty: bool,
+
+    /// The file the contents are kept in, if any.
+    #[cfg(feature = "std")]
+    path: Option<PathBuf>,
+}
+
+impl Flash {
+    /// Creates erased flash, with every byte `$FF`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the flash.
+    /// * `end` - The last address of the flash, inclusive.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Flash` struct.
+    pub fn new(start: u16, end: u16) -> Flash {
+        Flash {
+            data: vec![0xFF; (end - start) as usize + 1],
+            start,
+            end,
+            state: FlashState::Ready,
+            dirty: false,
+            #[cfg(feature = "std")]
+            path: None,
+        }
+    }
+
+    /// Creates flash whose contents are kept in a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the flash.
+    /// * `end` - The last address of the flash, inclusive.
+    /// * `path` - The backing file. If it doesn't exist yet, the flash starts
+    ///   erased and the file is created when the flash is flushed. A file
+    ///   shorter than the flash leaves the rest erased.
+    ///
+    /// # Returns
+    ///
+    /// The flash, or the error reading the file. A file longer than the flash
+    /// is an `InvalidData` error.
+    #[cfg(feature = "std")]
+    pub fn open(start: u16, end: u16, path: impl AsRef<Path>) -> std::io::Result<Flash> {
+        let mut flash = Flash::new(start, end);
+        flash.data = crate::bus::backing::read_image(path.as_ref(), flash.data.len(), 0xFF)?;
+        flash.path = Some(path.as_ref().to_path_buf());
+        Ok(flash)
+    }
+
+    /// Writes the contents to the backing file, if they changed since the last flush.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the contents were written, or didn't need to be.
+    #[cfg(feature = "std")]
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        crate::bus::backing::flush(self.path.as_deref(), &self.data, &mut self.dirty)
+    }
+
+    /// Erases the 4K sector holding an offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Any offset from the start of the flash in the sector.
+    fn erase_sector(&mut self, offset: usize) {
+        let start = offset - offset % SECTOR_SIZE;
+        let end = (start + SECTOR_SIZE).min(self.data.len());
+        self.data[start..end].fill(0xFF);
+    }
+}
+
+impl BusDevice for Flash {
+    fn read(&self, address: u16) -> u8 {
+        self.data[(address - self.start) as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        let offset = (address - self.start) as usize;
+        let command = offset & 0x7FFF;
+
+        self.state = match (self.state, command, value) {
+            (FlashState::Program, _, _) => {
+                self.data[offset] &= value;
+                self.dirty = true;
+                FlashState::Ready
+            }
+            (FlashState::Ready, 0x5555, 0xAA) => FlashState::Unlock1,
+            (FlashState::Unlock1, 0x2AAA, 0x55) => FlashState::Unlock2,
+            (FlashState::Unlock2, 0x5555, 0xA0) => FlashState::Program,
+            (FlashState::Unlock2, 0x5555, 0x80) => FlashState::EraseSetup,
+            (FlashState::EraseSetup, 0x5555, 0xAA) => FlashState::EraseUnlock1,
+            (FlashState::EraseUnlock1, 0x2AAA, 0x55) => FlashState::EraseUnlock2,
+            (FlashState::EraseUnlock2, _, 0x30) => {
+                self.erase_sector(offset);
+                self.dirty = true;
+                FlashState::Ready
+            }
+            (FlashState::EraseUnlock2, 0x5555, 0x10) => {
+                self.data.fill(0xFF);
+                self.dirty = true;
+                FlashState::Ready
+            }
+            // $F0 or anything out of sequence goes back to reading
+            _ => FlashState::Ready,
+        };
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        self.data[(address - self.start) as usize] = value;
+        self.dirty = true;
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        // A reset abandons a command sequence, but the contents are non-volatile
+        self.state = FlashState::Ready;
+    }
+
+    fn name(&self) -> String {
+        String::from("Flash")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.end
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        // The saved contents must fill the flash exactly
+        if data.len() != self.data.len() {
+            return Err(SnapshotError::InvalidData(self.name()));
+        }
+        self.data.copy_from_slice(data);
+        self.state = FlashState::Ready;
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Flash {
+    fn drop(&mut self) {
+        crate::bus::backing::flush_on_drop(self.path.as_deref(), &self.data, &mut self.dirty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::BusDevice;
+    use crate::bus::flash::Flash;
+
+    /// Writes the unlock sequence followed by a command to flash mapped at $8000.
+    fn command(flash: &mut Flash, command: u8) {
+        flash.write(0xD555, 0xAA);
+        flash.write(0xAAAA, 0x55);
+        flash.write(0xD555, command);
+    }
+
+    #[test]
+    fn programs_and_erases_with_command_sequences() {
+        let mut flash = Flash::new(0x8000, 0xFFFF);
+
+        // Writes outside a command sequence are ignored
+        flash.write(0x9000, 0x12);
+        assert_eq!(flash.read(0x9000), 0xFF);
+
+        command(&mut flash, 0xA0);
+        flash.write(0x9000, 0x12);
+        assert_eq!(flash.read(0x9000), 0x12);
+
+        // Programming can only clear bits
+        command(&mut flash, 0xA0);
+        flash.write(0x9000, 0x21);
+        assert_eq!(flash.read(0x9000), 0x00);
+
+        command(&mut flash, 0xA0);
+        flash.write(0xA000, 0x34);
+
+        // Erase the sector at $9000-$9FFF only
+        command(&mut flash, 0x80);
+        flash.write(0xD555, 0xAA);
+        flash.write(0xAAAA, 0x55);
+        flash.write(0x9ABC, 0x30);
+        assert_eq!(flash.read(0x9000), 0xFF);
+        assert_eq!(flash.read(0xA000), 0x34);
+
+        command(&mut flash, 0x80);
+        command(&mut flash, 0x10);
+        assert_eq!(flash.read(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn abandons_sequences_that_go_wrong() {
+        let mut flash = Flash::new(0x8000, 0xFFFF);
+
+        flash.write(0xD555, 0xAA);
+        flash.write(0xAAAA, 0x54);
+        flash.write(0xD555, 0xA0);
+        flash.write(0x9000, 0x12);
+        assert_eq!(flash.read(0x9000), 0xFF);
+
+        command(&mut flash, 0xA0);
+        flash.reset();
+        flash.write(0x9000, 0x12);
+        assert_eq!(flash.read(0x9000), 0xFF);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn keeps_its_contents_in_a_backing_file() {
+        let path = std::env::temp_dir().join(std::format!("butterflyrs-flash-{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut flash = Flash::open(0x8000, 0xFFFF, &path).unwrap();
+        assert_eq!(flash.read(0xF000), 0xFF);
+        command(&mut flash, 0xA0);
+        flash.write(0xF000, 0x42);
+        drop(flash);
+
+        let flash = Flash::open(0x8000, 0xFFFF, &path).unwrap();
+        assert_eq!(flash.read(0xF000), 0x42);
+        assert!(Flash::open(0xC000, 0xFFFF, &path).is_err());
+        drop(flash);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
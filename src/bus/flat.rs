@@ -0,0 +1,179 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::bus::{BusDevice, MainBus, SnapshotError};
+
+/// 64K of plain memory covering the whole address space.
+///
+/// For machines that are just a CPU with RAM and ROM, such as those running
+/// test suites, a `FlatBus` replaces every device with one array. Reads and
+/// writes index it directly, and with a single device on the bus every page
+/// decodes to it without a scan.
+///
+/// Ranges can be write-protected to stand in for ROM. Writes to them are
+/// dropped, but `poke()` still changes them so a debugger can patch them.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::flat::FlatBus;
+/// use butterflyrs::bus::MainBus;
+///
+/// let mut memory = FlatBus::new();
+/// memory.load(0xC000, &[0xEA, 0xEA]);
+/// memory.protect(0xC000, 0xFFFF);
+///
+/// let mut bus = MainBus::from(memory);
+/// bus.write(0xC000, 0x00);
+/// assert_eq!(bus.read(0xC000), 0xEA);
+/// ```
+pub struct FlatBus {
+    /// The contents of memory.
+    pub data: Box<[u8; 0x10000]>,
+
+    /// The ranges that writes are dropped for.
+    protected: Vec<RangeInclusive<u16>>,
+}
+
+impl FlatBus {
+    /// Creates 64K of memory filled with zeros and nothing protected.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `FlatBus` struct.
+    pub fn new() -> FlatBus {
+        FlatBus {
+            data: Box::new([0x00; 0x10000]),
+            protected: Vec::new(),
+        }
+    }
+
+    /// Copies bytes into memory, ignoring write protection.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the first byte. Later bytes wrap around
+    ///   the top of memory.
+    /// * `data` - The bytes to copy.
+    pub fn load(&mut self, address: u16, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            self.data[address.wrapping_add(offset as u16) as usize] = *byte;
+        }
+    }
+
+    /// Write-protects a range of addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address to protect.
+    /// * `end` - The last address to protect, inclusive.
+    pub fn protect(&mut self, start: u16, end: u16) {
+        self.protected.push(start..=end);
+    }
+
+    /// Removes all write protection.
+    pub fn unprotect_all(&mut self) {
+        self.protected.clear();
+    }
+
+    /// Returns whether writes to an address are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to check.
+    pub fn is_protected(&self, address: u16) -> bool {
+        self.protected.iter().any(|range| range.contains(&address))
+    }
+}
+
+impl Default for FlatBus {
+    fn default() -> FlatBus {
+        FlatBus::new()
+    }
+}
+
+impl From<FlatBus> for MainBus {
+    /// Creates a bus with the memory as its only device.
+    fn from(memory: FlatBus) -> MainBus {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(memory));
+        bus
+    }
+}
+
+impl BusDevice for FlatBus {
+    fn read(&self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if !self.is_protected(address) {
+            self.data[address as usize] = value;
+        }
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        self.data[address as usize] = value;
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        // Memory keeps its contents over a reset, like the RAM and ROM it stands in for
+    }
+
+    fn name(&self) -> String {
+        String::from("Flat")
+    }
+
+    fn start_address(&self) -> u16 {
+        0x0000
+    }
+
+    fn end_address(&self) -> u16 {
+        0xFFFF
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.data.to_vec()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        // The saved contents must fill memory exactly
+        if data.len() != self.data.len() {
+            return Err(SnapshotError::InvalidData(self.name()));
+        }
+        self.data.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn contains(&self, _address: u16) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::flat::FlatBus;
+    use crate::bus::MainBus;
+
+    #[test]
+    fn protected_ranges_drop_writes_but_not_pokes() {
+        let mut memory = FlatBus::new();
+        memory.load(0xFFFE, &[0x01, 0x02, 0x03]);
+        memory.protect(0xF000, 0xFFFF);
+        let mut bus = MainBus::from(memory);
+
+        bus.write(0xFFFF, 0x42);
+        bus.write(0x0000, 0x42);
+        assert_eq!(bus.read(0xFFFF), 0x02);
+        assert_eq!(bus.read(0x0000), 0x42);
+
+        bus.poke(0xFFFF, 0x42);
+        assert_eq!(bus.peek(0xFFFF), 0x42);
+    }
+}
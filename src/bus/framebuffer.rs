@@ -0,0 +1,184 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The colours of the 16 palette entries, as `0x00RRGGBB`.
+///
+/// These are the colours used by easy6502 and many of the simulators it
+/// inspired, so programs written for them show up as intended.
+pub const PALETTE: [u32; 16] = [
+    0x000000, 0xFFFFFF, 0x880000, 0xAAFFEE, 0xCC44CC, 0x00CC55, 0x0000AA, 0xEEEE77, 0xDD8855, 0x664400, 0xFF7777,
+    0x333333, 0x777777, 0xAAFF66, 0x0088FF, 0xBBBBBB,
+];
+
+/// A bitmapped screen whose pixels are mapped onto the bus.
+///
+/// Each byte is one pixel, from the top left, row by row. The low four bits
+/// of a byte pick one of the 16 colours in `palette`. A 32x32 screen at $0200
+/// matches the one in easy6502.
+///
+/// The host reads the pixels with `frame()`, or as colours with `to_rgba()`
+/// or `to_rgb32()`, and can reach the device once it is on the bus with
+/// `MainBus::device_of_type()`.
+pub struct Framebuffer {
+    /// The pixels, row by row.
+    pub data: Vec<u8>,
+
+    /// The address of the top left pixel.
+    pub start: u16,
+
+    /// The colours of the palette entries, as `0x00RRGGBB`.
+    pub palette: [u32; 16],
+
+    /// The width of the screen in pixels.
+    width: usize,
+
+    /// The height of the screen in pixels.
+    height: usize,
+}
+
+impl Framebuffer {
+    /// Creates a screen with every pixel black.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the top left pixel.
+    /// * `width` - The width of the screen in pixels.
+    /// * `height` - The height of the screen in pixels.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Framebuffer` struct.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the screen is empty or doesn't fit between `start` and the top of memory.
+    pub fn new(start: u16, width: usize, height: usize) -> Framebuffer {
+        let size = width * height;
+        assert!(size > 0 && start as usize + size <= 0x10000, "a {}x{} screen doesn't fit at {:04X}", width, height, start);
+
+        Framebuffer {
+            data: vec![0x00; size],
+            start,
+            palette: PALETTE,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the width of the screen in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the screen in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixels as they are in memory, one byte each, row by row.
+    pub fn frame(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the colour of every pixel as `0x00RRGGBB`, row by row.
+    ///
+    /// This is the format most windowing libraries take for a software framebuffer.
+    pub fn to_rgb32(&self) -> Vec<u32> {
+        self.data.iter().map(|&pixel| self.palette[(pixel & 0x0F) as usize]).collect()
+    }
+
+    /// Returns the colour of every pixel as four bytes of red, green, blue and
+    /// alpha, row by row.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        rgb32_to_rgba(&self.to_rgb32())
+    }
+}
+
+/// Converts `0x00RRGGBB` colours to four bytes of red, green, blue and alpha each.
+///
+/// # Arguments
+///
+/// * `colours` - The colours to convert.
+///
+/// # Returns
+///
+/// The colours as bytes, fully opaque.
+pub(crate) fn rgb32_to_rgba(colours: &[u32]) -> Vec<u8> {
+    colours
+        .iter()
+        .flat_map(|colour| {
+            let [_, red, green, blue] = colour.to_be_bytes();
+            [red, green, blue, 0xFF]
+        })
+        .collect()
+}
+
+impl BusDevice for Framebuffer {
+    fn read(&self, address: u16) -> u8 {
+        self.data[(address - self.start) as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.data[(address - self.start) as usize] = value;
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        // The picture stays on the screen through a reset
+    }
+
+    fn power_on(&mut self) {
+        self.data.fill(0x00);
+    }
+
+    fn name(&self) -> String {
+        String::from("Framebuffer")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + (self.data.len() - 1) as u16
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        // The saved pixels must fill the screen exactly
+        if data.len() != self.data.len() {
+            return Err(SnapshotError::InvalidData(self.name()));
+        }
+        self.data.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::framebuffer::Framebuffer;
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn maps_pixels_to_palette_colours() {
+        let mut screen = Framebuffer::new(0x0200, 32, 32);
+        assert_eq!(screen.end_address(), 0x05FF);
+
+        // The top left pixel white, and the bottom right one red with the high bits ignored
+        screen.write(0x0200, 0x01);
+        screen.write(0x05FF, 0xF2);
+
+        assert_eq!(screen.frame()[0], 0x01);
+        let colours = screen.to_rgb32();
+        assert_eq!((colours[0], colours[1], colours[1023]), (0xFFFFFF, 0x000000, 0x880000));
+        assert_eq!(&screen.to_rgba()[4092..], [0x88, 0x00, 0x00, 0xFF]);
+    }
+}
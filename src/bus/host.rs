@@ -0,0 +1,493 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::{Component, Path, PathBuf};
+
+use crate::bus::{BusDevice, MainBus, SnapshotError};
+
+/// The offset of the low byte of the pointer to the call's data. The high byte follows it.
+pub const POINTER: u16 = 0;
+
+/// The offset of the byte argument of the call.
+pub const ARGUMENT: u16 = 2;
+
+/// The offset of the function register. Writing a function code to it makes the call.
+pub const FUNCTION: u16 = 3;
+
+/// The offset of the low byte of the call's result. The high byte follows it.
+pub const RESULT: u16 = 4;
+
+/// The offset of the status register.
+pub const STATUS: u16 = 6;
+
+/// Prints the zero-terminated string at the pointer. The result is its length.
+pub const PRINT: u8 = 0x01;
+
+/// Reads a file into memory. The pointer points to the address to load it
+/// at, followed by the zero-terminated name of the file. The result is the
+/// number of bytes loaded, so a file must be shorter than 64K.
+pub const READ_FILE: u8 = 0x02;
+
+/// Stores the number of cycles since power on at the pointer, as four bytes
+/// from least significant. The result is the low two bytes.
+pub const TICKS: u8 = 0x03;
+
+/// Asks the host to stop the machine, with the argument as the exit code.
+pub const EXIT: u8 = 0x04;
+
+/// The status of a call that succeeded.
+pub const OK: u8 = 0x00;
+
+/// The status of a call that failed.
+pub const FAILED: u8 = 0x01;
+
+/// The status of a call to a function the host doesn't provide.
+pub const UNSUPPORTED: u8 = 0x02;
+
+/// The status of a call the host hasn't carried out yet.
+pub const BUSY: u8 = 0x80;
+
+/// A function called with the bytes a program prints through a `HostCall`.
+pub type HostOutput = Box<dyn FnMut(&[u8]) + Send>;
+
+/// A paravirtual device that lets a program call on the host, in the manner
+/// of ARM semihosting.
+///
+/// Programs written for the emulator, such as test ROMs and demos, use it to
+/// print, load files, read the time and exit without drivers for real
+/// hardware. A call is made through a few registers:
+///
+/// * `POINTER` - The address of the call's data, low byte first.
+/// * `ARGUMENT` - A byte argument.
+/// * `FUNCTION` - Writing `PRINT`, `READ_FILE`, `TICKS` or `EXIT` makes the call.
+/// * `RESULT` - What the call returns, low byte first.
+/// * `STATUS` - `OK`, `FAILED` or `UNSUPPORTED` once the call is done.
+///
+/// The call is carried out once the instruction that wrote `FUNCTION` has
+/// finished, when the CPU ticks the bus, so the result is there for the next
+/// instruction. The host reads and writes memory as a debugger would, with
+/// `MainBus::peek()` and `MainBus::poke()`.
+///
+/// Printed bytes go to standard output, or to a function set with
+/// `with_output()`. Reading files is only possible with the `std` feature,
+/// and only from a directory the host allows with `with_file_root()`. After
+/// an `EXIT` call, the host finds the code with `exit_code()`.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::host::{HostCall, EXIT, FUNCTION, ARGUMENT};
+/// use butterflyrs::bus::ram::Ram;
+/// use butterflyrs::bus::MainBus;
+///
+/// let mut bus = MainBus::new();
+/// bus.add_device(Box::new(Ram::new(0x0000, 0xFEFF)));
+/// bus.add_device(Box::new(HostCall::new(0xFF00)));
+///
+/// bus.write(0xFF00 + ARGUMENT, 3);
+/// bus.write(0xFF00 + FUNCTION, EXIT);
+/// bus.tick(4);
+/// assert_eq!(bus.device_of_type::<HostCall>().unwrap().exit_code(), Some(3));
+/// ```
+pub struct HostCall {
+    /// The address of the first register.
+    pub start: u16,
+
+    /// The pointer register.
+    pointer: u16,
+
+    /// The argument register.
+    argument: u8,
+
+    /// The function register.
+    function: u8,
+
+    /// The result register.
+    result: u16,
+
+    /// The status register.
+    status: u8,
+
+    /// The number of cycles since power on.
+    cycles: u64,
+
+    /// The exit code of the last `EXIT` call, if there was one.
+    exit_code: Option<u8>,
+
+    /// Where printed bytes go instead of standard output, if anywhere.
+    output: Option<HostOutput>,
+
+    /// The directory files are read from, if reading them is allowed.
+    #[cfg(feature = "std")]
+    file_root: Option<PathBuf>,
+}
+
+impl HostCall {
+    /// Creates a host call device that prints to standard output and can't read files.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the first register.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `HostCall` struct.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the registers don't fit between `start` and the top of memory.
+    pub fn new(start: u16) -> HostCall {
+        assert!(start <= 0xFFFF - STATUS, "the host call registers don't fit at {:04X}", start);
+
+        HostCall {
+            start,
+            pointer: 0,
+            argument: 0,
+            function: 0,
+            result: 0,
+            status: OK,
+            cycles: 0,
+            exit_code: None,
+            output: None,
+            #[cfg(feature = "std")]
+            file_root: None,
+        }
+    }
+
+    /// Sends printed bytes to a function instead of standard output.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Called with the bytes of each `PRINT` call.
+    ///
+    /// # Returns
+    ///
+    /// The device, printing to `output`.
+    pub fn with_output(mut self, output: HostOutput) -> HostCall {
+        self.output = Some(output);
+        self
+    }
+
+    /// Lets the program read files from a directory.
+    ///
+    /// File names are relative to the directory, and names that would leave
+    /// it, such as absolute paths, ones with `..` in them or ones that lead
+    /// out of it through a symbolic link, fail.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory.
+    ///
+    /// # Returns
+    ///
+    /// The device, reading files from `root`.
+    #[cfg(feature = "std")]
+    pub fn with_file_root(mut self, root: impl AsRef<Path>) -> HostCall {
+        self.file_root = Some(root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Returns the exit code of the `EXIT` call, if the program has made one.
+    pub fn exit_code(&self) -> Option<u8> {
+        self.exit_code
+    }
+
+    /// Reads a zero-terminated string from memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus to read from.
+    /// * `address` - The address of the first byte.
+    ///
+    /// # Returns
+    ///
+    /// The bytes before the terminating zero, or up to the end of memory if there is none.
+    fn read_string(bus: &MainBus, address: u16) -> Vec<u8> {
+        (address..=0xFFFF).map(|address| bus.peek(address)).take_while(|&byte| byte != 0).collect()
+    }
+
+    /// Prints the string at the pointer.
+    fn print(&mut self, bus: &MainBus) -> Result<u16, u8> {
+        let text = HostCall::read_string(bus, self.pointer);
+        match &mut self.output {
+            Some(output) => output(&text),
+            #[cfg(feature = "std")]
+            None => {
+                use std::io::Write;
+
+                let mut stdout = std::io::stdout();
+                stdout.write_all(&text).and_then(|_| stdout.flush()).map_err(|_| FAILED)?;
+            }
+            #[cfg(not(feature = "std"))]
+            None => {}
+        }
+        Ok(text.len() as u16)
+    }
+
+    /// Reads the file named by the parameter block at the pointer into memory.
+    #[cfg(feature = "std")]
+    fn read_file(&mut self, bus: &mut MainBus) -> Result<u16, u8> {
+        let root = self.file_root.as_ref().ok_or(UNSUPPORTED)?;
+        let load_address = u16::from_le_bytes([bus.peek(self.pointer), bus.peek(self.pointer.wrapping_add(1))]);
+        let name = HostCall::read_string(bus, self.pointer.wrapping_add(2));
+
+        // The name has to stay inside the root
+        let name = String::from_utf8(name).map_err(|_| FAILED)?;
+        let name = Path::new(&name);
+        if name.as_os_str().is_empty() || !name.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(FAILED);
+        }
+
+        // So does the file it leads to, once any symbolic links are followed
+        let root = root.canonicalize().map_err(|_| FAILED)?;
+        let path = root.join(name).canonicalize().map_err(|_| FAILED)?;
+        if !path.starts_with(&root) {
+            return Err(FAILED);
+        }
+
+        // The file has to fit between the load address and the top of memory,
+        // and its length in the result
+        let data = std::fs::read(path).map_err(|_| FAILED)?;
+        if data.len() > 0x10000 - load_address as usize || data.len() > u16::MAX as usize {
+            return Err(FAILED);
+        }
+        for (offset, &byte) in data.iter().enumerate() {
+            bus.poke(load_address + offset as u16, byte);
+        }
+        Ok(data.len() as u16)
+    }
+
+    /// Reading files needs the standard library.
+    #[cfg(not(feature = "std"))]
+    fn read_file(&mut self, _bus: &mut MainBus) -> Result<u16, u8> {
+        Err(UNSUPPORTED)
+    }
+
+    /// Stores the cycle count at the pointer.
+    fn ticks(&mut self, bus: &mut MainBus) -> Result<u16, u8> {
+        let ticks = self.cycles as u32;
+        for (offset, byte) in ticks.to_le_bytes().into_iter().enumerate() {
+            bus.poke(self.pointer.wrapping_add(offset as u16), byte);
+        }
+        Ok(ticks as u16)
+    }
+}
+
+impl BusDevice for HostCall {
+    fn read(&self, address: u16) -> u8 {
+        match address - self.start {
+            POINTER => self.pointer as u8,
+            1 => (self.pointer >> 8) as u8,
+            ARGUMENT => self.argument,
+            FUNCTION => self.function,
+            RESULT => self.result as u8,
+            5 => (self.result >> 8) as u8,
+            _ => self.status,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address - self.start {
+            POINTER => self.pointer = (self.pointer & 0xFF00) | value as u16,
+            1 => self.pointer = (self.pointer & 0x00FF) | (value as u16) << 8,
+            ARGUMENT => self.argument = value,
+            FUNCTION => {
+                // The call is made when the bus is next ticked
+                self.function = value;
+                self.status = BUSY;
+            }
+            // The result and status can't be written
+            _ => {}
+        }
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        // Setting the function register from a debugger doesn't make a call
+        match address - self.start {
+            FUNCTION => self.function = value,
+            _ => self.write(address, value),
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        self.pointer = 0;
+        self.argument = 0;
+        self.function = 0;
+        self.result = 0;
+        self.status = OK;
+        self.exit_code = None;
+    }
+
+    fn power_on(&mut self) {
+        self.reset();
+        self.cycles = 0;
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.cycles += cycles;
+    }
+
+    fn bus_request(&self) -> bool {
+        self.status == BUSY
+    }
+
+    fn bus_grant(&mut self, bus: &mut MainBus) {
+        let result = match self.function {
+            PRINT => self.print(bus),
+            READ_FILE => self.read_file(bus),
+            TICKS => self.ticks(bus),
+            EXIT => {
+                self.exit_code = Some(self.argument);
+                Ok(0)
+            }
+            _ => Err(UNSUPPORTED),
+        };
+
+        (self.result, self.status) = match result {
+            Ok(result) => (result, OK),
+            Err(status) => (0, status),
+        };
+    }
+
+    fn name(&self) -> String {
+        String::from("Host call")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        // A start moved too high wraps, which the bus rejects as an invalid range
+        self.start.wrapping_add(STATUS)
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(15);
+        data.extend_from_slice(&self.pointer.to_le_bytes());
+        data.extend_from_slice(&[self.argument, self.function]);
+        data.extend_from_slice(&self.result.to_le_bytes());
+        data.push(self.status);
+        data.extend_from_slice(&self.cycles.to_le_bytes());
+        data
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let data: [u8; 15] = data.try_into().map_err(|_| SnapshotError::InvalidData(self.name()))?;
+        self.pointer = u16::from_le_bytes([data[0], data[1]]);
+        (self.argument, self.function) = (data[2], data[3]);
+        self.result = u16::from_le_bytes([data[4], data[5]]);
+        self.status = data[6];
+        self.cycles = u64::from_le_bytes(data[7..].try_into().unwrap());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    use crate::bus::host::{HostCall, BUSY, FUNCTION, OK, POINTER, PRINT, RESULT, STATUS, TICKS, UNSUPPORTED};
+    use crate::bus::ram::Ram;
+    use crate::bus::MainBus;
+
+    /// Creates a bus with RAM below a host call device at $FF00.
+    fn setup(device: HostCall) -> MainBus {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0xFEFF)));
+        bus.add_device(Box::new(device));
+        bus
+    }
+
+    /// Makes a call the way a program would, and lets the CPU's tick carry it out.
+    fn call(bus: &mut MainBus, function: u8, pointer: u16) -> (u16, u8) {
+        bus.write(0xFF00 + POINTER, pointer as u8);
+        bus.write(0xFF00 + POINTER + 1, (pointer >> 8) as u8);
+        bus.write(0xFF00 + FUNCTION, function);
+        assert_eq!(bus.read(0xFF00 + STATUS), BUSY);
+        bus.tick(2);
+
+        let result = u16::from_le_bytes([bus.read(0xFF00 + RESULT), bus.read(0xFF00 + RESULT + 1)]);
+        (result, bus.read(0xFF00 + STATUS))
+    }
+
+    #[test]
+    fn carries_out_calls_when_ticked() {
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let sink = printed.clone();
+        let mut bus = setup(HostCall::new(0xFF00).with_output(Box::new(move |text| sink.lock().unwrap().extend_from_slice(text))));
+
+        for (offset, &byte) in b"Hello\0".iter().enumerate() {
+            bus.write(0x1000 + offset as u16, byte);
+        }
+        assert_eq!(call(&mut bus, PRINT, 0x1000), (5, OK));
+        assert_eq!(*printed.lock().unwrap(), b"Hello");
+
+        // Two ticks were made by the print call and two by this one
+        assert_eq!(call(&mut bus, TICKS, 0x2000), (4, OK));
+        assert_eq!([bus.read(0x2000), bus.read(0x2001), bus.read(0x2002), bus.read(0x2003)], [4, 0, 0, 0]);
+
+        assert_eq!(call(&mut bus, 0x7F, 0x0000), (0, UNSUPPORTED));
+    }
+
+    #[test]
+    #[should_panic(expected = "the host call registers don't fit at FFFA")]
+    fn registers_must_fit_below_the_top_of_memory() {
+        HostCall::new(0xFFF9);
+        HostCall::new(0xFFFA);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn reads_files_only_from_its_root() {
+        use crate::bus::host::{FAILED, READ_FILE};
+
+        let root = std::env::temp_dir().join(std::format!("butterflyrs-host-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("data.bin"), [0xDE, 0xAD]).unwrap();
+
+        // The parameter block: the load address, then the name
+        let mut bus = setup(HostCall::new(0xFF00));
+        for (offset, &byte) in b"\x00\x30data.bin\0".iter().enumerate() {
+            bus.write(0x1000 + offset as u16, byte);
+        }
+        assert_eq!(call(&mut bus, READ_FILE, 0x1000), (0, UNSUPPORTED));
+
+        let mut bus = setup(HostCall::new(0xFF00).with_file_root(&root));
+        for (offset, &byte) in b"\x00\x30data.bin\0\x00\x30../data.bin\0".iter().enumerate() {
+            bus.write(0x1000 + offset as u16, byte);
+        }
+        assert_eq!(call(&mut bus, READ_FILE, 0x1000), (2, OK));
+        assert_eq!((bus.read(0x3000), bus.read(0x3001)), (0xDE, 0xAD));
+        assert_eq!(call(&mut bus, READ_FILE, 0x100B), (0, FAILED));
+
+        // A file of 64K can't have its length returned, even if it would fit
+        std::fs::write(root.join("full.bin"), std::vec![0xEA; 0x10000]).unwrap();
+        for (offset, &byte) in b"\x00\x00full.bin\0".iter().enumerate() {
+            bus.write(0x1000 + offset as u16, byte);
+        }
+        assert_eq!(call(&mut bus, READ_FILE, 0x1000), (0, FAILED));
+
+        // Nor can a symbolic link lead out of the root
+        #[cfg(unix)]
+        {
+            let outside = root.with_extension("outside");
+            std::fs::write(&outside, [0x01]).unwrap();
+            std::os::unix::fs::symlink(&outside, root.join("link.bin")).unwrap();
+            for (offset, &byte) in b"\x00\x30link.bin\0".iter().enumerate() {
+                bus.write(0x1000 + offset as u16, byte);
+            }
+            assert_eq!(call(&mut bus, READ_FILE, 0x1000), (0, FAILED));
+            std::fs::remove_file(&outside).unwrap();
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
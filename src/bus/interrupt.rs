@@ -0,0 +1,131 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// One source's input to the shared IRQ line.
+///
+/// Devices that can't report their interrupt through `BusDevice::irq()`, such
+/// as ones that live outside the bus or are driven from another thread, hold
+/// an `IrqLine` from an `InterruptController` instead. The CPU's IRQ is
+/// asserted while any source holds its line asserted, the way open-collector
+/// outputs share the 6502's IRQ pin.
+#[derive(Debug, Clone)]
+pub struct IrqLine {
+    /// The state of every line of the controller, one bit each.
+    lines: Arc<AtomicU32>,
+
+    /// The bit of this line.
+    mask: u32,
+}
+
+impl IrqLine {
+    /// Asserts the line. It stays asserted until `release()` is called.
+    pub fn assert(&self) {
+        self.lines.fetch_or(self.mask, Ordering::Relaxed);
+    }
+
+    /// Releases the line.
+    pub fn release(&self) {
+        self.lines.fetch_and(!self.mask, Ordering::Relaxed);
+    }
+
+    /// Drives the line.
+    ///
+    /// # Arguments
+    ///
+    /// * `asserted` - `true` to assert the line, `false` to release it.
+    pub fn set(&self, asserted: bool) {
+        if asserted {
+            self.assert();
+        } else {
+            self.release();
+        }
+    }
+
+    /// Returns whether the line is asserted.
+    pub fn is_asserted(&self) -> bool {
+        self.lines.load(Ordering::Relaxed) & self.mask != 0
+    }
+}
+
+/// Combines up to 32 `IrqLine`s into the single IRQ input of the CPU.
+///
+/// Every `MainBus` has one, which the CPU samples along with the devices on
+/// the bus at each instruction boundary.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::MainBus;
+///
+/// let mut bus = MainBus::new();
+/// let timer = bus.interrupts.line("Timer");
+///
+/// timer.assert();
+/// assert!(bus.irq());
+/// assert_eq!(bus.irq_sources(), ["Timer"]);
+/// timer.release();
+/// assert!(!bus.irq());
+/// ```
+#[derive(Debug, Default)]
+pub struct InterruptController {
+    /// The state of every line, one bit each.
+    lines: Arc<AtomicU32>,
+
+    /// The names of the lines handed out, in bit order.
+    names: Vec<String>,
+}
+
+impl InterruptController {
+    /// Creates a controller with no lines.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `InterruptController` struct.
+    pub fn new() -> InterruptController {
+        InterruptController::default()
+    }
+
+    /// Hands out a new line, released.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the source, shown when it is asserting the line.
+    ///
+    /// # Returns
+    ///
+    /// The line, for the source to keep.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the controller already has 32 lines.
+    pub fn line(&mut self, name: &str) -> IrqLine {
+        assert!(self.names.len() < 32, "an interrupt controller has at most 32 lines");
+
+        let mask = 1 << self.names.len();
+        self.names.push(String::from(name));
+        IrqLine { lines: self.lines.clone(), mask }
+    }
+
+    /// Returns whether any line is asserted.
+    pub fn is_asserted(&self) -> bool {
+        self.lines.load(Ordering::Relaxed) != 0
+    }
+
+    /// Returns the names of the lines that are asserted, in the order they were handed out.
+    pub fn asserted(&self) -> Vec<&str> {
+        let lines = self.lines.load(Ordering::Relaxed);
+        self.names
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| lines & (1 << bit) != 0)
+            .map(|(_, name)| name.as_str())
+            .collect()
+    }
+
+    /// Releases every line.
+    pub fn release_all(&self) {
+        self.lines.store(0, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,206 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The offset of the data register from the start of the keyboard.
+pub const DATA: u16 = 0;
+
+/// The offset of the status and control register from the start of the keyboard.
+pub const STATUS: u16 = 1;
+
+/// The status bit that is set while a key is waiting to be read.
+pub const KEY_AVAILABLE: u8 = 0x80;
+
+/// The control bit that makes the keyboard interrupt while a key is waiting.
+pub const IRQ_ENABLE: u8 = 0x01;
+
+/// A keyboard that the host types into.
+///
+/// The host queues keys with `push_key()`, and the machine reads them one at
+/// a time through two registers:
+///
+/// * `DATA` - Reading it takes the next key from the queue, or returns 0 if
+///   there is none.
+/// * `STATUS` - Bit 7 (`KEY_AVAILABLE`) is set while a key is waiting, and
+///   bit 0 (`IRQ_ENABLE`) reads back the control bit. Writing it sets the
+///   control bit.
+///
+/// With `IRQ_ENABLE` set, the keyboard asserts IRQ for as long as a key is
+/// waiting. The host can reach the keyboard once it is on the bus with
+/// `MainBus::device_of_type()`.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::keyboard::Keyboard;
+/// use butterflyrs::bus::MainBus;
+///
+/// let mut bus = MainBus::new();
+/// bus.add_device(Box::new(Keyboard::new(0xD010)));
+///
+/// bus.device_of_type_mut::<Keyboard>().unwrap().push_str("HI");
+/// assert_eq!(bus.read(0xD011), 0x80);
+/// assert_eq!(bus.read(0xD010), b'H');
+/// ```
+pub struct Keyboard {
+    /// The first address of the keyboard's registers.
+    pub start: u16,
+
+    /// The keys waiting to be read, oldest first. Reading `DATA` takes a key
+    /// even though reads don't borrow the device mutably.
+    queue: RefCell<VecDeque<u8>>,
+
+    /// The control bits written to `STATUS`.
+    control: u8,
+}
+
+impl Keyboard {
+    /// Creates a keyboard with nothing typed.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the data register. The status register follows it.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Keyboard` struct.
+    pub fn new(start: u16) -> Keyboard {
+        Keyboard {
+            start,
+            queue: RefCell::new(VecDeque::new()),
+            control: 0,
+        }
+    }
+
+    /// Queues a key for the machine to read.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The code of the key, such as an ASCII character.
+    pub fn push_key(&mut self, key: u8) {
+        self.queue.get_mut().push_back(key);
+    }
+
+    /// Queues every byte of a string, as if it was typed.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to type.
+    pub fn push_str(&mut self, text: &str) {
+        self.queue.get_mut().extend(text.bytes());
+    }
+
+    /// Returns the number of keys waiting to be read.
+    pub fn pending(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    /// Returns the value of the status register.
+    fn status(&self) -> u8 {
+        let available = if self.queue.borrow().is_empty() { 0 } else { KEY_AVAILABLE };
+        available | self.control
+    }
+}
+
+impl BusDevice for Keyboard {
+    fn read(&self, address: u16) -> u8 {
+        match address - self.start {
+            DATA => self.queue.borrow_mut().pop_front().unwrap_or(0),
+            _ => self.status(),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address - self.start == STATUS {
+            self.control = value & IRQ_ENABLE;
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        // Looks at the next key without taking it
+        match address - self.start {
+            DATA => self.queue.borrow().front().copied().unwrap_or(0),
+            _ => self.status(),
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // Keys typed before the reset are kept, so input can be queued before the machine starts
+        self.control = 0;
+    }
+
+    fn power_on(&mut self) {
+        self.control = 0;
+        self.queue.get_mut().clear();
+    }
+
+    fn irq(&self) -> bool {
+        // Asserted while interrupts are enabled and a key is waiting
+        self.control & IRQ_ENABLE != 0 && !self.queue.borrow().is_empty()
+    }
+
+    fn name(&self) -> String {
+        String::from("Keyboard")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + STATUS
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut data = vec![self.control];
+        data.extend(self.queue.borrow().iter());
+        data
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let (control, queue) = data.split_first().ok_or_else(|| SnapshotError::InvalidData(self.name()))?;
+        self.control = *control;
+        self.queue = RefCell::new(queue.iter().copied().collect());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::keyboard::{Keyboard, IRQ_ENABLE, KEY_AVAILABLE};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn reads_keys_in_order_and_interrupts_when_asked() {
+        let mut keyboard = Keyboard::new(0xD010);
+        assert_eq!(keyboard.read(0xD011), 0x00);
+
+        keyboard.push_key(b'A');
+        keyboard.push_key(b'B');
+        assert!(!keyboard.irq());
+        keyboard.write(0xD011, IRQ_ENABLE);
+        assert!(keyboard.irq());
+        assert_eq!(keyboard.read(0xD011), KEY_AVAILABLE | IRQ_ENABLE);
+
+        // Peeking doesn't take the key
+        assert_eq!(keyboard.peek(0xD010), b'A');
+        assert_eq!(keyboard.read(0xD010), b'A');
+        assert_eq!(keyboard.read(0xD010), b'B');
+        assert_eq!(keyboard.read(0xD010), 0x00);
+        assert!(!keyboard.irq());
+
+        // Typed keys survive a reset, but not a power cycle
+        keyboard.push_key(b'C');
+        keyboard.reset();
+        assert_eq!(keyboard.pending(), 1);
+        keyboard.power_on();
+        assert_eq!(keyboard.pending(), 0);
+    }
+}
@@ -0,0 +1,291 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Display;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::bus::{AccessKind, BusAccess, BusDevice, ClockRate, MainBus, SnapshotError};
+
+/// A function called with every access a `Logger` sees.
+pub type LogSink = Box<dyn FnMut(LoggedAccess) + Send>;
+
+/// A read or write made to a logged device.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LoggedAccess {
+    /// The cycle the access was made on, as read from the logger's clock, or
+    /// 0 if it doesn't have one.
+    pub cycle: u64,
+
+    /// The name of the logged device.
+    pub device: String,
+
+    /// The access itself.
+    pub access: BusAccess,
+}
+
+impl Display for LoggedAccess {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:>10}  {}  {}", self.cycle, self.device, self.access)
+    }
+}
+
+/// Wraps a device and logs every read and write made to it.
+///
+/// Accesses are passed through to the inner device unchanged, so a `Logger`
+/// can be added to the bus in place of the device it wraps. This is a lighter
+/// alternative to tracing the whole bus when only one device is of interest.
+///
+/// By default each access is reported as a `tracing` info event. A sink set with
+/// [`Logger::with_sink`] receives them instead. Debugger accesses made through
+/// `peek()` and `poke()` are not logged.
+///
+/// Devices don't see the CPU's cycle count, so the cycle an access was made on
+/// is read from a shared clock set with [`Logger::with_clock`], which the host
+/// advances as the CPU runs.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::logger::Logger;
+/// use butterflyrs::bus::ram::Ram;
+/// use butterflyrs::bus::{BusDevice, MainBus};
+///
+/// let mut bus = MainBus::new();
+/// bus.add_device(Box::new(Logger::new(Ram::new(0x0000, 0x00FF))));
+/// bus.write(0x0010, 0x42); // reports "         0  RAM  W 0010 <- 42"
+/// ```
+pub struct Logger<D: BusDevice> {
+    /// The wrapped device.
+    device: D,
+
+    /// Where accesses go instead of `tracing` events, if anywhere.
+    sink: Option<RefCell<LogSink>>,
+
+    /// The clock accesses are stamped with, if any.
+    clock: Option<Arc<AtomicU64>>,
+}
+
+impl<D: BusDevice> Logger<D> {
+    /// Wraps a device, reporting its accesses as `tracing` events.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to log.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Logger` struct.
+    pub fn new(device: D) -> Logger<D> {
+        Logger {
+            device,
+            sink: None,
+            clock: None,
+        }
+    }
+
+    /// Sends accesses to a function instead of reporting them as `tracing` events.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Called with every read and write made to the device.
+    ///
+    /// # Returns
+    ///
+    /// The logger, for chaining.
+    pub fn with_sink(mut self, sink: LogSink) -> Logger<D> {
+        self.sink = Some(RefCell::new(sink));
+        self
+    }
+
+    /// Stamps accesses with the value of a shared clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - The current cycle. The host keeps it up to date, for
+    ///   example by adding the cycles each instruction took.
+    ///
+    /// # Returns
+    ///
+    /// The logger, for chaining.
+    pub fn with_clock(mut self, clock: Arc<AtomicU64>) -> Logger<D> {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Returns the wrapped device.
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Returns the wrapped device mutably. Accesses made through it are not logged.
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    /// Unwraps the device.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    /// Passes an access to the sink, or reports it.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Whether the access was a read or a write.
+    /// * `address` - The address that was accessed.
+    /// * `value` - The byte that was read or written.
+    fn log(&self, kind: AccessKind, address: u16, value: u8) {
+        let entry = LoggedAccess {
+            cycle: self.clock.as_ref().map_or(0, |clock| clock.load(Ordering::Relaxed)),
+            device: self.device.name(),
+            access: BusAccess { kind, address, value },
+        };
+
+        match &self.sink {
+            Some(sink) => (sink.borrow_mut())(entry),
+            None => Self::report(entry),
+        }
+    }
+
+    /// Reports an access as an info event.
+    fn report(entry: LoggedAccess) {
+        tracing::info!("{}", entry);
+    }
+}
+
+impl<D: BusDevice> BusDevice for Logger<D> {
+    fn read(&self, address: u16) -> u8 {
+        let value = self.device.read(address);
+        self.log(AccessKind::Read, address, value);
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.device.write(address, value);
+        self.log(AccessKind::Write, address, value);
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.device.peek(address)
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        self.device.poke(address, value)
+    }
+
+    fn is_memory(&self) -> bool {
+        self.device.is_memory()
+    }
+
+    fn reset(&mut self) {
+        self.device.reset()
+    }
+
+    fn power_on(&mut self) {
+        self.device.power_on()
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.device.tick(cycles)
+    }
+
+    fn clock_rate(&self) -> ClockRate {
+        self.device.clock_rate()
+    }
+
+    fn bus_request(&self) -> bool {
+        self.device.bus_request()
+    }
+
+    fn bus_grant(&mut self, bus: &mut MainBus) {
+        self.device.bus_grant(bus)
+    }
+
+    fn irq(&self) -> bool {
+        self.device.irq()
+    }
+
+    fn name(&self) -> String {
+        self.device.name()
+    }
+
+    fn start_address(&self) -> u16 {
+        self.device.start_address()
+    }
+
+    fn end_address(&self) -> u16 {
+        self.device.end_address()
+    }
+
+    fn size(&self) -> u32 {
+        self.device.size()
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.device.save()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        self.device.load(data)
+    }
+
+    fn contains(&self, address: u16) -> bool {
+        self.device.contains(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use crate::bus::logger::{LoggedAccess, Logger};
+    use crate::bus::ram::Ram;
+    use crate::bus::{AccessKind, BusAccess, MainBus};
+
+    #[test]
+    fn logs_accesses_with_the_cycle_they_were_made_on() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = log.clone();
+        let clock = Arc::new(AtomicU64::new(0));
+        let logger = Logger::new(Ram::new(0x0000, 0x00FF))
+            .with_sink(Box::new(move |entry| sink.lock().unwrap().push(entry)))
+            .with_clock(clock.clone());
+
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(logger));
+        bus.add_device(Box::new(Ram::new(0x0100, 0x01FF)));
+
+        bus.write(0x0010, 0x42);
+        clock.store(12, Ordering::Relaxed);
+        assert_eq!(bus.read(0x0010), 0x42);
+
+        // Other devices and debugger accesses aren't logged
+        bus.write(0x0110, 0x01);
+        bus.poke(0x0011, 0x01);
+        assert_eq!(bus.peek(0x0011), 0x01);
+
+        let entries = log.lock().unwrap();
+        assert_eq!(
+            *entries,
+            vec![
+                LoggedAccess {
+                    cycle: 0,
+                    device: "RAM".into(),
+                    access: BusAccess { kind: AccessKind::Write, address: 0x0010, value: 0x42 },
+                },
+                LoggedAccess {
+                    cycle: 12,
+                    device: "RAM".into(),
+                    access: BusAccess { kind: AccessKind::Read, address: 0x0010, value: 0x42 },
+                },
+            ]
+        );
+        assert_eq!(std::format!("{}", entries[1]), "        12  RAM  R 0010 -> 42");
+    }
+}
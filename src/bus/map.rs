@@ -0,0 +1,87 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// Whether a device is memory or I/O.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeviceKind {
+    /// A device that stores bytes, such as RAM or ROM.
+    Memory,
+
+    /// Any other device.
+    Io,
+}
+
+impl Display for DeviceKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeviceKind::Memory => f.pad("Memory"),
+            DeviceKind::Io => f.pad("I/O"),
+        }
+    }
+}
+
+/// A device's place in the memory map.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MemoryMapEntry {
+    /// The first address the device decodes.
+    pub start: u16,
+
+    /// The last address the device decodes.
+    pub end: u16,
+
+    /// The name of the device.
+    pub name: String,
+
+    /// Whether the device is memory or I/O.
+    pub kind: DeviceKind,
+
+    /// The device's priority where it overlaps others.
+    pub priority: i32,
+}
+
+impl MemoryMapEntry {
+    /// Returns the number of addresses the device decodes.
+    ///
+    /// A device that covers the whole address space decodes 65,536.
+    pub fn size(&self) -> u32 {
+        self.end as u32 - self.start as u32 + 1
+    }
+}
+
+/// Every device on a bus, ordered by start address.
+///
+/// Printing a `MemoryMap` gives a table of the devices, which is handy for
+/// checking how a machine is wired:
+///
+/// ```text
+/// Start  End    Size   Kind    Priority  Name
+/// 0000   7FFF   32768  Memory  0         RAM
+/// 8000   8002   3      I/O     0         Blink8
+/// C000   FFFF   16384  Memory  0         ROM
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct MemoryMap {
+    /// The devices, ordered by start address. Devices that start at the same
+    /// address are in decode order.
+    pub entries: Vec<MemoryMapEntry>,
+}
+
+impl Display for MemoryMap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Start  End    Size   Kind    Priority  Name")?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{:04X}   {:04X}   {:<5}  {:<6}  {:<8}  {}",
+                entry.start,
+                entry.end,
+                entry.size(),
+                entry.kind,
+                entry.priority,
+                entry.name
+            )?;
+        }
+        Ok(())
+    }
+}
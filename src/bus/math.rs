@@ -0,0 +1,187 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The offset of the low byte of operand A, the multiplicand or dividend.
+pub const A_LOW: u16 = 0;
+
+/// The offset of the high byte of operand A. Only division uses it.
+pub const A_HIGH: u16 = 1;
+
+/// The offset of operand B, the multiplier or divisor.
+pub const B: u16 = 2;
+
+/// The offset of the low byte of the product.
+pub const PRODUCT_LOW: u16 = 3;
+
+/// The offset of the high byte of the product.
+pub const PRODUCT_HIGH: u16 = 4;
+
+/// The offset of the low byte of the quotient.
+pub const QUOTIENT_LOW: u16 = 5;
+
+/// The offset of the high byte of the quotient.
+pub const QUOTIENT_HIGH: u16 = 6;
+
+/// The offset of the remainder.
+pub const REMAINDER: u16 = 7;
+
+/// A multiply and divide unit, like those on some expansion boards.
+///
+/// The machine writes the operands and reads the results straight back,
+/// with no command to give and nothing to wait for:
+///
+/// * `A_LOW` and `A_HIGH` - Operand A, 16 bits.
+/// * `B` - Operand B, 8 bits.
+/// * `PRODUCT_LOW` and `PRODUCT_HIGH` - The low byte of A times B.
+/// * `QUOTIENT_LOW` and `QUOTIENT_HIGH` - A divided by B.
+/// * `REMAINDER` - A modulo B.
+///
+/// Dividing by zero gives a quotient of `$FFFF` and leaves A as the
+/// remainder, truncated to 8 bits, as the SNES's divider does. The results
+/// are read-only.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::math::{MathAccelerator, A_LOW, B, PRODUCT_HIGH, PRODUCT_LOW};
+/// use butterflyrs::bus::BusDevice;
+///
+/// let mut math = MathAccelerator::new(0xD030);
+/// math.write(0xD030 + A_LOW, 200);
+/// math.write(0xD030 + B, 100);
+///
+/// assert_eq!(math.read(0xD030 + PRODUCT_LOW), 0x20);
+/// assert_eq!(math.read(0xD030 + PRODUCT_HIGH), 0x4E);
+/// ```
+pub struct MathAccelerator {
+    /// The address of the first register.
+    pub start: u16,
+
+    /// Operand A.
+    a: u16,
+
+    /// Operand B.
+    b: u8,
+}
+
+impl MathAccelerator {
+    /// Creates a math unit with both operands 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the first register.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `MathAccelerator` struct.
+    pub fn new(start: u16) -> MathAccelerator {
+        MathAccelerator { start, a: 0, b: 0 }
+    }
+
+    /// Returns the product of the low byte of A and B.
+    pub fn product(&self) -> u16 {
+        (self.a & 0xFF) * self.b as u16
+    }
+
+    /// Returns the quotient and remainder of A divided by B.
+    pub fn quotient(&self) -> (u16, u8) {
+        match self.b {
+            0 => (0xFFFF, self.a as u8),
+            b => (self.a / b as u16, (self.a % b as u16) as u8),
+        }
+    }
+}
+
+impl BusDevice for MathAccelerator {
+    fn read(&self, address: u16) -> u8 {
+        let [product_low, product_high] = self.product().to_le_bytes();
+        let (quotient, remainder) = self.quotient();
+        let [quotient_low, quotient_high] = quotient.to_le_bytes();
+
+        match address - self.start {
+            A_LOW => self.a as u8,
+            A_HIGH => (self.a >> 8) as u8,
+            B => self.b,
+            PRODUCT_LOW => product_low,
+            PRODUCT_HIGH => product_high,
+            QUOTIENT_LOW => quotient_low,
+            QUOTIENT_HIGH => quotient_high,
+            _ => remainder,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address - self.start {
+            A_LOW => self.a = (self.a & 0xFF00) | value as u16,
+            A_HIGH => self.a = (self.a & 0x00FF) | (value as u16) << 8,
+            B => self.b = value,
+            // The results can't be written
+            _ => {}
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        self.a = 0;
+        self.b = 0;
+    }
+
+    fn name(&self) -> String {
+        String::from("Math accelerator")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + REMAINDER
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let [low, high] = self.a.to_le_bytes();
+        vec![low, high, self.b]
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let [low, high, b]: [u8; 3] = data.try_into().map_err(|_| SnapshotError::InvalidData(self.name()))?;
+        self.a = u16::from_le_bytes([low, high]);
+        self.b = b;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::math::{MathAccelerator, A_HIGH, A_LOW, B, PRODUCT_HIGH, QUOTIENT_HIGH, QUOTIENT_LOW, REMAINDER};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn multiplies_and_divides() {
+        let mut math = MathAccelerator::new(0xD030);
+
+        // 0xFF * 0xFF = 0xFE01, using only the low byte of A
+        math.write(0xD030 + A_LOW, 0xFF);
+        math.write(0xD030 + A_HIGH, 0x12);
+        math.write(0xD030 + B, 0xFF);
+        assert_eq!(math.product(), 0xFE01);
+        assert_eq!(math.read(0xD030 + PRODUCT_HIGH), 0xFE);
+
+        // 0x12FF / 0x10 = 0x012F remainder 0x0F
+        math.write(0xD030 + B, 0x10);
+        assert_eq!(math.read(0xD030 + QUOTIENT_LOW), 0x2F);
+        assert_eq!(math.read(0xD030 + QUOTIENT_HIGH), 0x01);
+        assert_eq!(math.read(0xD030 + REMAINDER), 0x0F);
+
+        // Dividing by zero doesn't panic, and the results can't be written
+        math.write(0xD030 + B, 0x00);
+        math.write(0xD030 + QUOTIENT_LOW, 0x00);
+        assert_eq!(math.quotient(), (0xFFFF, 0xFF));
+        assert_eq!(math.read(0xD030 + QUOTIENT_LOW), 0xFF);
+    }
+}
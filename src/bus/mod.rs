@@ -1,9 +1,56 @@
 pub mod ram;
 pub mod rom;
 pub mod blink8;
+pub mod block;
+pub mod character;
+pub mod exit;
+pub mod flash;
+pub mod flat;
+pub mod framebuffer;
+pub mod host;
+pub mod keyboard;
+pub mod logger;
+pub mod math;
+pub mod nvram;
+pub mod pia;
+pub mod rng;
+pub mod serial;
+pub mod sound;
+pub mod via;
+mod access;
+#[cfg(feature = "std")]
+mod backing;
+mod clock;
+mod error;
+mod interrupt;
+mod map;
+mod policy;
+mod scheduler;
+mod shared;
+mod snapshot;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::{Cell, RefCell};
+
+pub use crate::bus::access::{AccessKind, AccessOrigin, BusAccess, BusTracer};
+pub use crate::bus::clock::ClockRate;
+pub use crate::bus::error::{BusError, MapError};
+pub use crate::bus::interrupt::{InterruptController, IrqLine};
+pub use crate::bus::map::{DeviceKind, MemoryMap, MemoryMapEntry};
+pub use crate::bus::policy::UnmappedPolicy;
+pub use crate::bus::scheduler::{EventHandler, EventId, Scheduler};
+pub use crate::bus::shared::SharedBus;
+pub use crate::bus::snapshot::{BusSnapshot, DeviceSnapshot, SnapshotError};
 
 /// Represents a device connected to the bus.
-pub trait BusDevice {
+///
+/// Devices must be `Send` so that a machine built with the `sync` feature can
+/// be moved to another thread. They are also `Any`, so the host can get a
+/// device back from the bus by its type with `MainBus::device_of_type()`.
+pub trait BusDevice: Any + Send {
     /// Reads a byte from the device at the specified address.
     ///
     /// # Arguments
@@ -23,12 +70,117 @@ pub trait BusDevice {
     /// * `value` - The byte value to write.
     fn write(&mut self, address: u16, value: u8);
 
+    /// Reads a byte without any of the side effects a read would have.
+    ///
+    /// Debuggers and disassemblers use this to inspect a device without, for
+    /// example, clearing an interrupt flag or consuming a byte from a FIFO.
+    /// Devices whose reads have side effects should override it; the default
+    /// just calls `read()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte a read would return, or 0 if the address is out of range.
+    fn peek(&self, address: u16) -> u8 {
+        self.read(address)
+    }
+
+    /// Writes a byte without any of the side effects a write would have.
+    ///
+    /// Debuggers use this to patch a device's contents. Devices whose writes
+    /// do more than store a value should override it; the default just calls
+    /// `write()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write to.
+    /// * `value` - The byte value to write.
+    fn poke(&mut self, address: u16, value: u8) {
+        self.write(address, value)
+    }
+
     /// Returns whether the device is memory or I/O.
     fn is_memory(&self) -> bool;
 
-    /// Resets the device by clearing its data.
+    /// Resets the device, as when the machine's reset line is asserted.
+    ///
+    /// I/O devices go back to their register defaults. Memory keeps its
+    /// contents, as it would on real hardware, so a program loaded before a
+    /// reset is still there afterwards.
     fn reset(&mut self);
 
+    /// Puts the device in the state it has when the machine is switched on.
+    ///
+    /// Volatile memory is filled with its power-on pattern here. The default
+    /// just calls `reset()`, which suits I/O devices and memory that keeps its
+    /// contents, such as ROM.
+    fn power_on(&mut self) {
+        self.reset()
+    }
+
+    /// Advances the device by a number of CPU cycles.
+    ///
+    /// The CPU calls this through `MainBus::tick()` as it runs, so timers,
+    /// video beams, baud rate generators and the like keep in step with it.
+    /// With `Cpu::tick()` it is called every cycle; `Cpu::clock()` and
+    /// `Cpu::step()` call it once per instruction, with the instruction's
+    /// cycles, as the instruction executes. The default does nothing, which
+    /// suits devices that don't keep time.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles of the device's own clock that have
+    ///   elapsed since the last call. See `clock_rate()`.
+    fn tick(&mut self, cycles: u64) {
+        let _ = cycles;
+    }
+
+    /// Returns how fast the device is clocked, compared to the CPU.
+    ///
+    /// The bus scales the CPU cycles it passes to `tick()` by this rate, so a
+    /// device at CPU/16 sees one cycle for every sixteen the CPU runs, and a
+    /// video chip at three times the CPU clock sees three for every one. The
+    /// bus asks on every tick, so a device can change speed as it runs. The
+    /// default is the CPU's own clock.
+    fn clock_rate(&self) -> ClockRate {
+        ClockRate::CPU
+    }
+
+    /// Returns whether the device wants to use the bus itself, the way a DMA
+    /// controller does.
+    ///
+    /// The bus asks after ticking the device, and calls `bus_grant()` if it
+    /// does. The default never asks.
+    fn bus_request(&self) -> bool {
+        false
+    }
+
+    /// Uses the bus, after the device asked for it with `bus_request()`.
+    ///
+    /// The device is taken off the bus while it has it, so nothing decodes
+    /// its own addresses, and put back in its place afterwards. It must not
+    /// add or remove devices.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus, with every other device on it.
+    fn bus_grant(&mut self, bus: &mut MainBus) {
+        let _ = bus;
+    }
+
+    /// Returns whether the device is asserting the IRQ line.
+    ///
+    /// The bus combines every device's answer into the one IRQ input of the
+    /// CPU, which samples it before the last cycle of each instruction. IRQ is level
+    /// sensitive, so a device should keep asserting it until the interrupt
+    /// handler has dealt with the cause. The default never asserts it.
+    fn irq(&self) -> bool {
+        false
+    }
+
     /// Returns the name of the device.
     fn name(&self) -> String;
 
@@ -41,18 +193,58 @@ pub trait BusDevice {
     /// Returns the size of the device in bytes.
     ///
     /// The size is calculated by subtracting the start address from the end address,
-    /// and adding 1 to account for the inclusive range.
+    /// and adding 1 to account for the inclusive range. It is a `u32` so that a
+    /// device covering all 64K fits.
     ///
     /// # Returns
     ///
     /// The size of the device in bytes.
-    fn size(&self) -> u16 {
+    fn size(&self) -> u32 {
         // Calculate the size of the device
-        self.end_address() - self.start_address() + 1
+        self.end_address() as u32 - self.start_address() as u32 + 1
+    }
+
+    /// Returns the device's state for a save state.
+    ///
+    /// Devices without any state can rely on the default, which saves nothing.
+    ///
+    /// # Returns
+    ///
+    /// The device's state, in a format of the device's choosing.
+    fn save(&self) -> Vec<u8> {
+        Vec::new()
     }
-}
 
+    /// Restores state returned by `save()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The saved state.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the state was restored, or `SnapshotError::InvalidData` if it doesn't fit the device.
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        if data.is_empty() {
+            Ok(())
+        } else {
+            Err(SnapshotError::InvalidData(self.name()))
+        }
+    }
 
+    /// Returns whether the device decodes an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the address is between the start and end addresses, inclusive.
+    fn contains(&self, address: u16) -> bool {
+        self.start_address() <= address && address <= self.end_address()
+    }
+}
 
 /// Represents the main bus of the system.
 ///
@@ -60,8 +252,69 @@ pub trait BusDevice {
 pub struct MainBus {
     /// The list of devices connected to the bus.
     ///
-    /// Each device is represented by a `Box<dyn BusDevice>` trait object.
-    pub devices: Vec<Box<dyn BusDevice>>,
+    /// Each device is represented by a `Box<dyn BusDevice>` trait object. The
+    /// list is kept in decode order: highest priority first, and devices of equal
+    /// priority in the order they were added. See `add_device_with_priority()`.
+    /// It is only changed through the bus, which keeps `pages` in step with it.
+    devices: Vec<Box<dyn BusDevice>>,
+
+    /// The priority of each device, in the same order as `devices`.
+    priorities: Vec<i32>,
+
+    /// How far each device is into its next cycle, in the same order as
+    /// `devices`. Only devices whose clock rate doesn't divide evenly into the
+    /// CPU's use it. See `ClockRate::scale()`.
+    clock_phases: Vec<u64>,
+
+    /// How reads and writes to unmapped addresses are handled.
+    pub unmapped_policy: UnmappedPolicy,
+
+    /// Whether adding a device that overlaps one already on the bus is an error.
+    ///
+    /// Overlaps are allowed by default, since mapping one device over part of
+    /// another is a common way to wire a machine.
+    pub reject_overlaps: bool,
+
+    /// The last value driven on the data bus, returned by unmapped reads under
+    /// the open-bus policy.
+    data_bus: Cell<u8>,
+
+    /// The most recent unmapped access, recorded under the error policy.
+    error: Cell<Option<BusError>>,
+
+    /// Called with every read and write made through `read()`, `write()`,
+    /// `device_read()` and `device_write()`.
+    tracer: Option<RefCell<BusTracer>>,
+
+    /// Which device decodes each 256-byte page, so most accesses don't have to
+    /// scan the device list.
+    pages: Box<[Page; 256]>,
+
+    /// How many devices were on the bus when `pages` was built. The list is
+    /// one shorter while a device is off it to be granted the bus, and then
+    /// decoding falls back to a scan.
+    paged_devices: usize,
+
+    /// The IRQ lines of sources that don't report their interrupts through
+    /// `BusDevice::irq()`.
+    pub interrupts: InterruptController,
+
+    /// The events to fire at chosen cycles as the bus is ticked.
+    pub scheduler: Scheduler,
+}
+
+/// How an address in a page is decoded.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Page {
+    /// No device decodes any address in the page.
+    Unmapped,
+
+    /// The device at this index in `devices` comes first in decode order and
+    /// covers the whole page.
+    Device(usize),
+
+    /// Devices start or end inside the page, so the device list is scanned.
+    Mixed,
 }
 
 impl MainBus {
@@ -73,18 +326,68 @@ impl MainBus {
     pub fn new() -> MainBus {
         MainBus {
             devices: Vec::new(),
+            priorities: Vec::new(),
+            clock_phases: Vec::new(),
+            unmapped_policy: UnmappedPolicy::default(),
+            reject_overlaps: false,
+            data_bus: Cell::new(0),
+            error: Cell::new(None),
+            tracer: None,
+            pages: Box::new([Page::Unmapped; 256]),
+            paged_devices: 0,
+            interrupts: InterruptController::new(),
+            scheduler: Scheduler::new(),
         }
     }
 
-    /// Resets all the devices connected to the bus.
+    /// Asserts the reset line of every device connected to the bus.
     ///
-    /// This function clears the data of each device, resetting them to an empty state.
+    /// Each device resets in its own way: I/O devices go back to their
+    /// register defaults, while memory keeps its contents. See `BusDevice::reset()`.
     pub fn reset(&mut self) {
         for device in self.devices.iter_mut() {
             device.reset();
         }
     }
 
+    /// Advances every device connected to the bus by a number of CPU cycles.
+    ///
+    /// The CPU calls this as it runs. See `BusDevice::tick()`. Each device is
+    /// ticked at its own `BusDevice::clock_rate()`. Devices that ask for the
+    /// bus with `BusDevice::bus_request()` are granted it here, and then the
+    /// scheduler fires the events that have come due.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of CPU cycles that have elapsed since the last call.
+    pub fn tick(&mut self, cycles: u64) {
+        for index in 0..self.devices.len() {
+            let device_cycles = self.devices[index].clock_rate().scale(cycles, &mut self.clock_phases[index]);
+            if device_cycles > 0 {
+                self.devices[index].tick(device_cycles);
+            }
+
+            if self.devices[index].bus_request() {
+                // Decoding scans the list while the device is off it, then the page table is good again
+                let mut device = self.devices.remove(index);
+                device.bus_grant(self);
+                self.devices.insert(index, device);
+            }
+        }
+
+        let now = self.scheduler.now().saturating_add(cycles);
+        self.run_events(now);
+    }
+
+    /// Puts every device connected to the bus in its power-on state.
+    ///
+    /// Unlike `reset()`, this fills RAM with its power-on pattern. See `BusDevice::power_on()`.
+    pub fn power_on(&mut self) {
+        for device in self.devices.iter_mut() {
+            device.power_on();
+        }
+    }
+
     /// Checks if the given `address` is within the range of any memory devices connected to the bus.
     ///
     /// # Arguments
@@ -95,17 +398,7 @@ impl MainBus {
     ///
     /// Returns `true` if the address is within the range of a memory device, `false` otherwise.
     pub fn is_memory(&self, address: u16) -> bool {
-        // Iterate over each device connected to the bus
-        for device in self.devices.iter() {
-            // Check if the address is within the range of the current device
-            if device.start_address() <= address && address <= device.end_address() {
-                // If the device is memory, return `true`
-                // If the device is I/O, continue to the next device
-                return device.is_memory();
-            }
-        }
-        // If the address is not within the range of any device, return `false`
-        false
+        self.device_at(address).is_some_and(|device| device.is_memory())
     }
 
     /// Checks if the given `address` is within the range of any I/O devices connected to the bus.
@@ -118,75 +411,1098 @@ impl MainBus {
     ///
     /// Returns `true` if the address is within the range of an I/O device, `false` otherwise.
     pub fn is_io(&self, address: u16) -> bool {
-        // Iterate over each device connected to the bus
-        for device in self.devices.iter() {
-            // Check if the address is within the range of the current device
-            if device.start_address() <= address && address <= device.end_address() {
-                // If the device is memory, return `false`
-                // If the device is I/O, return `true`
-                return !device.is_memory();
+        self.device_at(address).is_some_and(|device| !device.is_memory())
+    }
+
+    /// Finds the device that decodes an address.
+    ///
+    /// Where devices overlap, the one with the highest priority wins, and of
+    /// devices with the same priority, the one added first. Every read, write and
+    /// memory/I/O check is routed the same way.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to decode.
+    ///
+    /// # Returns
+    ///
+    /// The device, or `None` if the address is unmapped.
+    pub fn device_at(&self, address: u16) -> Option<&dyn BusDevice> {
+        self.device_index_at(address).map(|index| self.devices[index].as_ref())
+    }
+
+    /// Returns the devices connected to the bus, in decode order.
+    ///
+    /// They are changed with `add_device()`, `remove_device()` and
+    /// `replace_device()`, which keep the bus's page table up to date.
+    pub fn devices(&self) -> &[Box<dyn BusDevice>] {
+        &self.devices
+    }
+
+    /// Finds the position in `devices` of the device that decodes an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to decode.
+    ///
+    /// # Returns
+    ///
+    /// The index of the device, or `None` if the address is unmapped.
+    pub fn device_index_at(&self, address: u16) -> Option<usize> {
+        // The page table is only trusted while the device list is the one it was built for
+        if self.paged_devices == self.devices.len() {
+            match self.pages[(address >> 8) as usize] {
+                Page::Unmapped => return None,
+                // A device can decode less than its range, in which case a later one may win
+                Page::Device(index) if self.devices[index].contains(address) => return Some(index),
+                _ => {}
             }
         }
-        // If the address is not within the range of any device, return `false`
-        false
+
+        self.devices.iter().position(|device| device.contains(address))
+    }
+
+    /// Rebuilds the table that maps each page of the address space to a device.
+    ///
+    /// The table is rebuilt whenever a device is added, removed or replaced.
+    /// Call this after changing a device's address range.
+    pub fn rebuild_page_table(&mut self) {
+        for (page, entry) in self.pages.iter_mut().enumerate() {
+            let start = (page as u16) << 8;
+            let end = start | 0x00FF;
+
+            // The first device in decode order that touches the page decides it
+            let first = self
+                .devices
+                .iter()
+                .position(|device| device.start_address() <= end && start <= device.end_address());
+            *entry = match first {
+                None => Page::Unmapped,
+                Some(index) if self.devices[index].start_address() <= start && end <= self.devices[index].end_address() => {
+                    Page::Device(index)
+                }
+                Some(_) => Page::Mixed,
+            };
+        }
+        self.paged_devices = self.devices.len();
+    }
+
+    /// Lists every pair of devices whose address ranges overlap.
+    ///
+    /// Overlaps are allowed, for example to map a ROM over part of a RAM, but
+    /// an unexpected one usually means a wiring mistake.
+    ///
+    /// # Returns
+    ///
+    /// The indices in `devices` of each overlapping pair. The first index is the
+    /// device that wins where they overlap.
+    pub fn overlaps(&self) -> Vec<(usize, usize)> {
+        let mut overlaps = Vec::new();
+        for (first, a) in self.devices.iter().enumerate() {
+            for (second, b) in self.devices.iter().enumerate().skip(first + 1) {
+                if a.start_address() <= b.end_address() && b.start_address() <= a.end_address() {
+                    overlaps.push((first, second));
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// Finds the device that decodes an address, for writing to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to decode.
+    ///
+    /// # Returns
+    ///
+    /// The device, or `None` if the address is unmapped.
+    fn device_at_mut(&mut self, address: u16) -> Option<&mut Box<dyn BusDevice>> {
+        self.device_index_at(address).map(|index| &mut self.devices[index])
     }
 
-    /// Adds a device to the bus.
+    /// Adds a device to the bus with the default priority of 0.
     ///
     /// # Arguments
     ///
     /// * `device` - The device to add to the bus.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the device can't be added. See `try_add_device()`.
     pub fn add_device(&mut self, device: Box<dyn BusDevice>) {
-        // Push the device to the list of devices connected to the bus.
-        self.devices.push(device);
+        self.add_device_with_priority(device, 0);
     }
 
-    /// Reads a byte from the bus at the specified address.
+    /// Adds a device to the bus with a priority for where it overlaps others.
+    ///
+    /// Where two devices decode the same address, the one with the higher
+    /// priority handles it. Devices with the same priority are decoded in the
+    /// order they were added, so the first one added wins.
     ///
     /// # Arguments
     ///
-    /// * `address` - The address to read from.
+    /// * `device` - The device to add to the bus.
+    /// * `priority` - The device's priority. Higher values win.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the device can't be added. See `try_add_device_with_priority()`.
+    pub fn add_device_with_priority(&mut self, device: Box<dyn BusDevice>, priority: i32) {
+        if let Err(error) = self.try_add_device_with_priority(device, priority) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Adds a device to the bus with the default priority of 0, checking that it fits.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to add to the bus.
     ///
     /// # Returns
     ///
-    /// The byte read from the bus, or 0 if the address is out of range.
-    pub fn read(&self, address: u16) -> u8 {
-        // Iterate over each device connected to the bus
-        for device in self.devices.iter() {
-            // Check if the address is within the range of the current device
-            if device.start_address() <= address && address <= device.end_address() {
-                // Return the byte read from the device
-                return device.read(address);
+    /// `Ok(())` if the device was added, or why it wasn't. See `try_add_device_with_priority()`.
+    pub fn try_add_device(&mut self, device: Box<dyn BusDevice>) -> Result<(), MapError> {
+        self.try_add_device_with_priority(device, 0)
+    }
+
+    /// Adds a device to the bus with a priority, checking that it fits.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to add to the bus.
+    /// * `priority` - The device's priority. Higher values win.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the device was added, `MapError::InvalidRange` if its start
+    /// address is after its end address, or `MapError::Overlap` if it overlaps
+    /// another device and `reject_overlaps` is set.
+    pub fn try_add_device_with_priority(&mut self, device: Box<dyn BusDevice>, priority: i32) -> Result<(), MapError> {
+        self.check_device(device.as_ref(), None)?;
+
+        // A device is off the list while it is granted the bus, so the priorities may be one longer
+        self.priorities.resize(self.devices.len(), 0);
+
+        // Go after every device of the same or higher priority
+        let index = self.priorities.iter().position(|&other| other < priority).unwrap_or(self.devices.len());
+        self.devices.insert(index, device);
+        self.priorities.insert(index, priority);
+        self.clock_phases.resize(self.devices.len() - 1, 0);
+        self.clock_phases.insert(index, 0);
+        self.rebuild_page_table();
+        Ok(())
+    }
+
+    /// Checks that a device can go on the bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to check.
+    /// * `replacing` - The index of a device it would replace, which it is
+    ///   allowed to overlap.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the device fits, or why it doesn't.
+    fn check_device(&self, device: &dyn BusDevice, replacing: Option<usize>) -> Result<(), MapError> {
+        let (start, end) = (device.start_address(), device.end_address());
+        if start > end {
+            return Err(MapError::InvalidRange { name: device.name(), start, end });
+        }
+        if self.reject_overlaps {
+            let overlapped = self
+                .devices
+                .iter()
+                .enumerate()
+                .find(|&(index, other)| Some(index) != replacing && other.start_address() <= end && start <= other.end_address());
+            if let Some((_, other)) = overlapped {
+                return Err(MapError::Overlap { name: device.name(), other: other.name() });
             }
         }
-        // If the address is not within the range of any device, return 0
-        0
+        Ok(())
     }
 
-    /// Writes a byte to the bus at the specified address.
+    /// Finds the first device in decode order with a name.
     ///
-    /// This function iterates over each device connected to the bus and checks if the address is within the range of the current device.
-    /// If the address is within the range, it calls the `write` method of the device to perform the write operation.
+    /// # Arguments
+    ///
+    /// * `name` - The name of the device, as returned by `BusDevice::name()`.
+    ///
+    /// # Returns
+    ///
+    /// The index of the device in `devices`, or `None` if no device has the name.
+    pub fn find_device(&self, name: &str) -> Option<usize> {
+        self.devices.iter().position(|device| device.name() == name)
+    }
+
+    /// Finds the first device in decode order of a type.
+    ///
+    /// This is how the host reaches a device once it is on the bus, for
+    /// example to feed it input or collect its output.
+    ///
+    /// # Returns
+    ///
+    /// The device, or `None` if there is no device of the type.
+    pub fn device_of_type<T: BusDevice>(&self) -> Option<&T> {
+        self.devices.iter().find_map(|device| (device.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    /// Finds the first device in decode order of a type, for changing it.
+    ///
+    /// # Returns
+    ///
+    /// The device, or `None` if there is no device of the type.
+    pub fn device_of_type_mut<T: BusDevice>(&mut self) -> Option<&mut T> {
+        self.devices.iter_mut().find_map(|device| (device.as_mut() as &mut dyn Any).downcast_mut::<T>())
+    }
+
+    /// Removes a device from the bus.
     ///
     /// # Arguments
     ///
-    /// * `address` - The address to write to.
-    /// * `value` - The byte value to write.
+    /// * `index` - The index of the device in `devices`.
+    ///
+    /// # Returns
+    ///
+    /// The device, so its state can be inspected or it can be added again
+    /// later, or `None` if there is no device at the index.
+    pub fn remove_device(&mut self, index: usize) -> Option<Box<dyn BusDevice>> {
+        if index >= self.devices.len() {
+            return None;
+        }
+
+        self.priorities.resize(self.devices.len(), 0);
+        self.priorities.remove(index);
+        self.clock_phases.resize(self.devices.len(), 0);
+        self.clock_phases.remove(index);
+        let device = self.devices.remove(index);
+        self.rebuild_page_table();
+        Some(device)
+    }
+
+    /// Removes the first device in decode order with a name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the device, as returned by `BusDevice::name()`.
+    ///
+    /// # Returns
+    ///
+    /// The device, or `None` if no device has the name.
+    pub fn remove_device_named(&mut self, name: &str) -> Option<Box<dyn BusDevice>> {
+        let index = self.find_device(name)?;
+        self.remove_device(index)
+    }
+
+    /// Moves every device from another bus onto this one, keeping their priorities.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The bus to take the devices from, which is left with none.
+    pub(crate) fn take_devices(&mut self, other: &mut MainBus) {
+        if other.devices.is_empty() {
+            return;
+        }
+
+        other.priorities.resize(other.devices.len(), 0);
+        for (device, priority) in other.devices.drain(..).zip(other.priorities.drain(..)) {
+            self.add_device_with_priority(device, priority);
+        }
+        other.clock_phases.clear();
+        other.rebuild_page_table();
+    }
+
+    /// Swaps a device on the bus for another, such as when changing cartridges.
+    ///
+    /// The new device takes the old one's place in decode order and its priority.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the device in `devices`.
+    /// * `device` - The device to put in its place.
+    ///
+    /// # Returns
+    ///
+    /// The device that was replaced.
     ///
     /// # Panics
     ///
-    /// If the address is out of range, the function will panic.
-    pub fn write(&mut self, address: u16, value: u8) {
-        // Iterate over each device connected to the bus
-        for device in self.devices.iter_mut() {
-            // Check if the address is within the range of the current device
-            if device.start_address() <= address && address <= device.end_address() {
-                // Call the `write` method of the device to perform the write operation
-                device.write(address, value);
-                return;
-            }
+    /// Panics if there is no device at the index, or if the new device can't be
+    /// added for one of the reasons `try_add_device()` gives. It may overlap the
+    /// device it replaces.
+    pub fn replace_device(&mut self, index: usize, device: Box<dyn BusDevice>) -> Box<dyn BusDevice> {
+        assert!(index < self.devices.len(), "no device at index {}", index);
+        if let Err(error) = self.check_device(device.as_ref(), Some(index)) {
+            panic!("{}", error);
+        }
+
+        let old = core::mem::replace(&mut self.devices[index], device);
+        if let Some(phase) = self.clock_phases.get_mut(index) {
+            *phase = 0;
         }
-        // If the address is not within the range of any device, panic with an error message
-        panic!("Address out of range: {:04X}", address);
+        self.rebuild_page_table();
+        old
     }
-}
\ No newline at end of file
+
+    /// Describes where every device on the bus is mapped.
+    ///
+    /// # Returns
+    ///
+    /// The memory map, which prints as a table.
+    pub fn memory_map(&self) -> MemoryMap {
+        let mut entries: Vec<MemoryMapEntry> = self
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(index, device)| MemoryMapEntry {
+                start: device.start_address(),
+                end: device.end_address(),
+                name: device.name(),
+                kind: if device.is_memory() { DeviceKind::Memory } else { DeviceKind::Io },
+                priority: self.priorities.get(index).copied().unwrap_or(0),
+            })
+            .collect();
+
+        // The sort is stable, so devices that start together stay in decode order
+        entries.sort_by_key(|entry| entry.start);
+        MemoryMap { entries }
+    }
+
+    /// Reads a byte from the bus at the specified address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte read from the bus. If the address is out of range, the value
+    /// depends on the `unmapped_policy`.
+    pub fn read(&self, address: u16) -> u8 {
+        self.read_from(AccessOrigin::Cpu, address)
+    }
+
+    /// Reads a byte from the bus on behalf of a device, such as a DMA controller.
+    ///
+    /// The read is handled exactly like `read()`, but is traced as coming from a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte read from the bus.
+    pub fn device_read(&self, address: u16) -> u8 {
+        self.read_from(AccessOrigin::Device, address)
+    }
+
+    /// Reads a byte from the bus, applying the unmapped policy and tracing the access.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Who is making the access.
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte read from the bus.
+    fn read_from(&self, origin: AccessOrigin, address: u16) -> u8 {
+        let value = match self.try_read(address) {
+            Ok(value) => {
+                // Remember the value for open-bus reads
+                self.data_bus.set(value);
+                value
+            }
+            Err(error) => self.unmapped(error),
+        };
+
+        self.trace(origin, BusAccess { kind: AccessKind::Read, address, value });
+        value
+    }
+
+    /// Reads a byte from the bus, reporting unmapped addresses as an error.
+    ///
+    /// The `unmapped_policy` is not consulted.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte read from the device, or `BusError::UnmappedRead` if no device decodes the address.
+    pub fn try_read(&self, address: u16) -> Result<u8, BusError> {
+        match self.device_at(address) {
+            Some(device) => Ok(device.read(address)),
+            None => Err(BusError::UnmappedRead(address)),
+        }
+    }
+
+    /// Writes a byte to the bus at the specified address.
+    ///
+    /// This function iterates over each device connected to the bus and checks if the address is within the range of the current device.
+    /// If the address is within the range, it calls the `write` method of the device to perform the write operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write to.
+    /// * `value` - The byte value to write.
+    ///
+    /// If the address is out of range, the write is handled according to the `unmapped_policy`.
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.write_from(AccessOrigin::Cpu, address, value);
+    }
+
+    /// Writes a byte to the bus on behalf of a device, such as a DMA controller.
+    ///
+    /// The write is handled exactly like `write()`, but is traced as coming from a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write to.
+    /// * `value` - The byte value to write.
+    pub fn device_write(&mut self, address: u16, value: u8) {
+        self.write_from(AccessOrigin::Device, address, value);
+    }
+
+    /// Writes a byte to the bus, applying the unmapped policy and tracing the access.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Who is making the access.
+    /// * `address` - The address to write to.
+    /// * `value` - The byte value to write.
+    fn write_from(&mut self, origin: AccessOrigin, address: u16, value: u8) {
+        // The value is driven onto the bus whether or not anything is listening
+        self.data_bus.set(value);
+
+        if let Err(error) = self.try_write(address, value) {
+            self.unmapped(error);
+        }
+
+        self.trace(origin, BusAccess { kind: AccessKind::Write, address, value });
+    }
+
+    /// Saves the state of the bus and every device on it.
+    ///
+    /// # Returns
+    ///
+    /// A `BusSnapshot` that can be restored with `load_state()`.
+    pub fn save_state(&self) -> BusSnapshot {
+        BusSnapshot {
+            devices: self
+                .devices
+                .iter()
+                .map(|device| DeviceSnapshot {
+                    name: device.name(),
+                    start: device.start_address(),
+                    end: device.end_address(),
+                    data: device.save(),
+                })
+                .collect(),
+            data_bus: self.data_bus.get(),
+        }
+    }
+
+    /// Restores a state saved by `save_state()`.
+    ///
+    /// The bus must have the same devices, in the same order, as when the
+    /// snapshot was taken. Nothing is changed unless they all match.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The saved state.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the state was restored, or the reason it couldn't be.
+    pub fn load_state(&mut self, snapshot: &BusSnapshot) -> Result<(), SnapshotError> {
+        // Make sure the snapshot was taken from a bus wired the same way
+        if snapshot.devices.len() != self.devices.len() {
+            return Err(SnapshotError::DeviceCount { expected: self.devices.len(), found: snapshot.devices.len() });
+        }
+        for (index, (device, saved)) in self.devices.iter().zip(snapshot.devices.iter()).enumerate() {
+            if device.name() != saved.name || device.start_address() != saved.start || device.end_address() != saved.end {
+                return Err(SnapshotError::DeviceMismatch {
+                    index,
+                    expected: format!("{} {:04X}-{:04X}", device.name(), device.start_address(), device.end_address()),
+                    found: format!("{} {:04X}-{:04X}", saved.name, saved.start, saved.end),
+                });
+            }
+        }
+
+        // A device can still reject its data, so keep what is there now to put back
+        let current: Vec<Vec<u8>> = self.devices.iter().map(|device| device.save()).collect();
+        for (index, saved) in snapshot.devices.iter().enumerate() {
+            if let Err(error) = self.devices[index].load(&saved.data) {
+                // A device always takes back its own save, so undoing can't fail
+                for (device, data) in self.devices.iter_mut().zip(current.iter()).take(index + 1) {
+                    let _ = device.load(data);
+                }
+                return Err(error);
+            }
+        }
+        self.data_bus.set(snapshot.data_bus);
+
+        Ok(())
+    }
+
+    /// Sets a function to be called with every read and write on the bus.
+    ///
+    /// `try_read()` and `try_write()` bypass the tracer, so hosts can inspect
+    /// memory without showing up in the trace.
+    ///
+    /// # Arguments
+    ///
+    /// * `tracer` - Called with the origin and details of each access.
+    pub fn set_tracer(&mut self, tracer: BusTracer) {
+        self.tracer = Some(RefCell::new(tracer));
+    }
+
+    /// Removes the tracer, if one is set.
+    ///
+    /// # Returns
+    ///
+    /// The tracer that was removed.
+    pub fn clear_tracer(&mut self) -> Option<BusTracer> {
+        self.tracer.take().map(RefCell::into_inner)
+    }
+
+    /// Passes an access to the tracer, if one is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Who made the access.
+    /// * `access` - The access that was made.
+    fn trace(&self, origin: AccessOrigin, access: BusAccess) {
+        // Only borrow the tracer if there is one, since this runs on every access
+        if let Some(tracer) = &self.tracer {
+            (tracer.borrow_mut())(origin, access);
+        }
+    }
+
+    /// Writes a byte to the bus, reporting unmapped addresses as an error.
+    ///
+    /// The `unmapped_policy` is not consulted.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write to.
+    /// * `value` - The byte value to write.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if a device accepted the write, or `BusError::UnmappedWrite` if no device decodes the address.
+    pub fn try_write(&mut self, address: u16, value: u8) -> Result<(), BusError> {
+        match self.device_at_mut(address) {
+            Some(device) => {
+                device.write(address, value);
+                Ok(())
+            }
+            None => Err(BusError::UnmappedWrite(address, value)),
+        }
+    }
+
+    /// Reads a byte for a debugger, without side effects.
+    ///
+    /// The device's `peek()` is used instead of `read()`, the access isn't
+    /// traced, the data bus keeps its value and the `unmapped_policy` is not
+    /// consulted.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte at `address`, or 0 if no device decodes it.
+    pub fn peek(&self, address: u16) -> u8 {
+        self.device_at(address).map_or(0, |device| device.peek(address))
+    }
+
+    /// Writes a byte for a debugger, without side effects.
+    ///
+    /// The device's `poke()` is used instead of `write()`, the access isn't
+    /// traced and the data bus keeps its value. Writes to unmapped addresses
+    /// are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write to.
+    /// * `value` - The byte value to write.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        if let Some(device) = self.device_at_mut(address) {
+            device.poke(address, value);
+        }
+    }
+
+    /// Returns the last value driven on the data bus.
+    ///
+    /// This is what an unmapped read returns under `UnmappedPolicy::OpenBus`.
+    /// It is tracked under every policy, so it can be inspected whatever the
+    /// bus is set to.
+    ///
+    /// # Returns
+    ///
+    /// The value of the last read or write made through `read()` or `write()`.
+    pub fn data_bus(&self) -> u8 {
+        self.data_bus.get()
+    }
+
+    /// Returns whether anything is asserting the IRQ line.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a device's `irq()` says so, or a line of `interrupts` is asserted.
+    pub fn irq(&self) -> bool {
+        self.interrupts.is_asserted() || self.devices.iter().any(|device| device.irq())
+    }
+
+    /// Returns what is asserting the IRQ line, for a debugger to show.
+    ///
+    /// # Returns
+    ///
+    /// The names of the devices asserting it, followed by the names of the
+    /// asserted lines of `interrupts`.
+    pub fn irq_sources(&self) -> Vec<String> {
+        let devices = self.devices.iter().filter(|device| device.irq()).map(|device| device.name());
+        devices.chain(self.interrupts.asserted().into_iter().map(String::from)).collect()
+    }
+
+    /// Returns and clears the last unmapped access recorded under `UnmappedPolicy::Error`.
+    ///
+    /// # Returns
+    ///
+    /// The most recent `BusError`, or `None` if there hasn't been one since the last call.
+    pub fn take_error(&self) -> Option<BusError> {
+        self.error.take()
+    }
+
+    /// Applies the `unmapped_policy` to an access that no device decoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The unmapped access.
+    ///
+    /// # Returns
+    ///
+    /// The value an unmapped read should return.
+    fn unmapped(&self, error: BusError) -> u8 {
+        match self.unmapped_policy {
+            UnmappedPolicy::Ignore => 0,
+            UnmappedPolicy::OpenBus => self.data_bus.get(),
+            UnmappedPolicy::Log => {
+                tracing::warn!("{}", error);
+                0
+            }
+            UnmappedPolicy::Error => {
+                self.error.set(Some(error));
+                0
+            }
+        }
+    }
+}
+
+impl Default for MainBus {
+    fn default() -> MainBus {
+        MainBus::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    use crate::bus::ram::Ram;
+    use crate::bus::rom::Rom;
+    use crate::bus::blink8::Blink8;
+    use crate::bus::{
+        AccessKind, AccessOrigin, BusAccess, BusDevice, BusError, ClockRate, MainBus, MapError, SnapshotError,
+        UnmappedPolicy,
+    };
+
+    /// Creates a bus with RAM mapped at $0000-$00FF only.
+    fn setup(policy: UnmappedPolicy) -> MainBus {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x00FF)));
+        bus.unmapped_policy = policy;
+        bus
+    }
+
+    #[test]
+    fn priority_decides_overlapping_devices() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        bus.add_device_with_priority(Box::new(Blink8::new()), 1);
+        bus.add_device(Box::new(Ram::new(0x8000, 0x8FFF)));
+
+        // Blink8 was added after the full RAM, but its priority puts it first
+        assert_eq!(bus.device_at(0x8000).unwrap().name(), "Blink8");
+        assert_eq!(bus.device_index_at(0x8000), Some(0));
+
+        // Of the two RAMs, the one added first wins
+        assert_eq!(bus.device_index_at(0x8100), Some(1));
+        assert_eq!(bus.device_at(0x8100).unwrap().end_address(), 0xFFFF);
+
+        assert_eq!(bus.overlaps(), [(0, 1), (0, 2), (1, 2)]);
+    }
+
+    /// A device at $9000 whose reads pop bytes from a queue, like a receive FIFO.
+    struct Fifo {
+        bytes: core::cell::RefCell<Vec<u8>>,
+    }
+
+    impl BusDevice for Fifo {
+        fn read(&self, _address: u16) -> u8 {
+            self.bytes.borrow_mut().pop().unwrap_or(0)
+        }
+
+        fn peek(&self, _address: u16) -> u8 {
+            self.bytes.borrow().last().copied().unwrap_or(0)
+        }
+
+        fn write(&mut self, _address: u16, value: u8) {
+            self.bytes.get_mut().insert(0, value);
+        }
+
+        fn is_memory(&self) -> bool {
+            false
+        }
+
+        fn reset(&mut self) {
+            self.bytes.get_mut().clear();
+        }
+
+        fn name(&self) -> String {
+            String::from("FIFO")
+        }
+
+        fn start_address(&self) -> u16 {
+            0x9000
+        }
+
+        fn end_address(&self) -> u16 {
+            0x9000
+        }
+    }
+
+    /// A device with no registers that counts the cycles it is ticked by.
+    struct Ticker {
+        rate: ClockRate,
+        ticks: u64,
+    }
+
+    impl BusDevice for Ticker {
+        fn read(&self, _address: u16) -> u8 {
+            0
+        }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+
+        fn is_memory(&self) -> bool {
+            false
+        }
+
+        fn reset(&mut self) {}
+
+        fn tick(&mut self, cycles: u64) {
+            self.ticks += cycles;
+        }
+
+        fn clock_rate(&self) -> ClockRate {
+            self.rate
+        }
+
+        fn name(&self) -> String {
+            String::from("Ticker")
+        }
+
+        fn start_address(&self) -> u16 {
+            0xA000
+        }
+
+        fn end_address(&self) -> u16 {
+            0xA000
+        }
+    }
+
+    #[test]
+    fn devices_are_ticked_at_their_own_clock_rate() {
+        let mut bus = MainBus::new();
+        for rate in [ClockRate::CPU, ClockRate::divided(16), ClockRate::multiplied(3), ClockRate::ratio(2, 3)] {
+            bus.add_device(Box::new(Ticker { rate, ticks: 0 }));
+        }
+        let ticks = |bus: &MainBus| -> Vec<u64> {
+            bus.devices
+                .iter()
+                .map(|device| (device.as_ref() as &dyn core::any::Any).downcast_ref::<Ticker>().unwrap().ticks)
+                .collect()
+        };
+
+        // Instructions of 2 to 7 cycles, 50 cycles in all: the fractions carry over
+        for cycles in [2, 3, 7, 4, 5, 6, 2, 2, 3, 7, 4, 5] {
+            bus.tick(cycles);
+        }
+        assert_eq!(ticks(&bus), [50, 3, 150, 33]);
+
+        // Replacing a device starts it at the beginning of a cycle
+        bus.replace_device(3, Box::new(Ticker { rate: ClockRate::ratio(2, 3), ticks: 0 }));
+        bus.tick(1);
+        assert_eq!(ticks(&bus)[3], 0);
+        bus.tick(1);
+        assert_eq!(ticks(&bus)[3], 1);
+    }
+
+    #[test]
+    fn peek_and_poke_have_no_side_effects() {
+        let mut bus = setup(UnmappedPolicy::OpenBus);
+        bus.add_device(Box::new(Fifo { bytes: core::cell::RefCell::new(vec![0x22, 0x11]) }));
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let log = trace.clone();
+        bus.set_tracer(Box::new(move |origin, access| log.lock().unwrap().push((origin, access))));
+        bus.write(0x0000, 0x77);
+
+        // Peeking doesn't consume the byte, drive the data bus or get traced
+        assert_eq!(bus.peek(0x9000), 0x11);
+        assert_eq!(bus.peek(0x9000), 0x11);
+        assert_eq!(bus.peek(0x1234), 0x00);
+        assert_eq!(bus.data_bus(), 0x77);
+        assert_eq!(trace.lock().unwrap().len(), 1);
+
+        // Devices that don't override peek and poke use read and write
+        bus.poke(0x0010, 0x42);
+        assert_eq!(bus.peek(0x0010), 0x42);
+        bus.poke(0x1234, 0x42);
+        assert_eq!(trace.lock().unwrap().len(), 1);
+
+        assert_eq!(bus.read(0x9000), 0x11);
+        assert_eq!(bus.read(0x9000), 0x22);
+    }
+
+    #[test]
+    fn page_table_decodes_like_a_scan() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        bus.add_device_with_priority(Box::new(Blink8::new()), 1);
+        bus.add_device(Box::new(Ram::new(0x8000, 0x80FF)));
+        bus.add_device(Box::new(Ram::new(0x9010, 0x9FEF)));
+        bus.add_device_with_priority(Box::new(Ram::new(0x4000, 0x40FF)), 1);
+
+        for address in 0..=0xFFFF {
+            let scanned = bus.devices.iter().position(|device| device.contains(address));
+            assert_eq!(bus.device_index_at(address), scanned, "{:04X}", address);
+        }
+
+        // A device replaced with one elsewhere is decoded where it is now
+        bus.replace_device(2, Box::new(Ram::new(0xF000, 0xFFFF)));
+        assert_eq!(bus.device_index_at(0xF000), Some(2));
+        assert_eq!(bus.device_index_at(0x4000), Some(1));
+    }
+
+    #[test]
+    fn devices_can_be_removed_and_replaced() {
+        let mut bus = setup(UnmappedPolicy::default());
+        bus.add_device_with_priority(Box::new(Blink8::new()), 1);
+        bus.add_device(Box::new(Rom::new(0xC000, 0xFFFF)));
+        bus.write(0x0010, 0x42);
+
+        // Removing a device hands it back with its contents
+        let ram = bus.remove_device_named("RAM").unwrap();
+        assert_eq!(ram.read(0x0010), 0x42);
+        assert_eq!(bus.read(0x0010), 0x00);
+        assert!(bus.remove_device_named("VIA").is_none());
+        assert!(bus.remove_device(2).is_none());
+
+        // A replacement keeps the old device's priority
+        let mut cartridge = Rom::new(0xC000, 0xFFFF);
+        cartridge.data[0] = 0xEA;
+        let old = bus.replace_device(1, Box::new(cartridge));
+        assert_eq!(old.start_address(), 0xC000);
+        assert_eq!(bus.read(0xC000), 0xEA);
+
+        bus.add_device(Box::new(Ram::new(0x8000, 0x80FF)));
+        assert_eq!(bus.device_at(0x8000).unwrap().name(), "Blink8");
+        let blink8 = bus.find_device("Blink8").unwrap();
+        bus.replace_device(blink8, Box::new(Ram::new(0x8000, 0x8000)));
+        assert_eq!(bus.device_index_at(0x8000), Some(0));
+        assert_eq!(bus.device_index_at(0x8001), Some(2));
+    }
+
+    #[test]
+    fn memory_map_lists_devices_by_address() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x8000, 0xFFFF)));
+        bus.add_device_with_priority(Box::new(Blink8::new()), 1);
+        bus.add_device(Box::new(Ram::new(0x0000, 0x00FF)));
+
+        assert_eq!(
+            bus.memory_map().to_string(),
+            "\
+Start  End    Size   Kind    Priority  Name
+0000   00FF   256    Memory  0         RAM
+8000   8002   3      I/O     1         Blink8
+8000   FFFF   32768  Memory  0         RAM
+"
+        );
+    }
+
+    #[test]
+    fn adding_devices_checks_their_ranges() {
+        let mut bus = setup(UnmappedPolicy::default());
+        let mut backwards = Ram::new(0x0100, 0x01FF);
+        backwards.end = 0x00FF;
+
+        assert_eq!(
+            bus.try_add_device(Box::new(backwards)),
+            Err(MapError::InvalidRange { name: "RAM".into(), start: 0x0100, end: 0x00FF })
+        );
+
+        // Overlaps are only rejected when asked
+        assert_eq!(bus.try_add_device(Box::new(Ram::new(0x0080, 0x017F))), Ok(()));
+        bus.reject_overlaps = true;
+        assert_eq!(bus.try_add_device(Box::new(Blink8::new())), Ok(()));
+        assert_eq!(
+            bus.try_add_device(Box::new(Ram::new(0x0100, 0x01FF))),
+            Err(MapError::Overlap { name: "RAM".into(), other: "RAM".into() })
+        );
+        assert_eq!(bus.devices.len(), 3);
+    }
+
+    #[test]
+    fn unmapped_write_is_ignored_by_default() {
+        let mut bus = setup(UnmappedPolicy::default());
+
+        bus.write(0x1234, 0x56);
+
+        assert_eq!(bus.read(0x1234), 0x00);
+        assert_eq!(bus.take_error(), None);
+    }
+
+    #[test]
+    fn open_bus_returns_last_value() {
+        let mut bus = setup(UnmappedPolicy::OpenBus);
+        bus.write(0x0010, 0xA5);
+
+        assert_eq!(bus.read(0x1234), 0xA5);
+
+        // A read of mapped memory drives the bus too
+        bus.write(0x0011, 0x3C);
+        bus.write(0x2000, 0xFF);
+        bus.read(0x0011);
+        assert_eq!(bus.read(0x1234), 0x3C);
+        assert_eq!(bus.data_bus(), 0x3C);
+    }
+
+    #[test]
+    fn data_bus_is_tracked_under_every_policy() {
+        let mut bus = setup(UnmappedPolicy::Ignore);
+        bus.write(0x2000, 0x5A);
+
+        assert_eq!(bus.read(0x1234), 0x00);
+        assert_eq!(bus.data_bus(), 0x5A);
+
+        // Switching to open bus picks up the value already on the bus
+        bus.unmapped_policy = UnmappedPolicy::OpenBus;
+        assert_eq!(bus.read(0x1234), 0x5A);
+    }
+
+    #[test]
+    fn error_policy_records_unmapped_access() {
+        let mut bus = setup(UnmappedPolicy::Error);
+
+        bus.write(0x1234, 0x56);
+
+        assert_eq!(bus.take_error(), Some(BusError::UnmappedWrite(0x1234, 0x56)));
+        assert_eq!(bus.take_error(), None);
+    }
+
+    #[test]
+    fn try_read_and_try_write_report_unmapped_addresses() {
+        let mut bus = setup(UnmappedPolicy::Ignore);
+
+        assert_eq!(bus.try_write(0x0010, 0x42), Ok(()));
+        assert_eq!(bus.try_read(0x0010), Ok(0x42));
+        assert_eq!(bus.try_read(0x8000), Err(BusError::UnmappedRead(0x8000)));
+        assert_eq!(bus.try_write(0x8000, 0x01), Err(BusError::UnmappedWrite(0x8000, 0x01)));
+    }
+
+    #[test]
+    fn tracer_sees_cpu_and_device_accesses() {
+        let mut bus = setup(UnmappedPolicy::Ignore);
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let log = trace.clone();
+        bus.set_tracer(Box::new(move |origin, access| log.lock().unwrap().push((origin, access))));
+
+        bus.write(0x0010, 0x42);
+        bus.device_read(0x0010);
+        bus.try_read(0x0010).unwrap();
+        bus.read(0x1234);
+
+        assert_eq!(
+            *trace.lock().unwrap(),
+            vec![
+                (AccessOrigin::Cpu, BusAccess { kind: AccessKind::Write, address: 0x0010, value: 0x42 }),
+                (AccessOrigin::Device, BusAccess { kind: AccessKind::Read, address: 0x0010, value: 0x42 }),
+                (AccessOrigin::Cpu, BusAccess { kind: AccessKind::Read, address: 0x1234, value: 0x00 }),
+            ]
+        );
+
+        assert!(bus.clear_tracer().is_some());
+        bus.read(0x0010);
+        assert_eq!(trace.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn load_state_restores_devices() {
+        let mut bus = setup(UnmappedPolicy::default());
+        bus.add_device(Box::new(Blink8::new()));
+        bus.write(0x0010, 0x42);
+        let snapshot = bus.save_state();
+
+        bus.write(0x0010, 0x00);
+        bus.load_state(&snapshot).unwrap();
+
+        assert_eq!(bus.read(0x0010), 0x42);
+        assert_eq!(bus.save_state(), snapshot);
+    }
+
+    #[test]
+    fn load_state_rejects_different_devices() {
+        let mut bus = setup(UnmappedPolicy::default());
+        let mut snapshot = bus.save_state();
+        snapshot.devices[0].end = 0x01FF;
+
+        assert!(matches!(bus.load_state(&snapshot), Err(SnapshotError::DeviceMismatch { index: 0, .. })));
+
+        snapshot.devices.clear();
+        assert_eq!(bus.load_state(&snapshot), Err(SnapshotError::DeviceCount { expected: 1, found: 0 }));
+    }
+
+    #[test]
+    fn load_state_changes_nothing_when_a_device_rejects_its_data() {
+        let mut bus = setup(UnmappedPolicy::default());
+        bus.add_device(Box::new(Ram::new(0x0100, 0x01FF)));
+        bus.add_device(Box::new(Ram::new(0x0200, 0x02FF)));
+        for address in [0x0010, 0x0110, 0x0210] {
+            bus.write(address, 0x42);
+        }
+        let mut snapshot = bus.save_state();
+        snapshot.devices[1].data.truncate(0x10);
+
+        for address in [0x0010, 0x0110, 0x0210] {
+            bus.write(address, 0x24);
+        }
+        let before = bus.save_state();
+
+        // The first device would have been restored before the second one failed
+        assert_eq!(bus.load_state(&snapshot), Err(SnapshotError::InvalidData(Ram::new(0x0100, 0x01FF).name())));
+        assert_eq!(bus.save_state(), before);
+        assert_eq!(bus.read(0x0010), 0x24);
+    }
+}
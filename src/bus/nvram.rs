@@ -0,0 +1,172 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// Battery-backed RAM, whose contents survive between runs.
+///
+/// `NvRam` behaves like `Ram`, except that resetting it doesn't clear it.
+/// With the `std` feature its contents can be kept in a file with
+/// `NvRam::open()`: they are loaded when it is opened and written back by
+/// `flush()` and when the device is dropped, so a machine can keep high
+/// scores, settings or a filesystem across runs.
+pub struct NvRam {
+    /// The contents of the RAM.
+    pub data: Vec<u8>,
+
+    /// The start address of the RAM.
+    pub start: u16,
+
+    /// The end address of the RAM.
+    pub end: u16,
+
+    /// Whether the contents changed since they were last flushed.
+    dirty: bool,
+
+    /// The file the contents are kept in, if any.
+    #[cfg(feature = "std")]
+    path: Option<PathBuf>,
+}
+
+impl NvRam {
+    /// Creates battery-backed RAM filled with zeros and no backing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the RAM.
+    /// * `end` - The last address of the RAM, inclusive.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `NvRam` struct.
+    pub fn new(start: u16, end: u16) -> NvRam {
+        NvRam {
+            data: vec![0x00; (end - start) as usize + 1],
+            start,
+            end,
+            dirty: false,
+            #[cfg(feature = "std")]
+            path: None,
+        }
+    }
+
+    /// Creates battery-backed RAM whose contents are kept in a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the RAM.
+    /// * `end` - The last address of the RAM, inclusive.
+    /// * `path` - The backing file. If it doesn't exist yet, the RAM starts
+    ///   filled with zeros and the file is created when the RAM is flushed. A
+    ///   file shorter than the RAM leaves the rest zero.
+    ///
+    /// # Returns
+    ///
+    /// The RAM, or the error reading the file. A file longer than the RAM is
+    /// an `InvalidData` error.
+    #[cfg(feature = "std")]
+    pub fn open(start: u16, end: u16, path: impl AsRef<Path>) -> std::io::Result<NvRam> {
+        let mut ram = NvRam::new(start, end);
+        ram.data = crate::bus::backing::read_image(path.as_ref(), ram.data.len(), 0x00)?;
+        ram.path = Some(path.as_ref().to_path_buf());
+        Ok(ram)
+    }
+
+    /// Writes the contents to the backing file, if they changed since the last flush.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the contents were written, or didn't need to be.
+    #[cfg(feature = "std")]
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        crate::bus::backing::flush(self.path.as_deref(), &self.data, &mut self.dirty)
+    }
+}
+
+impl BusDevice for NvRam {
+    fn read(&self, address: u16) -> u8 {
+        self.data[(address - self.start) as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.data[(address - self.start) as usize] = value;
+        self.dirty = true;
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        // The battery keeps the contents over a reset
+    }
+
+    fn name(&self) -> String {
+        String::from("NVRAM")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.end
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        // The saved contents must fill the RAM exactly
+        if data.len() != self.data.len() {
+            return Err(SnapshotError::InvalidData(self.name()));
+        }
+        self.data.copy_from_slice(data);
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for NvRam {
+    fn drop(&mut self) {
+        crate::bus::backing::flush_on_drop(self.path.as_deref(), &self.data, &mut self.dirty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::BusDevice;
+    use crate::bus::nvram::NvRam;
+
+    #[test]
+    fn keeps_its_contents_over_a_reset() {
+        let mut ram = NvRam::new(0x6000, 0x7FFF);
+        ram.write(0x7FFF, 0x42);
+        ram.reset();
+
+        assert_eq!(ram.read(0x7FFF), 0x42);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn keeps_its_contents_in_a_backing_file() {
+        let path = std::env::temp_dir().join(std::format!("butterflyrs-nvram-{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut ram = NvRam::open(0x6000, 0x60FF, &path).unwrap();
+        ram.write(0x6010, 0x42);
+        ram.flush().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[0x10], 0x42);
+        ram.write(0x6011, 0x43);
+        drop(ram);
+
+        let ram = NvRam::open(0x6000, 0x60FF, &path).unwrap();
+        assert_eq!((ram.read(0x6010), ram.read(0x6011)), (0x42, 0x43));
+        drop(ram);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The offset of port A, where keys are read. With `DDR_SELECT` clear in
+/// `CRA` it is the data direction register instead.
+pub const KBD: u16 = 0;
+
+/// The offset of control register A. Bit 7 is set while a key is waiting.
+pub const KBDCR: u16 = 1;
+
+/// The offset of port B, where characters are written to the display. With
+/// `DDR_SELECT` clear in `CRB` it is the data direction register instead.
+pub const DSP: u16 = 2;
+
+/// The offset of control register B.
+pub const DSPCR: u16 = 3;
+
+/// The control register bit that selects the port's data register rather than its data direction register.
+pub const DDR_SELECT: u8 = 0x04;
+
+/// The control register bit that makes CA1, the keyboard strobe, interrupt.
+pub const IRQ_ENABLE: u8 = 0x01;
+
+/// The control register A bit that is set while a key is waiting.
+pub const KEY_AVAILABLE: u8 = 0x80;
+
+/// A 6821 Peripheral Interface Adapter, wired to a keyboard and a display as on the Apple 1.
+///
+/// The host queues keys with `push_key()` and takes the characters the
+/// machine has displayed with `take_output()`. The machine sees four
+/// registers, at the offsets the Apple 1's monitor uses:
+///
+/// * `KBD` - Reading it takes the next key from the queue, with bit 7 set as
+///   the Apple 1's keyboard sets it, or returns 0 if there is none.
+/// * `KBDCR` - Bit 7 (`KEY_AVAILABLE`) is set while a key is waiting. The
+///   other bits read back what was written.
+/// * `DSP` - Writing it sends a character to the display with bit 7
+///   cleared. Reading it returns bit 7 clear, as the display is always ready.
+/// * `DSPCR` - Reads back what was written.
+///
+/// `KBD` and `DSP` are the data direction registers until `DDR_SELECT` is
+/// set in the control registers, as on a real 6821. The keyboard asserts IRQ
+/// while a key is waiting if `IRQ_ENABLE` is set in `KBDCR`. The Apple 1
+/// only has upper case, so the host should convert keys before queueing
+/// them and `\r` ends a line in both directions.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::pia::{Pia, DDR_SELECT};
+/// use butterflyrs::bus::BusDevice;
+///
+/// let mut pia = Pia::new(0xD010);
+/// pia.write(0xD011, DDR_SELECT);
+/// pia.write(0xD013, DDR_SELECT);
+///
+/// pia.push_key(b'A');
+/// assert_eq!(pia.read(0xD011), 0x80 | DDR_SELECT);
+/// assert_eq!(pia.read(0xD010), 0x80 | b'A');
+///
+/// pia.write(0xD012, 0x80 | b'A');
+/// assert_eq!(pia.take_output(), b"A");
+/// ```
+pub struct Pia {
+    /// The first address of the PIA's registers.
+    pub start: u16,
+
+    /// The keys waiting to be read, oldest first. Reading `KBD` takes a key
+    /// even though reads don't borrow the device mutably.
+    keys: RefCell<VecDeque<u8>>,
+
+    /// The characters the machine has displayed since the host last took them.
+    output: Vec<u8>,
+
+    /// The control registers for ports A and B, without their flag bits.
+    cra: u8,
+    crb: u8,
+
+    /// The data direction registers for ports A and B.
+    ddra: u8,
+    ddrb: u8,
+
+    /// The output register for port B, which holds the last character displayed.
+    orb: u8,
+}
+
+impl Pia {
+    /// Creates a PIA with nothing typed or displayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of `KBD`. The other three registers follow it.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Pia` struct.
+    pub fn new(start: u16) -> Pia {
+        Pia {
+            start,
+            keys: RefCell::new(VecDeque::new()),
+            output: Vec::new(),
+            cra: 0,
+            crb: 0,
+            ddra: 0,
+            ddrb: 0,
+            orb: 0,
+        }
+    }
+
+    /// Queues a key for the machine to read.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The ASCII code of the key. Bit 7 is set when it is read.
+    pub fn push_key(&mut self, key: u8) {
+        self.keys.get_mut().push_back(key);
+    }
+
+    /// Queues every byte of a string, as if it was typed.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to type.
+    pub fn push_str(&mut self, text: &str) {
+        self.keys.get_mut().extend(text.bytes());
+    }
+
+    /// Returns the number of keys waiting to be read.
+    pub fn pending(&self) -> usize {
+        self.keys.borrow().len()
+    }
+
+    /// Takes the characters the machine has displayed since the last call.
+    ///
+    /// # Returns
+    ///
+    /// The characters, oldest first, as 7-bit ASCII.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.output)
+    }
+
+    /// Returns the value of control register A.
+    fn control_a(&self) -> u8 {
+        let available = if self.keys.borrow().is_empty() { 0 } else { KEY_AVAILABLE };
+        available | self.cra
+    }
+}
+
+impl BusDevice for Pia {
+    fn read(&self, address: u16) -> u8 {
+        match (address - self.start) & 0x03 {
+            KBD if self.cra & DDR_SELECT != 0 => self.keys.borrow_mut().pop_front().map_or(0, |key| key | 0x80),
+            KBD => self.ddra,
+            KBDCR => self.control_a(),
+            // PB7 is an input from the display, which is never busy
+            DSP if self.crb & DDR_SELECT != 0 => self.orb & self.ddrb & 0x7F,
+            DSP => self.ddrb,
+            _ => self.crb,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match (address - self.start) & 0x03 {
+            // The keyboard only drives port A, so writes to it have nowhere to go
+            KBD if self.cra & DDR_SELECT != 0 => {}
+            KBD => self.ddra = value,
+            KBDCR => self.cra = value & 0x3F,
+            DSP if self.crb & DDR_SELECT != 0 => {
+                self.orb = value;
+                self.output.push(value & 0x7F);
+            }
+            DSP => self.ddrb = value,
+            _ => self.crb = value & 0x3F,
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        // Looks at the next key without taking it
+        match (address - self.start) & 0x03 {
+            KBD if self.cra & DDR_SELECT != 0 => self.keys.borrow().front().map_or(0, |key| key | 0x80),
+            _ => self.read(address),
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // Keys typed before the reset are kept, so input can be queued before the machine starts
+        self.cra = 0;
+        self.crb = 0;
+        self.ddra = 0;
+        self.ddrb = 0;
+        self.orb = 0;
+    }
+
+    fn power_on(&mut self) {
+        self.reset();
+        self.keys.get_mut().clear();
+        self.output.clear();
+    }
+
+    fn irq(&self) -> bool {
+        self.cra & IRQ_ENABLE != 0 && !self.keys.borrow().is_empty()
+    }
+
+    fn name(&self) -> String {
+        String::from("PIA")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + DSPCR
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut data = vec![self.cra, self.crb, self.ddra, self.ddrb, self.orb];
+        data.extend(self.keys.borrow().iter());
+        data
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let [cra, crb, ddra, ddrb, orb, ref keys @ ..] = *data else {
+            return Err(SnapshotError::InvalidData(self.name()));
+        };
+        self.cra = cra;
+        self.crb = crb;
+        self.ddra = ddra;
+        self.ddrb = ddrb;
+        self.orb = orb;
+        self.keys = RefCell::new(keys.iter().copied().collect());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::pia::{Pia, DDR_SELECT, IRQ_ENABLE, KEY_AVAILABLE};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn works_as_the_apple_1_terminal() {
+        // The Apple 1 monitor's setup: port B pins 0-6 are outputs, then both data registers are selected
+        let mut pia = Pia::new(0xD010);
+        pia.write(0xD012, 0x7F);
+        pia.write(0xD011, 0xA7);
+        pia.write(0xD013, 0xA7);
+        assert_eq!(pia.read(0xD011), 0x27);
+        assert!(!pia.irq());
+
+        pia.push_str("1\r");
+        assert!(pia.irq());
+        assert_eq!(pia.read(0xD011), KEY_AVAILABLE | 0x27);
+        assert_eq!(pia.peek(0xD010), 0xB1);
+        assert_eq!(pia.read(0xD010), 0xB1);
+        assert_eq!(pia.read(0xD010), 0x8D);
+        assert_eq!(pia.read(0xD011) & KEY_AVAILABLE, 0);
+
+        // Characters are echoed without bit 7, and the display is never busy
+        pia.write(0xD012, 0xB1);
+        pia.write(0xD012, 0x8D);
+        assert_eq!(pia.read(0xD012) & 0x80, 0);
+        assert_eq!(pia.take_output(), b"1\r");
+        assert!(pia.take_output().is_empty());
+
+        let saved = pia.save();
+        pia.push_key(b'X');
+        pia.power_on();
+        assert_eq!(pia.read(0xD011), 0x00);
+        pia.load(&saved).unwrap();
+        assert_eq!(pia.read(0xD011), 0x27);
+        assert_eq!(pia.read(0xD011) & (DDR_SELECT | IRQ_ENABLE), DDR_SELECT | IRQ_ENABLE);
+    }
+}
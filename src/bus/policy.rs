@@ -0,0 +1,34 @@
+use core::fmt::Display;
+
+/// How the bus handles accesses to addresses that no device decodes.
+///
+/// Whatever the policy, unmapped writes never reach a device and never panic.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum UnmappedPolicy {
+    /// Reads return 0 and writes are dropped silently.
+    #[default]
+    Ignore,
+
+    /// Reads return the last value driven on the data bus, like real hardware
+    /// with nothing attached. Writes are dropped.
+    OpenBus,
+
+    /// Reads return 0 and writes are dropped, and every such access is reported
+    /// as a `tracing` warning.
+    Log,
+
+    /// Reads return 0 and writes are dropped, and the access is recorded as a
+    /// `BusError` that the host can collect with `MainBus::take_error()`.
+    Error,
+}
+
+impl Display for UnmappedPolicy {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            UnmappedPolicy::Ignore => write!(f, "Ignore"),
+            UnmappedPolicy::OpenBus => write!(f, "Open bus"),
+            UnmappedPolicy::Log => write!(f, "Log"),
+            UnmappedPolicy::Error => write!(f, "Error"),
+        }
+    }
+}
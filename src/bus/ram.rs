@@ -1,17 +1,40 @@
-use crate::bus::BusDevice;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::bus::{BusDevice, SnapshotError};
 
 pub struct Ram {
     pub data: Vec<u8>,
     pub start: u16,
     pub end: u16,
+    /// The value every byte holds at power on.
+    pub fill: u8,
 }
 
 impl Ram {
     pub fn new(start: u16, end: u16) -> Ram {
+        Ram::with_fill(start, end, 0x00)
+    }
+
+    /// Creates RAM that holds a pattern at power on, rather than zeros.
+    ///
+    /// Real RAM comes up holding whatever its cells settled to, and some
+    /// software depends on, or is broken by, what that is.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address of the RAM.
+    /// * `end` - The last address of the RAM, inclusive.
+    /// * `fill` - The value every byte holds at power on.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Ram` struct.
+    pub fn with_fill(start: u16, end: u16, fill: u8) -> Ram {
         Ram {
-            data: vec![0x00; (end - start + 1) as usize],
+            data: vec![fill; (end - start) as usize + 1],
             start,
             end,
+            fill,
         }
     }
 }
@@ -30,7 +53,11 @@ impl BusDevice for Ram {
     }
 
     fn reset(&mut self) {
-        self.data = vec![0x00; (self.end - self.start + 1) as usize];
+        // RAM keeps its contents through a reset
+    }
+
+    fn power_on(&mut self) {
+        self.data.fill(self.fill);
     }
 
     fn name(&self) -> String {
@@ -44,4 +71,44 @@ impl BusDevice for Ram {
     fn end_address(&self) -> u16 {
         self.end
     }
-}
\ No newline at end of file
+
+    fn save(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        // The saved contents must fill the RAM exactly
+        if data.len() != self.data.len() {
+            return Err(SnapshotError::InvalidData(self.name()));
+        }
+        self.data.copy_from_slice(data);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::bus::BusDevice;
+    use crate::bus::ram::Ram;
+
+    #[test]
+    fn can_cover_the_whole_address_space() {
+        let mut ram = Ram::new(0x0000, 0xFFFF);
+        ram.write(0xFFFF, 0x42);
+
+        assert_eq!(ram.data.len(), 0x10000);
+        assert_eq!(ram.size(), 0x10000);
+        assert_eq!(ram.read(0xFFFF), 0x42);
+    }
+
+    #[test]
+    fn keeps_its_contents_until_powered_on() {
+        let mut ram = Ram::with_fill(0x0000, 0x00FF, 0xEA);
+        ram.write(0x0010, 0x42);
+
+        ram.reset();
+        assert_eq!(ram.read(0x0010), 0x42);
+
+        ram.power_on();
+        assert_eq!(ram.read(0x0010), 0xEA);
+    }
+}
@@ -0,0 +1,190 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// A random number generator with a single register.
+///
+/// Reading the register returns the next pseudorandom byte, so programs get
+/// random numbers without relying on whatever happens to be in RAM at power
+/// on. Writing a byte to it reseeds the generator with that byte, which lets a
+/// program replay the same sequence.
+///
+/// The bytes come from SplitMix64, which is fast and good enough for games,
+/// but is not suitable for cryptography. The same seed always gives the same
+/// bytes, so tests can seed the generator with `new()` and get reproducible
+/// runs. With the `std` feature, `from_entropy()` seeds it differently on
+/// every run.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::rng::Rng;
+/// use butterflyrs::bus::BusDevice;
+///
+/// let first = Rng::new(0xD020, 42);
+/// let second = Rng::new(0xD020, 42);
+/// assert_eq!(first.read(0xD020), second.read(0xD020));
+/// ```
+pub struct Rng {
+    /// The address of the register.
+    pub start: u16,
+
+    /// The seed the generator starts from at power on.
+    seed: u64,
+
+    /// The state of the generator. Reading the register advances it even
+    /// though reads don't borrow the device mutably.
+    state: Cell<u64>,
+}
+
+impl Rng {
+    /// Creates a generator with a fixed seed.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the register.
+    /// * `seed` - The seed to start from.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Rng` struct.
+    pub fn new(start: u16, seed: u64) -> Rng {
+        Rng {
+            start,
+            seed,
+            state: Cell::new(seed),
+        }
+    }
+
+    /// Creates a generator seeded from the host, so each run gives different bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the register.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Rng` struct.
+    #[cfg(feature = "std")]
+    pub fn from_entropy(start: u16) -> Rng {
+        use std::hash::{BuildHasher, Hasher};
+
+        // The standard library seeds every RandomState with fresh random keys
+        let seed = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        Rng::new(start, seed)
+    }
+
+    /// Returns the seed the generator starts from at power on.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reseeds the generator, restarting its sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed to start from, now and at every power on.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.state.set(seed);
+    }
+
+    /// Returns the next byte from a state, and the state that follows it.
+    fn next(state: u64) -> (u8, u64) {
+        let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut mixed = state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        mixed ^= mixed >> 31;
+
+        // The top bits are the best mixed
+        ((mixed >> 56) as u8, state)
+    }
+}
+
+impl BusDevice for Rng {
+    fn read(&self, _address: u16) -> u8 {
+        let (value, state) = Rng::next(self.state.get());
+        self.state.set(state);
+        value
+    }
+
+    fn write(&mut self, _address: u16, value: u8) {
+        self.reseed(value as u64);
+    }
+
+    fn peek(&self, _address: u16) -> u8 {
+        // Shows the next byte without using it up
+        Rng::next(self.state.get()).0
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // The sequence carries on through a reset, as a free-running generator would
+    }
+
+    fn power_on(&mut self) {
+        self.state.set(self.seed);
+    }
+
+    fn name(&self) -> String {
+        String::from("RNG")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut data = self.seed.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.state.get().to_le_bytes());
+        data
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let data: &[u8; 16] = data.try_into().map_err(|_| SnapshotError::InvalidData(self.name()))?;
+        let (seed, state) = data.split_at(8);
+        self.seed = u64::from_le_bytes(seed.try_into().unwrap());
+        self.state.set(u64::from_le_bytes(state.try_into().unwrap()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::bus::rng::Rng;
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn the_same_seed_gives_the_same_bytes() {
+        let mut rng = Rng::new(0xD020, 1234);
+        let bytes: Vec<u8> = (0..16).map(|_| rng.read(0xD020)).collect();
+
+        // Not stuck on one value
+        assert!(bytes.iter().any(|&byte| byte != bytes[0]));
+
+        // Powering on restarts the sequence, and peeking doesn't use a byte up
+        rng.power_on();
+        assert_eq!(rng.peek(0xD020), bytes[0]);
+        assert_eq!((0..16).map(|_| rng.read(0xD020)).collect::<Vec<u8>>(), bytes);
+
+        // A program can reseed it, and a snapshot carries on where it left off
+        rng.write(0xD020, 7);
+        let saved = rng.save();
+        let expected = Rng::new(0xD020, 7).read(0xD020);
+        assert_eq!(rng.read(0xD020), expected);
+        rng.load(&saved).unwrap();
+        assert_eq!(rng.read(0xD020), expected);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::bus::BusDevice;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::bus::{BusDevice, SnapshotError};
 
 pub struct Rom {
     pub data: Vec<u8>,
@@ -9,7 +11,7 @@ pub struct Rom {
 impl Rom {
     pub fn new(start: u16, end: u16) -> Rom {
         Rom {
-            data: vec![0x00; (end - start + 1) as usize],
+            data: vec![0x00; (end - start) as usize + 1],
             start,
             end,
         }
@@ -18,12 +20,23 @@ impl Rom {
 
 impl BusDevice for Rom {
     fn read(&self, address: u16) -> u8 {
-        self.data[(address - self.start) as usize]
+        // An image shorter than the ROM leaves the rest unprogrammed, which
+        // reads back as erased EPROM
+        self.data.get((address - self.start) as usize).copied().unwrap_or(0xFF)
     }
 
     fn write(&mut self, address: u16, value: u8) {
         // ROM is read-only
-        println!("Illegal ROM write: {:04X} = {:02X}", address, value);
+        tracing::warn!("Illegal ROM write: {:04X} = {:02X}", address, value);
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        // A debugger or loader can patch ROM, padding a short image with erased bytes
+        let offset = (address - self.start) as usize;
+        if offset >= self.data.len() {
+            self.data.resize(offset + 1, 0xFF);
+        }
+        self.data[offset] = value;
     }
 
     fn is_memory(&self) -> bool {
@@ -31,13 +44,11 @@ impl BusDevice for Rom {
     }
 
     fn reset(&mut self) {
-        // ROM is read-only
-        println!("ROM reset, you probably didn't want to do that. Bye bye data!");
-        self.data = vec![0x00; (self.end - self.start + 1) as usize];
+        // ROM keeps its contents through a reset and a power cycle
     }
 
     fn name(&self) -> String {
-        String::from("RAM")
+        String::from("ROM")
     }
 
     fn start_address(&self) -> u16 {
@@ -47,4 +58,37 @@ impl BusDevice for Rom {
     fn end_address(&self) -> u16 {
         self.end
     }
-}
\ No newline at end of file
+
+    fn save(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        self.data = data.to_vec();
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::bus::BusDevice;
+    use crate::bus::rom::Rom;
+
+    #[test]
+    fn short_image_reads_as_erased() {
+        let mut rom = Rom::new(0xC000, 0xFFFF);
+        rom.data = vec![0xEA, 0x4C];
+
+        assert_eq!(rom.read(0xC001), 0x4C);
+        assert_eq!(rom.read(0xFFFC), 0xFF);
+    }
+
+    #[test]
+    fn keeps_its_contents_over_a_power_cycle() {
+        let mut rom = Rom::new(0xC000, 0xFFFF);
+        rom.data[0] = 0xEA;
+        rom.reset();
+        rom.power_on();
+
+        assert_eq!(rom.read(0xC000), 0xEA);
+    }
+}
@@ -0,0 +1,306 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::MainBus;
+
+/// A function called when a scheduled event comes due, with the bus it is scheduled on.
+pub type EventHandler = Box<dyn FnMut(&mut MainBus) + Send>;
+
+/// Identifies an event handed to a `Scheduler`, so that it can be cancelled.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct EventId(u64);
+
+/// Event times are kept in fractions of a cycle, so that periods such as a
+/// scanline of 113.67 cycles don't drift.
+const FRACTION_BITS: u32 = 16;
+
+/// One cycle, in the units event times are kept in.
+const ONE_CYCLE: u64 = 1 << FRACTION_BITS;
+
+/// An event waiting to come due.
+struct Event {
+    /// The event's handle.
+    id: EventId,
+
+    /// The name of the event, for `pending()`.
+    name: String,
+
+    /// When the event comes due, in fractions of a cycle.
+    due: u64,
+
+    /// How often the event repeats, in fractions of a cycle, or 0 if it only happens once.
+    period: u64,
+
+    /// What the event does.
+    handler: EventHandler,
+}
+
+/// Fires events at chosen CPU cycles.
+///
+/// Every `MainBus` has one, which keeps time with the cycles the CPU ticks
+/// the bus by: `Cpu::clock()` and `Cpu::step()` advance it a whole instruction
+/// at a time and `Cpu::tick()` a cycle at a time. Events that come due are
+/// fired in order, each with the bus, so a handler can assert an IRQ line,
+/// poke a device or schedule more events. Repeating events can have a period
+/// that isn't a whole number of cycles, such as the scanline of a video chip.
+///
+/// The host schedules events through `MainBus::scheduler`. A device can do
+/// the same when the bus is granted to it with `BusDevice::bus_grant()`.
+///
+/// Events hold closures, so they aren't part of a save state.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::MainBus;
+///
+/// let mut bus = MainBus::new();
+/// let timer = bus.interrupts.line("Timer");
+/// bus.scheduler.at(100, "Timer", Box::new(move |_| timer.assert()));
+///
+/// bus.tick(99);
+/// assert!(!bus.irq());
+/// bus.tick(1);
+/// assert!(bus.irq());
+/// ```
+#[derive(Default)]
+pub struct Scheduler {
+    /// The cycles the bus has been ticked by.
+    now: u64,
+
+    /// The events waiting to come due, soonest first.
+    events: Vec<Event>,
+
+    /// The handle given to the next event.
+    next_id: u64,
+
+    /// The event whose handler is running, and whether it has cancelled itself.
+    firing: Option<(EventId, bool)>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler at cycle 0 with no events.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Scheduler` struct.
+    pub fn new() -> Scheduler {
+        Scheduler::default()
+    }
+
+    /// Returns the current cycle.
+    ///
+    /// While an event's handler runs, this is the cycle the event came due on.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Schedules an event to happen once, at a cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycle` - The cycle to fire the event on. A cycle that has already passed fires on the next tick.
+    /// * `name` - The name of the event.
+    /// * `handler` - What the event does.
+    ///
+    /// # Returns
+    ///
+    /// The event's handle.
+    pub fn at(&mut self, cycle: u64, name: &str, handler: EventHandler) -> EventId {
+        self.insert(cycle.saturating_mul(ONE_CYCLE), 0, name, handler)
+    }
+
+    /// Schedules an event to happen once, a number of cycles from now.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The cycles to wait.
+    /// * `name` - The name of the event.
+    /// * `handler` - What the event does.
+    ///
+    /// # Returns
+    ///
+    /// The event's handle.
+    pub fn after(&mut self, cycles: u64, name: &str, handler: EventHandler) -> EventId {
+        self.at(self.now.saturating_add(cycles), name, handler)
+    }
+
+    /// Schedules an event to repeat for as long as it isn't cancelled.
+    ///
+    /// The event first fires one period from now. A period with a fraction is
+    /// kept to within 1/65536 of a cycle, and each firing happens on the first
+    /// whole cycle at or after its exact time.
+    ///
+    /// # Arguments
+    ///
+    /// * `period` - The cycles between firings, such as 113.67 for a scanline.
+    /// * `name` - The name of the event.
+    /// * `handler` - What the event does.
+    ///
+    /// # Returns
+    ///
+    /// The event's handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the period isn't at least one cycle.
+    pub fn every(&mut self, period: f64, name: &str, handler: EventHandler) -> EventId {
+        assert!(period >= 1.0, "an event can't repeat more than once a cycle");
+
+        let period = (period * ONE_CYCLE as f64) as u64;
+        let due = self.now.saturating_mul(ONE_CYCLE).saturating_add(period);
+        self.insert(due, period, name, handler)
+    }
+
+    /// Cancels an event. A handler may cancel the event it belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The event's handle.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the event was waiting to come due, `false` if it had already
+    /// fired for the last time or been cancelled.
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        if let Some((firing, cancelled)) = &mut self.firing {
+            if *firing == id {
+                return !core::mem::replace(cancelled, true);
+            }
+        }
+
+        let count = self.events.len();
+        self.events.retain(|event| event.id != id);
+        self.events.len() != count
+    }
+
+    /// Returns the cycle the next event comes due on, if there is one.
+    pub fn next_due(&self) -> Option<u64> {
+        self.events.first().map(|event| event.due.div_ceil(ONE_CYCLE))
+    }
+
+    /// Returns the names of the events waiting to come due, soonest first.
+    pub fn pending(&self) -> Vec<&str> {
+        self.events.iter().map(|event| event.name.as_str()).collect()
+    }
+
+    /// Adds an event, keeping the list in the order the events come due.
+    ///
+    /// # Arguments
+    ///
+    /// * `due` - When the event comes due, in fractions of a cycle.
+    /// * `period` - How often it repeats, in fractions of a cycle, or 0.
+    /// * `name` - The name of the event.
+    /// * `handler` - What the event does.
+    ///
+    /// # Returns
+    ///
+    /// The event's handle.
+    fn insert(&mut self, due: u64, period: u64, name: &str, handler: EventHandler) -> EventId {
+        let id = EventId(self.next_id);
+        self.next_id += 1;
+        self.push(Event { id, name: String::from(name), due, period, handler });
+        id
+    }
+
+    /// Puts an event in its place in the list. Events due at the same time stay in the order they were added.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event.
+    fn push(&mut self, event: Event) {
+        let index = self.events.partition_point(|other| (other.due, other.id) < (event.due, event.id));
+        self.events.insert(index, event);
+    }
+}
+
+impl MainBus {
+    /// Advances the scheduler to a cycle, firing the events that come due on the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycle` - The cycle to advance to.
+    pub(super) fn run_events(&mut self, cycle: u64) {
+        while self.scheduler.next_due().is_some_and(|due| due <= cycle) {
+            let mut event = self.scheduler.events.remove(0);
+            self.scheduler.now = self.scheduler.now.max(event.due.div_ceil(ONE_CYCLE));
+            self.scheduler.firing = Some((event.id, false));
+            (event.handler)(self);
+
+            // A repeating event goes back in the list, unless it cancelled itself
+            let cancelled = self.scheduler.firing.take().is_some_and(|(_, cancelled)| cancelled);
+            if event.period != 0 && !cancelled {
+                event.due += event.period;
+                self.scheduler.push(event);
+            }
+        }
+        self.scheduler.now = cycle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use std::sync::Mutex;
+
+    use crate::bus::MainBus;
+
+    #[test]
+    fn events_fire_in_order_as_the_bus_is_ticked() {
+        let mut bus = MainBus::new();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = |name: &'static str| {
+            let fired = fired.clone();
+            Box::new(move |bus: &mut MainBus| fired.lock().unwrap().push((name, bus.scheduler.now())))
+        };
+
+        bus.scheduler.at(10, "late", log("late"));
+        let cancelled = bus.scheduler.at(5, "cancelled", log("cancelled"));
+        bus.scheduler.after(5, "early", log("early"));
+        assert!(bus.scheduler.cancel(cancelled));
+        assert_eq!(bus.scheduler.pending(), ["early", "late"]);
+        assert_eq!(bus.scheduler.next_due(), Some(5));
+
+        // Both events come due within one tick, and each sees its own cycle
+        bus.tick(4);
+        assert!(fired.lock().unwrap().is_empty());
+        bus.tick(7);
+        assert_eq!(*fired.lock().unwrap(), [("early", 5), ("late", 10)]);
+        assert_eq!(bus.scheduler.now(), 11);
+        assert!(!bus.scheduler.cancel(cancelled));
+        assert_eq!(bus.scheduler.next_due(), None);
+    }
+
+    #[test]
+    fn repeating_events_keep_fractional_periods() {
+        let mut bus = MainBus::new();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let id = Arc::new(Mutex::new(None));
+        let (scanlines, own_id) = (fired.clone(), id.clone());
+        let scanline = bus.scheduler.every(
+            113.67,
+            "Scanline",
+            Box::new(move |bus| {
+                let mut scanlines = scanlines.lock().unwrap();
+                scanlines.push(bus.scheduler.now());
+                // The event stops itself after eight scanlines
+                if scanlines.len() == 8 {
+                    assert!(bus.scheduler.cancel(own_id.lock().unwrap().unwrap()));
+                }
+            }),
+        );
+        *id.lock().unwrap() = Some(scanline);
+
+        for _ in 0..2000 {
+            bus.tick(1);
+        }
+
+        // 113.67, 227.34, 341.01, 454.68... each on the first whole cycle at or after
+        assert_eq!(fired.lock().unwrap()[..4], [114, 228, 342, 455]);
+        assert_eq!(fired.lock().unwrap().len(), 8);
+        assert!(bus.scheduler.pending().is_empty());
+    }
+}
@@ -0,0 +1,477 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::Cell;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The offset of the data register from the start of the ACIA.
+pub const DATA: u16 = 0;
+
+/// The offset of the status register. Writing it resets the ACIA.
+pub const STATUS: u16 = 1;
+
+/// The offset of the command register.
+pub const COMMAND: u16 = 2;
+
+/// The offset of the control register.
+pub const CONTROL: u16 = 3;
+
+/// The status bit that is set while the ACIA is asserting IRQ.
+pub const IRQ: u8 = 0x80;
+
+/// The status bit that is set while a received byte is waiting to be read.
+pub const RECEIVE_FULL: u8 = 0x08;
+
+/// The status bit that is set while a byte can be sent. Bytes are sent at
+/// once, so it is always set.
+pub const TRANSMIT_EMPTY: u8 = 0x10;
+
+/// The command bit that readies the ACIA, which must be set for it to interrupt.
+pub const DTR_READY: u8 = 0x01;
+
+/// The command bit that stops a received byte from interrupting.
+pub const RECEIVE_IRQ_DISABLE: u8 = 0x02;
+
+/// The cycles between polls of the link for a received byte, which is about
+/// the time a byte takes at 9600 baud with a 1 MHz clock.
+pub const POLL_CYCLES: u64 = 1000;
+
+/// The host's end of a serial line.
+///
+/// An `Acia` sends the bytes the machine writes to its link, and polls the
+/// link for bytes to receive every `POLL_CYCLES` cycles. Links are `Any`, so
+/// the host can get one back from the ACIA by its type with
+/// `Acia::link_mut()`.
+pub trait SerialLink: Any + Send {
+    /// Sends a byte from the machine.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The byte the machine wrote.
+    fn send(&mut self, value: u8);
+
+    /// Receives a byte for the machine, if one has arrived.
+    ///
+    /// # Returns
+    ///
+    /// The byte, or `None` if there isn't one yet. This must not block.
+    fn receive(&mut self) -> Option<u8>;
+}
+
+/// A serial link to queues in memory, for hosts that handle the bytes themselves.
+#[derive(Debug, Default)]
+pub struct QueueLink {
+    /// The bytes waiting for the machine to receive, oldest first.
+    pub input: VecDeque<u8>,
+
+    /// The bytes the machine has sent, oldest first.
+    pub output: Vec<u8>,
+}
+
+impl SerialLink for QueueLink {
+    fn send(&mut self, value: u8) {
+        self.output.push(value);
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.input.pop_front()
+    }
+}
+
+/// A serial interface in the style of the 6551 ACIA.
+///
+/// The machine talks through four registers:
+///
+/// * `DATA` - Reading it takes the received byte. Writing it sends a byte.
+/// * `STATUS` - Bit 3 (`RECEIVE_FULL`) is set while a received byte is
+///   waiting, bit 4 (`TRANSMIT_EMPTY`) while a byte can be sent, and bit 7
+///   (`IRQ`) while the ACIA is asserting IRQ. Writing it resets the ACIA.
+/// * `COMMAND` - With `DTR_READY` set and `RECEIVE_IRQ_DISABLE` clear, as
+///   after writing `$09`, the ACIA asserts IRQ while a received byte is
+///   waiting. The other bits have no effect.
+/// * `CONTROL` - Holds what is written to it, so setup code written for a
+///   real 6551 runs, but the baud rate and frame format have no effect.
+///
+/// The other end of the line is a `SerialLink`. By default it is a
+/// `QueueLink` that the host reaches with `link_mut()`. The link is polled as
+/// the ACIA is ticked, once every `POLL_CYCLES` cycles while no received byte
+/// is waiting, so a byte arrives at most that long after the host sends it.
+/// With the `std` feature a `TcpLink` lets socket clients, such as `telnet`,
+/// use the machine's console.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::serial::{Acia, QueueLink, POLL_CYCLES};
+/// use butterflyrs::bus::BusDevice;
+///
+/// let mut acia = Acia::new(0xD000);
+/// acia.write(0xD000, b'A');
+/// acia.link_mut::<QueueLink>().unwrap().input.push_back(b'B');
+///
+/// acia.tick(POLL_CYCLES);
+/// assert_eq!(acia.read(0xD000), b'B');
+/// assert_eq!(acia.link_mut::<QueueLink>().unwrap().output, b"A");
+/// ```
+pub struct Acia {
+    /// The first address of the ACIA's registers.
+    pub start: u16,
+
+    /// The other end of the line.
+    link: Box<dyn SerialLink>,
+
+    /// The received byte waiting to be read, if any. Reading `DATA` takes it
+    /// even though reads don't borrow the device mutably.
+    received: Cell<Option<u8>>,
+
+    /// The cycles since the link was last polled.
+    since_poll: u64,
+
+    /// The value of the command register.
+    command: u8,
+
+    /// The value of the control register.
+    control: u8,
+}
+
+impl Acia {
+    /// Creates an ACIA linked to a `QueueLink`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the data register. The other registers follow it.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Acia` struct.
+    pub fn new(start: u16) -> Acia {
+        Acia::with_link(start, Box::new(QueueLink::default()))
+    }
+
+    /// Creates an ACIA linked to the host through a `SerialLink`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the data register. The other registers follow it.
+    /// * `link` - The other end of the line.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Acia` struct.
+    pub fn with_link(start: u16, link: Box<dyn SerialLink>) -> Acia {
+        Acia {
+            start,
+            link,
+            received: Cell::new(None),
+            since_poll: 0,
+            command: 0,
+            control: 0,
+        }
+    }
+
+    /// Returns the link, if it has the type asked for.
+    ///
+    /// # Returns
+    ///
+    /// The link, or `None` if it is some other type.
+    pub fn link_mut<T: SerialLink>(&mut self) -> Option<&mut T> {
+        let link: &mut dyn Any = self.link.as_mut();
+        link.downcast_mut::<T>()
+    }
+
+    /// Returns whether a received byte interrupts.
+    fn receive_irq_enabled(&self) -> bool {
+        self.command & (DTR_READY | RECEIVE_IRQ_DISABLE) == DTR_READY
+    }
+
+    /// Returns the value of the status register.
+    ///
+    /// # Arguments
+    ///
+    /// * `received` - The received byte waiting to be read, if any.
+    fn status(&self, received: Option<u8>) -> u8 {
+        match received {
+            Some(_) if self.receive_irq_enabled() => IRQ | RECEIVE_FULL | TRANSMIT_EMPTY,
+            Some(_) => RECEIVE_FULL | TRANSMIT_EMPTY,
+            None => TRANSMIT_EMPTY,
+        }
+    }
+}
+
+impl BusDevice for Acia {
+    fn read(&self, address: u16) -> u8 {
+        match address - self.start {
+            DATA => self.received.take().unwrap_or(0),
+            STATUS => self.status(self.received.get()),
+            COMMAND => self.command,
+            _ => self.control,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address - self.start {
+            DATA => self.link.send(value),
+            STATUS => self.reset(),
+            COMMAND => self.command = value,
+            _ => self.control = value,
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        // Shows the received byte without taking it
+        match address - self.start {
+            DATA => self.received.get().unwrap_or(0),
+            STATUS => self.status(self.received.get()),
+            _ => self.read(address),
+        }
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        // Only the registers that just hold a value can be changed without side effects
+        match address - self.start {
+            COMMAND => self.command = value,
+            CONTROL => self.control = value,
+            _ => {}
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // Bytes still on their way from the link stay there
+        self.command = 0;
+        self.control = 0;
+        self.received.set(None);
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.since_poll += cycles;
+        if self.since_poll < POLL_CYCLES {
+            return;
+        }
+
+        self.since_poll = 0;
+        if self.received.get().is_none() {
+            self.received.set(self.link.receive());
+        }
+    }
+
+    fn irq(&self) -> bool {
+        // Asserted until the received byte is read
+        self.receive_irq_enabled() && self.received.get().is_some()
+    }
+
+    fn name(&self) -> String {
+        String::from("ACIA")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + CONTROL
+    }
+
+    fn save(&self) -> Vec<u8> {
+        match self.received.get() {
+            Some(value) => vec![self.command, self.control, value],
+            None => vec![self.command, self.control],
+        }
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        match *data {
+            [command, control, ref received @ ..] if received.len() <= 1 => {
+                self.command = command;
+                self.control = control;
+                self.received.set(received.first().copied());
+                Ok(())
+            }
+            _ => Err(SnapshotError::InvalidData(self.name())),
+        }
+    }
+}
+
+/// A serial link to clients connected to a TCP port.
+///
+/// Everything the machine sends goes to every connected client, and bytes
+/// from any client are received in the order they arrive. Clients can come
+/// and go while the machine runs, and are accepted whenever the ACIA sends or
+/// polls for a byte. Bytes are passed through untouched, with no telnet
+/// option negotiation, which suits `telnet` and `nc` in line mode.
+///
+/// ```no_run
+/// use butterflyrs::bus::serial::{Acia, TcpLink};
+///
+/// let link = TcpLink::listen("127.0.0.1:6502").unwrap();
+/// let acia = Acia::with_link(0xD000, Box::new(link));
+/// ```
+#[cfg(feature = "std")]
+pub struct TcpLink {
+    /// The socket clients connect to.
+    listener: std::net::TcpListener,
+
+    /// The connected clients.
+    clients: Vec<std::net::TcpStream>,
+
+    /// Bytes read from the clients but not yet received.
+    input: VecDeque<u8>,
+}
+
+#[cfg(feature = "std")]
+impl TcpLink {
+    /// Listens for clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to listen on, such as `"127.0.0.1:6502"`. Port
+    ///   0 picks a free port, which `local_addr()` tells.
+    ///
+    /// # Returns
+    ///
+    /// The link, or the error binding the socket.
+    pub fn listen(address: impl std::net::ToSocketAddrs) -> std::io::Result<TcpLink> {
+        let listener = std::net::TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(TcpLink {
+            listener,
+            clients: Vec::new(),
+            input: VecDeque::new(),
+        })
+    }
+
+    /// Returns the address the link is listening on.
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns the number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accepts any clients waiting to connect.
+    fn accept(&mut self) {
+        while let Ok((client, _)) = self.listener.accept() {
+            // Small writes should reach the client at once, as they would on a terminal
+            if client.set_nonblocking(true).is_ok() {
+                let _ = client.set_nodelay(true);
+                self.clients.push(client);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl SerialLink for TcpLink {
+    fn send(&mut self, value: u8) {
+        use std::io::Write;
+
+        self.accept();
+
+        // A client that can't keep up misses the byte, and one that has gone is dropped
+        self.clients.retain_mut(|client| match client.write(&[value]) {
+            Ok(_) => true,
+            Err(error) => error.kind() == std::io::ErrorKind::WouldBlock,
+        });
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        use std::io::Read;
+
+        if self.input.is_empty() {
+            self.accept();
+
+            let mut buffer = [0u8; 256];
+            let input = &mut self.input;
+            self.clients.retain_mut(|client| match client.read(&mut buffer) {
+                Ok(0) => false,
+                Ok(length) => {
+                    input.extend(&buffer[..length]);
+                    true
+                }
+                Err(error) => error.kind() == std::io::ErrorKind::WouldBlock,
+            });
+        }
+        self.input.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::serial::{Acia, QueueLink, COMMAND, DTR_READY, IRQ, POLL_CYCLES, RECEIVE_FULL, TRANSMIT_EMPTY};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn sends_and_receives_through_its_link() {
+        let mut acia = Acia::new(0xD000);
+        assert_eq!(acia.read(0xD001), TRANSMIT_EMPTY);
+
+        acia.write(0xD000, b'O');
+        acia.write(0xD000, b'K');
+        acia.link_mut::<QueueLink>().unwrap().input.extend(b"HI");
+
+        // The link is only polled once the ACIA has been ticked for long enough
+        assert_eq!(acia.read(0xD001), TRANSMIT_EMPTY);
+        acia.tick(POLL_CYCLES - 1);
+        assert_eq!(acia.read(0xD001), TRANSMIT_EMPTY);
+        acia.tick(1);
+        assert_eq!(acia.read(0xD001), RECEIVE_FULL | TRANSMIT_EMPTY);
+        assert_eq!(acia.peek(0xD000), b'H');
+        assert_eq!(acia.read(0xD000), b'H');
+        assert_eq!(acia.read(0xD000), 0x00);
+        acia.tick(POLL_CYCLES);
+        assert_eq!(acia.read(0xD000), b'I');
+        assert_eq!(acia.link_mut::<QueueLink>().unwrap().output, b"OK");
+
+        // Writing the status register is a programmed reset
+        acia.write(0xD000 + COMMAND, 0x0B);
+        assert_eq!(acia.read(0xD000 + COMMAND), 0x0B);
+        acia.write(0xD001, 0x00);
+        assert_eq!(acia.read(0xD000 + COMMAND), 0x00);
+
+        // A received byte interrupts until it is read, once the receiver interrupt is enabled
+        acia.link_mut::<QueueLink>().unwrap().input.push_back(b'!');
+        acia.tick(POLL_CYCLES);
+        assert!(!acia.irq());
+        acia.write(0xD000 + COMMAND, DTR_READY);
+        assert!(acia.irq());
+        assert_eq!(acia.read(0xD001), IRQ | RECEIVE_FULL | TRANSMIT_EMPTY);
+        assert_eq!(acia.read(0xD000), b'!');
+        assert!(!acia.irq());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn bridges_to_tcp_clients() {
+        use std::io::{Read, Write};
+
+        use crate::bus::serial::TcpLink;
+
+        let link = TcpLink::listen("127.0.0.1:0").unwrap();
+        let mut client = std::net::TcpStream::connect(link.local_addr().unwrap()).unwrap();
+        let mut acia = Acia::with_link(0xD000, alloc::boxed::Box::new(link));
+
+        // The client is accepted when the machine first sends
+        acia.write(0xD000, b'>');
+        let mut prompt = [0u8; 1];
+        client.read_exact(&mut prompt).unwrap();
+        assert_eq!(&prompt, b">");
+
+        client.write_all(b"X").unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while acia.read(0xD001) & RECEIVE_FULL == 0 {
+            assert!(std::time::Instant::now() < deadline, "the byte never arrived");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            acia.tick(POLL_CYCLES);
+        }
+        assert_eq!(acia.read(0xD000), b'X');
+        assert_eq!(acia.link_mut::<TcpLink>().unwrap().clients(), 1);
+    }
+}
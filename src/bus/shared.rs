@@ -0,0 +1,87 @@
+#[cfg(not(feature = "sync"))]
+use alloc::rc::Rc;
+#[cfg(not(feature = "sync"))]
+use core::cell::RefCell;
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "sync")]
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::bus::MainBus;
+
+/// A handle to a `MainBus` shared between the CPU and the rest of the machine.
+///
+/// By default this is an `SharedBus`, which is as cheap as sharing
+/// gets but ties the machine to one thread. With the `sync` feature it is an
+/// `Arc<Mutex<MainBus>>` instead, which makes `Cpu` `Send` so the whole machine
+/// can be moved to a worker thread.
+#[derive(Clone)]
+pub struct SharedBus {
+    #[cfg(not(feature = "sync"))]
+    inner: Rc<RefCell<MainBus>>,
+
+    #[cfg(feature = "sync")]
+    inner: Arc<Mutex<MainBus>>,
+}
+
+impl SharedBus {
+    /// Wraps a bus so it can be shared.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus to share.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `SharedBus` struct.
+    pub fn new(bus: MainBus) -> SharedBus {
+        #[cfg(not(feature = "sync"))]
+        let inner = Rc::new(RefCell::new(bus));
+        #[cfg(feature = "sync")]
+        let inner = Arc::new(Mutex::new(bus));
+
+        SharedBus { inner }
+    }
+
+    /// Borrows the bus for reading.
+    ///
+    /// # Returns
+    ///
+    /// A guard that dereferences to the bus. Holding it while borrowing the
+    /// bus again panics, or deadlocks with the `sync` feature.
+    pub fn borrow(&self) -> impl Deref<Target = MainBus> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.inner.borrow();
+        #[cfg(feature = "sync")]
+        return self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// Borrows the bus for writing.
+    ///
+    /// # Returns
+    ///
+    /// A guard that dereferences mutably to the bus.
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = MainBus> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.inner.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// Checks whether there are other handles to the bus than this one.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the bus is reachable through another `SharedBus`.
+    pub(crate) fn is_shared(&self) -> bool {
+        #[cfg(not(feature = "sync"))]
+        return Rc::strong_count(&self.inner) > 1;
+        #[cfg(feature = "sync")]
+        return Arc::strong_count(&self.inner) > 1;
+    }
+}
+
+impl From<MainBus> for SharedBus {
+    fn from(bus: MainBus) -> SharedBus {
+        SharedBus::new(bus)
+    }
+}
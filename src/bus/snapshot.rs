@@ -0,0 +1,78 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The saved contents of one device on the bus.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceSnapshot {
+    /// The name of the device, used to check that the snapshot matches the bus.
+    pub name: String,
+
+    /// The start address of the device.
+    pub start: u16,
+
+    /// The end address of the device.
+    pub end: u16,
+
+    /// The state returned by `BusDevice::save()`.
+    pub data: Vec<u8>,
+}
+
+/// The saved state of the bus and every device connected to it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BusSnapshot {
+    /// The devices, in the order they were added to the bus.
+    pub devices: Vec<DeviceSnapshot>,
+
+    /// The last value driven on the data bus.
+    pub data_bus: u8,
+}
+
+/// Why a snapshot could not be restored.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SnapshotError {
+    /// The snapshot has a different number of devices than the bus.
+    DeviceCount {
+        /// The number of devices on the bus.
+        expected: usize,
+
+        /// The number of devices in the snapshot.
+        found: usize,
+    },
+
+    /// The device at an index doesn't match the one in the snapshot.
+    DeviceMismatch {
+        /// The position of the device on the bus.
+        index: usize,
+
+        /// The name and range of the device on the bus.
+        expected: String,
+
+        /// The name and range of the device in the snapshot.
+        found: String,
+    },
+
+    /// A device rejected its saved state.
+    InvalidData(String),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SnapshotError::DeviceCount { expected, found } => {
+                write!(f, "Expected {} devices, snapshot has {}", expected, found)
+            }
+            SnapshotError::DeviceMismatch { index, expected, found } => {
+                write!(f, "Device {} is {}, snapshot has {}", index, expected, found)
+            }
+            SnapshotError::InvalidData(name) => write!(f, "Invalid saved state for {}", name),
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
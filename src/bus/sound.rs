@@ -0,0 +1,194 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The offset of the low byte of the frequency register.
+pub const FREQUENCY_LOW: u16 = 0;
+
+/// The offset of the high byte of the frequency register.
+pub const FREQUENCY_HIGH: u16 = 1;
+
+/// The offset of the duty cycle register.
+pub const DUTY: u16 = 2;
+
+/// The offset of the volume register.
+pub const VOLUME: u16 = 3;
+
+/// A square wave tone generator.
+///
+/// The machine programs the tone through four registers:
+///
+/// * `FREQUENCY_LOW` and `FREQUENCY_HIGH` - The frequency in Hz, 0 for silence.
+/// * `DUTY` - The fraction of each period the wave is high, in 256ths. The
+///   default of 128 is a square wave.
+/// * `VOLUME` - The volume, from 0 for silence to 255 for full scale.
+///
+/// The generator renders samples at `sample_rate` as the CPU runs, advanced
+/// by the cycles the bus ticks it with, or by the host calling `advance()`
+/// when it isn't on a bus. The host collects them with `take_samples()` and
+/// plays them or writes them out. With the `audio` feature,
+/// `crate::audio::AudioOutput` plays them through the host's sound card.
+pub struct ToneGenerator {
+    /// The first address of the generator's registers.
+    pub start: u16,
+
+    /// The rate the CPU is clocked at, in Hz.
+    pub clock_rate: u32,
+
+    /// The rate samples are rendered at, in Hz.
+    pub sample_rate: u32,
+
+    /// The register values, in register order.
+    registers: [u8; 4],
+
+    /// The position in the current period of the wave, as a fraction of 2^32.
+    phase: u32,
+
+    /// Cycles not yet rendered, in units of 1/`sample_rate` cycles.
+    pending: u64,
+
+    /// The samples rendered but not yet taken, from -1.0 to 1.0.
+    samples: Vec<f32>,
+}
+
+impl ToneGenerator {
+    /// Creates a silent tone generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the first register.
+    /// * `clock_rate` - The rate the CPU is clocked at, in Hz.
+    /// * `sample_rate` - The rate to render samples at, in Hz.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `ToneGenerator` struct.
+    pub fn new(start: u16, clock_rate: u32, sample_rate: u32) -> ToneGenerator {
+        ToneGenerator {
+            start,
+            clock_rate,
+            sample_rate,
+            registers: [0x00, 0x00, 0x80, 0x00],
+            phase: 0,
+            pending: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Returns the frequency of the tone in Hz.
+    pub fn frequency(&self) -> u16 {
+        u16::from_le_bytes([self.registers[FREQUENCY_LOW as usize], self.registers[FREQUENCY_HIGH as usize]])
+    }
+
+    /// Renders the samples for the time the CPU took to run some cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles since the last call.
+    pub fn advance(&mut self, cycles: u64) {
+        self.pending += cycles * self.sample_rate as u64;
+
+        let step = ((self.frequency() as u64) << 32) / self.sample_rate.max(1) as u64;
+        let duty = self.registers[DUTY as usize] as u32;
+        let level = self.registers[VOLUME as usize] as f32 / 255.0;
+
+        while self.pending >= self.clock_rate as u64 {
+            self.pending -= self.clock_rate as u64;
+
+            let sample = if self.frequency() == 0 {
+                0.0
+            } else if self.phase >> 24 < duty {
+                level
+            } else {
+                -level
+            };
+            self.samples.push(sample);
+            self.phase = self.phase.wrapping_add(step as u32);
+        }
+    }
+
+    /// Takes the samples rendered since the last call.
+    ///
+    /// # Returns
+    ///
+    /// The samples, from -1.0 to 1.0, at `sample_rate`.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        core::mem::take(&mut self.samples)
+    }
+}
+
+impl BusDevice for ToneGenerator {
+    fn read(&self, address: u16) -> u8 {
+        self.registers[(address - self.start) as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.registers[(address - self.start) as usize] = value;
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.advance(cycles);
+    }
+
+    fn reset(&mut self) {
+        // Silence the generator, keeping the samples the host hasn't taken yet
+        self.registers = [0x00, 0x00, 0x80, 0x00];
+        self.phase = 0;
+    }
+
+    fn name(&self) -> String {
+        String::from("Tone generator")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + VOLUME
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.registers.to_vec()
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        self.registers = data.try_into().map_err(|_| SnapshotError::InvalidData(self.name()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::sound::{ToneGenerator, DUTY, FREQUENCY_HIGH, FREQUENCY_LOW, VOLUME};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn renders_a_square_wave() {
+        // A 1 kHz tone sampled at 8 kHz, with a 1 MHz CPU
+        let mut tone = ToneGenerator::new(0xD400, 1_000_000, 8_000);
+        tone.write(0xD400 + FREQUENCY_LOW, 0xE8);
+        tone.write(0xD400 + FREQUENCY_HIGH, 0x03);
+        tone.write(0xD400 + VOLUME, 0xFF);
+
+        // 1 ms is one period of eight samples, half high and half low
+        tone.advance(1_000);
+        assert_eq!(tone.take_samples(), [1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0]);
+
+        // A quarter duty cycle, with the time split over several calls
+        tone.write(0xD400 + DUTY, 0x40);
+        for _ in 0..10 {
+            tone.advance(100);
+        }
+        assert_eq!(tone.take_samples(), [1.0, 1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0]);
+
+        tone.reset();
+        tone.advance(1_000);
+        assert_eq!(tone.take_samples(), [0.0; 8]);
+    }
+}
@@ -0,0 +1,485 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+use crate::bus::{BusDevice, SnapshotError};
+
+/// The offset of output register B, which reads back the pins of port B.
+pub const ORB: u16 = 0x0;
+
+/// The offset of output register A, which reads back the pins of port A.
+pub const ORA: u16 = 0x1;
+
+/// The offset of the data direction register for port B. A set bit makes the pin an output.
+pub const DDRB: u16 = 0x2;
+
+/// The offset of the data direction register for port A.
+pub const DDRA: u16 = 0x3;
+
+/// The offset of the low byte of timer 1's counter. Writing it sets the latch instead.
+pub const T1C_L: u16 = 0x4;
+
+/// The offset of the high byte of timer 1's counter. Writing it starts the timer.
+pub const T1C_H: u16 = 0x5;
+
+/// The offset of the low byte of timer 1's latch.
+pub const T1L_L: u16 = 0x6;
+
+/// The offset of the high byte of timer 1's latch.
+pub const T1L_H: u16 = 0x7;
+
+/// The offset of the low byte of timer 2's counter. Writing it sets the latch instead.
+pub const T2C_L: u16 = 0x8;
+
+/// The offset of the high byte of timer 2's counter. Writing it starts the timer.
+pub const T2C_H: u16 = 0x9;
+
+/// The offset of the shift register.
+pub const SR: u16 = 0xA;
+
+/// The offset of the auxiliary control register.
+pub const ACR: u16 = 0xB;
+
+/// The offset of the peripheral control register.
+pub const PCR: u16 = 0xC;
+
+/// The offset of the interrupt flag register.
+pub const IFR: u16 = 0xD;
+
+/// The offset of the interrupt enable register.
+pub const IER: u16 = 0xE;
+
+/// The offset of output register A without handshaking.
+pub const ORA_NO_HANDSHAKE: u16 = 0xF;
+
+/// The interrupt flag that is set when timer 1 runs out.
+pub const TIMER1: u8 = 0x40;
+
+/// The interrupt flag that is set when timer 2 runs out.
+pub const TIMER2: u8 = 0x20;
+
+/// The auxiliary control bit that makes timer 1 reload from its latch and keep running.
+pub const TIMER1_FREE_RUN: u8 = 0x40;
+
+/// The auxiliary control bit that makes timer 2 count pulses on PB6 rather than cycles.
+pub const TIMER2_PULSE_COUNT: u8 = 0x20;
+
+/// A 6522 Versatile Interface Adapter, with two parallel ports and two timers.
+///
+/// The ports are 8 bits wide, and each pin is an input or an output as set by
+/// its bit in `DDRA` or `DDRB`. Reading a port returns the output register
+/// for output pins and what the host has put on the pins with
+/// `set_port_a()` or `set_port_b()` for input pins. The host sees what the
+/// machine drives with `port_a()` and `port_b()`.
+///
+/// Timer 1 counts down every cycle from the value written to it and sets the
+/// `TIMER1` interrupt flag when it runs out. It stops there, or reloads from
+/// its latch and keeps going if `TIMER1_FREE_RUN` is set in `ACR`. Timer 2 is
+/// a one-shot timer. The VIA asserts IRQ while a flag is set that is also
+/// enabled in `IER`.
+///
+/// The handshake lines, the shift register's shifting and timer 1's output
+/// on PB7 aren't emulated: `PCR` and `SR` just hold what is written to them.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::via::{Via, DDRB, ORB};
+/// use butterflyrs::bus::BusDevice;
+///
+/// let mut via = Via::new(0x6000);
+/// via.write(0x6000 + DDRB, 0xFF);
+/// via.write(0x6000 + ORB, 0x42);
+/// assert_eq!(via.port_b(), 0x42);
+/// ```
+pub struct Via {
+    /// The first address of the VIA's registers.
+    pub start: u16,
+
+    /// The output registers for ports A and B.
+    ora: u8,
+    orb: u8,
+
+    /// The data direction registers for ports A and B.
+    ddra: u8,
+    ddrb: u8,
+
+    /// The levels the host has put on the pins of ports A and B.
+    input_a: u8,
+    input_b: u8,
+
+    /// Timer 1's counter and latch.
+    t1_counter: u16,
+    t1_latch: u16,
+
+    /// Whether timer 1 will set its flag when it next runs out.
+    t1_armed: bool,
+
+    /// Timer 2's counter, and the low byte of its latch.
+    t2_counter: u16,
+    t2_latch: u8,
+
+    /// Whether timer 2 will set its flag when it next runs out.
+    t2_armed: bool,
+
+    /// The shift, auxiliary control and peripheral control registers.
+    sr: u8,
+    acr: u8,
+    pcr: u8,
+
+    /// The interrupt flags. Reading a timer's low counter byte clears its
+    /// flag even though reads don't borrow the device mutably.
+    ifr: Cell<u8>,
+
+    /// The interrupt enable bits.
+    ier: u8,
+}
+
+impl Via {
+    /// Creates a VIA in its reset state, with both ports set to inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The address of the first register. The VIA takes 16 addresses from there.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Via` struct.
+    pub fn new(start: u16) -> Via {
+        Via {
+            start,
+            ora: 0,
+            orb: 0,
+            ddra: 0,
+            ddrb: 0,
+            input_a: 0xFF,
+            input_b: 0xFF,
+            t1_counter: 0,
+            t1_latch: 0,
+            t1_armed: false,
+            t2_counter: 0,
+            t2_latch: 0,
+            t2_armed: false,
+            sr: 0,
+            acr: 0,
+            pcr: 0,
+            ifr: Cell::new(0),
+            ier: 0,
+        }
+    }
+
+    /// Returns the levels of the pins of port A: the output register for
+    /// output pins and the host's input for input pins.
+    pub fn port_a(&self) -> u8 {
+        (self.ora & self.ddra) | (self.input_a & !self.ddra)
+    }
+
+    /// Returns the levels of the pins of port B.
+    pub fn port_b(&self) -> u8 {
+        (self.orb & self.ddrb) | (self.input_b & !self.ddrb)
+    }
+
+    /// Puts levels on the pins of port A, which the machine reads on its input pins.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The levels, one bit per pin. Unconnected pins float high.
+    pub fn set_port_a(&mut self, value: u8) {
+        self.input_a = value;
+    }
+
+    /// Puts levels on the pins of port B.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The levels, one bit per pin.
+    pub fn set_port_b(&mut self, value: u8) {
+        self.input_b = value;
+    }
+
+    /// Returns the value of the interrupt flag register, with bit 7 set while
+    /// an enabled flag is.
+    fn flags(&self) -> u8 {
+        let flags = self.ifr.get();
+        if flags & self.ier & 0x7F != 0 {
+            flags | 0x80
+        } else {
+            flags
+        }
+    }
+
+    /// Clears interrupt flags.
+    fn clear_flags(&self, flags: u8) {
+        self.ifr.set(self.ifr.get() & !flags);
+    }
+
+    /// Advances both timers by one cycle.
+    fn step(&mut self) {
+        self.t1_counter = self.t1_counter.wrapping_sub(1);
+        if self.t1_counter == 0xFFFF {
+            if self.t1_armed {
+                self.ifr.set(self.ifr.get() | TIMER1);
+            }
+            if self.acr & TIMER1_FREE_RUN != 0 {
+                self.t1_counter = self.t1_latch;
+            } else {
+                self.t1_armed = false;
+            }
+        }
+
+        if self.acr & TIMER2_PULSE_COUNT == 0 {
+            self.t2_counter = self.t2_counter.wrapping_sub(1);
+            if self.t2_counter == 0xFFFF && self.t2_armed {
+                self.ifr.set(self.ifr.get() | TIMER2);
+                self.t2_armed = false;
+            }
+        }
+    }
+}
+
+impl BusDevice for Via {
+    fn read(&self, address: u16) -> u8 {
+        match (address - self.start) & 0x0F {
+            ORB => self.port_b(),
+            ORA | ORA_NO_HANDSHAKE => self.port_a(),
+            DDRB => self.ddrb,
+            DDRA => self.ddra,
+            T1C_L => {
+                self.clear_flags(TIMER1);
+                self.t1_counter as u8
+            }
+            T1C_H => (self.t1_counter >> 8) as u8,
+            T1L_L => self.t1_latch as u8,
+            T1L_H => (self.t1_latch >> 8) as u8,
+            T2C_L => {
+                self.clear_flags(TIMER2);
+                self.t2_counter as u8
+            }
+            T2C_H => (self.t2_counter >> 8) as u8,
+            SR => self.sr,
+            ACR => self.acr,
+            PCR => self.pcr,
+            IFR => self.flags(),
+            _ => self.ier | 0x80,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match (address - self.start) & 0x0F {
+            ORB => self.orb = value,
+            ORA | ORA_NO_HANDSHAKE => self.ora = value,
+            DDRB => self.ddrb = value,
+            DDRA => self.ddra = value,
+            T1C_L | T1L_L => self.t1_latch = (self.t1_latch & 0xFF00) | value as u16,
+            T1C_H => {
+                // Writing the high byte loads the counter from the latch and starts it
+                self.t1_latch = (self.t1_latch & 0x00FF) | (value as u16) << 8;
+                self.t1_counter = self.t1_latch;
+                self.t1_armed = true;
+                self.clear_flags(TIMER1);
+            }
+            T1L_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | (value as u16) << 8;
+                self.clear_flags(TIMER1);
+            }
+            T2C_L => self.t2_latch = value,
+            T2C_H => {
+                self.t2_counter = (value as u16) << 8 | self.t2_latch as u16;
+                self.t2_armed = true;
+                self.clear_flags(TIMER2);
+            }
+            SR => self.sr = value,
+            ACR => self.acr = value,
+            PCR => self.pcr = value,
+            IFR => self.clear_flags(value & 0x7F),
+            _ => {
+                // Bit 7 says whether the other set bits are enabled or disabled
+                if value & 0x80 != 0 {
+                    self.ier |= value & 0x7F;
+                } else {
+                    self.ier &= !value;
+                }
+            }
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        // Reads the counters without clearing the timer flags
+        match (address - self.start) & 0x0F {
+            T1C_L => self.t1_counter as u8,
+            T2C_L => self.t2_counter as u8,
+            _ => self.read(address),
+        }
+    }
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        // Reset clears every register except the timers and the shift register
+        self.ora = 0;
+        self.orb = 0;
+        self.ddra = 0;
+        self.ddrb = 0;
+        self.acr = 0;
+        self.pcr = 0;
+        self.ifr.set(0);
+        self.ier = 0;
+        self.t1_armed = false;
+        self.t2_armed = false;
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        for _ in 0..cycles {
+            self.step();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.ifr.get() & self.ier & 0x7F != 0
+    }
+
+    fn name(&self) -> String {
+        String::from("VIA")
+    }
+
+    fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    fn end_address(&self) -> u16 {
+        self.start + 0x0F
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let [t1_counter_low, t1_counter_high] = self.t1_counter.to_le_bytes();
+        let [t1_latch_low, t1_latch_high] = self.t1_latch.to_le_bytes();
+        let [t2_counter_low, t2_counter_high] = self.t2_counter.to_le_bytes();
+        vec![
+            self.ora,
+            self.orb,
+            self.ddra,
+            self.ddrb,
+            self.input_a,
+            self.input_b,
+            t1_counter_low,
+            t1_counter_high,
+            t1_latch_low,
+            t1_latch_high,
+            self.t1_armed as u8,
+            t2_counter_low,
+            t2_counter_high,
+            self.t2_latch,
+            self.t2_armed as u8,
+            self.sr,
+            self.acr,
+            self.pcr,
+            self.ifr.get(),
+            self.ier,
+        ]
+    }
+
+    fn load(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let [
+            ora,
+            orb,
+            ddra,
+            ddrb,
+            input_a,
+            input_b,
+            t1_counter_low,
+            t1_counter_high,
+            t1_latch_low,
+            t1_latch_high,
+            t1_armed,
+            t2_counter_low,
+            t2_counter_high,
+            t2_latch,
+            t2_armed,
+            sr,
+            acr,
+            pcr,
+            ifr,
+            ier,
+        ] = *data
+        else {
+            return Err(SnapshotError::InvalidData(self.name()));
+        };
+        self.ora = ora;
+        self.orb = orb;
+        self.ddra = ddra;
+        self.ddrb = ddrb;
+        self.input_a = input_a;
+        self.input_b = input_b;
+        self.t1_counter = u16::from_le_bytes([t1_counter_low, t1_counter_high]);
+        self.t1_latch = u16::from_le_bytes([t1_latch_low, t1_latch_high]);
+        self.t1_armed = t1_armed != 0;
+        self.t2_counter = u16::from_le_bytes([t2_counter_low, t2_counter_high]);
+        self.t2_latch = t2_latch;
+        self.t2_armed = t2_armed != 0;
+        self.sr = sr;
+        self.acr = acr;
+        self.pcr = pcr;
+        self.ifr.set(ifr);
+        self.ier = ier;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::via::{Via, ACR, DDRA, IER, IFR, ORA, T1C_H, T1C_L, T2C_H, T2C_L};
+    use crate::bus::via::{TIMER1, TIMER1_FREE_RUN, TIMER2};
+    use crate::bus::BusDevice;
+
+    #[test]
+    fn mixes_inputs_and_outputs_on_a_port() {
+        let mut via = Via::new(0x6000);
+        via.set_port_a(0xA5);
+        assert_eq!(via.read(0x6000 + ORA), 0xA5);
+
+        // The low nibble drives the pins, the high nibble still reads the host
+        via.write(0x6000 + DDRA, 0x0F);
+        via.write(0x6000 + ORA, 0x3C);
+        assert_eq!(via.read(0x6000 + ORA), 0xAC);
+        assert_eq!(via.port_a(), 0xAC);
+
+        let saved = via.save();
+        via.reset();
+        assert_eq!(via.port_a(), 0xA5);
+        via.load(&saved).unwrap();
+        assert_eq!(via.port_a(), 0xAC);
+    }
+
+    #[test]
+    fn timers_interrupt_when_they_run_out() {
+        let mut via = Via::new(0x6000);
+        via.write(0x6000 + IER, 0x80 | TIMER1 | TIMER2);
+        assert_eq!(via.read(0x6000 + IER), 0x80 | TIMER1 | TIMER2);
+
+        // Timer 1 runs freely with a period of 10 cycles
+        via.write(0x6000 + ACR, TIMER1_FREE_RUN);
+        via.write(0x6000 + T1C_L, 9);
+        via.write(0x6000 + T1C_H, 0);
+        via.tick(9);
+        assert!(!via.irq());
+        via.tick(1);
+        assert!(via.irq());
+        assert_eq!(via.read(0x6000 + IFR), 0x80 | TIMER1);
+        assert_eq!(via.read(0x6000 + T1C_L), 9);
+        assert!(!via.irq());
+        via.tick(10);
+        assert!(via.irq());
+        via.write(0x6000 + IFR, TIMER1);
+
+        // Timer 2 only goes off once
+        via.write(0x6000 + T2C_L, 4);
+        via.write(0x6000 + T2C_H, 0);
+        via.tick(5);
+        assert_eq!(via.read(0x6000 + IFR) & TIMER2, TIMER2);
+        via.write(0x6000 + IFR, TIMER2);
+        via.tick(0x10000);
+        assert_eq!(via.peek(0x6000 + IFR) & TIMER2, 0);
+    }
+}
@@ -1,48 +1,100 @@
-use std::fmt::Display;
+use core::fmt::Display;
 use crate::cpu::Cpu;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// The ways an instruction can locate its operand.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum AddressingMode {
+    /// No addressing mode has been decoded yet.
     None,
+    /// Operates on the accumulator (`ASL A`).
+    Accumulator,
+    /// A full 16-bit address (`LDA $1234`).
     Absolute,
+    /// A 16-bit address plus X (`LDA $1234,X`).
     AbsoluteX,
+    /// A 16-bit address plus Y (`LDA $1234,Y`).
     AbsoluteY,
+    /// The operand follows the opcode (`LDA #$12`).
     Immediate,
+    /// No operand (`INX`).
     Implied,
+    /// A pointer to the target address, used by JMP (`JMP ($1234)`).
     Indirect,
+    /// A pointer at a 16-bit address plus X, used by the 65C02 JMP (`JMP ($1234,X)`).
+    AbsoluteIndexedIndirect,
+    /// A pointer at a zero page address plus X (`LDA ($12,X)`).
     IndexedIndirect,
+    /// A pointer at a zero page address, plus Y (`LDA ($12),Y`).
     IndirectIndexed,
+    /// A signed branch offset (`BNE label`).
     Relative,
+    /// An address in page zero (`LDA $12`).
     ZeroPage,
+    /// A zero page address plus X, wrapping within page zero (`LDA $12,X`).
     ZeroPageX,
+    /// A zero page address plus Y, wrapping within page zero (`LDX $12,Y`).
     ZeroPageY,
+    /// A pointer at a zero page address, on the 65C02 (`LDA ($12)`).
+    ZeroPageIndirect,
+    /// A zero page address and a branch offset, used by BBR and BBS (`BBR0 $12,label`).
+    ZeroPageRelative,
 }
 
 impl Display for AddressingMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             AddressingMode::None => write!(f, "None"),
+            AddressingMode::Accumulator => write!(f, "Accumulator"),
             AddressingMode::Absolute => write!(f, "Absolute"),
             AddressingMode::AbsoluteX => write!(f, "AbsoluteX"),
             AddressingMode::AbsoluteY => write!(f, "AbsoluteY"),
             AddressingMode::Immediate => write!(f, "Immediate"),
             AddressingMode::Implied => write!(f, "Implied"),
             AddressingMode::Indirect => write!(f, "Indirect"),
+            AddressingMode::AbsoluteIndexedIndirect => write!(f, "AbsoluteIndexedIndirect"),
             AddressingMode::IndexedIndirect => write!(f, "IndexedIndirect"),
             AddressingMode::IndirectIndexed => write!(f, "IndirectIndexed"),
             AddressingMode::Relative => write!(f, "Relative"),
             AddressingMode::ZeroPage => write!(f, "ZeroPage"),
             AddressingMode::ZeroPageX => write!(f, "ZeroPageX"),
             AddressingMode::ZeroPageY => write!(f, "ZeroPageY"),
+            AddressingMode::ZeroPageIndirect => write!(f, "ZeroPageIndirect"),
+            AddressingMode::ZeroPageRelative => write!(f, "ZeroPageRelative"),
         }
     }
 }
 
 impl AddressingMode {
+    /// Returns the number of operand bytes that follow the opcode in this mode.
+    pub fn operand_length(&self) -> u16 {
+        match self {
+            AddressingMode::None | AddressingMode::Accumulator | AddressingMode::Implied => 0,
+            AddressingMode::Immediate
+            | AddressingMode::IndexedIndirect
+            | AddressingMode::IndirectIndexed
+            | AddressingMode::Relative
+            | AddressingMode::ZeroPage
+            | AddressingMode::ZeroPageX
+            | AddressingMode::ZeroPageY
+            | AddressingMode::ZeroPageIndirect => 1,
+            AddressingMode::Absolute
+            | AddressingMode::AbsoluteX
+            | AddressingMode::AbsoluteY
+            | AddressingMode::Indirect
+            | AddressingMode::AbsoluteIndexedIndirect
+            | AddressingMode::ZeroPageRelative => 2,
+        }
+    }
+
     /// Execute an addressing mode, returns true if an extra cycle is needed
     pub fn execute(&self, cpu: &mut Cpu) -> bool {
         match self {
             AddressingMode::None => false,
+            AddressingMode::Accumulator => {
+                cpu.fetched_data = cpu.a.get();
+                false
+            }
             AddressingMode::Absolute => {
                 let address = cpu.read16(cpu.pc.get());
                 cpu.address_absolute = address;
@@ -51,7 +103,7 @@ impl AddressingMode {
             }
             AddressingMode::AbsoluteX => {
                 let address = cpu.read16(cpu.pc.get());
-                cpu.address_absolute = address + cpu.x.get() as u16;
+                cpu.address_absolute = address.wrapping_add(cpu.x.get() as u16);
                 cpu.pc += 2;
 
                 // If page boundary is crossed, we need an extra cycle
@@ -62,7 +114,7 @@ impl AddressingMode {
             }
             AddressingMode::AbsoluteY => {
                 let address = cpu.read16(cpu.pc.get());
-                cpu.address_absolute = address + cpu.y.get() as u16;
+                cpu.address_absolute = address.wrapping_add(cpu.y.get() as u16);
                 cpu.pc += 2;
 
                 // If page boundary is crossed, we need an extra cycle
@@ -82,31 +134,43 @@ impl AddressingMode {
             }
             AddressingMode::Indirect => {
                 let addr_lo = cpu.read8(cpu.pc.get());
-                let addr_hi = cpu.read8(cpu.pc.get() + 1);
+                let addr_hi = cpu.read8(cpu.pc.get().wrapping_add(1));
                 let addr = (addr_hi as u16) << 8 | (addr_lo as u16);
 
-                if addr_lo == 0x00FF {
-                    // We crossed a page boundary, so we need to simulate the hardware bug
+                if addr_lo == 0x00FF && !cpu.model.is_cmos() {
+                    // We crossed a page boundary, so we need to simulate the NMOS hardware bug
                     cpu.address_absolute = (cpu.read8(addr & 0xFF00) as u16) << 8 | cpu.read8(addr) as u16;
                 } else {
-                    cpu.address_absolute = (cpu.read8(addr + 1) as u16) << 8 | cpu.read8(addr) as u16;
+                    cpu.address_absolute = (cpu.read8(addr.wrapping_add(1)) as u16) << 8 | cpu.read8(addr) as u16;
                 }
                 cpu.pc += 2;
                 false
             }
+            AddressingMode::AbsoluteIndexedIndirect => {
+                // The pointer is the absolute operand plus X, and unlike the
+                // NMOS indirect jump it may cross a page
+                let pointer = cpu.read16(cpu.pc.get()).wrapping_add(cpu.x.get() as u16);
+                cpu.address_absolute = cpu.read16(pointer);
+                cpu.pc += 2;
+                false
+            }
             AddressingMode::IndexedIndirect => {
-                let temp = cpu.read8(cpu.pc.get());
-                let lo = cpu.read8(temp as u16 + cpu.x.get() as u16);
-                let hi = cpu.read8(temp as u16 + cpu.x.get() as u16 + 1);
+                // The pointer lives in page zero, so both the index and the high
+                // byte fetch wrap around within it
+                let pointer = cpu.read8(cpu.pc.get()).wrapping_add(cpu.x.get());
+                let lo = cpu.read8(pointer as u16);
+                let hi = cpu.read8(pointer.wrapping_add(1) as u16);
                 cpu.address_absolute = (hi as u16) << 8 | (lo as u16);
                 cpu.pc += 1;
                 false
             }
             AddressingMode::IndirectIndexed => {
-                let temp = cpu.read8(cpu.pc.get());
-                let lo = cpu.read8(temp as u16);
-                let hi = cpu.read8(temp as u16 + 1);
-                cpu.address_absolute = (hi as u16) << 8 | (lo as u16);
+                // The pointer lives in page zero, so the high byte fetch wraps around within it
+                let pointer = cpu.read8(cpu.pc.get());
+                let lo = cpu.read8(pointer as u16);
+                let hi = cpu.read8(pointer.wrapping_add(1) as u16);
+                let base = (hi as u16) << 8 | (lo as u16);
+                cpu.address_absolute = base.wrapping_add(cpu.y.get() as u16);
                 cpu.pc += 1;
 
                 // If page boundary is crossed, we need an extra cycle
@@ -138,6 +202,186 @@ impl AddressingMode {
                 cpu.pc += 1;
                 false
             }
+            AddressingMode::ZeroPageIndirect => {
+                // The pointer lives in page zero, so the high byte fetch wraps around within it
+                let pointer = cpu.read8(cpu.pc.get());
+                let lo = cpu.read8(pointer as u16);
+                let hi = cpu.read8(pointer.wrapping_add(1) as u16);
+                cpu.address_absolute = (hi as u16) << 8 | (lo as u16);
+                cpu.pc += 1;
+                false
+            }
+            AddressingMode::ZeroPageRelative => {
+                // A zero page address to test, followed by a branch offset
+                cpu.address_absolute = cpu.read8(cpu.pc.get()) as u16;
+                cpu.address_relative = cpu.read8(cpu.pc.get().wrapping_add(1)) as u16;
+                cpu.pc += 2;
+                if cpu.address_relative & 0x80 > 0 {
+                    cpu.address_relative |= 0xFF00;
+                }
+                false
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use crate::bus::{MainBus, SharedBus};
+    use crate::bus::ram::Ram;
+    use crate::cpu::{Cpu, CpuModel};
+    use crate::cpu::addressing::AddressingMode;
+
+    /// Creates a CPU backed by 64K of RAM with the program counter at $0200.
+    fn setup() -> Cpu {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        bus.add_device(Box::new(Ram::new(0x8000, 0xFFFF)));
+        let mut cpu = Cpu::new(SharedBus::new(bus));
+        cpu.pc.set(0x0200);
+        cpu
+    }
+
+    #[test]
+    fn indexed_indirect_wraps_index_within_zero_page() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFF);
+        cpu.x.set(0x02);
+        cpu.write8(0x0001, 0x34);
+        cpu.write8(0x0002, 0x12);
+        cpu.write8(0x0101, 0xAA);
+        cpu.write8(0x0102, 0xBB);
+
+        AddressingMode::IndexedIndirect.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x1234);
+        assert_eq!(cpu.pc.get(), 0x0201);
+    }
+
+    #[test]
+    fn indexed_indirect_wraps_pointer_high_byte() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFE);
+        cpu.x.set(0x01);
+        cpu.write8(0x00FF, 0x34);
+        cpu.write8(0x0000, 0x12);
+        cpu.write8(0x0100, 0xBB);
+
+        AddressingMode::IndexedIndirect.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x1234);
+    }
+
+    #[test]
+    fn indirect_indexed_wraps_pointer_high_byte() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFF);
+        cpu.y.set(0x10);
+        cpu.write8(0x00FF, 0x00);
+        cpu.write8(0x0000, 0x30);
+        cpu.write8(0x0100, 0x40);
+
+        let extra_cycle = AddressingMode::IndirectIndexed.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x3010);
+        assert!(!extra_cycle);
+        assert_eq!(cpu.pc.get(), 0x0201);
+    }
+
+    #[test]
+    fn indirect_indexed_reports_page_cross() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0x80);
+        cpu.y.set(0x01);
+        cpu.write8(0x0080, 0xFF);
+        cpu.write8(0x0081, 0x20);
+
+        let extra_cycle = AddressingMode::IndirectIndexed.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x2100);
+        assert!(extra_cycle);
+    }
+
+    #[test]
+    fn absolute_x_wraps_past_end_of_memory() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFF);
+        cpu.write8(0x0201, 0xFF);
+        cpu.x.set(0x02);
+
+        let extra_cycle = AddressingMode::AbsoluteX.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x0001);
+        assert!(extra_cycle);
+        assert_eq!(cpu.pc.get(), 0x0202);
+    }
+
+    #[test]
+    fn absolute_y_wraps_past_end_of_memory() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xF0);
+        cpu.write8(0x0201, 0xFF);
+        cpu.y.set(0x10);
+
+        let extra_cycle = AddressingMode::AbsoluteY.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x0000);
+        assert!(extra_cycle);
+    }
+
+    #[test]
+    fn absolute_x_at_top_of_memory_without_wrap() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFE);
+        cpu.write8(0x0201, 0xFF);
+        cpu.x.set(0x01);
+
+        let extra_cycle = AddressingMode::AbsoluteX.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0xFFFF);
+        assert!(!extra_cycle);
+    }
+
+    #[test]
+    fn indirect_keeps_page_wrap_bug_on_nmos() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFF);
+        cpu.write8(0x0201, 0x10);
+        cpu.write8(0x10FF, 0x34);
+        cpu.write8(0x1000, 0x12);
+        cpu.write8(0x1100, 0x56);
+
+        AddressingMode::Indirect.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x1234);
+    }
+
+    #[test]
+    fn indirect_crosses_page_on_65c02() {
+        let mut cpu = setup();
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.write8(0x0200, 0xFF);
+        cpu.write8(0x0201, 0x10);
+        cpu.write8(0x10FF, 0x34);
+        cpu.write8(0x1000, 0x12);
+        cpu.write8(0x1100, 0x56);
+
+        AddressingMode::Indirect.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x5634);
+    }
+
+    #[test]
+    fn zero_page_indirect_wraps_within_zero_page() {
+        let mut cpu = setup();
+        cpu.write8(0x0200, 0xFF);
+        cpu.write8(0x00FF, 0x34);
+        cpu.write8(0x0000, 0x12);
+
+        AddressingMode::ZeroPageIndirect.execute(&mut cpu);
+
+        assert_eq!(cpu.address_absolute, 0x1234);
+        assert_eq!(cpu.pc.get(), 0x0201);
+    }
+}
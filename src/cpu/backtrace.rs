@@ -0,0 +1,109 @@
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// How a frame of the call stack was entered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CallKind {
+    /// A JSR instruction called a subroutine.
+    Subroutine,
+
+    /// A BRK instruction.
+    Break,
+
+    /// A hardware IRQ.
+    Irq,
+
+    /// A non-maskable interrupt.
+    Nmi,
+}
+
+impl Display for CallKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CallKind::Subroutine => write!(f, "JSR"),
+            CallKind::Break => write!(f, "BRK"),
+            CallKind::Irq => write!(f, "IRQ"),
+            CallKind::Nmi => write!(f, "NMI"),
+        }
+    }
+}
+
+/// A subroutine call or interrupt that hasn't returned yet, as returned by `Cpu::backtrace()`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CallFrame {
+    /// How the frame was entered.
+    pub kind: CallKind,
+
+    /// The address of the JSR or BRK instruction, or of the instruction an
+    /// interrupt was taken before.
+    pub from: u16,
+
+    /// The address of the subroutine or interrupt handler.
+    pub to: u16,
+
+    /// Where execution carries on when the frame returns.
+    pub return_address: u16,
+
+    /// The stack pointer once the return address was pushed. The frame has
+    /// returned once the stack pointer rises above it.
+    pub sp: u8,
+}
+
+impl Display for CallFrame {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} {:04X} from {:04X}", self.kind, self.to, self.from)
+    }
+}
+
+/// A shadow of the 6502's stack that keeps track of the calls in progress.
+///
+/// The real stack only holds return addresses and whatever else programs
+/// push, so the calls are recorded as they are made instead. Programs play
+/// tricks with the stack, such as pulling a return address to return two
+/// levels at once or pushing one to jump with RTS, so returns aren't paired
+/// up with calls. Instead a frame is gone once the stack pointer has risen
+/// above where its return address was pushed, which is true whichever way
+/// the program got rid of it.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CallStack {
+    /// The frames, outermost first. Outer frames were pushed earlier, so
+    /// their stack pointers are higher.
+    frames: Vec<CallFrame>,
+}
+
+impl CallStack {
+    /// Records a call that has just pushed its return address.
+    ///
+    /// Frames that had already returned by the time of the call are dropped
+    /// first, since the call may have pushed over their return addresses.
+    pub(crate) fn enter(&mut self, frame: CallFrame) {
+        // JSR pushes the return address, interrupts the status as well. The
+        // stack pointer before the call isn't wrapped, so a call that pushes
+        // past $0100 drops every older frame rather than keeping them all.
+        let pushed = if frame.kind == CallKind::Subroutine { 2 } else { 3 };
+        let live = self.live(frame.sp as u16 + pushed);
+        self.frames.truncate(live);
+        self.frames.push(frame);
+    }
+
+    /// Drops the frames that have returned, now that the stack pointer is `sp`.
+    pub(crate) fn unwind(&mut self, sp: u8) {
+        let live = self.live(sp as u16);
+        self.frames.truncate(live);
+    }
+
+    /// Returns the frames that haven't returned with the stack pointer at `sp`, outermost first.
+    pub(crate) fn frames(&self, sp: u8) -> &[CallFrame] {
+        &self.frames[..self.live(sp as u16)]
+    }
+
+    /// Forgets every frame, as when the CPU is reset.
+    pub(crate) fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Counts the frames whose return addresses are still on a stack whose pointer is at `sp`.
+    fn live(&self, sp: u16) -> usize {
+        self.frames.iter().take_while(|frame| frame.sp as u16 >= sp).count()
+    }
+}
@@ -0,0 +1,34 @@
+use crate::cpu::Condition;
+
+/// A breakpoint, which stops execution before the instruction at its address.
+///
+/// A breakpoint with a condition only counts as hit when the condition holds,
+/// and one with a `skip` count lets that many hits through first, so a loop
+/// can be stopped on a particular pass without stepping through the others.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::cpu::{Breakpoint, Condition};
+/// use butterflyrs::disasm::SymbolTable;
+///
+/// // Stops the 5th time the instruction is reached with X above 3
+/// let breakpoint = Breakpoint {
+///     condition: Some(Condition::parse("X > 3", &SymbolTable::new()).unwrap()),
+///     skip: 4,
+///     ..Breakpoint::default()
+/// };
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Breakpoint {
+    /// The condition that must hold for the breakpoint to be hit, or `None`
+    /// to hit it every time its address is reached.
+    pub condition: Option<Condition>,
+
+    /// The number of hits to let through before stopping. Once they have
+    /// gone by, every hit stops execution.
+    pub skip: u32,
+
+    /// The number of times the breakpoint has been hit.
+    pub hits: u32,
+}
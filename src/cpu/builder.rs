@@ -0,0 +1,194 @@
+use crate::bus::{MainBus, SharedBus};
+use crate::cpu::{Cpu, CpuModel, DebugLevel};
+
+/// Configures and creates a `Cpu`.
+///
+/// Every setting starts out with the same default as `Cpu::new()`, so only the
+/// ones that differ need to be given.
+///
+/// # Example
+///
+/// ```ignore
+/// let cpu = CpuBuilder::new()
+///     .model(CpuModel::Wdc65C02)
+///     .illegal_opcodes(true)
+///     .debug(DebugLevel::Instruction)
+///     .bus(SharedBus::new(MainBus::new()))
+///     .build();
+/// ```
+#[derive(Default)]
+pub struct CpuBuilder {
+    /// The bus to connect, or `None` for an empty one.
+    bus: Option<SharedBus>,
+
+    /// The CPU variant to emulate.
+    model: CpuModel,
+
+    /// Whether illegal opcodes are enabled.
+    illegal_opcodes: bool,
+
+    /// Whether read-modify-write instructions perform the NMOS dummy write.
+    rmw_dummy_write: bool,
+
+    /// How much the CPU reports while executing.
+    debug: DebugLevel,
+
+    /// The number of instructions kept in the history, or 0 for none.
+    history: usize,
+}
+
+impl CpuBuilder {
+    /// Creates a builder with the default configuration.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `CpuBuilder` struct.
+    pub fn new() -> CpuBuilder {
+        CpuBuilder::default()
+    }
+
+    /// Sets the bus the CPU is connected to.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - A shared handle to a `MainBus` object.
+    ///
+    /// # Returns
+    ///
+    /// The builder, for chaining.
+    pub fn bus(mut self, bus: SharedBus) -> CpuBuilder {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// Sets the CPU variant to emulate.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - The CPU model.
+    ///
+    /// # Returns
+    ///
+    /// The builder, for chaining.
+    pub fn model(mut self, model: CpuModel) -> CpuBuilder {
+        self.model = model;
+        self
+    }
+
+    /// Sets whether illegal opcodes are executed.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to execute illegal opcodes, `false` to treat them as NOPs.
+    ///
+    /// # Returns
+    ///
+    /// The builder, for chaining.
+    pub fn illegal_opcodes(mut self, enabled: bool) -> CpuBuilder {
+        self.illegal_opcodes = enabled;
+        self
+    }
+
+    /// Sets whether read-modify-write instructions perform the NMOS dummy write.
+    ///
+    /// CMOS models read the address again instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to write the unmodified value before the result.
+    ///
+    /// # Returns
+    ///
+    /// The builder, for chaining.
+    pub fn rmw_dummy_write(mut self, enabled: bool) -> CpuBuilder {
+        self.rmw_dummy_write = enabled;
+        self
+    }
+
+    /// Sets how much the CPU reports while executing.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The debug level.
+    ///
+    /// # Returns
+    ///
+    /// The builder, for chaining.
+    pub fn debug(mut self, level: DebugLevel) -> CpuBuilder {
+        self.debug = level;
+        self
+    }
+
+    /// Sets how many recently executed instructions the CPU keeps in its history.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of instructions kept, or 0 to keep no history.
+    ///
+    /// # Returns
+    ///
+    /// The builder, for chaining.
+    pub fn history(mut self, capacity: usize) -> CpuBuilder {
+        self.history = capacity;
+        self
+    }
+
+    /// Creates the configured CPU.
+    ///
+    /// The CPU is not reset; call `Cpu::reset()` once the bus holds the reset vector.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Cpu` struct.
+    pub fn build(self) -> Cpu {
+        let bus = self.bus.unwrap_or_else(|| SharedBus::new(MainBus::new()));
+
+        let mut cpu = Cpu::new(bus);
+        cpu.model = self.model;
+        cpu.enable_illegal_opcodes = self.illegal_opcodes;
+        cpu.rmw_dummy_write = self.rmw_dummy_write;
+        cpu.debug = self.debug;
+        if self.history > 0 {
+            cpu.enable_history(self.history);
+        }
+        cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use crate::bus::{MainBus, SharedBus};
+    use crate::bus::ram::Ram;
+    use crate::cpu::{CpuBuilder, CpuModel, DebugLevel};
+
+    #[test]
+    fn builder_applies_configuration() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x00FF)));
+        bus.write(0x0010, 0x42);
+
+        let cpu = CpuBuilder::new()
+            .model(CpuModel::Wdc65C02)
+            .illegal_opcodes(true)
+            .debug(DebugLevel::Instruction)
+            .bus(SharedBus::new(bus))
+            .build();
+
+        assert_eq!(cpu.model, CpuModel::Wdc65C02);
+        assert!(cpu.enable_illegal_opcodes);
+        assert!(!cpu.rmw_dummy_write);
+        assert_eq!(cpu.debug, DebugLevel::Instruction);
+        assert_eq!(cpu.read8(0x0010), 0x42);
+    }
+
+    #[test]
+    fn builder_defaults_match_new() {
+        let cpu = CpuBuilder::new().build();
+
+        assert_eq!(cpu.model, CpuModel::Nmos6502);
+        assert!(!cpu.enable_illegal_opcodes);
+        assert_eq!(cpu.debug, DebugLevel::Off);
+        assert!(cpu.history().is_none());
+    }
+}
@@ -0,0 +1,410 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use crate::cpu::Cpu;
+use crate::disasm::SymbolTable;
+
+/// Why the text of a condition couldn't be parsed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConditionError {
+    /// The text ended in the middle of an expression, or was empty.
+    UnexpectedEnd,
+
+    /// A character or token was found where it doesn't belong.
+    Unexpected(String),
+
+    /// A name is neither a register, a flag nor a label.
+    UnknownName(String),
+
+    /// A number has no digits, or doesn't fit in 32 bits.
+    InvalidNumber(String),
+}
+
+impl Display for ConditionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConditionError::UnexpectedEnd => write!(f, "Unexpected end of expression"),
+            ConditionError::Unexpected(text) => write!(f, "Unexpected {}", text),
+            ConditionError::UnknownName(name) => write!(f, "Not a register, flag or label: {}", name),
+            ConditionError::InvalidNumber(text) => write!(f, "Not a number: {}", text),
+        }
+    }
+}
+
+impl core::error::Error for ConditionError {}
+
+/// An expression over the CPU's registers and memory, such as `A == $10 && X > 3`.
+///
+/// Conditions make breakpoints conditional, with `Cpu::set_breakpoint()`,
+/// and a debugger can evaluate them to watch values as a program runs.
+///
+/// An expression is made of:
+///
+/// * Numbers, in decimal or in hex with a `$` or `0x` prefix.
+/// * The registers `A`, `X`, `Y`, `S` (or `SP`), `P` and `PC`, and the flags
+///   `N`, `V`, `B`, `D`, `I`, `Z` and `C`, which are 1 when set. Case doesn't
+///   matter.
+/// * Labels from the CPU's symbol table, which stand for their address.
+/// * `[address]`, the byte in memory at an address. It is peeked, so
+///   evaluating a condition doesn't disturb devices.
+/// * The operators `||`, `&&`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `|`, `^`,
+///   `&`, `+`, `-` and unary `!`, from lowest to highest precedence, and
+///   parentheses.
+///
+/// Values are 32-bit and unsigned, arithmetic wraps, and comparisons give 1
+/// or 0. A condition is true when its value isn't 0.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::cpu::{Condition, Cpu};
+/// use butterflyrs::bus::{MainBus, SharedBus};
+/// use butterflyrs::disasm::SymbolTable;
+///
+/// let mut cpu = Cpu::new(SharedBus::new(MainBus::new()));
+/// cpu.a.set(0x10);
+/// cpu.x.set(4);
+///
+/// let condition = Condition::parse("A == $10 && X > 3", &SymbolTable::new()).unwrap();
+/// assert!(condition.is_true(&cpu));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Condition {
+    /// The text the condition was parsed from, shown by `Display`.
+    source: String,
+
+    /// The parsed expression.
+    expression: Expression,
+}
+
+impl Condition {
+    /// Parses a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The expression.
+    /// * `symbols` - The labels that can be used in place of addresses. They
+    ///   are looked up now, so later changes to the table don't affect the condition.
+    ///
+    /// # Returns
+    ///
+    /// The condition, or what is wrong with the text.
+    pub fn parse(text: &str, symbols: &SymbolTable) -> Result<Condition, ConditionError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens: &tokens, position: 0, symbols };
+        let expression = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(Condition { source: String::from(text.trim()), expression }),
+            Some(token) => Err(ConditionError::Unexpected(token.to_string())),
+        }
+    }
+
+    /// Evaluates the condition against the CPU's current state.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The CPU whose registers are used, and whose bus memory is read from.
+    ///
+    /// # Returns
+    ///
+    /// The value of the expression.
+    pub fn evaluate(&self, cpu: &Cpu) -> u32 {
+        self.expression.evaluate(cpu)
+    }
+
+    /// Returns whether the condition holds, that is, whether its value isn't 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The CPU to evaluate the condition against.
+    pub fn is_true(&self, cpu: &Cpu) -> bool {
+        self.evaluate(cpu) != 0
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A value an expression can read from the CPU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Operand {
+    A,
+    X,
+    Y,
+    S,
+    P,
+    Pc,
+    /// A status flag, given by its bit in P.
+    Flag(u8),
+}
+
+/// A node of a parsed expression.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Expression {
+    Number(u32),
+    Operand(Operand),
+    Memory(Box<Expression>),
+    Not(Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Works out the value of the expression.
+    fn evaluate(&self, cpu: &Cpu) -> u32 {
+        match self {
+            Expression::Number(value) => *value,
+            Expression::Operand(operand) => match operand {
+                Operand::A => cpu.a.get() as u32,
+                Operand::X => cpu.x.get() as u32,
+                Operand::Y => cpu.y.get() as u32,
+                Operand::S => cpu.sp.get() as u32,
+                Operand::P => cpu.p.get() as u32,
+                Operand::Pc => cpu.pc.get() as u32,
+                Operand::Flag(bit) => (cpu.p.get() & bit != 0) as u32,
+            },
+            Expression::Memory(address) => cpu.peek8(address.evaluate(cpu) as u16) as u32,
+            Expression::Not(operand) => (operand.evaluate(cpu) == 0) as u32,
+            // The logical operators don't evaluate their right side when the left decides the answer
+            Expression::Binary("&&", left, right) => (left.evaluate(cpu) != 0 && right.evaluate(cpu) != 0) as u32,
+            Expression::Binary("||", left, right) => (left.evaluate(cpu) != 0 || right.evaluate(cpu) != 0) as u32,
+            Expression::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate(cpu), right.evaluate(cpu));
+                match *operator {
+                    "==" => (left == right) as u32,
+                    "!=" => (left != right) as u32,
+                    "<" => (left < right) as u32,
+                    "<=" => (left <= right) as u32,
+                    ">" => (left > right) as u32,
+                    ">=" => (left >= right) as u32,
+                    "|" => left | right,
+                    "^" => left ^ right,
+                    "&" => left & right,
+                    "+" => left.wrapping_add(right),
+                    _ => left.wrapping_sub(right),
+                }
+            }
+        }
+    }
+}
+
+/// A token of an expression's text.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Number(u32),
+    Name(String),
+    Symbol(&'static str),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+/// The operators and brackets, with the two-character ones first so they
+/// aren't taken for two one-character ones.
+const SYMBOLS: [&str; 18] =
+    ["||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "^", "&", "+", "-", "!", "(", ")", "[", "]"];
+
+/// Splits an expression's text into tokens.
+fn tokenize(text: &str) -> Result<Vec<Token>, ConditionError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(next) = rest.chars().next() {
+        let length = if next == '$' || next.is_ascii_digit() {
+            let end = rest[1..].find(|c: char| !c.is_ascii_alphanumeric()).map_or(rest.len(), |end| end + 1);
+            let text = &rest[..end];
+            let value = match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+                Some(digits) => u32::from_str_radix(digits, 16),
+                None => text.parse(),
+            };
+            tokens.push(Token::Number(value.map_err(|_| ConditionError::InvalidNumber(String::from(text)))?));
+            end
+        } else if next.is_alphabetic() || next == '_' {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+            tokens.push(Token::Name(String::from(&rest[..end])));
+            end
+        } else {
+            let symbol = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol));
+            let symbol = symbol.ok_or_else(|| ConditionError::Unexpected(next.to_string()))?;
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        };
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the tokens of an expression, with a method
+/// for each level of precedence.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    symbols: &'a SymbolTable,
+}
+
+impl Parser<'_> {
+    /// Takes the next token if it is one of the operators given.
+    fn operator(&mut self, operators: &[&'static str]) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(symbol)) if operators.contains(symbol) => {
+                self.position += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses a run of operands joined by the operators of one level, left to right.
+    fn binary(
+        &mut self,
+        operators: &[&'static str],
+        operand: fn(&mut Self) -> Result<Expression, ConditionError>,
+    ) -> Result<Expression, ConditionError> {
+        let mut left = operand(self)?;
+        while let Some(operator) = self.operator(operators) {
+            left = Expression::Binary(operator, Box::new(left), Box::new(operand(self)?));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["&&"], Self::equality)
+    }
+
+    fn equality(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["==", "!="], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["<", "<=", ">", ">="], Self::bit_or)
+    }
+
+    fn bit_or(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["|"], Self::bit_xor)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["^"], Self::bit_and)
+    }
+
+    fn bit_and(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["&"], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expression, ConditionError> {
+        self.binary(&["+", "-"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ConditionError> {
+        match self.operator(&["!"]) {
+            Some(_) => Ok(Expression::Not(Box::new(self.unary()?))),
+            None => self.primary(),
+        }
+    }
+
+    /// Parses a number, a name, or a bracketed expression.
+    fn primary(&mut self) -> Result<Expression, ConditionError> {
+        let token = self.tokens.get(self.position).ok_or(ConditionError::UnexpectedEnd)?;
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Ok(Expression::Number(*value)),
+            Token::Name(name) => self.name(name),
+            Token::Symbol(open @ ("(" | "[")) => {
+                let inner = self.or()?;
+                let close = if *open == "(" { ")" } else { "]" };
+                if self.operator(&[close]).is_none() {
+                    return Err(match self.tokens.get(self.position) {
+                        Some(token) => ConditionError::Unexpected(token.to_string()),
+                        None => ConditionError::UnexpectedEnd,
+                    });
+                }
+                Ok(if *open == "(" { inner } else { Expression::Memory(Box::new(inner)) })
+            }
+            Token::Symbol(symbol) => Err(ConditionError::Unexpected(String::from(*symbol))),
+        }
+    }
+
+    /// Resolves a name to a register, a flag or the address of a label.
+    fn name(&self, name: &str) -> Result<Expression, ConditionError> {
+        let operand = match name.to_ascii_uppercase().as_str() {
+            "A" => Operand::A,
+            "X" => Operand::X,
+            "Y" => Operand::Y,
+            "S" | "SP" => Operand::S,
+            "P" => Operand::P,
+            "PC" => Operand::Pc,
+            "N" => Operand::Flag(0x80),
+            "V" => Operand::Flag(0x40),
+            "B" => Operand::Flag(0x10),
+            "D" => Operand::Flag(0x08),
+            "I" => Operand::Flag(0x04),
+            "Z" => Operand::Flag(0x02),
+            "C" => Operand::Flag(0x01),
+            _ => {
+                let address = self.symbols.address_of(name);
+                let address = address.ok_or_else(|| ConditionError::UnknownName(String::from(name)))?;
+                return Ok(Expression::Number(address as u32));
+            }
+        };
+        Ok(Expression::Operand(operand))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::{String, ToString};
+
+    use crate::bus::ram::Ram;
+    use crate::bus::{MainBus, SharedBus};
+    use crate::cpu::{Condition, ConditionError, Cpu};
+    use crate::disasm::SymbolTable;
+
+    #[test]
+    fn evaluates_registers_memory_and_labels() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        bus.write(0x0010, 0x05);
+        let mut cpu = Cpu::new(SharedBus::new(bus));
+        cpu.a.set(0x10);
+        cpu.x.set(4);
+        cpu.p.set(0x01);
+
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0x0010, "counter");
+        let value = |text: &str| Condition::parse(text, &symbols).unwrap().evaluate(&cpu);
+
+        assert_eq!(value("a == 0x10 && X > 3"), 1);
+        assert_eq!(value("[counter] + 1"), 6);
+        assert_eq!(value("[$0000 + counter] == 5 || [0] == 1"), 1);
+        assert_eq!(value("c && !z"), 1);
+        assert_eq!(value("(A | X) & $F ^ 1"), 5);
+        assert_eq!(value("X - 5"), 0xFFFF_FFFF);
+        assert_eq!(Condition::parse(" A == 16 ", &symbols).unwrap().to_string(), "A == 16");
+
+        let error = |text: &str| Condition::parse(text, &symbols).unwrap_err();
+        assert_eq!(error("A =="), ConditionError::UnexpectedEnd);
+        assert_eq!(error("[A"), ConditionError::UnexpectedEnd);
+        assert_eq!(error("A = 1"), ConditionError::Unexpected(String::from("=")));
+        assert_eq!(error("A 1"), ConditionError::Unexpected(String::from("1")));
+        assert_eq!(error("missing"), ConditionError::UnknownName(String::from("missing")));
+        assert_eq!(error("$G"), ConditionError::InvalidNumber(String::from("$G")));
+    }
+}
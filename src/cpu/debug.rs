@@ -0,0 +1,32 @@
+use alloc::boxed::Box;
+use core::fmt::Display;
+
+/// A function called with every line of debug output, along with the level
+/// that produced it: `Instruction` for an instruction and `Cycle` for the CPU
+/// state around it.
+pub type DebugSink = Box<dyn FnMut(DebugLevel, &str) + Send>;
+
+/// How much the CPU reports about what it is executing.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum DebugLevel {
+    /// No debug output.
+    #[default]
+    Off,
+
+    /// Report each instruction as it is executed, as a `tracing` debug event.
+    Instruction,
+
+    /// Report each instruction along with the CPU state before and after it,
+    /// which are `tracing` trace events.
+    Cycle,
+}
+
+impl Display for DebugLevel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DebugLevel::Off => write!(f, "Off"),
+            DebugLevel::Instruction => write!(f, "Instruction"),
+            DebugLevel::Cycle => write!(f, "Cycle"),
+        }
+    }
+}
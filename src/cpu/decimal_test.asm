@@ -0,0 +1,295 @@
+; Checks decimal mode ADC and SBC against the NMOS 6502, or the 65C02.
+;
+; This follows the method of Bruce Clark's decimal mode test: every pair of
+; operands, valid BCD or not, is added and subtracted with each carry in,
+; and the accumulator and the N, V, Z and C flags are compared with a
+; prediction worked out in binary, using the steps the chip is known to
+; take (see "Decimal Mode" by Bruce Clark on 6502.org, appendix A).
+;
+; The NMOS chip is predicted unless CMOS is set before the test starts.
+;
+; It runs from start and ends at done with ERROR 0 if every result matched.
+; Otherwise it ends at done with ERROR 1 for ADC or 2 for SBC, leaving the
+; operands, the results and the predictions in page zero.
+
+ERROR = $00     ; 0 once the test has passed
+N1 = $01        ; the accumulator before ADC or SBC
+N2 = $02        ; the operand
+CIN = $03       ; the carry in, 0 or 1
+DA = $04        ; the accumulator after the decimal ADC or SBC
+DP = $05        ; its flags, masked to N, V, Z and C
+AR = $06        ; the predicted accumulator
+PF = $07        ; the predicted flags
+AL = $08        ; the adjusted low nibble of the result
+UL = $09        ; a 16-bit intermediate result, low byte
+UH = $0A        ; and high byte
+T1 = $0B        ; scratch
+T2 = $0C        ; scratch
+CMOS = $0D      ; non-zero to predict the 65C02
+
+NVZC = $C3      ; the flags that are compared
+
+        .org $0200
+start:  cld
+        lda #$FF
+        sta ERROR
+        lda #0
+        sta N1
+loop1:  lda #0
+        sta N2
+loop2:  lda #0
+        sta CIN
+loop3:  jsr checkadc
+        bne done
+        jsr checksbc
+        bne done
+        inc CIN
+        lda CIN
+        cmp #2
+        bne loop3
+        inc N2
+        bne loop2
+        inc N1
+        bne loop1
+        lda #0
+        sta ERROR
+done:   jmp done
+
+; Adds N2 to N1 with carry in CIN, in decimal and in the prediction.
+; Returns with Z clear and ERROR 1 if they differ.
+checkadc:
+        lda CIN
+        lsr
+        sed
+        lda N1
+        adc N2
+        php
+        cld
+        sta DA
+        pla
+        and #NVZC
+        sta DP
+
+        ; The low nibbles, adjusted past 9: AL is 0-9 or $10-$1F
+        lda CIN
+        lsr
+        lda N1
+        and #$0F
+        sta T1
+        lda N2
+        and #$0F
+        adc T1
+        cmp #$0A
+        bcc adclow
+        adc #$05                ; the carry is set, so this adds 6
+        and #$0F
+        ora #$10
+adclow: sta AL
+
+        ; U = (N1 & $F0) + (N2 & $F0) + AL, in 16 bits
+        lda N1
+        and #$F0
+        sta T1
+        lda N2
+        and #$F0
+        clc
+        adc T1
+        sta UL
+        lda #0
+        rol
+        sta UH
+        lda UL
+        clc
+        adc AL
+        sta UL
+        lda UH
+        adc #0
+        sta UH
+
+        ; N comes from U before the high nibble is adjusted
+        lda UL
+        and #$80
+        sta PF
+
+        ; V is set if U, taking the high nibbles of N1 and N2 as signed,
+        ; doesn't fit in a signed byte: its high byte then isn't the sign
+        ; extension of its low byte
+        lda UL
+        asl
+        lda UH
+        adc #0
+        ldx N1
+        bpl adcpos1
+        sec
+        sbc #1
+adcpos1:
+        ldx N2
+        bpl adcpos2
+        sec
+        sbc #1
+adcpos2:
+        cmp #0
+        beq adcnov
+        lda PF
+        ora #$40
+        sta PF
+adcnov:
+
+        ; Z comes from the binary sum
+        lda CIN
+        lsr
+        lda N1
+        adc N2
+        bne adcnz
+        lda PF
+        ora #$02
+        sta PF
+adcnz:
+
+        ; The high nibble is adjusted past 9, which gives the carry
+        lda UH
+        bne adchigh
+        lda UL
+        cmp #$A0
+        bcc adcdone
+adchigh:
+        lda UL
+        clc
+        adc #$60
+        sta UL
+        lda UH
+        adc #0
+        sta UH
+adcdone:
+        lda UL
+        sta AR
+        lda UH
+        beq adcnc
+        lda PF
+        ora #$01
+        sta PF
+adcnc:
+        lda CMOS
+        beq adcnmos
+        jsr cmosnz
+adcnmos:
+
+        lda #1
+        jmp compare
+
+; Subtracts N2 from N1 with carry in CIN, in decimal and in the prediction.
+; Returns with Z clear and ERROR 2 if they differ.
+checksbc:
+        lda CIN
+        lsr
+        sed
+        lda N1
+        sbc N2
+        php
+        cld
+        sta DA
+        pla
+        and #NVZC
+        sta DP
+
+        ; Every flag comes from the binary difference
+        lda CIN
+        lsr
+        lda N1
+        sbc N2
+        php
+        pla
+        and #NVZC
+        sta PF
+
+        ; The low nibbles, adjusted below 0 with a borrow from the high nibbles
+        lda CIN
+        lsr
+        lda N1
+        and #$0F
+        sta T1
+        lda N2
+        and #$0F
+        sta T2
+        lda T1
+        sbc T2
+        ldx #0
+        bcs sbclow
+        sec
+        sbc #6
+        and #$0F
+        ldx #$10
+sbclow: sta AL
+        stx T2
+
+        ; (N1 & $F0) + AL - (N2 & $F0) - the borrow, adjusted below 0
+        lda N1
+        and #$F0
+        ora AL
+        sta T1
+        lda N2
+        and #$F0
+        sta UL
+        ldx #0
+        lda T1
+        sec
+        sbc UL
+        bcs sbcpos1
+        inx
+sbcpos1:
+        sec
+        sbc T2
+        bcs sbcpos2
+        inx
+sbcpos2:
+        cpx #0
+        beq sbcdone
+        sec
+        sbc #$60
+sbcdone:
+        sta AR
+
+        ; The 65C02 adjusts the binary difference instead, by $60 if it
+        ; borrowed and by 6 more if the low nibbles did
+        lda CMOS
+        beq sbcnmos
+        lda CIN
+        lsr
+        lda N1
+        sbc N2
+        bcs sbccnb
+        sbc #$5F                ; the carry is clear, so this subtracts $60
+sbccnb: ldx T2
+        beq sbccdone
+        sec
+        sbc #6
+sbccdone:
+        sta AR
+        jsr cmosnz
+sbcnmos:
+
+        lda #2
+
+; Compares the decimal result with the prediction, with the error code in A.
+compare:
+        ldx DA
+        cpx AR
+        bne differ
+        ldx DP
+        cpx PF
+        bne differ
+        rts
+differ: sta ERROR
+        rts
+
+; Predicts N and Z from the accumulator result, as the 65C02 sets them.
+cmosnz: lda PF
+        and #$7D
+        sta PF
+        lda AR
+        php
+        pla
+        and #$82
+        ora PF
+        sta PF
+        rts
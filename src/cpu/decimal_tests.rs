@@ -0,0 +1,80 @@
+//! Runs the decimal mode test program in `decimal_test.asm`.
+//!
+//! The program adds and subtracts every pair of operands in decimal mode,
+//! including ones that aren't valid BCD, with the carry clear and set, and
+//! checks the accumulator and flags against the NMOS 6502 or, with `CMOS`
+//! set, the 65C02. It stops at its `done` label with the outcome in `ERROR`,
+//! which this harness reads back.
+//!
+//! That takes about 57 million cycles, or several seconds in a debug build.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use crate::asm::assemble;
+use crate::bus::ram::Ram;
+use crate::cpu::CpuModel;
+use crate::emulator::{Emulator, StopReason};
+
+/// The test runs about 57 million cycles, so it is stopped well after that.
+const MAX_CYCLES: u64 = 200_000_000;
+
+/// The cycles run between checks on the cycle limit.
+const SLICE: u64 = 1_000_000;
+
+/// The page zero variables the program leaves behind, in order from `ERROR`.
+const VARIABLES: [&str; 8] = ["ERROR", "N1", "N2", "CIN", "DA", "DP", "AR", "PF"];
+
+/// The page zero variable that has the program predict the 65C02.
+const CMOS: u16 = 0x0D;
+
+/// Runs the test program on a model, and checks that it passed.
+///
+/// # Arguments
+///
+/// * `model` - The model to run on. The program predicts the 65C02 on CMOS parts.
+fn run_decimal_test(model: CpuModel) {
+    let assembly = assemble(include_str!("decimal_test.asm")).unwrap();
+    let (origin, image) = assembly.image(0x00);
+
+    let mut emulator = Emulator::new();
+    emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    emulator.load(origin, &image);
+    emulator.cpu.model = model;
+    emulator.cpu.symbols = assembly.symbols;
+    emulator.load(CMOS, &[model.is_cmos() as u8]);
+    emulator.cpu.pc.set(emulator.cpu.symbols.address_of("start").unwrap());
+    emulator.cpu.add_breakpoint_at_label("done");
+
+    let mut total = 0;
+    let reason = emulator.run(SLICE, |_, elapsed| {
+        total += elapsed;
+        match total < MAX_CYCLES {
+            true => ControlFlow::Continue(()),
+            false => ControlFlow::Break(()),
+        }
+    });
+    let done = emulator.cpu.symbols.address_of("done").unwrap();
+    assert_eq!(reason, StopReason::Breakpoint(done), "the test didn't finish");
+
+    // On failure, the operands, results and predictions show what went wrong
+    let variables: Vec<String> = VARIABLES
+        .iter()
+        .enumerate()
+        .map(|(address, name)| format!("{}=${:02X}", name, emulator.peek(address as u16)))
+        .collect();
+    assert_eq!(emulator.peek(0x00), 0, "{}", variables.join(" "));
+}
+
+#[test]
+fn decimal_mode_matches_the_nmos_6502() {
+    run_decimal_test(CpuModel::Nmos6502);
+}
+
+#[test]
+fn decimal_mode_matches_the_65c02() {
+    run_decimal_test(CpuModel::Wdc65C02);
+}
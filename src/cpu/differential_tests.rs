@@ -0,0 +1,250 @@
+//! Runs random programs on the CPU core and on the `mos6502` crate side by side.
+//!
+//! Both CPUs start from the same random registers and the same 64K of random
+//! memory, with a random program at `ORIGIN`. After every instruction their
+//! registers, the bytes they wrote and the cycles they took must agree, so a
+//! handler that goes wrong is caught on the first instruction that shows it.
+//! The programs are free to jump, branch and call anywhere in the random
+//! memory. A program ends early at an opcode the table marks as illegal, since
+//! the unstable undocumented instructions don't behave the same on every chip.
+//!
+//! The programs come from the crate's own `Rng` with a fixed seed, so a
+//! failure names the program that shows it and can be run again.
+//!
+//! The reference has two known differences from a real NMOS 6502, which
+//! `ignored()` leaves out of the comparison: it doesn't set N, V and Z after
+//! a decimal ADC or SBC the way the NMOS chip does, and it adds a page
+//! crossing cycle to the read-modify-write instructions, which always take
+//! seven cycles with absolute,X addressing. Flags left out are copied over to
+//! the reference, so they don't show up as differences later on.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use mos6502::cpu::CPU;
+use mos6502::instruction::Nmos6502;
+use mos6502::memory::Bus;
+use mos6502::registers::{StackPointer, Status};
+
+use crate::bus::ram::Ram;
+use crate::bus::rng::Rng;
+use crate::bus::{AccessKind, BusDevice, MainBus, SharedBus};
+use crate::cpu::instructions::lookup;
+use crate::cpu::{Cpu, CpuModel};
+
+/// How many programs are run.
+const PROGRAMS: u64 = 300;
+
+/// The most instructions each program runs.
+const STEPS: usize = 32;
+
+/// Where each program is placed, and where both CPUs start.
+const ORIGIN: u16 = 0x0200;
+
+/// The zero flag.
+const Z: u8 = 0x02;
+
+/// The decimal flag.
+const D: u8 = 0x08;
+
+/// The break flag, which only exists on the stack.
+const B: u8 = 0x10;
+
+/// The unused flag, which always reads as set.
+const U: u8 = 0x20;
+
+/// The overflow flag.
+const V: u8 = 0x40;
+
+/// The negative flag.
+const N: u8 = 0x80;
+
+/// The `mos6502` crate's memory, which remembers what each instruction wrote.
+struct Reference {
+    /// The 64K of memory.
+    memory: Vec<u8>,
+
+    /// The bytes written since the last instruction, by address.
+    written: BTreeMap<u16, u8>,
+}
+
+impl Bus for Reference {
+    fn get_byte(&mut self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn set_byte(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+        self.written.insert(address, value);
+    }
+}
+
+/// The registers of either CPU, with the status register as it reads on the stack without B.
+#[derive(Debug, PartialEq, Eq)]
+struct Registers {
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    s: u8,
+    pc: u16,
+}
+
+impl Registers {
+    /// Reads the registers of the CPU core.
+    fn of(cpu: &Cpu) -> Registers {
+        Registers {
+            a: cpu.a.get(),
+            x: cpu.x.get(),
+            y: cpu.y.get(),
+            p: (cpu.p.get() | U) & !B,
+            s: cpu.sp.get(),
+            pc: cpu.pc.get(),
+        }
+    }
+
+    /// Reads the registers of the reference CPU.
+    fn of_reference(cpu: &CPU<Reference, Nmos6502>) -> Registers {
+        let registers = &cpu.registers;
+        Registers {
+            a: registers.accumulator,
+            x: registers.index_x,
+            y: registers.index_y,
+            p: (registers.status.bits() | U) & !B,
+            s: registers.stack_pointer.0,
+            pc: registers.program_counter,
+        }
+    }
+}
+
+/// Returns what the reference gets wrong for an instruction.
+///
+/// # Arguments
+///
+/// * `opcode` - The instruction's opcode.
+/// * `p` - The status register before the instruction.
+///
+/// # Returns
+///
+/// The flags to leave out of the comparison, and whether to leave out the cycle count.
+fn ignored(opcode: u8, p: u8) -> (u8, bool) {
+    let instruction = lookup(CpuModel::Nmos6502, opcode);
+    let flags = match instruction.name {
+        "ADC" | "SBC" if p & D != 0 => N | V | Z,
+        _ => 0,
+    };
+    let cycles = matches!(opcode, 0x1E | 0x3E | 0x5E | 0x7E | 0xDE | 0xFE);
+    (flags, cycles)
+}
+
+/// Runs one random program on both CPUs.
+///
+/// # Arguments
+///
+/// * `seed` - The seed for the program, its memory and its registers.
+///
+/// # Returns
+///
+/// `Ok(())` if the CPUs agreed throughout, or a description of the first difference.
+fn run(seed: u64) -> Result<(), String> {
+    let rng = Rng::new(0, seed);
+    let random = || rng.read(0);
+
+    // Random memory with a random program in it
+    let mut memory: Vec<u8> = (0..0x10000).map(|_| random()).collect();
+    let mut address = ORIGIN as usize;
+    for _ in 0..STEPS {
+        let opcode = loop {
+            let opcode = random();
+            if !lookup(CpuModel::Nmos6502, opcode).illegal {
+                break opcode;
+            }
+        };
+        memory[address] = opcode;
+        address += 1 + lookup(CpuModel::Nmos6502, opcode).mode.operand_length() as usize;
+    }
+
+    let mut bus = MainBus::new();
+    bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    for (address, value) in memory.iter().enumerate() {
+        bus.poke(address as u16, *value);
+    }
+    let mut cpu = Cpu::new(SharedBus::new(bus));
+    let mut reference = CPU::new(Reference { memory, written: BTreeMap::new() }, Nmos6502);
+
+    let (a, x, y, p, s) = (random(), random(), random(), random() | U, random());
+    cpu.a.set(a);
+    cpu.x.set(x);
+    cpu.y.set(y);
+    cpu.p.set(p);
+    cpu.sp.set(s);
+    cpu.pc.set(ORIGIN);
+    reference.registers.accumulator = a;
+    reference.registers.index_x = x;
+    reference.registers.index_y = y;
+    reference.registers.status = Status::from_bits_truncate(p);
+    reference.registers.stack_pointer = StackPointer(s);
+    reference.registers.program_counter = ORIGIN;
+
+    for step in 0..STEPS {
+        let pc = cpu.pc.get();
+        let opcode = cpu.peek8(pc);
+        if lookup(CpuModel::Nmos6502, opcode).illegal {
+            break;
+        }
+        let (flags, any_cycles) = ignored(opcode, cpu.p.get());
+
+        let record = cpu.step();
+        let cycles = reference.cycles;
+        reference.memory.written.clear();
+        reference.single_step();
+
+        let mut differences = Vec::new();
+        // Flags the reference gets wrong are copied over, so they can't differ later on
+        let (mut ours, mut theirs) = (Registers::of(&cpu), Registers::of_reference(&reference));
+        ours.p &= !flags;
+        theirs.p &= !flags;
+        if flags != 0 && ours == theirs {
+            reference.registers.status = Status::from_bits_truncate(cpu.p.get());
+        }
+        if ours != theirs {
+            differences.push(format!("registers {:02X?}, expected {:02X?}", ours, theirs));
+        }
+
+        let written: BTreeMap<u16, u8> = record
+            .accesses
+            .iter()
+            .filter(|access| access.kind == AccessKind::Write)
+            .map(|access| (access.address, cpu.peek8(access.address)))
+            .collect();
+        if written != reference.memory.written {
+            differences.push(format!("wrote {:02X?}, expected {:02X?}", written, reference.memory.written));
+        }
+
+        let expected_cycles = (reference.cycles - cycles) as u32;
+        if record.cycles != expected_cycles && !any_cycles {
+            differences.push(format!("took {} cycles, expected {}", record.cycles, expected_cycles));
+        }
+
+        if !differences.is_empty() {
+            return Err(format!(
+                "program {} step {}: {} at {:04X}: {}",
+                seed,
+                step,
+                record.mnemonic,
+                pc,
+                differences.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn random_programs_match_the_mos6502_crate() {
+    let failures: Vec<String> = (0..PROGRAMS).filter_map(|seed| run(seed).err()).collect();
+    assert!(failures.is_empty(), "{} of {} programs differ:\n{}", failures.len(), PROGRAMS, failures.join("\n"));
+}
@@ -0,0 +1,602 @@
+//! Golden tests for every documented NMOS 6502 opcode.
+//!
+//! Each case runs one instruction from a known state, then checks every
+//! register, every byte the instruction wrote and the number of cycles it
+//! took. The cases cover the edges of each instruction: carry in and out, the
+//! overflow cases, zero page wrapping, the 6502's JMP ($xxFF) bug, and the
+//! extra cycles for crossing a page or taking a branch.
+//!
+//! `every_documented_opcode_has_a_case` fails for any opcode the instruction
+//! table doesn't mark as illegal that has no case, so a handler can't be added
+//! or rewritten without its cases running. That includes the undocumented
+//! NOPs and the second SBC, which programs do use.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bus::ram::Ram;
+use crate::bus::{AccessKind, MainBus, SharedBus};
+use crate::cpu::instructions::instruction_table;
+use crate::cpu::{Cpu, CpuModel};
+
+use self::Field::*;
+
+/// The carry flag.
+const C: u8 = 0x01;
+
+/// The zero flag.
+const Z: u8 = 0x02;
+
+/// The interrupt disable flag.
+const I: u8 = 0x04;
+
+/// The decimal flag.
+const D: u8 = 0x08;
+
+/// The break flag, as pushed by BRK and PHP.
+const B: u8 = 0x10;
+
+/// The unused flag, which always reads as set.
+const U: u8 = 0x20;
+
+/// The overflow flag.
+const V: u8 = 0x40;
+
+/// The negative flag.
+const N: u8 = 0x80;
+
+/// Where each case's instruction is placed, and where the CPU starts.
+const ORIGIN: u16 = 0x0200;
+
+/// A register or memory byte, before or after a case's instruction.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    A(u8),
+    X(u8),
+    Y(u8),
+    /// The status register, without `U`, which is always set.
+    P(u8),
+    S(u8),
+    Pc(u16),
+    /// A byte of memory. After the instruction, one the instruction wrote.
+    Mem(u16, u8),
+}
+
+/// One instruction, run from a known state.
+struct Case {
+    /// What the case checks.
+    name: &'static str,
+
+    /// The instruction's bytes, placed at `ORIGIN`.
+    code: &'static [u8],
+
+    /// The state before the instruction. Registers not given are 0, except
+    /// S, which is $FD, and P, which only has `U` set.
+    given: &'static [Field],
+
+    /// What the instruction changed. Registers not given must be unchanged,
+    /// PC must point past the instruction unless given, and the instruction
+    /// must write exactly the bytes given.
+    expected: &'static [Field],
+
+    /// The number of cycles the instruction takes.
+    cycles: u32,
+}
+
+/// Creates a case. See `Case`.
+const fn case(
+    name: &'static str,
+    code: &'static [u8],
+    given: &'static [Field],
+    expected: &'static [Field],
+    cycles: u32,
+) -> Case {
+    Case { name, code, given, expected, cycles }
+}
+
+/// The registers of the CPU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Registers {
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    s: u8,
+    pc: u16,
+}
+
+impl Registers {
+    /// Applies the register fields to the registers, ignoring memory.
+    fn with(mut self, fields: &[Field]) -> Registers {
+        for field in fields {
+            match *field {
+                A(value) => self.a = value,
+                X(value) => self.x = value,
+                Y(value) => self.y = value,
+                P(value) => self.p = value | U,
+                S(value) => self.s = value,
+                Pc(value) => self.pc = value,
+                Mem(..) => {}
+            }
+        }
+        self
+    }
+}
+
+/// Runs a case.
+///
+/// # Returns
+///
+/// `Ok(())` if the CPU did what the case expects, or a description of the differences.
+fn run(case: &Case) -> Result<(), String> {
+    let mut bus = MainBus::new();
+    bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    let mut cpu = Cpu::new(SharedBus::new(bus));
+
+    for (offset, byte) in case.code.iter().enumerate() {
+        cpu.bus.borrow_mut().poke(ORIGIN + offset as u16, *byte);
+    }
+    for field in case.given {
+        if let Mem(address, value) = *field {
+            cpu.bus.borrow_mut().poke(address, value);
+        }
+    }
+
+    let before = Registers { a: 0, x: 0, y: 0, p: U, s: 0xFD, pc: ORIGIN }.with(case.given);
+    cpu.a.set(before.a);
+    cpu.x.set(before.x);
+    cpu.y.set(before.y);
+    cpu.p.set(before.p);
+    cpu.sp.set(before.s);
+    cpu.pc.set(before.pc);
+
+    let record = cpu.step();
+
+    let mut differences = Vec::new();
+    let next = Registers { pc: ORIGIN + case.code.len() as u16, ..before };
+    let expected = next.with(case.expected);
+    let actual = Registers {
+        a: cpu.a.get(),
+        x: cpu.x.get(),
+        y: cpu.y.get(),
+        p: cpu.p.get(),
+        s: cpu.sp.get(),
+        pc: cpu.pc.get(),
+    };
+    if actual != expected {
+        differences.push(format!("registers {:02X?}, expected {:02X?}", actual, expected));
+    }
+
+    // Every byte written must be expected, and end up with the expected value
+    let expected_writes: Vec<(u16, u8)> = case
+        .expected
+        .iter()
+        .filter_map(|field| match *field {
+            Mem(address, value) => Some((address, value)),
+            _ => None,
+        })
+        .collect();
+    let written: BTreeSet<u16> = record
+        .accesses
+        .iter()
+        .filter(|access| access.kind == AccessKind::Write)
+        .map(|access| access.address)
+        .collect();
+    let expected_addresses: BTreeSet<u16> = expected_writes.iter().map(|(address, _)| *address).collect();
+    if written != expected_addresses {
+        differences.push(format!("wrote {:04X?}, expected {:04X?}", written, expected_addresses));
+    }
+    for (address, value) in expected_writes {
+        let found = cpu.bus.borrow().peek(address);
+        if found != value {
+            differences.push(format!("${:04X} is ${:02X}, expected ${:02X}", address, found, value));
+        }
+    }
+
+    if record.cycles != case.cycles {
+        differences.push(format!("took {} cycles, expected {}", record.cycles, case.cycles));
+    }
+
+    match differences.is_empty() {
+        true => Ok(()),
+        false => Err(format!("{}: {}", case.name, differences.join(", "))),
+    }
+}
+
+/// The cases, grouped by instruction.
+const CASES: &[Case] = &[
+    // ADC
+    case("ADC #: adds the carry in", &[0x69, 0x05], &[A(0x10), P(C)], &[A(0x16), P(0)], 2),
+    case("ADC #: carries out to zero", &[0x69, 0x01], &[A(0xFF)], &[A(0x00), P(Z | C)], 2),
+    case("ADC #: positive overflow", &[0x69, 0x01], &[A(0x7F)], &[A(0x80), P(N | V)], 2),
+    case("ADC #: negative overflow", &[0x69, 0xFF], &[A(0x80)], &[A(0x7F), P(V | C)], 2),
+    case("ADC #: decimal", &[0x69, 0x01], &[A(0x19), P(D)], &[A(0x20), P(D)], 2),
+    case("ADC zp", &[0x65, 0x10], &[A(0x11), Mem(0x0010, 0x22)], &[A(0x33)], 3),
+    case("ADC zp,X: wraps in zero page", &[0x75, 0xF0], &[A(0x01), X(0x20), Mem(0x0010, 0x01)], &[A(0x02)], 4),
+    case("ADC abs", &[0x6D, 0x00, 0x03], &[A(0x03), Mem(0x0300, 0x05)], &[A(0x08)], 4),
+    case("ADC abs,X", &[0x7D, 0x00, 0x03], &[A(0x01), X(0x01), Mem(0x0301, 0x01)], &[A(0x02)], 4),
+    case("ADC abs,X: crosses a page", &[0x7D, 0xFF, 0x03], &[A(0x01), X(0x01), Mem(0x0400, 0x01)], &[A(0x02)], 5),
+    case("ADC abs,Y", &[0x79, 0x00, 0x03], &[A(0x01), Y(0x01), Mem(0x0301, 0x01)], &[A(0x02)], 4),
+    case("ADC abs,Y: crosses a page", &[0x79, 0xFF, 0x03], &[A(0x01), Y(0x01), Mem(0x0400, 0x01)], &[A(0x02)], 5),
+    case(
+        "ADC (zp,X)",
+        &[0x61, 0x0E],
+        &[A(0x01), X(0x02), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x01)],
+        &[A(0x02)],
+        6,
+    ),
+    case(
+        "ADC (zp,X): the pointer wraps in zero page",
+        &[0x61, 0xFF],
+        &[A(0x01), Mem(0x00FF, 0x00), Mem(0x0000, 0x03), Mem(0x0300, 0x01)],
+        &[A(0x02)],
+        6,
+    ),
+    case(
+        "ADC (zp),Y",
+        &[0x71, 0x10],
+        &[A(0x01), Y(0x01), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0301, 0x01)],
+        &[A(0x02)],
+        5,
+    ),
+    case(
+        "ADC (zp),Y: crosses a page",
+        &[0x71, 0x10],
+        &[A(0x01), Y(0x01), Mem(0x0010, 0xFF), Mem(0x0011, 0x03), Mem(0x0400, 0x01)],
+        &[A(0x02)],
+        6,
+    ),
+    // AND
+    case("AND #", &[0x29, 0x0F], &[A(0x3C)], &[A(0x0C)], 2),
+    case("AND #: zero", &[0x29, 0x0F], &[A(0xF0)], &[A(0x00), P(Z)], 2),
+    case("AND zp", &[0x25, 0x10], &[A(0xFF), Mem(0x0010, 0x80)], &[A(0x80), P(N)], 3),
+    case("AND zp,X", &[0x35, 0x10], &[A(0xFF), X(0x01), Mem(0x0011, 0x01)], &[A(0x01)], 4),
+    case("AND abs", &[0x2D, 0x00, 0x03], &[A(0xFF), Mem(0x0300, 0x01)], &[A(0x01)], 4),
+    case("AND abs,X", &[0x3D, 0xFF, 0x03], &[A(0xFF), X(0x01), Mem(0x0400, 0x01)], &[A(0x01)], 5),
+    case("AND abs,Y", &[0x39, 0x00, 0x03], &[A(0xFF), Y(0x01), Mem(0x0301, 0x01)], &[A(0x01)], 4),
+    case(
+        "AND (zp,X)",
+        &[0x21, 0x10],
+        &[A(0xFF), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x01)],
+        &[A(0x01)],
+        6,
+    ),
+    case(
+        "AND (zp),Y",
+        &[0x31, 0x10],
+        &[A(0xFF), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x01)],
+        &[A(0x01)],
+        5,
+    ),
+    // ASL
+    case("ASL A: carries out", &[0x0A], &[A(0x81)], &[A(0x02), P(C)], 2),
+    case("ASL zp", &[0x06, 0x10], &[Mem(0x0010, 0x40)], &[Mem(0x0010, 0x80), P(N)], 5),
+    case("ASL zp,X", &[0x16, 0x10], &[X(0x01), Mem(0x0011, 0x80)], &[Mem(0x0011, 0x00), P(Z | C)], 6),
+    case("ASL abs", &[0x0E, 0x00, 0x03], &[Mem(0x0300, 0x01)], &[Mem(0x0300, 0x02)], 6),
+    case("ASL abs,X: no extra cycle", &[0x1E, 0xFF, 0x03], &[X(0x01), Mem(0x0400, 0x01)], &[Mem(0x0400, 0x02)], 7),
+    // Branches
+    case("BCC: taken", &[0x90, 0x10], &[], &[Pc(0x0212)], 3),
+    case("BCC: not taken", &[0x90, 0x10], &[P(C)], &[], 2),
+    case("BCC: taken across a page", &[0x90, 0xF0], &[], &[Pc(0x01F2)], 4),
+    case("BCS: taken", &[0xB0, 0x10], &[P(C)], &[Pc(0x0212)], 3),
+    case("BCS: not taken", &[0xB0, 0x10], &[], &[], 2),
+    case("BEQ: taken", &[0xF0, 0x10], &[P(Z)], &[Pc(0x0212)], 3),
+    case("BEQ: not taken", &[0xF0, 0x10], &[], &[], 2),
+    case("BMI: taken backwards", &[0x30, 0xFE], &[P(N)], &[Pc(0x0200)], 3),
+    case("BMI: not taken", &[0x30, 0xFE], &[], &[], 2),
+    case("BNE: taken", &[0xD0, 0x10], &[], &[Pc(0x0212)], 3),
+    case("BNE: not taken", &[0xD0, 0x10], &[P(Z)], &[], 2),
+    case("BPL: taken across a page", &[0x10, 0xF0], &[], &[Pc(0x01F2)], 4),
+    case("BPL: not taken", &[0x10, 0xF0], &[P(N)], &[], 2),
+    case("BVC: taken", &[0x50, 0x10], &[], &[Pc(0x0212)], 3),
+    case("BVC: not taken", &[0x50, 0x10], &[P(V)], &[], 2),
+    case("BVS: taken", &[0x70, 0x10], &[P(V)], &[Pc(0x0212)], 3),
+    case("BVS: not taken", &[0x70, 0x10], &[], &[], 2),
+    // BIT
+    case("BIT zp: copies N and V, zero result", &[0x24, 0x10], &[A(0x01), Mem(0x0010, 0xC0)], &[P(N | V | Z)], 3),
+    case("BIT abs: clears N and V", &[0x2C, 0x00, 0x03], &[A(0x01), P(N | V), Mem(0x0300, 0x01)], &[P(0)], 4),
+    // BRK
+    case(
+        "BRK: pushes PC+2 and P with B",
+        &[0x00, 0xEA],
+        &[P(C), Mem(0xFFFE, 0x00), Mem(0xFFFF, 0x04)],
+        &[Pc(0x0400), S(0xFA), P(C | I), Mem(0x01FD, 0x02), Mem(0x01FC, 0x02), Mem(0x01FB, C | B | U)],
+        7,
+    ),
+    // Flag instructions
+    case("CLC", &[0x18], &[P(C | N)], &[P(N)], 2),
+    case("CLD", &[0xD8], &[P(D)], &[P(0)], 2),
+    case("CLI", &[0x58], &[P(I)], &[P(0)], 2),
+    case("CLV", &[0xB8], &[P(V)], &[P(0)], 2),
+    case("SEC", &[0x38], &[], &[P(C)], 2),
+    case("SED", &[0xF8], &[], &[P(D)], 2),
+    case("SEI", &[0x78], &[], &[P(I)], 2),
+    // CMP, CPX and CPY
+    case("CMP #: equal", &[0xC9, 0x10], &[A(0x10)], &[P(Z | C)], 2),
+    case("CMP #: less", &[0xC9, 0x20], &[A(0x10)], &[P(N)], 2),
+    case("CMP #: greater", &[0xC9, 0x10], &[A(0x20)], &[P(C)], 2),
+    case("CMP #: unsigned", &[0xC9, 0x01], &[A(0x80)], &[P(C)], 2),
+    case("CMP zp", &[0xC5, 0x10], &[A(0x10), Mem(0x0010, 0x10)], &[P(Z | C)], 3),
+    case("CMP zp,X", &[0xD5, 0x10], &[A(0x10), X(0x01), Mem(0x0011, 0x10)], &[P(Z | C)], 4),
+    case("CMP abs", &[0xCD, 0x00, 0x03], &[A(0x10), Mem(0x0300, 0x10)], &[P(Z | C)], 4),
+    case("CMP abs,X", &[0xDD, 0xFF, 0x03], &[A(0x10), X(0x01), Mem(0x0400, 0x10)], &[P(Z | C)], 5),
+    case("CMP abs,Y", &[0xD9, 0x00, 0x03], &[A(0x10), Y(0x01), Mem(0x0301, 0x10)], &[P(Z | C)], 4),
+    case(
+        "CMP (zp,X)",
+        &[0xC1, 0x10],
+        &[A(0x10), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x10)],
+        &[P(Z | C)],
+        6,
+    ),
+    case(
+        "CMP (zp),Y",
+        &[0xD1, 0x10],
+        &[A(0x10), Y(0x01), Mem(0x0010, 0xFF), Mem(0x0011, 0x03), Mem(0x0400, 0x10)],
+        &[P(Z | C)],
+        6,
+    ),
+    case("CPX #", &[0xE0, 0x10], &[X(0x20)], &[P(C)], 2),
+    case("CPX zp", &[0xE4, 0x10], &[X(0x10), Mem(0x0010, 0x20)], &[P(N)], 3),
+    case("CPX abs", &[0xEC, 0x00, 0x03], &[X(0x10), Mem(0x0300, 0x10)], &[P(Z | C)], 4),
+    case("CPY #", &[0xC0, 0x10], &[Y(0x20)], &[P(C)], 2),
+    case("CPY zp", &[0xC4, 0x10], &[Y(0x10), Mem(0x0010, 0x20)], &[P(N)], 3),
+    case("CPY abs", &[0xCC, 0x00, 0x03], &[Y(0x10), Mem(0x0300, 0x10)], &[P(Z | C)], 4),
+    // DEC, DEX and DEY
+    case("DEC zp: to zero", &[0xC6, 0x10], &[Mem(0x0010, 0x01)], &[Mem(0x0010, 0x00), P(Z)], 5),
+    case("DEC zp,X: wraps", &[0xD6, 0x10], &[X(0x01), Mem(0x0011, 0x00)], &[Mem(0x0011, 0xFF), P(N)], 6),
+    case("DEC abs", &[0xCE, 0x00, 0x03], &[Mem(0x0300, 0x02)], &[Mem(0x0300, 0x01)], 6),
+    case("DEC abs,X", &[0xDE, 0xFF, 0x03], &[X(0x01), Mem(0x0400, 0x02)], &[Mem(0x0400, 0x01)], 7),
+    case("DEX: wraps", &[0xCA], &[X(0x00)], &[X(0xFF), P(N)], 2),
+    case("DEY: to zero", &[0x88], &[Y(0x01)], &[Y(0x00), P(Z)], 2),
+    // EOR
+    case("EOR #", &[0x49, 0xFF], &[A(0x0F)], &[A(0xF0), P(N)], 2),
+    case("EOR #: zero", &[0x49, 0x0F], &[A(0x0F)], &[A(0x00), P(Z)], 2),
+    case("EOR zp", &[0x45, 0x10], &[A(0x01), Mem(0x0010, 0x03)], &[A(0x02)], 3),
+    case("EOR zp,X", &[0x55, 0x10], &[A(0x01), X(0x01), Mem(0x0011, 0x03)], &[A(0x02)], 4),
+    case("EOR abs", &[0x4D, 0x00, 0x03], &[A(0x01), Mem(0x0300, 0x03)], &[A(0x02)], 4),
+    case("EOR abs,X", &[0x5D, 0x00, 0x03], &[A(0x01), X(0x01), Mem(0x0301, 0x03)], &[A(0x02)], 4),
+    case("EOR abs,Y", &[0x59, 0xFF, 0x03], &[A(0x01), Y(0x01), Mem(0x0400, 0x03)], &[A(0x02)], 5),
+    case(
+        "EOR (zp,X)",
+        &[0x41, 0x10],
+        &[A(0x01), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x03)],
+        &[A(0x02)],
+        6,
+    ),
+    case(
+        "EOR (zp),Y",
+        &[0x51, 0x10],
+        &[A(0x01), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x03)],
+        &[A(0x02)],
+        5,
+    ),
+    // INC, INX and INY
+    case("INC zp: wraps to zero", &[0xE6, 0x10], &[Mem(0x0010, 0xFF)], &[Mem(0x0010, 0x00), P(Z)], 5),
+    case("INC zp,X: wraps in zero page", &[0xF6, 0xFF], &[X(0x11), Mem(0x0010, 0x7F)], &[Mem(0x0010, 0x80), P(N)], 6),
+    case("INC abs", &[0xEE, 0x00, 0x03], &[Mem(0x0300, 0x01)], &[Mem(0x0300, 0x02)], 6),
+    case("INC abs,X", &[0xFE, 0x00, 0x03], &[X(0x01), Mem(0x0301, 0x01)], &[Mem(0x0301, 0x02)], 7),
+    case("INX: wraps", &[0xE8], &[X(0xFF)], &[X(0x00), P(Z)], 2),
+    case("INY", &[0xC8], &[Y(0x7F)], &[Y(0x80), P(N)], 2),
+    // JMP, JSR, RTS and RTI
+    case("JMP abs", &[0x4C, 0x00, 0x04], &[], &[Pc(0x0400)], 3),
+    case("JMP (abs)", &[0x6C, 0x00, 0x03], &[Mem(0x0300, 0x00), Mem(0x0301, 0x04)], &[Pc(0x0400)], 5),
+    case(
+        "JMP (abs): the pointer's high byte comes from the same page",
+        &[0x6C, 0xFF, 0x03],
+        &[Mem(0x03FF, 0x00), Mem(0x0300, 0x05), Mem(0x0400, 0x04)],
+        &[Pc(0x0500)],
+        5,
+    ),
+    case(
+        "JSR: pushes the address of its last byte",
+        &[0x20, 0x00, 0x04],
+        &[],
+        &[Pc(0x0400), S(0xFB), Mem(0x01FD, 0x02), Mem(0x01FC, 0x02)],
+        6,
+    ),
+    case(
+        "RTS: returns past the pushed address",
+        &[0x60],
+        &[S(0xFB), Mem(0x01FC, 0x02), Mem(0x01FD, 0x04)],
+        &[Pc(0x0403), S(0xFD)],
+        6,
+    ),
+    case(
+        "RTI: pulls P without B, then PC",
+        &[0x40],
+        &[S(0xFA), Mem(0x01FB, N | B | C), Mem(0x01FC, 0x00), Mem(0x01FD, 0x04)],
+        &[Pc(0x0400), S(0xFD), P(N | C)],
+        6,
+    ),
+    // LDA
+    case("LDA #: zero", &[0xA9, 0x00], &[A(0x42)], &[A(0x00), P(Z)], 2),
+    case("LDA #: negative", &[0xA9, 0x80], &[], &[A(0x80), P(N)], 2),
+    case("LDA zp", &[0xA5, 0x10], &[Mem(0x0010, 0x42)], &[A(0x42)], 3),
+    case("LDA zp,X: wraps in zero page", &[0xB5, 0xFF], &[X(0x11), Mem(0x0010, 0x42)], &[A(0x42)], 4),
+    case("LDA abs", &[0xAD, 0x00, 0x03], &[Mem(0x0300, 0x42)], &[A(0x42)], 4),
+    case("LDA abs,X", &[0xBD, 0x00, 0x03], &[X(0x01), Mem(0x0301, 0x42)], &[A(0x42)], 4),
+    case("LDA abs,X: crosses a page", &[0xBD, 0xFF, 0x03], &[X(0x01), Mem(0x0400, 0x42)], &[A(0x42)], 5),
+    case("LDA abs,X: wraps around memory", &[0xBD, 0xFF, 0xFF], &[X(0x11), Mem(0x0010, 0x42)], &[A(0x42)], 5),
+    case("LDA abs,Y", &[0xB9, 0xFF, 0x03], &[Y(0x01), Mem(0x0400, 0x42)], &[A(0x42)], 5),
+    case(
+        "LDA (zp,X): the index wraps in zero page",
+        &[0xA1, 0xF0],
+        &[X(0x20), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x42)],
+        &[A(0x42)],
+        6,
+    ),
+    case(
+        "LDA (zp),Y: the pointer wraps in zero page",
+        &[0xB1, 0xFF],
+        &[Y(0x01), Mem(0x00FF, 0x00), Mem(0x0000, 0x03), Mem(0x0301, 0x42)],
+        &[A(0x42)],
+        5,
+    ),
+    // LDX and LDY
+    case("LDX #", &[0xA2, 0x80], &[], &[X(0x80), P(N)], 2),
+    case("LDX zp", &[0xA6, 0x10], &[X(0x01), Mem(0x0010, 0x00)], &[X(0x00), P(Z)], 3),
+    case("LDX zp,Y: wraps in zero page", &[0xB6, 0xF0], &[Y(0x20), Mem(0x0010, 0x42)], &[X(0x42)], 4),
+    case("LDX abs", &[0xAE, 0x00, 0x03], &[Mem(0x0300, 0x42)], &[X(0x42)], 4),
+    case("LDX abs,Y: crosses a page", &[0xBE, 0xFF, 0x03], &[Y(0x01), Mem(0x0400, 0x42)], &[X(0x42)], 5),
+    case("LDY #", &[0xA0, 0x80], &[], &[Y(0x80), P(N)], 2),
+    case("LDY zp", &[0xA4, 0x10], &[Y(0x01), Mem(0x0010, 0x00)], &[Y(0x00), P(Z)], 3),
+    case("LDY zp,X", &[0xB4, 0x10], &[X(0x01), Mem(0x0011, 0x42)], &[Y(0x42)], 4),
+    case("LDY abs", &[0xAC, 0x00, 0x03], &[Mem(0x0300, 0x42)], &[Y(0x42)], 4),
+    case("LDY abs,X: crosses a page", &[0xBC, 0xFF, 0x03], &[X(0x01), Mem(0x0400, 0x42)], &[Y(0x42)], 5),
+    // LSR
+    case("LSR A: carries out to zero", &[0x4A], &[A(0x01)], &[A(0x00), P(Z | C)], 2),
+    case("LSR zp: clears N", &[0x46, 0x10], &[P(N), Mem(0x0010, 0x80)], &[Mem(0x0010, 0x40), P(0)], 5),
+    case("LSR zp,X", &[0x56, 0x10], &[X(0x01), Mem(0x0011, 0x02)], &[Mem(0x0011, 0x01)], 6),
+    case("LSR abs", &[0x4E, 0x00, 0x03], &[Mem(0x0300, 0x03)], &[Mem(0x0300, 0x01), P(C)], 6),
+    case("LSR abs,X", &[0x5E, 0x00, 0x03], &[X(0x01), Mem(0x0301, 0x02)], &[Mem(0x0301, 0x01)], 7),
+    // NOP
+    case("NOP", &[0xEA], &[A(0x01), P(C)], &[], 2),
+    case("NOP (undocumented)", &[0x1A], &[], &[], 2),
+    case("NOP (undocumented)", &[0x3A], &[], &[], 2),
+    case("NOP (undocumented)", &[0x5A], &[], &[], 2),
+    case("NOP (undocumented)", &[0x7A], &[], &[], 2),
+    case("NOP (undocumented)", &[0xDA], &[], &[], 2),
+    case("NOP (undocumented)", &[0xFA], &[], &[], 2),
+    case("NOP # (undocumented)", &[0x80, 0xFF], &[], &[], 2),
+    case("NOP # (undocumented)", &[0x82, 0xFF], &[], &[], 2),
+    case("NOP # (undocumented)", &[0x89, 0xFF], &[], &[], 2),
+    case("NOP # (undocumented)", &[0xC2, 0xFF], &[], &[], 2),
+    case("NOP # (undocumented)", &[0xE2, 0xFF], &[], &[], 2),
+    case("NOP zp (undocumented)", &[0x04, 0x10], &[], &[], 3),
+    case("NOP zp (undocumented)", &[0x44, 0x10], &[], &[], 3),
+    case("NOP zp (undocumented)", &[0x64, 0x10], &[], &[], 3),
+    case("NOP zp,X (undocumented)", &[0x14, 0x10], &[X(0x01)], &[], 4),
+    case("NOP zp,X (undocumented)", &[0x34, 0x10], &[X(0x01)], &[], 4),
+    case("NOP zp,X (undocumented)", &[0x54, 0x10], &[X(0x01)], &[], 4),
+    case("NOP zp,X (undocumented)", &[0x74, 0x10], &[X(0x01)], &[], 4),
+    case("NOP zp,X (undocumented)", &[0xD4, 0x10], &[X(0x01)], &[], 4),
+    case("NOP zp,X (undocumented)", &[0xF4, 0x10], &[X(0x01)], &[], 4),
+    case("NOP abs (undocumented)", &[0x0C, 0x00, 0x03], &[], &[], 4),
+    case("NOP abs,X (undocumented)", &[0x1C, 0x00, 0x03], &[X(0x01)], &[], 4),
+    case("NOP abs,X (undocumented): crosses a page", &[0x3C, 0xFF, 0x03], &[X(0x01)], &[], 5),
+    case("NOP abs,X (undocumented)", &[0x5C, 0x00, 0x03], &[X(0x01)], &[], 4),
+    case("NOP abs,X (undocumented): crosses a page", &[0x7C, 0xFF, 0x03], &[X(0x01)], &[], 5),
+    case("NOP abs,X (undocumented)", &[0xDC, 0x00, 0x03], &[X(0x01)], &[], 4),
+    case("NOP abs,X (undocumented): crosses a page", &[0xFC, 0xFF, 0x03], &[X(0x01)], &[], 5),
+    // ORA
+    case("ORA #", &[0x09, 0x80], &[A(0x01)], &[A(0x81), P(N)], 2),
+    case("ORA #: zero", &[0x09, 0x00], &[A(0x00)], &[P(Z)], 2),
+    case("ORA zp", &[0x05, 0x10], &[A(0x01), Mem(0x0010, 0x02)], &[A(0x03)], 3),
+    case("ORA zp,X", &[0x15, 0x10], &[A(0x01), X(0x01), Mem(0x0011, 0x02)], &[A(0x03)], 4),
+    case("ORA abs", &[0x0D, 0x00, 0x03], &[A(0x01), Mem(0x0300, 0x02)], &[A(0x03)], 4),
+    case("ORA abs,X", &[0x1D, 0xFF, 0x03], &[A(0x01), X(0x01), Mem(0x0400, 0x02)], &[A(0x03)], 5),
+    case("ORA abs,Y", &[0x19, 0x00, 0x03], &[A(0x01), Y(0x01), Mem(0x0301, 0x02)], &[A(0x03)], 4),
+    case(
+        "ORA (zp,X)",
+        &[0x01, 0x10],
+        &[A(0x01), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x02)],
+        &[A(0x03)],
+        6,
+    ),
+    case(
+        "ORA (zp),Y",
+        &[0x11, 0x10],
+        &[A(0x01), Y(0x01), Mem(0x0010, 0xFF), Mem(0x0011, 0x03), Mem(0x0400, 0x02)],
+        &[A(0x03)],
+        6,
+    ),
+    // Stack
+    case("PHA", &[0x48], &[A(0x42)], &[S(0xFC), Mem(0x01FD, 0x42)], 3),
+    case("PHA: the stack wraps in page one", &[0x48], &[A(0x42), S(0x00)], &[S(0xFF), Mem(0x0100, 0x42)], 3),
+    case("PHP: pushes B and U", &[0x08], &[P(C)], &[S(0xFC), Mem(0x01FD, C | B | U)], 3),
+    case("PLA", &[0x68], &[S(0xFC), Mem(0x01FD, 0x80)], &[A(0x80), S(0xFD), P(N)], 4),
+    case("PLA: zero", &[0x68], &[A(0x42), S(0xFC), Mem(0x01FD, 0x00)], &[A(0x00), S(0xFD), P(Z)], 4),
+    case("PLP: ignores B", &[0x28], &[S(0xFC), Mem(0x01FD, 0xFF)], &[S(0xFD), P(N | V | D | I | Z | C)], 4),
+    // ROL
+    case("ROL A: carries in and out", &[0x2A], &[A(0x80), P(C)], &[A(0x01), P(C)], 2),
+    case("ROL zp", &[0x26, 0x10], &[Mem(0x0010, 0x40)], &[Mem(0x0010, 0x80), P(N)], 5),
+    case("ROL zp,X", &[0x36, 0x10], &[X(0x01), Mem(0x0011, 0x80)], &[Mem(0x0011, 0x00), P(Z | C)], 6),
+    case("ROL abs", &[0x2E, 0x00, 0x03], &[P(C), Mem(0x0300, 0x00)], &[Mem(0x0300, 0x01), P(0)], 6),
+    case("ROL abs,X", &[0x3E, 0x00, 0x03], &[X(0x01), Mem(0x0301, 0x01)], &[Mem(0x0301, 0x02)], 7),
+    // ROR
+    case("ROR A: carries in and out", &[0x6A], &[A(0x01), P(C)], &[A(0x80), P(N | C)], 2),
+    case("ROR zp", &[0x66, 0x10], &[Mem(0x0010, 0x02)], &[Mem(0x0010, 0x01)], 5),
+    case("ROR zp,X", &[0x76, 0x10], &[X(0x01), Mem(0x0011, 0x01)], &[Mem(0x0011, 0x00), P(Z | C)], 6),
+    case("ROR abs", &[0x6E, 0x00, 0x03], &[P(C), Mem(0x0300, 0x00)], &[Mem(0x0300, 0x80), P(N)], 6),
+    case("ROR abs,X", &[0x7E, 0x00, 0x03], &[X(0x01), Mem(0x0301, 0x02)], &[Mem(0x0301, 0x01)], 7),
+    // SBC
+    case("SBC #: no borrow", &[0xE9, 0x01], &[A(0x10), P(C)], &[A(0x0F), P(C)], 2),
+    case("SBC #: borrows in and out", &[0xE9, 0x00], &[A(0x00)], &[A(0xFF), P(N)], 2),
+    case("SBC #: to zero", &[0xE9, 0x10], &[A(0x10), P(C)], &[A(0x00), P(Z | C)], 2),
+    case("SBC #: negative overflow", &[0xE9, 0x01], &[A(0x80), P(C)], &[A(0x7F), P(V | C)], 2),
+    case("SBC #: positive overflow", &[0xE9, 0xFF], &[A(0x7F), P(C)], &[A(0x80), P(N | V)], 2),
+    case("SBC #: decimal", &[0xE9, 0x01], &[A(0x20), P(D | C)], &[A(0x19), P(D | C)], 2),
+    case("SBC # (undocumented)", &[0xEB, 0x01], &[A(0x80), P(C)], &[A(0x7F), P(V | C)], 2),
+    case("SBC zp", &[0xE5, 0x10], &[A(0x03), P(C), Mem(0x0010, 0x01)], &[A(0x02)], 3),
+    case("SBC zp,X", &[0xF5, 0x10], &[A(0x03), X(0x01), P(C), Mem(0x0011, 0x01)], &[A(0x02)], 4),
+    case("SBC abs", &[0xED, 0x00, 0x03], &[A(0x03), P(C), Mem(0x0300, 0x01)], &[A(0x02)], 4),
+    case("SBC abs,X", &[0xFD, 0xFF, 0x03], &[A(0x03), X(0x01), P(C), Mem(0x0400, 0x01)], &[A(0x02)], 5),
+    case("SBC abs,Y", &[0xF9, 0x00, 0x03], &[A(0x03), Y(0x01), P(C), Mem(0x0301, 0x01)], &[A(0x02)], 4),
+    case(
+        "SBC (zp,X)",
+        &[0xE1, 0x10],
+        &[A(0x03), P(C), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0300, 0x01)],
+        &[A(0x02)],
+        6,
+    ),
+    case(
+        "SBC (zp),Y",
+        &[0xF1, 0x10],
+        &[A(0x03), Y(0x01), P(C), Mem(0x0010, 0x00), Mem(0x0011, 0x03), Mem(0x0301, 0x01)],
+        &[A(0x02)],
+        5,
+    ),
+    // STA, STX and STY, which never take an extra cycle
+    case("STA zp", &[0x85, 0x10], &[A(0x42)], &[Mem(0x0010, 0x42)], 3),
+    case("STA zp,X: wraps in zero page", &[0x95, 0xFF], &[A(0x42), X(0x11)], &[Mem(0x0010, 0x42)], 4),
+    case("STA abs", &[0x8D, 0x00, 0x03], &[A(0x42)], &[Mem(0x0300, 0x42)], 4),
+    case("STA abs,X", &[0x9D, 0x00, 0x03], &[A(0x42), X(0x01)], &[Mem(0x0301, 0x42)], 5),
+    case("STA abs,Y: crosses a page", &[0x99, 0xFF, 0x03], &[A(0x42), Y(0x01)], &[Mem(0x0400, 0x42)], 5),
+    case(
+        "STA (zp,X)",
+        &[0x81, 0x0E],
+        &[A(0x42), X(0x02), Mem(0x0010, 0x00), Mem(0x0011, 0x03)],
+        &[Mem(0x0300, 0x42)],
+        6,
+    ),
+    case(
+        "STA (zp),Y: crosses a page",
+        &[0x91, 0x10],
+        &[A(0x42), Y(0x01), Mem(0x0010, 0xFF), Mem(0x0011, 0x03)],
+        &[Mem(0x0400, 0x42)],
+        6,
+    ),
+    case("STX zp", &[0x86, 0x10], &[X(0x42)], &[Mem(0x0010, 0x42)], 3),
+    case("STX zp,Y: wraps in zero page", &[0x96, 0xFF], &[X(0x42), Y(0x11)], &[Mem(0x0010, 0x42)], 4),
+    case("STX abs", &[0x8E, 0x00, 0x03], &[X(0x42)], &[Mem(0x0300, 0x42)], 4),
+    case("STY zp", &[0x84, 0x10], &[Y(0x42)], &[Mem(0x0010, 0x42)], 3),
+    case("STY zp,X", &[0x94, 0x10], &[Y(0x42), X(0x01)], &[Mem(0x0011, 0x42)], 4),
+    case("STY abs", &[0x8C, 0x00, 0x03], &[Y(0x42)], &[Mem(0x0300, 0x42)], 4),
+    // Transfers
+    case("TAX: zero", &[0xAA], &[A(0x00), X(0x42)], &[X(0x00), P(Z)], 2),
+    case("TAY: negative", &[0xA8], &[A(0x80)], &[Y(0x80), P(N)], 2),
+    case("TSX", &[0xBA], &[S(0x80)], &[X(0x80), P(N)], 2),
+    case("TXA", &[0x8A], &[X(0x42), A(0x80)], &[A(0x42)], 2),
+    case("TXS: leaves the flags alone", &[0x9A], &[X(0x00)], &[S(0x00)], 2),
+    case("TYA", &[0x98], &[Y(0x00), A(0x42)], &[A(0x00), P(Z)], 2),
+];
+
+#[test]
+fn every_case_matches() {
+    let failures: Vec<String> = CASES.iter().filter_map(|case| run(case).err()).collect();
+    assert!(failures.is_empty(), "{} of {} cases failed:\n{}", failures.len(), CASES.len(), failures.join("\n"));
+}
+
+#[test]
+fn every_documented_opcode_has_a_case() {
+    let covered: BTreeSet<u8> = CASES.iter().map(|case| case.code[0]).collect();
+    let missing: Vec<String> = instruction_table(CpuModel::Nmos6502)
+        .iter()
+        .filter(|instruction| !instruction.illegal && !covered.contains(&instruction.opcode))
+        .map(|instruction| format!("${:02X} {}", instruction.opcode, instruction.name))
+        .collect();
+    assert!(missing.is_empty(), "documented opcodes without a case: {}", missing.join(", "));
+}
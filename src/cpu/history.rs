@@ -0,0 +1,161 @@
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use core::fmt::Display;
+
+use crate::cpu::AddressingMode;
+use crate::disasm::DisassembledInstruction;
+
+/// An instruction the CPU executed, with the registers as they were before it.
+///
+/// Entries are kept small so that recording them doesn't slow execution down
+/// much, so unlike an `InstructionRecord` they don't include bus accesses.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HistoryEntry {
+    /// The address the instruction was fetched from.
+    pub pc: u16,
+
+    /// The opcode of the instruction.
+    pub opcode: u8,
+
+    /// The operand bytes. Only as many as the addressing mode uses are meaningful.
+    pub operands: [u8; 2],
+
+    /// The mnemonic of the instruction, such as "LDA".
+    pub mnemonic: &'static str,
+
+    /// The addressing mode of the instruction.
+    pub mode: AddressingMode,
+
+    /// Whether the opcode is undocumented.
+    pub illegal: bool,
+
+    /// The accumulator before the instruction.
+    pub a: u8,
+
+    /// The X register before the instruction.
+    pub x: u8,
+
+    /// The Y register before the instruction.
+    pub y: u8,
+
+    /// The status register before the instruction.
+    pub p: u8,
+
+    /// The stack pointer before the instruction.
+    pub sp: u8,
+}
+
+impl HistoryEntry {
+    /// Returns the instruction in the form the disassembler produces.
+    ///
+    /// # Returns
+    ///
+    /// The decoded instruction. Its cycle count is 0, since the entry doesn't record one.
+    pub fn disassemble(&self) -> DisassembledInstruction {
+        let length = self.mode.operand_length();
+        DisassembledInstruction {
+            address: self.pc,
+            opcode: self.opcode,
+            mnemonic: self.mnemonic,
+            mode: self.mode,
+            operands: self.operands[..length as usize].to_vec(),
+            length: length + 1,
+            cycles: 0,
+            illegal: self.illegal,
+        }
+    }
+}
+
+impl Display for HistoryEntry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:04X}  {:<14} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.pc,
+            self.disassemble().to_string(),
+            self.a,
+            self.x,
+            self.y,
+            self.p,
+            self.sp
+        )
+    }
+}
+
+/// Keeps the most recently executed instructions in a ring buffer.
+///
+/// Once `capacity` entries are held, the oldest one is dropped to make room,
+/// so after a crash or jam the history shows the path that led there.
+pub struct History {
+    /// The maximum number of entries held.
+    capacity: usize,
+
+    /// The entries, oldest first.
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    /// Creates a new, empty `History`.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of entries held. 0 is treated as 1.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `History` struct.
+    pub fn new(capacity: usize) -> History {
+        let capacity = capacity.max(1);
+        History {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the maximum number of entries held.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries held.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no entries are held.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops every entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the entries, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    /// Returns the most recently executed instruction.
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    /// Adds an entry, dropping the oldest one if the history is full.
+    pub(crate) fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+impl Display for History {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
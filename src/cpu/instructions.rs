@@ -1,9 +1,15 @@
+use core::ops::{AddAssign, SubAssign};
+
 use crate::cpu::addresses::IRQ_VECTOR;
 use crate::cpu::addressing::AddressingMode;
-use crate::cpu::{Cpu, StatusFlags};
+use crate::cpu::{CallKind, Cpu, CpuModel, CpuState, StatusFlags};
 
+#[derive(Clone, Copy)]
 pub struct Instruction {
     pub illegal: bool,
+    /// Whether the instruction jams the CPU. These halt it even while the
+    /// other undocumented opcodes are disabled, as they do on the chip.
+    pub jam: bool,
     pub opcode: u8,
     pub name: &'static str,
     pub mode: AddressingMode,
@@ -11,10 +17,11 @@ pub struct Instruction {
     pub function: fn(_cpu: &mut Cpu) -> u8,
 }
 
-/// List of all 6502 instructions
+/// List of all NMOS 6502 instructions. The other models' tables are derived from it.
 pub const INSTRUCTION_LIST: [Instruction; 256] = [
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x00,
         name: "BRK",
         mode: AddressingMode::Immediate,
@@ -23,6 +30,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x01,
         name: "ORA",
         mode: AddressingMode::IndexedIndirect,
@@ -31,6 +39,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x02,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -39,6 +48,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x03,
         name: "SLO",
         mode: AddressingMode::IndexedIndirect,
@@ -47,6 +57,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x04,
         name: "NOP",
         mode: AddressingMode::ZeroPage,
@@ -55,6 +66,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x05,
         name: "ORA",
         mode: AddressingMode::ZeroPage,
@@ -63,6 +75,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x06,
         name: "ASL",
         mode: AddressingMode::ZeroPage,
@@ -71,6 +84,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x07,
         name: "SLO",
         mode: AddressingMode::ZeroPage,
@@ -79,6 +93,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x08,
         name: "PHP",
         mode: AddressingMode::Implied,
@@ -87,6 +102,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x09,
         name: "ORA",
         mode: AddressingMode::Immediate,
@@ -95,14 +111,16 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x0A,
         name: "ASL",
-        mode: AddressingMode::Implied,
+        mode: AddressingMode::Accumulator,
         cycles: 2,
         function: asl,
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x0B,
         name: "ANC",
         mode: AddressingMode::Immediate,
@@ -111,6 +129,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x0C,
         name: "NOP",
         mode: AddressingMode::Absolute,
@@ -119,6 +138,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x0D,
         name: "ORA",
         mode: AddressingMode::Absolute,
@@ -127,6 +147,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x0E,
         name: "ASL",
         mode: AddressingMode::Absolute,
@@ -135,6 +156,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x0F,
         name: "SLO",
         mode: AddressingMode::Absolute,
@@ -143,6 +165,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x10,
         name: "BPL",
         mode: AddressingMode::Relative,
@@ -151,6 +174,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x11,
         name: "ORA",
         mode: AddressingMode::IndirectIndexed,
@@ -159,6 +183,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x12,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -167,6 +192,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x13,
         name: "SLO",
         mode: AddressingMode::IndirectIndexed,
@@ -175,6 +201,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x14,
         name: "NOP",
         mode: AddressingMode::ZeroPageX,
@@ -183,6 +210,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x15,
         name: "ORA",
         mode: AddressingMode::ZeroPageX,
@@ -191,6 +219,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x16,
         name: "ASL",
         mode: AddressingMode::ZeroPageX,
@@ -199,6 +228,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x17,
         name: "SLO",
         mode: AddressingMode::ZeroPageX,
@@ -207,6 +237,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x18,
         name: "CLC",
         mode: AddressingMode::Implied,
@@ -215,6 +246,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x19,
         name: "ORA",
         mode: AddressingMode::AbsoluteY,
@@ -223,6 +255,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x1A,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -231,6 +264,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x1B,
         name: "SLO",
         mode: AddressingMode::AbsoluteY,
@@ -239,6 +273,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x1C,
         name: "NOP",
         mode: AddressingMode::AbsoluteX,
@@ -247,6 +282,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x1D,
         name: "ORA",
         mode: AddressingMode::AbsoluteX,
@@ -255,6 +291,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x1E,
         name: "ASL",
         mode: AddressingMode::AbsoluteX,
@@ -263,6 +300,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x1F,
         name: "SLO",
         mode: AddressingMode::AbsoluteX,
@@ -271,6 +309,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x20,
         name: "JSR",
         mode: AddressingMode::Absolute,
@@ -279,6 +318,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x21,
         name: "AND",
         mode: AddressingMode::IndexedIndirect,
@@ -287,6 +327,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x22,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -295,6 +336,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x23,
         name: "RLA",
         mode: AddressingMode::IndexedIndirect,
@@ -303,6 +345,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x24,
         name: "BIT",
         mode: AddressingMode::ZeroPage,
@@ -311,6 +354,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x25,
         name: "AND",
         mode: AddressingMode::ZeroPage,
@@ -319,6 +363,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x26,
         name: "ROL",
         mode: AddressingMode::ZeroPage,
@@ -327,6 +372,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x27,
         name: "RLA",
         mode: AddressingMode::ZeroPage,
@@ -335,6 +381,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x28,
         name: "PLP",
         mode: AddressingMode::Implied,
@@ -343,6 +390,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x29,
         name: "AND",
         mode: AddressingMode::Immediate,
@@ -351,14 +399,16 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x2A,
         name: "ROL",
-        mode: AddressingMode::Implied,
+        mode: AddressingMode::Accumulator,
         cycles: 2,
         function: rol,
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x2B,
         name: "ANC",
         mode: AddressingMode::Immediate,
@@ -367,6 +417,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x2C,
         name: "BIT",
         mode: AddressingMode::Absolute,
@@ -375,6 +426,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x2D,
         name: "AND",
         mode: AddressingMode::Absolute,
@@ -383,6 +435,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x2E,
         name: "ROL",
         mode: AddressingMode::Absolute,
@@ -391,6 +444,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x2F,
         name: "RLA",
         mode: AddressingMode::Absolute,
@@ -399,6 +453,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x30,
         name: "BMI",
         mode: AddressingMode::Relative,
@@ -407,6 +462,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x31,
         name: "AND",
         mode: AddressingMode::IndirectIndexed,
@@ -415,6 +471,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x32,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -423,6 +480,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x33,
         name: "RLA",
         mode: AddressingMode::IndirectIndexed,
@@ -431,6 +489,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x34,
         name: "NOP",
         mode: AddressingMode::ZeroPageX,
@@ -439,6 +498,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x35,
         name: "AND",
         mode: AddressingMode::ZeroPageX,
@@ -447,6 +507,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x36,
         name: "ROL",
         mode: AddressingMode::ZeroPageX,
@@ -455,6 +516,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x37,
         name: "RLA",
         mode: AddressingMode::ZeroPageX,
@@ -463,6 +525,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x38,
         name: "SEC",
         mode: AddressingMode::Implied,
@@ -471,6 +534,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x39,
         name: "AND",
         mode: AddressingMode::AbsoluteY,
@@ -479,6 +543,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x3A,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -487,6 +552,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x3B,
         name: "RLA",
         mode: AddressingMode::AbsoluteY,
@@ -495,6 +561,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x3C,
         name: "NOP",
         mode: AddressingMode::AbsoluteX,
@@ -503,6 +570,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x3D,
         name: "AND",
         mode: AddressingMode::AbsoluteX,
@@ -511,6 +579,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x3E,
         name: "ROL",
         mode: AddressingMode::AbsoluteX,
@@ -519,6 +588,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x3F,
         name: "RLA",
         mode: AddressingMode::AbsoluteX,
@@ -527,6 +597,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x40,
         name: "RTI",
         mode: AddressingMode::Implied,
@@ -535,6 +606,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x41,
         name: "EOR",
         mode: AddressingMode::IndexedIndirect,
@@ -543,6 +615,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x42,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -551,6 +624,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x43,
         name: "SRE",
         mode: AddressingMode::IndexedIndirect,
@@ -559,6 +633,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x44,
         name: "NOP",
         mode: AddressingMode::ZeroPage,
@@ -567,6 +642,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x45,
         name: "EOR",
         mode: AddressingMode::ZeroPage,
@@ -575,6 +651,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x46,
         name: "LSR",
         mode: AddressingMode::ZeroPage,
@@ -583,6 +660,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x47,
         name: "SRE",
         mode: AddressingMode::ZeroPage,
@@ -591,6 +669,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x48,
         name: "PHA",
         mode: AddressingMode::Implied,
@@ -599,6 +678,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x49,
         name: "EOR",
         mode: AddressingMode::Immediate,
@@ -607,14 +687,16 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x4A,
         name: "LSR",
-        mode: AddressingMode::Implied,
+        mode: AddressingMode::Accumulator,
         cycles: 2,
         function: lsr,
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x4B,
         name: "ALR",
         mode: AddressingMode::Immediate,
@@ -623,6 +705,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x4C,
         name: "JMP",
         mode: AddressingMode::Absolute,
@@ -631,6 +714,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x4D,
         name: "EOR",
         mode: AddressingMode::Absolute,
@@ -639,6 +723,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x4E,
         name: "LSR",
         mode: AddressingMode::Absolute,
@@ -647,6 +732,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x4F,
         name: "SRE",
         mode: AddressingMode::Absolute,
@@ -655,6 +741,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x50,
         name: "BVC",
         mode: AddressingMode::Relative,
@@ -663,6 +750,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x51,
         name: "EOR",
         mode: AddressingMode::IndirectIndexed,
@@ -671,6 +759,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x52,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -679,6 +768,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x53,
         name: "SRE",
         mode: AddressingMode::IndirectIndexed,
@@ -687,6 +777,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x54,
         name: "NOP",
         mode: AddressingMode::ZeroPageX,
@@ -695,6 +786,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x55,
         name: "EOR",
         mode: AddressingMode::ZeroPageX,
@@ -703,6 +795,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x56,
         name: "LSR",
         mode: AddressingMode::ZeroPageX,
@@ -711,6 +804,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x57,
         name: "SRE",
         mode: AddressingMode::ZeroPageX,
@@ -719,6 +813,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x58,
         name: "CLI",
         mode: AddressingMode::Implied,
@@ -727,6 +822,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x59,
         name: "EOR",
         mode: AddressingMode::AbsoluteY,
@@ -735,6 +831,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x5A,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -743,6 +840,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x5B,
         name: "SRE",
         mode: AddressingMode::AbsoluteY,
@@ -751,6 +849,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x5C,
         name: "NOP",
         mode: AddressingMode::AbsoluteX,
@@ -759,6 +858,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x5D,
         name: "EOR",
         mode: AddressingMode::AbsoluteX,
@@ -767,6 +867,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x5E,
         name: "LSR",
         mode: AddressingMode::AbsoluteX,
@@ -775,6 +876,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x5F,
         name: "SRE",
         mode: AddressingMode::AbsoluteX,
@@ -783,6 +885,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x60,
         name: "RTS",
         mode: AddressingMode::Implied,
@@ -791,6 +894,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x61,
         name: "ADC",
         mode: AddressingMode::IndexedIndirect,
@@ -799,6 +903,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x62,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -807,6 +912,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x63,
         name: "RRA",
         mode: AddressingMode::IndexedIndirect,
@@ -815,6 +921,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x64,
         name: "NOP",
         mode: AddressingMode::ZeroPage,
@@ -823,6 +930,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x65,
         name: "ADC",
         mode: AddressingMode::ZeroPage,
@@ -831,6 +939,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x66,
         name: "ROR",
         mode: AddressingMode::ZeroPage,
@@ -839,6 +948,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x67,
         name: "RRA",
         mode: AddressingMode::ZeroPage,
@@ -847,6 +957,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x68,
         name: "PLA",
         mode: AddressingMode::Implied,
@@ -855,6 +966,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x69,
         name: "ADC",
         mode: AddressingMode::Immediate,
@@ -863,14 +975,16 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x6A,
-        name: "RORA",
-        mode: AddressingMode::Implied,
+        name: "ROR",
+        mode: AddressingMode::Accumulator,
         cycles: 2,
-        function: ror_a,
+        function: ror,
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x6B,
         name: "ARR",
         mode: AddressingMode::Immediate,
@@ -879,6 +993,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x6C,
         name: "JMP",
         mode: AddressingMode::Indirect,
@@ -887,6 +1002,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x6D,
         name: "ADC",
         mode: AddressingMode::Absolute,
@@ -895,6 +1011,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x6E,
         name: "ROR",
         mode: AddressingMode::Absolute,
@@ -903,6 +1020,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x6F,
         name: "RRA",
         mode: AddressingMode::Absolute,
@@ -911,6 +1029,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x70,
         name: "BVS",
         mode: AddressingMode::Relative,
@@ -919,6 +1038,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x71,
         name: "ADC",
         mode: AddressingMode::IndirectIndexed,
@@ -927,6 +1047,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x72,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -935,6 +1056,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x73,
         name: "RRA",
         mode: AddressingMode::IndirectIndexed,
@@ -943,6 +1065,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x74,
         name: "NOP",
         mode: AddressingMode::ZeroPageX,
@@ -951,6 +1074,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x75,
         name: "ADC",
         mode: AddressingMode::ZeroPageX,
@@ -959,6 +1083,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x76,
         name: "ROR",
         mode: AddressingMode::ZeroPageX,
@@ -967,6 +1092,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x77,
         name: "RRA",
         mode: AddressingMode::ZeroPageX,
@@ -975,6 +1101,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x78,
         name: "SEI",
         mode: AddressingMode::Implied,
@@ -983,6 +1110,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x79,
         name: "ADC",
         mode: AddressingMode::AbsoluteY,
@@ -991,6 +1119,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x7A,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -999,6 +1128,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x7B,
         name: "RRA",
         mode: AddressingMode::AbsoluteY,
@@ -1007,6 +1137,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x7C,
         name: "NOP",
         mode: AddressingMode::AbsoluteX,
@@ -1015,6 +1146,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x7D,
         name: "ADC",
         mode: AddressingMode::AbsoluteX,
@@ -1023,6 +1155,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x7E,
         name: "ROR",
         mode: AddressingMode::AbsoluteX,
@@ -1031,6 +1164,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x7F,
         name: "RRA",
         mode: AddressingMode::AbsoluteX,
@@ -1039,6 +1173,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x80,
         name: "NOP",
         mode: AddressingMode::Immediate,
@@ -1047,6 +1182,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x81,
         name: "STA",
         mode: AddressingMode::IndexedIndirect,
@@ -1055,6 +1191,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x82,
         name: "NOP",
         mode: AddressingMode::Immediate,
@@ -1063,6 +1200,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x83,
         name: "SAX",
         mode: AddressingMode::IndexedIndirect,
@@ -1071,6 +1209,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x84,
         name: "STY",
         mode: AddressingMode::ZeroPage,
@@ -1079,6 +1218,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x85,
         name: "STA",
         mode: AddressingMode::ZeroPage,
@@ -1087,6 +1227,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x86,
         name: "STX",
         mode: AddressingMode::ZeroPage,
@@ -1095,6 +1236,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x87,
         name: "SAX",
         mode: AddressingMode::ZeroPage,
@@ -1103,6 +1245,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x88,
         name: "DEY",
         mode: AddressingMode::Implied,
@@ -1111,6 +1254,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x89,
         name: "NOP",
         mode: AddressingMode::Immediate,
@@ -1119,6 +1263,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x8A,
         name: "TXA",
         mode: AddressingMode::Implied,
@@ -1127,6 +1272,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x8B,
         name: "XAA",
         mode: AddressingMode::Immediate,
@@ -1135,6 +1281,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x8C,
         name: "STY",
         mode: AddressingMode::Absolute,
@@ -1143,6 +1290,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x8D,
         name: "STA",
         mode: AddressingMode::Absolute,
@@ -1151,6 +1299,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x8E,
         name: "STX",
         mode: AddressingMode::Absolute,
@@ -1159,6 +1308,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x8F,
         name: "SAX",
         mode: AddressingMode::Absolute,
@@ -1167,6 +1317,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x90,
         name: "BCC",
         mode: AddressingMode::Relative,
@@ -1175,6 +1326,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x91,
         name: "STA",
         mode: AddressingMode::IndirectIndexed,
@@ -1183,6 +1335,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0x92,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -1191,6 +1344,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x93,
         name: "AHX",
         mode: AddressingMode::IndirectIndexed,
@@ -1199,6 +1353,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x94,
         name: "STY",
         mode: AddressingMode::ZeroPageX,
@@ -1207,6 +1362,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x95,
         name: "STA",
         mode: AddressingMode::ZeroPageX,
@@ -1215,6 +1371,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x96,
         name: "STX",
         mode: AddressingMode::ZeroPageY,
@@ -1223,6 +1380,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x97,
         name: "SAX",
         mode: AddressingMode::ZeroPageY,
@@ -1231,6 +1389,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x98,
         name: "TYA",
         mode: AddressingMode::Implied,
@@ -1239,6 +1398,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x99,
         name: "STA",
         mode: AddressingMode::AbsoluteY,
@@ -1247,6 +1407,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x9A,
         name: "TXS",
         mode: AddressingMode::Implied,
@@ -1255,6 +1416,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x9B,
         name: "TAS",
         mode: AddressingMode::AbsoluteY,
@@ -1263,6 +1425,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x9C,
         name: "SHY",
         mode: AddressingMode::AbsoluteX,
@@ -1271,6 +1434,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0x9D,
         name: "STA",
         mode: AddressingMode::AbsoluteX,
@@ -1279,6 +1443,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x9E,
         name: "SHX",
         mode: AddressingMode::AbsoluteY,
@@ -1287,6 +1452,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0x9F,
         name: "AHX",
         mode: AddressingMode::AbsoluteY,
@@ -1295,6 +1461,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA0,
         name: "LDY",
         mode: AddressingMode::Immediate,
@@ -1303,6 +1470,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA1,
         name: "LDA",
         mode: AddressingMode::IndexedIndirect,
@@ -1311,6 +1479,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA2,
         name: "LDX",
         mode: AddressingMode::Immediate,
@@ -1319,6 +1488,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xA3,
         name: "LAX",
         mode: AddressingMode::IndexedIndirect,
@@ -1327,6 +1497,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA4,
         name: "LDY",
         mode: AddressingMode::ZeroPage,
@@ -1335,6 +1506,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA5,
         name: "LDA",
         mode: AddressingMode::ZeroPage,
@@ -1343,6 +1515,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA6,
         name: "LDX",
         mode: AddressingMode::ZeroPage,
@@ -1351,6 +1524,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xA7,
         name: "LAX",
         mode: AddressingMode::ZeroPage,
@@ -1359,6 +1533,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA8,
         name: "TAY",
         mode: AddressingMode::Implied,
@@ -1367,6 +1542,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xA9,
         name: "LDA",
         mode: AddressingMode::Immediate,
@@ -1375,6 +1551,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xAA,
         name: "TAX",
         mode: AddressingMode::Implied,
@@ -1383,6 +1560,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xAB,
         name: "LAX",
         mode: AddressingMode::Immediate,
@@ -1391,6 +1569,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xAC,
         name: "LDY",
         mode: AddressingMode::Absolute,
@@ -1399,6 +1578,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xAD,
         name: "LDA",
         mode: AddressingMode::Absolute,
@@ -1407,6 +1587,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xAE,
         name: "LDX",
         mode: AddressingMode::Absolute,
@@ -1415,6 +1596,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xAF,
         name: "LAX",
         mode: AddressingMode::Absolute,
@@ -1423,6 +1605,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB0,
         name: "BCS",
         mode: AddressingMode::Relative,
@@ -1431,6 +1614,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB1,
         name: "LDA",
         mode: AddressingMode::IndirectIndexed,
@@ -1439,6 +1623,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0xB2,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -1447,6 +1632,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xB3,
         name: "LAX",
         mode: AddressingMode::IndirectIndexed,
@@ -1455,6 +1641,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB4,
         name: "LDY",
         mode: AddressingMode::ZeroPageX,
@@ -1463,6 +1650,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB5,
         name: "LDA",
         mode: AddressingMode::ZeroPageX,
@@ -1471,6 +1659,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB6,
         name: "LDX",
         mode: AddressingMode::ZeroPageY,
@@ -1479,6 +1668,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xB7,
         name: "LAX",
         mode: AddressingMode::ZeroPageY,
@@ -1487,6 +1677,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB8,
         name: "CLV",
         mode: AddressingMode::Implied,
@@ -1495,6 +1686,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xB9,
         name: "LDA",
         mode: AddressingMode::AbsoluteY,
@@ -1503,6 +1695,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xBA,
         name: "TSX",
         mode: AddressingMode::Implied,
@@ -1511,6 +1704,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xBB,
         name: "LAS",
         mode: AddressingMode::AbsoluteY,
@@ -1519,6 +1713,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xBC,
         name: "LDY",
         mode: AddressingMode::AbsoluteX,
@@ -1527,6 +1722,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xBD,
         name: "LDA",
         mode: AddressingMode::AbsoluteX,
@@ -1535,6 +1731,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xBE,
         name: "LDX",
         mode: AddressingMode::AbsoluteY,
@@ -1543,6 +1740,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xBF,
         name: "LAX",
         mode: AddressingMode::AbsoluteY,
@@ -1551,6 +1749,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC0,
         name: "CPY",
         mode: AddressingMode::Immediate,
@@ -1559,6 +1758,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC1,
         name: "CMP",
         mode: AddressingMode::IndexedIndirect,
@@ -1567,6 +1767,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC2,
         name: "NOP",
         mode: AddressingMode::Immediate,
@@ -1575,6 +1776,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xC3,
         name: "DCP",
         mode: AddressingMode::IndexedIndirect,
@@ -1583,6 +1785,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC4,
         name: "CPY",
         mode: AddressingMode::ZeroPage,
@@ -1591,6 +1794,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC5,
         name: "CMP",
         mode: AddressingMode::ZeroPage,
@@ -1599,6 +1803,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC6,
         name: "DEC",
         mode: AddressingMode::ZeroPage,
@@ -1607,6 +1812,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xC7,
         name: "DCP",
         mode: AddressingMode::ZeroPage,
@@ -1615,6 +1821,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC8,
         name: "INY",
         mode: AddressingMode::Implied,
@@ -1623,6 +1830,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xC9,
         name: "CMP",
         mode: AddressingMode::Immediate,
@@ -1631,6 +1839,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xCA,
         name: "DEX",
         mode: AddressingMode::Implied,
@@ -1639,6 +1848,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xCB,
         name: "AXS",
         mode: AddressingMode::Immediate,
@@ -1647,6 +1857,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xCC,
         name: "CPY",
         mode: AddressingMode::Absolute,
@@ -1655,6 +1866,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xCD,
         name: "CMP",
         mode: AddressingMode::Absolute,
@@ -1663,6 +1875,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xCE,
         name: "DEC",
         mode: AddressingMode::Absolute,
@@ -1671,6 +1884,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xCF,
         name: "DCP",
         mode: AddressingMode::Absolute,
@@ -1679,6 +1893,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD0,
         name: "BNE",
         mode: AddressingMode::Relative,
@@ -1687,6 +1902,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD1,
         name: "CMP",
         mode: AddressingMode::IndirectIndexed,
@@ -1695,6 +1911,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0xD2,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -1703,6 +1920,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xD3,
         name: "DCP",
         mode: AddressingMode::IndirectIndexed,
@@ -1711,6 +1929,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD4,
         name: "NOP",
         mode: AddressingMode::ZeroPageX,
@@ -1719,6 +1938,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD5,
         name: "CMP",
         mode: AddressingMode::ZeroPageX,
@@ -1727,6 +1947,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD6,
         name: "DEC",
         mode: AddressingMode::ZeroPageX,
@@ -1735,6 +1956,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xD7,
         name: "DCP",
         mode: AddressingMode::ZeroPageX,
@@ -1743,6 +1965,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD8,
         name: "CLD",
         mode: AddressingMode::Implied,
@@ -1751,6 +1974,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xD9,
         name: "CMP",
         mode: AddressingMode::AbsoluteY,
@@ -1759,6 +1983,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xDA,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -1767,6 +1992,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xDB,
         name: "DCP",
         mode: AddressingMode::AbsoluteY,
@@ -1775,6 +2001,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xDC,
         name: "NOP",
         mode: AddressingMode::AbsoluteX,
@@ -1783,6 +2010,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xDD,
         name: "CMP",
         mode: AddressingMode::AbsoluteX,
@@ -1791,6 +2019,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xDE,
         name: "DEC",
         mode: AddressingMode::AbsoluteX,
@@ -1798,7 +2027,8 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
         function: dec,
     },
     Instruction {
-        illegal: false,
+        illegal: true,
+        jam: false,
         opcode: 0xDF,
         name: "DCP",
         mode: AddressingMode::AbsoluteX,
@@ -1807,6 +2037,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE0,
         name: "CPX",
         mode: AddressingMode::Immediate,
@@ -1815,6 +2046,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE1,
         name: "SBC",
         mode: AddressingMode::IndexedIndirect,
@@ -1823,6 +2055,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE2,
         name: "NOP",
         mode: AddressingMode::Immediate,
@@ -1831,6 +2064,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xE3,
         name: "ISC",
         mode: AddressingMode::IndexedIndirect,
@@ -1839,6 +2073,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE4,
         name: "CPX",
         mode: AddressingMode::ZeroPage,
@@ -1847,6 +2082,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE5,
         name: "SBC",
         mode: AddressingMode::ZeroPage,
@@ -1855,6 +2091,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE6,
         name: "INC",
         mode: AddressingMode::ZeroPage,
@@ -1863,6 +2100,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xE7,
         name: "ISC",
         mode: AddressingMode::ZeroPage,
@@ -1871,6 +2109,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE8,
         name: "INX",
         mode: AddressingMode::Implied,
@@ -1879,6 +2118,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xE9,
         name: "SBC",
         mode: AddressingMode::Immediate,
@@ -1887,6 +2127,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xEA,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -1895,6 +2136,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xEB,
         name: "SBC",
         mode: AddressingMode::Immediate,
@@ -1903,6 +2145,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xEC,
         name: "CPX",
         mode: AddressingMode::Absolute,
@@ -1911,6 +2154,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xED,
         name: "SBC",
         mode: AddressingMode::Absolute,
@@ -1919,6 +2163,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xEE,
         name: "INC",
         mode: AddressingMode::Absolute,
@@ -1927,6 +2172,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xEF,
         name: "ISC",
         mode: AddressingMode::Absolute,
@@ -1935,6 +2181,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF0,
         name: "BEQ",
         mode: AddressingMode::Relative,
@@ -1943,6 +2190,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF1,
         name: "SBC",
         mode: AddressingMode::IndirectIndexed,
@@ -1951,6 +2199,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: true,
         opcode: 0xF2,
         name: "KIL",
         mode: AddressingMode::Implied,
@@ -1959,6 +2208,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xF3,
         name: "ISC",
         mode: AddressingMode::IndirectIndexed,
@@ -1967,6 +2217,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF4,
         name: "NOP",
         mode: AddressingMode::ZeroPageX,
@@ -1975,6 +2226,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF5,
         name: "SBC",
         mode: AddressingMode::ZeroPageX,
@@ -1983,6 +2235,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF6,
         name: "INC",
         mode: AddressingMode::ZeroPageX,
@@ -1991,6 +2244,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xF7,
         name: "ISC",
         mode: AddressingMode::ZeroPageX,
@@ -1999,6 +2253,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF8,
         name: "SED",
         mode: AddressingMode::Implied,
@@ -2007,6 +2262,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xF9,
         name: "SBC",
         mode: AddressingMode::AbsoluteY,
@@ -2015,6 +2271,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xFA,
         name: "NOP",
         mode: AddressingMode::Implied,
@@ -2023,6 +2280,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xFB,
         name: "ISC",
         mode: AddressingMode::AbsoluteY,
@@ -2031,6 +2289,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xFC,
         name: "NOP",
         mode: AddressingMode::AbsoluteX,
@@ -2039,6 +2298,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xFD,
         name: "SBC",
         mode: AddressingMode::AbsoluteX,
@@ -2047,6 +2307,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: false,
+        jam: false,
         opcode: 0xFE,
         name: "INC",
         mode: AddressingMode::AbsoluteX,
@@ -2055,6 +2316,7 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
     Instruction {
         illegal: true,
+        jam: false,
         opcode: 0xFF,
         name: "ISC",
         mode: AddressingMode::AbsoluteX,
@@ -2063,260 +2325,2247 @@ pub const INSTRUCTION_LIST: [Instruction; 256] = [
     },
 ];
 
-pub fn get_cycles(opcode: u8) -> u8 {
-    INSTRUCTION_LIST[opcode as usize].cycles
-}
-
-pub fn get_addr_mode(opcode: u8) -> AddressingMode {
-    INSTRUCTION_LIST[opcode as usize].mode
-}
-
-pub fn get_illegal(opcode: u8) -> bool {
-    INSTRUCTION_LIST[opcode as usize].illegal
-}
-
-fn store_result(cpu: &mut Cpu, value: u16) {
-    if cpu.address_mode == AddressingMode::Implied {
-        cpu.a.set((value & 0x00FF) as u8);
-    } else {
-        cpu.write8(cpu.address_absolute, (value & 0x00FF) as u8);
-    }
-}
-
-fn adc(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement ADC
-    return 0;
-}
-
-fn and(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement AND
-    return 0;
-}
-
-fn asl(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement ASL
-    return 0;
-}
-
-fn bcc(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BCC
-    return 0;
-}
-
-fn bcs(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BCS
-    return 0;
-}
-
-fn beq(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BEQ
-    return 0;
-}
-
-fn bit(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BIT
-    return 0;
-}
-
-fn bmi(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BMI
-    return 0;
-}
-
-fn bne(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BNE
-    return 0;
-}
-
-fn bpl(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BPL
-    return 0;
-}
-
-fn brk(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BRK
-    return 0;
-}
-
-fn bvc(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BVC
-    return 0;
-}
-
-fn bvs(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement BVS
-    return 0;
-}
-
-fn clc(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CLC
-    return 0;
-}
-
-fn cld(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CLD
-    return 0;
-}
-
-fn cli(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CLI
-    return 0;
-}
-
-fn clv(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CLV
-    return 0;
-}
-
-fn cmp(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CMP
-    return 0;
-}
-
-fn cpx(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CPX
-    return 0;
-}
-
-fn cpy(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement CPY
-    return 0;
-}
-
-fn dec(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement DEC
-    return 0;
-}
+/// The Rockwell bit manipulation instructions, which replace the NMOS opcodes in
+/// columns 7 (RMB/SMB) and F (BBR/BBS) on the 65C02.
+///
+/// Entries are indexed by `opcode >> 3`, so they alternate between column 7 and
+/// column F of each row of the opcode matrix.
+const ROCKWELL_BIT_INSTRUCTIONS: [Instruction; 32] = [
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x07,
+        name: "RMB0",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x0F,
+        name: "BBR0",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x17,
+        name: "RMB1",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x1F,
+        name: "BBR1",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x27,
+        name: "RMB2",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x2F,
+        name: "BBR2",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x37,
+        name: "RMB3",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x3F,
+        name: "BBR3",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x47,
+        name: "RMB4",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x4F,
+        name: "BBR4",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x57,
+        name: "RMB5",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x5F,
+        name: "BBR5",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x67,
+        name: "RMB6",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x6F,
+        name: "BBR6",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x77,
+        name: "RMB7",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: rmb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x7F,
+        name: "BBR7",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbr,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x87,
+        name: "SMB0",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x8F,
+        name: "BBS0",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x97,
+        name: "SMB1",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x9F,
+        name: "BBS1",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xA7,
+        name: "SMB2",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xAF,
+        name: "BBS2",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xB7,
+        name: "SMB3",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xBF,
+        name: "BBS3",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xC7,
+        name: "SMB4",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xCF,
+        name: "BBS4",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xD7,
+        name: "SMB5",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xDF,
+        name: "BBS5",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xE7,
+        name: "SMB6",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xEF,
+        name: "BBS6",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xF7,
+        name: "SMB7",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: smb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xFF,
+        name: "BBS7",
+        mode: AddressingMode::ZeroPageRelative,
+        cycles: 5,
+        function: bbs,
+    },
+];
 
-fn dex(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement DEX
-    return 0;
-}
+/// The WDC WAI (wait for interrupt) instruction, which replaces opcode 0xCB on the 65C02.
+const WAI_INSTRUCTION: Instruction = Instruction {
+    illegal: false,
+    jam: false,
+    opcode: 0xCB,
+    name: "WAI",
+    mode: AddressingMode::Implied,
+    cycles: 3,
+    function: wai,
+};
 
-fn dey(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement DEY
-    return 0;
-}
+/// The WDC STP (stop) instruction, which replaces opcode 0xDB on the 65C02.
+const STP_INSTRUCTION: Instruction = Instruction {
+    illegal: false,
+    jam: false,
+    opcode: 0xDB,
+    name: "STP",
+    mode: AddressingMode::Implied,
+    cycles: 3,
+    function: stp,
+};
 
-fn eor(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement EOR
-    return 0;
-}
+/// The 65C816 REP (reset status bits) instruction, which replaces opcode 0xC2.
+const REP_INSTRUCTION: Instruction = Instruction {
+    illegal: false,
+    jam: false,
+    opcode: 0xC2,
+    name: "REP",
+    mode: AddressingMode::Immediate,
+    cycles: 3,
+    function: rep,
+};
 
-fn inc(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement INC
-    return 0;
-}
+/// The 65C816 SEP (set status bits) instruction, which replaces opcode 0xE2.
+const SEP_INSTRUCTION: Instruction = Instruction {
+    illegal: false,
+    jam: false,
+    opcode: 0xE2,
+    name: "SEP",
+    mode: AddressingMode::Immediate,
+    cycles: 3,
+    function: sep,
+};
 
-fn inx(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement INX
-    return 0;
-}
+/// The 65C816 XBA (exchange B and A) instruction, which replaces opcode 0xEB.
+const XBA_INSTRUCTION: Instruction = Instruction {
+    illegal: false,
+    jam: false,
+    opcode: 0xEB,
+    name: "XBA",
+    mode: AddressingMode::Implied,
+    cycles: 3,
+    function: xba,
+};
 
-fn iny(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement INY
-    return 0;
-}
+/// The 65C816 XCE (exchange carry and emulation) instruction, which replaces opcode 0xFB.
+const XCE_INSTRUCTION: Instruction = Instruction {
+    illegal: false,
+    jam: false,
+    opcode: 0xFB,
+    name: "XCE",
+    mode: AddressingMode::Implied,
+    cycles: 2,
+    function: xce,
+};
 
-/// Jump to the absolute address specified in the CPU's `address_absolute` field.
-///
-/// # Arguments
-///
-/// * `cpu` - A mutable reference to the `Cpu` struct.
-///
-/// # Returns
-///
-/// The number of CPU cycles taken by the instruction.
-fn jmp(cpu: &mut Cpu) -> u8 {
+/// The instructions that the 65C02 adds or changes relative to the NMOS 6502,
+/// not counting the Rockwell and WDC extensions.
+const CMOS_INSTRUCTIONS: [Instruction; 28] = [
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x04,
+        name: "TSB",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: tsb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x0C,
+        name: "TSB",
+        mode: AddressingMode::Absolute,
+        cycles: 6,
+        function: tsb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x12,
+        name: "ORA",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: ora,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x14,
+        name: "TRB",
+        mode: AddressingMode::ZeroPage,
+        cycles: 5,
+        function: trb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x1A,
+        name: "INC",
+        mode: AddressingMode::Accumulator,
+        cycles: 2,
+        function: inc,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x1C,
+        name: "TRB",
+        mode: AddressingMode::Absolute,
+        cycles: 6,
+        function: trb,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x32,
+        name: "AND",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: and,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x34,
+        name: "BIT",
+        mode: AddressingMode::ZeroPageX,
+        cycles: 4,
+        function: bit,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x3A,
+        name: "DEC",
+        mode: AddressingMode::Accumulator,
+        cycles: 2,
+        function: dec,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x3C,
+        name: "BIT",
+        mode: AddressingMode::AbsoluteX,
+        cycles: 4,
+        function: bit,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x52,
+        name: "EOR",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: eor,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x5A,
+        name: "PHY",
+        mode: AddressingMode::Implied,
+        cycles: 3,
+        function: phy,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x64,
+        name: "STZ",
+        mode: AddressingMode::ZeroPage,
+        cycles: 3,
+        function: stz,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x6C,
+        name: "JMP",
+        mode: AddressingMode::Indirect,
+        cycles: 6,
+        function: jmp,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x72,
+        name: "ADC",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: adc,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x74,
+        name: "STZ",
+        mode: AddressingMode::ZeroPageX,
+        cycles: 4,
+        function: stz,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x7A,
+        name: "PLY",
+        mode: AddressingMode::Implied,
+        cycles: 4,
+        function: ply,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x7C,
+        name: "JMP",
+        mode: AddressingMode::AbsoluteIndexedIndirect,
+        cycles: 6,
+        function: jmp,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x80,
+        name: "BRA",
+        mode: AddressingMode::Relative,
+        cycles: 2,
+        function: bra,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x89,
+        name: "BIT",
+        mode: AddressingMode::Immediate,
+        cycles: 2,
+        function: bit_immediate,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x92,
+        name: "STA",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: sta,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x9C,
+        name: "STZ",
+        mode: AddressingMode::Absolute,
+        cycles: 4,
+        function: stz,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0x9E,
+        name: "STZ",
+        mode: AddressingMode::AbsoluteX,
+        cycles: 5,
+        function: stz,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xB2,
+        name: "LDA",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: lda,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xD2,
+        name: "CMP",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: cmp,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xDA,
+        name: "PHX",
+        mode: AddressingMode::Implied,
+        cycles: 3,
+        function: phx,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xF2,
+        name: "SBC",
+        mode: AddressingMode::ZeroPageIndirect,
+        cycles: 5,
+        function: sbc,
+    },
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode: 0xFA,
+        name: "PLX",
+        mode: AddressingMode::Implied,
+        cycles: 4,
+        function: plx,
+    },
+];
+
+/// The NMOS 6502 instruction set, used by the NMOS 6502 and the Ricoh 2A03.
+static NMOS_6502_INSTRUCTIONS: [Instruction; 256] = INSTRUCTION_LIST;
+
+/// The WDC 65C02 instruction set.
+static WDC_65C02_INSTRUCTIONS: [Instruction; 256] = wdc_65c02_instructions();
+
+/// The WDC 65C816 instruction set, as far as it is emulated.
+static WDC_65C816_INSTRUCTIONS: [Instruction; 256] = wdc_65c816_instructions();
+
+/// Builds a NOP that stands in for an opcode that does nothing useful.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode of the NOP.
+/// * `mode` - The addressing mode, which determines how many operand bytes are skipped.
+/// * `cycles` - The number of cycles the NOP takes.
+///
+/// # Returns
+///
+/// The instruction table entry for the NOP.
+const fn nop_instruction(opcode: u8, mode: AddressingMode, cycles: u8) -> Instruction {
+    Instruction {
+        illegal: false,
+        jam: false,
+        opcode,
+        name: "NOP",
+        mode,
+        cycles,
+        function: nop,
+    }
+}
+
+/// Copies every instruction in `instructions` into `table` at its opcode.
+///
+/// # Arguments
+///
+/// * `table` - The instruction table to update.
+/// * `instructions` - The instructions to place in the table.
+const fn overlay(table: &mut [Instruction; 256], instructions: &[Instruction]) {
+    let mut i = 0;
+    while i < instructions.len() {
+        table[instructions[i].opcode as usize] = instructions[i];
+        i += 1;
+    }
+}
+
+/// Builds the instruction set shared by the CMOS parts.
+///
+/// The NMOS undocumented opcodes become NOPs whose length and timing depend on
+/// their column, and the 65C02 instructions are placed on top.
+///
+/// # Arguments
+///
+/// * `reserved` - Whether the leftover NOPs stand for instructions that aren't
+///   emulated yet, in which case they are marked as undocumented.
+///
+/// # Returns
+///
+/// The CMOS instruction table.
+const fn cmos_instructions(reserved: bool) -> [Instruction; 256] {
+    let mut table = INSTRUCTION_LIST;
+
+    let mut opcode = 0;
+    while opcode < 256 {
+        if table[opcode].illegal {
+            let op = opcode as u8;
+            table[opcode] = match op & 0x0F {
+                0x02 => nop_instruction(op, AddressingMode::Immediate, 2),
+                0x03 | 0x0B => nop_instruction(op, AddressingMode::Implied, 1),
+                0x04 if op == 0x44 => nop_instruction(op, AddressingMode::ZeroPage, 3),
+                0x04 => nop_instruction(op, AddressingMode::ZeroPageX, 4),
+                0x0C if op == 0x5C => nop_instruction(op, AddressingMode::Absolute, 8),
+                0x0C => nop_instruction(op, AddressingMode::Absolute, 4),
+                _ => nop_instruction(op, AddressingMode::Implied, 2),
+            };
+            table[opcode].illegal = reserved;
+        }
+        opcode += 1;
+    }
+
+    overlay(&mut table, &CMOS_INSTRUCTIONS);
+    table
+}
+
+/// Builds the WDC 65C02 instruction set.
+///
+/// # Returns
+///
+/// The 65C02 instruction table.
+const fn wdc_65c02_instructions() -> [Instruction; 256] {
+    let mut table = cmos_instructions(false);
+    overlay(&mut table, &ROCKWELL_BIT_INSTRUCTIONS);
+    overlay(&mut table, &[WAI_INSTRUCTION, STP_INSTRUCTION]);
+    table
+}
+
+/// Builds the WDC 65C816 instruction set.
+///
+/// The opcodes that are NOPs on the 65C02 hold new instructions on the 65C816.
+/// Those that aren't emulated yet are marked as undocumented.
+///
+/// # Returns
+///
+/// The 65C816 instruction table.
+const fn wdc_65c816_instructions() -> [Instruction; 256] {
+    let mut table = cmos_instructions(true);
+    overlay(
+        &mut table,
+        &[WAI_INSTRUCTION, STP_INSTRUCTION, REP_INSTRUCTION, SEP_INSTRUCTION, XBA_INSTRUCTION, XCE_INSTRUCTION],
+    );
+    table
+}
+
+/// Returns the instruction table for a CPU model.
+///
+/// # Arguments
+///
+/// * `model` - The CPU model.
+///
+/// # Returns
+///
+/// The 256-entry instruction table for the model.
+pub fn instruction_table(model: CpuModel) -> &'static [Instruction; 256] {
+    match model {
+        CpuModel::Nmos6502 | CpuModel::Ricoh2A03 => &NMOS_6502_INSTRUCTIONS,
+        CpuModel::Wdc65C02 => &WDC_65C02_INSTRUCTIONS,
+        CpuModel::Wdc65C816 => &WDC_65C816_INSTRUCTIONS,
+    }
+}
+
+/// Looks up the instruction for an opcode in the given model's instruction set.
+///
+/// # Arguments
+///
+/// * `model` - The CPU model whose instruction set is used.
+/// * `opcode` - The opcode to look up.
+///
+/// # Returns
+///
+/// The instruction table entry for the opcode.
+pub fn lookup(model: CpuModel, opcode: u8) -> &'static Instruction {
+    &instruction_table(model)[opcode as usize]
+}
+
+pub fn get_cycles(model: CpuModel, opcode: u8) -> u8 {
+    lookup(model, opcode).cycles
+}
+
+#[cfg(test)]
+pub fn get_illegal(model: CpuModel, opcode: u8) -> bool {
+    lookup(model, opcode).illegal
+}
+
+/// Pulls the processor status from the stack, ignoring the Break flag and forcing Unused on.
+///
+/// In 65C816 native mode those two bits are the X and M flags, so they are
+/// restored like any other flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+fn pull_status(cpu: &mut Cpu) {
+    let status = cpu.pop();
+    if cpu.emulation_mode() {
+        cpu.p.set((status & !StatusFlags::Break.bits()) | StatusFlags::Unused.bits());
+    } else {
+        cpu.p.set(status);
+    }
+}
+
+/// Takes a relative branch if the condition holds.
+///
+/// A taken branch costs one extra cycle, plus another if the target is on a
+/// different page than the instruction that follows the branch.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+/// * `condition` - Whether the branch should be taken.
+fn branch(cpu: &mut Cpu, condition: bool) {
+    if !condition {
+        return;
+    }
+
+    // Taking the branch costs an extra cycle
+    cpu.cycles += 1;
+
+    // Calculate the target relative to the instruction that follows the branch
+    let target = cpu.pc.wrapping_add_signed(cpu.address_relative as i16);
+
+    // Crossing into another page costs one more
+    if (target & 0xFF00) != (cpu.pc.get() & 0xFF00) {
+        cpu.cycles += 1;
+    }
+
+    cpu.pc.set(target);
+}
+
+/// Returns the bit number encoded in the high nibble of a Rockwell bit instruction.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode of the bit instruction.
+fn rockwell_bit(opcode: u8) -> u8 {
+    1 << ((opcode >> 4) & 0x07)
+}
+
+/// Writes the result of a read-modify-write instruction back to memory.
+///
+/// When `rmw_dummy_write` is enabled the unmodified operand is written first,
+/// just like the NMOS 6502 does on the cycle before the real write. Devices
+/// that react to writes (acknowledging interrupts, clearing flags) rely on it.
+/// The 65C02 and 65C816 read the address again on that cycle instead, so on
+/// CMOS models the option makes a dummy read rather than a second write.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+/// * `value` - The modified value to write.
+fn write_back(cpu: &mut Cpu, value: u8) {
+    // Write the original operand back before the modified one, or read it again on CMOS
+    if cpu.rmw_dummy_write {
+        if cpu.model.is_cmos() {
+            cpu.read8(cpu.address_absolute);
+        } else {
+            cpu.write8(cpu.address_absolute, cpu.fetched_data);
+        }
+    }
+
+    cpu.write8(cpu.address_absolute, value);
+}
+
+fn store_result(cpu: &mut Cpu, value: u16) {
+    if cpu.address_mode == AddressingMode::Accumulator {
+        cpu.a.set((value & 0x00FF) as u8);
+    } else {
+        write_back(cpu, (value & 0x00FF) as u8);
+    }
+}
+
+/// Adds an operand and the carry flag to the accumulator.
+///
+/// Decimal mode is ignored on models without it, such as the Ricoh 2A03.
+/// Otherwise, in decimal mode the NMOS behaviour is reproduced: the zero flag is derived
+/// from the binary sum, while the negative and overflow flags are taken from the
+/// intermediate result after the low nibble has been adjusted. CMOS parts take
+/// the negative and zero flags from the BCD result instead, and the 65C02 spends
+/// an extra cycle doing so.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+/// * `value` - The operand to add.
+fn add_with_carry(cpu: &mut Cpu, value: u8) {
+    let a = cpu.a.get() as u16;
+    let m = value as u16;
+    let carry = cpu.get_flag(StatusFlags::Carry) as u16;
+
+    // The binary sum drives the flags in binary mode, and the zero flag in decimal mode
+    let binary = a + m + carry;
+
+    if cpu.get_flag(StatusFlags::DecimalMode) && cpu.model.has_decimal_mode() {
+        // Add the low nibbles and adjust them back into the 0-9 range
+        let mut lo = (a & 0x0F) + (m & 0x0F) + carry;
+        if lo >= 0x0A {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
+        }
+
+        // Add the high nibbles on top of the adjusted low nibble
+        let mut result = (a & 0xF0) + (m & 0xF0) + lo;
+
+        // Negative and overflow come from the result before the high nibble is adjusted
+        cpu.set_flag(StatusFlags::Zero, (binary & 0x00FF) == 0);
+        cpu.set_flag(StatusFlags::Negative, (result & 0x80) != 0);
+        cpu.set_flag(StatusFlags::Overflow, (!(a ^ m) & (a ^ result) & 0x80) != 0);
+
+        // Adjust the high nibble and derive the carry from it
+        if result >= 0xA0 {
+            result += 0x60;
+        }
+        cpu.set_flag(StatusFlags::Carry, result > 0xFF);
+
+        cpu.a.set((result & 0x00FF) as u8);
+        if cpu.model.is_cmos() {
+            cpu.set_zn_flags(cpu.a.get());
+        }
+        if cpu.model == CpuModel::Wdc65C02 {
+            cpu.cycles += 1;
+        }
+    } else {
+        // Carry out of bit 7 and signed overflow of the sum
+        cpu.set_flag(StatusFlags::Carry, binary > 0xFF);
+        cpu.set_flag(StatusFlags::Overflow, (!(a ^ m) & (a ^ binary) & 0x80) != 0);
+
+        cpu.a.set((binary & 0x00FF) as u8);
+        cpu.set_zn_flags(cpu.a.get());
+    }
+}
+
+/// Subtracts an operand and the inverted carry flag from the accumulator.
+///
+/// The carry and overflow flags are derived from the binary difference, and
+/// so are the negative and zero flags on the NMOS 6502, even in decimal mode.
+/// CMOS parts correct the difference differently in decimal mode, take the
+/// negative and zero flags from the BCD result, and on the 65C02 spend an extra
+/// cycle doing so. The accumulator is only BCD corrected on models that have
+/// decimal mode.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+/// * `value` - The operand to subtract.
+fn subtract_with_borrow(cpu: &mut Cpu, value: u8) {
+    let a = cpu.a.get() as i16;
+    let m = value as i16;
+    let borrow = 1 - cpu.get_flag(StatusFlags::Carry) as i16;
+
+    // The binary difference drives every flag
+    let binary = a - m - borrow;
+    cpu.set_flag(StatusFlags::Carry, binary >= 0);
+    cpu.set_flag(StatusFlags::Overflow, ((a ^ m) & (a ^ binary) & 0x80) != 0);
+    cpu.set_zn_flags((binary & 0x00FF) as u8);
+
+    if cpu.get_flag(StatusFlags::DecimalMode) && cpu.model.has_decimal_mode() {
+        // Subtract the low nibbles and adjust them back into the 0-9 range
+        let mut lo = (a & 0x0F) - (m & 0x0F) - borrow;
+
+        let result = if cpu.model.is_cmos() {
+            // The whole difference is adjusted, then the low nibble if it borrowed
+            let mut result = binary;
+            if result < 0 {
+                result -= 0x60;
+            }
+            if lo < 0 {
+                result -= 0x06;
+            }
+            result
+        } else {
+            if lo < 0 {
+                lo = ((lo - 0x06) & 0x0F) - 0x10;
+            }
+
+            // Subtract the high nibbles on top of the adjusted low nibble
+            let mut result = (a & 0xF0) - (m & 0xF0) + lo;
+            if result < 0 {
+                result -= 0x60;
+            }
+            result
+        };
+
+        cpu.a.set((result & 0x00FF) as u8);
+        if cpu.model.is_cmos() {
+            cpu.set_zn_flags(cpu.a.get());
+        }
+        if cpu.model == CpuModel::Wdc65C02 {
+            cpu.cycles += 1;
+        }
+    } else {
+        cpu.a.set((binary & 0x00FF) as u8);
+    }
+}
+
+/// Adds a value in memory and the carry flag to the accumulator.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn adc(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and add it to the accumulator
+    let value = cpu.fetch();
+    add_with_carry(cpu, value);
+
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
+}
+
+/// Performs a bitwise AND between the accumulator and a value in memory.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn and(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and AND it into the accumulator
+    let value = cpu.a.get() & cpu.fetch();
+    cpu.a.set(value);
+
+    // Set the zero and negative flags based on the new value of the accumulator
+    cpu.set_zn_flags(value);
+
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
+}
+
+/// Arithmetic shift left of the accumulator or a memory operand.
+///
+/// Bit 7 is shifted into the carry flag and bit 0 is cleared. The result is
+/// written back to wherever the operand came from via `store_result`.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn asl(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and widen it so the shifted-out bit lands in bit 8
+    let temp = (cpu.fetch() as u16) << 1;
+
+    // Bit 8 of the result becomes the new carry
+    cpu.set_flag(StatusFlags::Carry, (temp & 0xFF00) > 0);
+
+    // Set the zero and negative flags based on the low byte of the result
+    cpu.set_zn_flags((temp & 0x00FF) as u8);
+
+    // Write the result back to the accumulator or memory
+    store_result(cpu, temp);
+
+    0
+}
+
+/// Branches to the relative address if the carry flag is clear.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bcc(cpu: &mut Cpu) -> u8 {
+    branch(cpu, !cpu.get_flag(StatusFlags::Carry));
+    0
+}
+
+/// Branches to the relative address if the carry flag is set.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bcs(cpu: &mut Cpu) -> u8 {
+    branch(cpu, cpu.get_flag(StatusFlags::Carry));
+    0
+}
+
+/// Branches to the relative address if the zero flag is set.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn beq(cpu: &mut Cpu) -> u8 {
+    branch(cpu, cpu.get_flag(StatusFlags::Zero));
+    0
+}
+
+/// Tests bits in memory against the accumulator.
+///
+/// The zero flag is set from the AND of the accumulator and the operand, while
+/// the negative and overflow flags are copied straight from bits 7 and 6 of the
+/// operand. The accumulator itself is not modified.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bit(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand from memory
+    let value = cpu.fetch();
+
+    // The zero flag reflects the masked value
+    cpu.set_flag(StatusFlags::Zero, (cpu.a.get() & value) == 0);
+
+    // Bits 7 and 6 of the operand are copied into the negative and overflow flags
+    cpu.set_flag(StatusFlags::Negative, (value & 0x80) != 0);
+    cpu.set_flag(StatusFlags::Overflow, (value & 0x40) != 0);
+
+    // The 65C02's BIT abs,X takes an extra cycle when a page boundary is crossed
+    1
+}
+
+/// Tests bits in an immediate operand against the accumulator (65C02).
+///
+/// Unlike the other forms of BIT, only the zero flag is affected.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bit_immediate(cpu: &mut Cpu) -> u8 {
+    let value = cpu.fetch();
+    cpu.set_flag(StatusFlags::Zero, (cpu.a.get() & value) == 0);
+    0
+}
+
+/// Branches to the relative address if the negative flag is set.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bmi(cpu: &mut Cpu) -> u8 {
+    branch(cpu, cpu.get_flag(StatusFlags::Negative));
+    0
+}
+
+/// Branches to the relative address if the zero flag is clear.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bne(cpu: &mut Cpu) -> u8 {
+    branch(cpu, !cpu.get_flag(StatusFlags::Zero));
+    0
+}
+
+/// Branches to the relative address if the negative flag is clear.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bpl(cpu: &mut Cpu) -> u8 {
+    branch(cpu, !cpu.get_flag(StatusFlags::Negative));
+    0
+}
+
+/// Forces a software interrupt through the IRQ vector.
+///
+/// The byte following the opcode is skipped (it was consumed by the immediate
+/// addressing mode), and the status pushed to the stack has the Break flag set.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn brk(cpu: &mut Cpu) -> u8 {
+    // Push the return address and status, then jump through the IRQ vector
+    cpu.do_interrupt(IRQ_VECTOR, true);
+
+    0
+}
+
+/// Branches to the relative address if the overflow flag is clear.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bvc(cpu: &mut Cpu) -> u8 {
+    branch(cpu, !cpu.get_flag(StatusFlags::Overflow));
+    0
+}
+
+/// Branches to the relative address if the overflow flag is set.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bvs(cpu: &mut Cpu) -> u8 {
+    branch(cpu, cpu.get_flag(StatusFlags::Overflow));
+    0
+}
+
+/// Clears the carry flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn clc(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::Carry, false);
+    0
+}
+
+/// Clears the decimal mode flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn cld(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::DecimalMode, false);
+    0
+}
+
+/// Clears the interrupt disable flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn cli(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::InterruptDisable, false);
+    0
+}
+
+/// Clears the overflow flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn clv(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::Overflow, false);
+    0
+}
+
+/// Compares a register against an operand.
+///
+/// The operand is subtracted from the register without storing the result.
+/// Carry is set if the register is greater than or equal to the operand, and
+/// the zero and negative flags reflect the difference.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+/// * `register` - The value of the register being compared.
+/// * `value` - The operand to compare against.
+fn compare(cpu: &mut Cpu, register: u8, value: u8) {
+    // Carry is set when no borrow is required
+    cpu.set_flag(StatusFlags::Carry, register >= value);
+
+    // Set the zero and negative flags based on the difference
+    cpu.set_zn_flags(register.wrapping_sub(value));
+}
+
+/// Compares the accumulator with a value in memory.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn cmp(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and compare the accumulator with it
+    let value = cpu.fetch();
+    compare(cpu, cpu.a.get(), value);
+
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
+}
+
+/// Compares the X register with a value in memory.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn cpx(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and compare the X register with it
+    let value = cpu.fetch();
+    compare(cpu, cpu.x.get(), value);
+
+    0
+}
+
+/// Compares the Y register with a value in memory.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn cpy(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and compare the Y register with it
+    let value = cpu.fetch();
+    compare(cpu, cpu.y.get(), value);
+
+    0
+}
+
+/// Decrements the accumulator or a value in memory by one, wrapping from 0x00 to 0xFF.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn dec(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and decrement it
+    let value = cpu.fetch().wrapping_sub(1);
+
+    // Write the decremented value back to the accumulator or memory
+    store_result(cpu, value as u16);
+
+    // Set the zero and negative flags based on the result
+    cpu.set_zn_flags(value);
+
+    0
+}
+
+/// Decrements the X register by one, wrapping around on overflow.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn dex(cpu: &mut Cpu) -> u8 {
+    // Decrement the X register
+    cpu.x.sub_assign(1);
+
+    // Set the zero and negative flags based on the new value of the X register
+    cpu.set_zn_flags(cpu.x.get());
+
+    0
+}
+
+/// Decrements the Y register by one, wrapping around on overflow.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn dey(cpu: &mut Cpu) -> u8 {
+    // Decrement the Y register
+    cpu.y.sub_assign(1);
+
+    // Set the zero and negative flags based on the new value of the Y register
+    cpu.set_zn_flags(cpu.y.get());
+
+    0
+}
+
+/// Performs a bitwise exclusive OR between the accumulator and a value in memory.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn eor(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and exclusive OR it into the accumulator
+    let value = cpu.a.get() ^ cpu.fetch();
+    cpu.a.set(value);
+
+    // Set the zero and negative flags based on the new value of the accumulator
+    cpu.set_zn_flags(value);
+
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
+}
+
+/// Increments a value in memory by one, wrapping from 0xFF to 0x00.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn inc(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and increment it
+    let value = cpu.fetch().wrapping_add(1);
+
+    // Write the incremented value back to the accumulator or memory
+    store_result(cpu, value as u16);
+
+    // Set the zero and negative flags based on the result
+    cpu.set_zn_flags(value);
+
+    0
+}
+
+/// Increments the X register by one, wrapping around on overflow.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn inx(cpu: &mut Cpu) -> u8 {
+    // Increment the X register
+    cpu.x.add_assign(1);
+
+    // Set the zero and negative flags based on the new value of the X register
+    cpu.set_zn_flags(cpu.x.get());
+
+    0
+}
+
+/// Increments the Y register by one, wrapping around on overflow.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn iny(cpu: &mut Cpu) -> u8 {
+    // Increment the Y register
+    cpu.y.add_assign(1);
+
+    // Set the zero and negative flags based on the new value of the Y register
+    cpu.set_zn_flags(cpu.y.get());
+
+    0
+}
+
+/// Jump to the absolute address specified in the CPU's `address_absolute` field.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of CPU cycles taken by the instruction.
+fn jmp(cpu: &mut Cpu) -> u8 {
     // Set the program counter to the absolute address
     cpu.pc.set(cpu.address_absolute);
 
-    // The instruction takes 0 CPU cycles
+    // The instruction takes 0 CPU cycles
+    0
+}
+
+/// Jumps to a subroutine, pushing the address of the last byte of the JSR instruction.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn jsr(cpu: &mut Cpu) -> u8 {
+    // The program counter has moved past the operand; RTS adds the missing one back
+    let return_address = cpu.pc.get();
+    cpu.push_word(return_address.wrapping_sub(1));
+
+    // Jump to the subroutine
+    cpu.pc.set(cpu.address_absolute);
+    cpu.enter_call(CallKind::Subroutine, return_address);
+
+    0
+}
+
+/// Loads the value from memory into the accumulator register.
+///
+/// # Arguments
+///
+/// * `cpu` - The mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn lda(cpu: &mut Cpu) -> u8 {
+    // Fetch the data from memory
+    cpu.fetch();
+
+    // Load the fetched data into the accumulator register
+    cpu.a.set(cpu.fetched_data);
+
+    // Set the zero and negative flags based on the value in the accumulator register
+    cpu.set_zn_flags(cpu.a.get());
+
+    1 // Return the number of extra cycles required to execute the instruction
+}
+
+/// Loads the value from memory into the X register.
+///
+/// # Arguments
+///
+/// * `cpu` - The mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn ldx(cpu: &mut Cpu) -> u8 {
+    // Fetch the data from memory
+    cpu.fetch();
+
+    // Load the fetched data into the X register
+    cpu.x.set(cpu.fetched_data);
+
+    // Set the zero and negative flags based on the value in the X register
+    cpu.set_zn_flags(cpu.x.get());
+
+    1 // Return the number of extra cycles required to execute the instruction
+}
+
+/// Loads the value from memory into the Y register.
+///
+/// # Arguments
+///
+/// * `cpu` - The mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn ldy(cpu: &mut Cpu) -> u8 {
+    // Fetch the data from memory
+    cpu.fetch();
+
+    // Load the fetched data into the Y register
+    cpu.y.set(cpu.fetched_data);
+
+    // Set the zero and negative flags based on the value in the Y register
+    cpu.set_zn_flags(cpu.y.get());
+
+    1 // Return the number of extra cycles required to execute the instruction
+}
+
+/// Logical shift right of the accumulator or a memory operand.
+///
+/// Bit 0 is shifted into the carry flag and bit 7 is cleared, so the result
+/// is never negative.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn lsr(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand
+    let value = cpu.fetch();
+
+    // The bit shifted out of position 0 becomes the new carry
+    cpu.set_flag(StatusFlags::Carry, (value & 0x01) > 0);
+
+    // Shift right, bringing a zero into bit 7
+    let temp = (value >> 1) as u16;
+
+    // Set the zero and negative flags based on the result
+    cpu.set_zn_flags(temp as u8);
+
+    // Write the result back to the accumulator or memory
+    store_result(cpu, temp);
+
+    0
+}
+
+/// Does nothing.
+///
+/// # Arguments
+///
+/// * `_cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction. The NMOS
+/// undocumented NOPs that index an absolute address take one when the index
+/// crosses a page, like the loads they are decoded as.
+fn nop(_cpu: &mut Cpu) -> u8 {
+    1
+}
+
+/// Performs a bitwise inclusive OR between the accumulator and a value in memory.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn ora(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and OR it into the accumulator
+    let value = cpu.a.get() | cpu.fetch();
+    cpu.a.set(value);
+
+    // Set the zero and negative flags based on the new value of the accumulator
+    cpu.set_zn_flags(value);
+
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
+}
+
+/// Pushes the accumulator onto the stack.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn pha(cpu: &mut Cpu) -> u8 {
+    cpu.push(cpu.a.get());
+    0
+}
+
+/// Pushes the processor status onto the stack.
+///
+/// The pushed copy always has the Break and Unused flags set, except in 65C816
+/// native mode where those bits hold the X and M flags and are pushed as they are.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn php(cpu: &mut Cpu) -> u8 {
+    if cpu.emulation_mode() {
+        cpu.push(cpu.p.get() | StatusFlags::Break.bits() | StatusFlags::Unused.bits());
+    } else {
+        cpu.push(cpu.p.get());
+    }
+    0
+}
+
+/// Pulls the accumulator from the stack.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn pla(cpu: &mut Cpu) -> u8 {
+    let value = cpu.pop();
+    cpu.a.set(value);
+    cpu.set_zn_flags(value);
+    0
+}
+
+/// Pulls the processor status from the stack.
+///
+/// The Break flag doesn't exist in the register, so it is discarded, and the
+/// Unused flag always reads back as set.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn plp(cpu: &mut Cpu) -> u8 {
+    pull_status(cpu);
+    0
+}
+
+/// Rotate the accumulator or a memory operand left by one bit through carry.
+///
+/// The old carry flag is shifted into bit 0 and bit 7 is shifted into the
+/// carry flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rol(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand, shift it left and bring the carry in at bit 0
+    let mut temp = (cpu.fetch() as u16) << 1;
+    if cpu.get_flag(StatusFlags::Carry) {
+        temp |= 0x01;
+    }
+
+    // Bit 8 of the result becomes the new carry
+    cpu.set_flag(StatusFlags::Carry, (temp & 0xFF00) > 0);
+
+    // Set the zero and negative flags based on the low byte of the result
+    cpu.set_zn_flags((temp & 0x00FF) as u8);
+
+    // Write the result back to the accumulator or memory
+    store_result(cpu, temp);
+
+    0
+}
+
+/// Rotate the accumulator or a memory operand right by one bit through carry.
+///
+/// The old carry flag is shifted into bit 7 and bit 0 is shifted into the
+/// carry flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn ror(cpu: &mut Cpu) -> u8 {
+    // Fetch the value and convert it to a 16-bit unsigned integer
+    let mut temp = cpu.fetch() as u16;
+
+    // If the carry flag is set, set the 9th bit of the temporary value to 1
+    if cpu.get_flag(StatusFlags::Carry) {
+        temp |= 0x100;
+    }
+
+    // Set the carry flag to the value of the least significant bit of the temporary value
+    cpu.set_flag(StatusFlags::Carry, (temp & 0x01) > 0);
+
+    // Perform the right rotation on the temporary value
+    temp >>= 1;
+
+    // Set the zero and negative flags based on the value of the temporary value
+    cpu.set_zn_flags(temp as u8);
+
+    // Write the result back to the accumulator or memory
+    store_result(cpu, temp);
+
+    0
+}
+
+/// Returns from an interrupt handler by pulling the status and program counter from the stack.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rti(cpu: &mut Cpu) -> u8 {
+    // Restore the status register, then the return address
+    pull_status(cpu);
+    let address = cpu.pop_word();
+    cpu.pc.set(address);
+    cpu.return_from_call();
+
+    0
+}
+
+/// Returns from a subroutine by pulling the program counter from the stack.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rts(cpu: &mut Cpu) -> u8 {
+    // JSR pushed the address of its last byte, so continue from the one after it
+    let address = cpu.pop_word();
+    cpu.pc.set(address.wrapping_add(1));
+    cpu.return_from_call();
+
     0
 }
 
-fn jsr(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement JSR
-    return 0;
+/// Subtracts a value in memory and the inverted carry flag from the accumulator.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn sbc(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and subtract it from the accumulator
+    let value = cpu.fetch();
+    subtract_with_borrow(cpu, value);
+
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
 }
 
-/// Loads the value from memory into the accumulator register.
+/// Sets the carry flag.
 ///
 /// # Arguments
 ///
-/// * `cpu` - The mutable reference to the `Cpu` struct.
+/// * `cpu` - A mutable reference to the `Cpu` struct.
 ///
 /// # Returns
 ///
 /// The number of extra cycles required to execute the instruction.
-fn lda(cpu: &mut Cpu) -> u8 {
-    // Fetch the data from memory
-    cpu.fetch();
+fn sec(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::Carry, true);
+    0
+}
 
-    // Load the fetched data into the accumulator register
-    cpu.a.set(cpu.fetched_data);
+/// Sets the decimal mode flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn sed(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::DecimalMode, true);
+    0
+}
 
-    // Set the zero and negative flags based on the value in the accumulator register
+/// Sets the interrupt disable flag.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn sei(cpu: &mut Cpu) -> u8 {
+    cpu.set_flag(StatusFlags::InterruptDisable, true);
+    0
+}
+
+/// Store the value of the accumulator in memory at the absolute address specified by `cpu.address_absolute`.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to a `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of cycles used by the instruction.
+fn sta(cpu: &mut Cpu) -> u8 {
+    // Write the value of the accumulator to memory
+    cpu.write8(cpu.address_absolute, cpu.a.get());
+
+    // Return the number of cycles used by the instruction
+    0
+}
+
+/// Store the value of the X register in memory at the absolute address specified by `cpu.address_absolute`.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to a `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of cycles used by the instruction.
+fn stx(cpu: &mut Cpu) -> u8 {
+    // Write the value of the X register to memory
+    cpu.write8(cpu.address_absolute, cpu.x.get());
+
+    // Return the number of cycles used by the instruction
+    0
+}
+
+/// Store the value of the Y register in memory at the absolute address specified by `cpu.address_absolute`.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to a `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of cycles used by the instruction.
+fn sty(cpu: &mut Cpu) -> u8 {
+    // Write the value of the Y register to memory
+    cpu.write8(cpu.address_absolute, cpu.y.get());
+
+    // Return the number of cycles used by the instruction
+    0
+}
+
+/// Transfers the accumulator to the X register.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn tax(cpu: &mut Cpu) -> u8 {
+    // Copy the accumulator into the X register
+    cpu.x.set(cpu.a.get());
+
+    // Set the zero and negative flags based on the new value of the X register
+    cpu.set_zn_flags(cpu.x.get());
+
+    0
+}
+
+/// Transfers the accumulator to the Y register.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn tay(cpu: &mut Cpu) -> u8 {
+    // Copy the accumulator into the Y register
+    cpu.y.set(cpu.a.get());
+
+    // Set the zero and negative flags based on the new value of the Y register
+    cpu.set_zn_flags(cpu.y.get());
+
+    0
+}
+
+/// Transfers the stack pointer to the X register.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn tsx(cpu: &mut Cpu) -> u8 {
+    // Copy the stack pointer into the X register
+    cpu.x.set(cpu.sp.get());
+
+    // Set the zero and negative flags based on the new value of the X register
+    cpu.set_zn_flags(cpu.x.get());
+
+    0
+}
+
+/// Transfers the X register to the accumulator.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn txa(cpu: &mut Cpu) -> u8 {
+    // Copy the X register into the accumulator
+    cpu.a.set(cpu.x.get());
+
+    // Set the zero and negative flags based on the new value of the accumulator
     cpu.set_zn_flags(cpu.a.get());
 
-    1 // Return the number of extra cycles required to execute the instruction
+    0
 }
 
-fn ldx(cpu: &mut Cpu) -> u8 {
-    // TODO: implement LDX
-    return 0;
+/// Transfers the X register to the stack pointer.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn txs(cpu: &mut Cpu) -> u8 {
+    // Copy the X register into the stack pointer. Unlike the other transfers,
+    // TXS leaves the flags untouched.
+    cpu.sp.set(cpu.x.get());
+
+    0
 }
 
-fn ldy(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement LDY
-    return 0;
+/// Transfers the Y register to the accumulator.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn tya(cpu: &mut Cpu) -> u8 {
+    // Copy the Y register into the accumulator
+    cpu.a.set(cpu.y.get());
+
+    // Set the zero and negative flags based on the new value of the accumulator
+    cpu.set_zn_flags(cpu.a.get());
+
+    0
 }
 
-fn lsr(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement LSR
-    return 0;
+/** Illegal instructions */
+fn ahx(_cpu: &mut Cpu) -> u8 {
+    // Unstable, so left unemulated: stores A & X & (the high byte of the address + 1), but whether the last AND
+    // happens, and what it does on a page crossing, differs between chips
+    0
 }
 
-fn nop(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement NOP
-    return 0;
+/// ANDs an immediate value into the accumulator and then shifts it right (AND + LSR A).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn alr(cpu: &mut Cpu) -> u8 {
+    // AND the operand into the accumulator
+    let value = cpu.a.get() & cpu.fetch();
+
+    // Shift the result right, moving bit 0 into the carry
+    cpu.set_flag(StatusFlags::Carry, (value & 0x01) != 0);
+    cpu.a.set(value >> 1);
+    cpu.set_zn_flags(cpu.a.get());
+
+    0
 }
 
-fn ora(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement ORA
-    return 0;
+/// ANDs an immediate value into the accumulator and copies the negative flag into the carry.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn anc(cpu: &mut Cpu) -> u8 {
+    // AND the operand into the accumulator
+    let result = cpu.a.get() & cpu.fetch();
+    cpu.a.set(result);
+    cpu.set_zn_flags(result);
+
+    // Bit 7 of the result becomes the carry, as if it had been shifted out
+    cpu.set_flag(StatusFlags::Carry, (result & 0x80) != 0);
+
+    0
 }
 
-fn pha(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement PHA
-    return 0;
+/// ANDs an immediate value into the accumulator and then rotates it right (AND + ROR A).
+///
+/// The carry and overflow flags come from the adder rather than the rotate:
+/// in binary mode the carry is bit 6 of the result and overflow is bit 6
+/// exclusive-ORed with bit 5. In decimal mode on the NMOS 6502 each nibble of
+/// the result is then BCD corrected, and the carry comes from correcting the
+/// high nibble.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn arr(cpu: &mut Cpu) -> u8 {
+    // AND the operand into the accumulator and rotate the result right through the carry
+    let value = cpu.a.get() & cpu.fetch();
+    let mut result = (value >> 1) | ((cpu.get_flag(StatusFlags::Carry) as u8) << 7);
+
+    if cpu.get_flag(StatusFlags::DecimalMode) && cpu.model.has_decimal_mode() {
+        // Negative and zero come from the rotated value, and overflow from bit 6 changing in the rotate
+        cpu.set_zn_flags(result);
+        cpu.set_flag(StatusFlags::Overflow, ((value ^ result) & 0x40) != 0);
+
+        // Correct the low nibble if it was over 5 before the rotate
+        if (value & 0x0F) + (value & 0x01) > 0x05 {
+            result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
+        }
+
+        // Correct the high nibble the same way, which is also where the carry comes from
+        let carry = (value as u16 & 0xF0) + (value as u16 & 0x10) > 0x50;
+        if carry {
+            result = result.wrapping_add(0x60);
+        }
+        cpu.set_flag(StatusFlags::Carry, carry);
+    } else {
+        cpu.set_zn_flags(result);
+        cpu.set_flag(StatusFlags::Carry, (result & 0x40) != 0);
+        cpu.set_flag(StatusFlags::Overflow, (((result >> 6) ^ (result >> 5)) & 0x01) != 0);
+    }
+
+    cpu.a.set(result);
+
+    0
 }
 
-fn php(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement PHP
-    return 0;
+/// Subtracts an immediate value from the accumulator ANDed with the X register,
+/// leaving the difference in X (also known as SBX).
+///
+/// The carry, negative and zero flags are set as CMP would set them, and the
+/// overflow and decimal flags are ignored.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn axs(cpu: &mut Cpu) -> u8 {
+    // Compare A & X with the operand, then keep the difference in X
+    let value = cpu.fetch();
+    let register = cpu.a.get() & cpu.x.get();
+    compare(cpu, register, value);
+    cpu.x.set(register.wrapping_sub(value));
+
+    0
 }
 
-fn pla(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement PLA
-    return 0;
+/// Decrements a value in memory and then compares the accumulator with it (DEC + CMP).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn dcp(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand, decrement it and write it back
+    let value = cpu.fetch().wrapping_sub(1);
+    write_back(cpu, value);
+
+    // Compare the accumulator against the decremented value
+    compare(cpu, cpu.a.get(), value);
+
+    0
 }
 
-fn plp(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement PLP
-    return 0;
+/// Increments a value in memory and then subtracts it from the accumulator (INC + SBC).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn isc(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand, increment it and write it back
+    let value = cpu.fetch().wrapping_add(1);
+    write_back(cpu, value);
+
+    // Subtract the incremented value from the accumulator
+    subtract_with_borrow(cpu, value);
+
+    0
 }
 
-fn rol(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement ROL
-    return 0;
+/// Halts the CPU (also known as JAM).
+///
+/// The program counter is left pointing at the KIL opcode and the CPU stops
+/// executing until it is reset.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn kil(cpu: &mut Cpu) -> u8 {
+    // Point the program counter back at the KIL opcode
+    cpu.pc.set(cpu.pc.get().wrapping_sub(1));
+
+    // Latch the jammed state
+    cpu.state = CpuState::Jammed;
+
+    0
 }
 
-/// Rotate the value in the A register right by one bit.
-///
-/// This function performs a right rotation on the value in the A register.
-/// The carry flag is set to the value of the least significant bit of the A register.
-/// The zero and negative flags are set based on the result.
+/// ANDs a value in memory with the stack pointer and loads the result into the
+/// accumulator, the X register and the stack pointer.
 ///
 /// # Arguments
 ///
@@ -2324,219 +4573,481 @@ fn rol(_cpu: &mut Cpu) -> u8 {
 ///
 /// # Returns
 ///
-/// The function does not return anything, but it modifies the `Cpu` struct.
-fn ror_a(cpu: &mut Cpu) -> u8 {
-    // Fetch the value from memory and convert it to a 16-bit unsigned integer
-    let mut temp = cpu.fetch() as u16;
-
-    // If the carry flag is set, set the 9th bit of the temporary value to 1
-    if cpu.get_flag(StatusFlags::Carry) {
-        temp |= 0x100;
-    }
-
-    // Set the carry flag to the value of the least significant bit of the temporary value
-    cpu.set_flag(StatusFlags::Carry, (temp & 0x01) > 0);
-
-    // Perform the right rotation on the temporary value
-    temp >>= 1;
+/// The number of extra cycles required to execute the instruction.
+fn las(cpu: &mut Cpu) -> u8 {
+    // AND the operand with the stack pointer and load the result into all three registers
+    let value = cpu.fetch() & cpu.sp.get();
+    cpu.a.set(value);
+    cpu.x.set(value);
+    cpu.sp.set(value);
 
-    // Clear the 9th bit of the temporary value
-    temp &= 0xFF;
+    // Set the zero and negative flags based on the loaded value
+    cpu.set_zn_flags(value);
 
-    // Set the zero and negative flags based on the value of the temporary value
-    cpu.set_zn_flags(temp as u8);
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
+}
 
-    // Set the value of the A register to the value of the temporary value
-    cpu.a.set(temp as u8);
+/// Loads a value from memory into both the accumulator and the X register (LDA + LDX).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn lax(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and load it into both registers
+    let value = cpu.fetch();
+    cpu.a.set(value);
+    cpu.x.set(value);
 
-    // Return 0 as the function does not return anything
-    0
-}
+    // Set the zero and negative flags based on the loaded value
+    cpu.set_zn_flags(value);
 
-fn ror(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement ROR
-    return 0;
+    // Indexed modes take an extra cycle when a page boundary is crossed
+    1
 }
 
-fn rti(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement RTI
-    return 0;
-}
+/// Rotates a value in memory left and then ANDs it into the accumulator (ROL + AND).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rla(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and rotate it left through the carry
+    let value = cpu.fetch();
+    let rotated = (value << 1) | cpu.get_flag(StatusFlags::Carry) as u8;
+    cpu.set_flag(StatusFlags::Carry, (value & 0x80) != 0);
+    write_back(cpu, rotated);
 
-fn rts(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement RTS
-    return 0;
-}
+    // AND the rotated value into the accumulator
+    let result = cpu.a.get() & rotated;
+    cpu.a.set(result);
+    cpu.set_zn_flags(result);
 
-fn sbc(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement SBC
-    return 0;
+    0
 }
 
-fn sec(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement SEC
-    return 0;
-}
+/// Rotates a value in memory right and then adds it to the accumulator (ROR + ADC).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rra(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and rotate it right through the carry
+    let value = cpu.fetch();
+    let rotated = (value >> 1) | ((cpu.get_flag(StatusFlags::Carry) as u8) << 7);
+    cpu.set_flag(StatusFlags::Carry, (value & 0x01) != 0);
+    write_back(cpu, rotated);
 
-fn sed(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement SED
-    return 0;
-}
+    // Add the rotated value to the accumulator, using the carry produced by the rotate
+    add_with_carry(cpu, rotated);
 
-fn sei(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement SEI
-    return 0;
+    0
 }
 
-/// Store the value of the X register in memory at the absolute address specified by `cpu.address_absolute`.
+/// Stores the bitwise AND of the accumulator and the X register in memory.
+///
+/// No flags are affected.
 ///
 /// # Arguments
 ///
-/// * `cpu` - A mutable reference to a `Cpu` struct.
+/// * `cpu` - A mutable reference to the `Cpu` struct.
 ///
 /// # Returns
 ///
-/// The number of cycles used by the instruction.
-fn sta(cpu: &mut Cpu) -> u8 {
-    // Write the value of the X register to memory
-    cpu.write8(cpu.address_absolute, cpu.a.get());
+/// The number of extra cycles required to execute the instruction.
+fn sax(cpu: &mut Cpu) -> u8 {
+    // Write A & X to memory
+    cpu.write8(cpu.address_absolute, cpu.a.get() & cpu.x.get());
 
-    // Return the number of cycles used by the instruction
     0
 }
 
-fn stx(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement STX
-    return 0;
+fn shx(_cpu: &mut Cpu) -> u8 {
+    // Unstable, so left unemulated: stores X & (the high byte of the address + 1), and crossing a page corrupts
+    // the address differently between chips
+    0
 }
 
-fn sty(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement STY
-    return 0;
+fn shy(_cpu: &mut Cpu) -> u8 {
+    // Unstable, so left unemulated: stores Y & (the high byte of the address + 1), and crossing a page corrupts
+    // the address differently between chips
+    0
 }
 
-fn tax(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement TAX
-    return 0;
-}
+/// Shifts a value in memory left and then ORs it into the accumulator (ASL + ORA).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn slo(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and shift it left, moving bit 7 into the carry
+    let value = cpu.fetch();
+    let shifted = value << 1;
+    cpu.set_flag(StatusFlags::Carry, (value & 0x80) != 0);
+    write_back(cpu, shifted);
 
-fn tay(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement TAY
-    return 0;
-}
+    // OR the shifted value into the accumulator
+    let result = cpu.a.get() | shifted;
+    cpu.a.set(result);
+    cpu.set_zn_flags(result);
 
-fn tsx(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement TSX
-    return 0;
+    0
 }
 
-fn txa(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement TXA
-    return 0;
-}
+/// Shifts a value in memory right and then EORs it into the accumulator (LSR + EOR).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn sre(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and shift it right, moving bit 0 into the carry
+    let value = cpu.fetch();
+    let shifted = value >> 1;
+    cpu.set_flag(StatusFlags::Carry, (value & 0x01) != 0);
+    write_back(cpu, shifted);
 
-fn txs(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement TXS
-    return 0;
+    // EOR the shifted value into the accumulator
+    let result = cpu.a.get() ^ shifted;
+    cpu.a.set(result);
+    cpu.set_zn_flags(result);
+
+    0
 }
 
-fn tya(_cpu: &mut Cpu) -> u8 {
-    // TODO: implement TYA
-    return 0;
+fn tas(_cpu: &mut Cpu) -> u8 {
+    // Unstable, so left unemulated: sets S to A & X and stores S & (the high byte of the address + 1), with the
+    // same chip-dependent behaviour as AHX
+    0
 }
 
-/** Illegal instructions */
-fn ahx(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add AHX implementation
+fn xaa(_cpu: &mut Cpu) -> u8 {
+    // Unstable, so left unemulated: the result depends on a "magic" constant that varies between chips and
+    // with temperature
     0
 }
 
-fn alr(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add ALR implementation
+// 65C02 instructions
+
+/// Branches to the relative address unconditionally (BRA).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bra(cpu: &mut Cpu) -> u8 {
+    branch(cpu, true);
     0
 }
 
-fn anc(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add ANC implementation
+/// Stores zero in memory (STZ).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn stz(cpu: &mut Cpu) -> u8 {
+    cpu.write8(cpu.address_absolute, 0x00);
     0
 }
 
-fn arr(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add ARR implementation
+/// Sets the bits of a memory operand that are set in the accumulator (TSB).
+///
+/// The zero flag is set from the AND of the accumulator and the original operand.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn tsb(cpu: &mut Cpu) -> u8 {
+    let value = cpu.fetch();
+    cpu.set_flag(StatusFlags::Zero, (cpu.a.get() & value) == 0);
+    write_back(cpu, value | cpu.a.get());
     0
 }
 
-fn axs(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add AXS implementation
+/// Clears the bits of a memory operand that are set in the accumulator (TRB).
+///
+/// The zero flag is set from the AND of the accumulator and the original operand.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn trb(cpu: &mut Cpu) -> u8 {
+    let value = cpu.fetch();
+    cpu.set_flag(StatusFlags::Zero, (cpu.a.get() & value) == 0);
+    write_back(cpu, value & !cpu.a.get());
     0
 }
 
-fn dcp(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add DCP implementation
+/// Pushes the X register onto the stack (PHX).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn phx(cpu: &mut Cpu) -> u8 {
+    cpu.push(cpu.x.get());
     0
 }
 
-fn isc(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add ISC implementation
+/// Pushes the Y register onto the stack (PHY).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn phy(cpu: &mut Cpu) -> u8 {
+    cpu.push(cpu.y.get());
     0
 }
 
-fn kil(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add KIL implementation
+/// Pulls the X register from the stack (PLX).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn plx(cpu: &mut Cpu) -> u8 {
+    let value = cpu.pop();
+    cpu.x.set(value);
+    cpu.set_zn_flags(value);
     0
 }
 
-fn las(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add LAS implementation
+/// Pulls the Y register from the stack (PLY).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn ply(cpu: &mut Cpu) -> u8 {
+    let value = cpu.pop();
+    cpu.y.set(value);
+    cpu.set_zn_flags(value);
     0
 }
 
-fn lax(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add LAX implementation
+// Rockwell bit manipulation instructions
+
+/// Resets (clears) a single bit of a zero page location (RMB0-RMB7).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rmb(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and clear the bit selected by the opcode
+    let value = cpu.fetch() & !rockwell_bit(cpu.opcode);
+    write_back(cpu, value);
+
     0
 }
 
-fn rla(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add RLA implementation
+/// Sets a single bit of a zero page location (SMB0-SMB7).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn smb(cpu: &mut Cpu) -> u8 {
+    // Fetch the operand and set the bit selected by the opcode
+    let value = cpu.fetch() | rockwell_bit(cpu.opcode);
+    write_back(cpu, value);
+
     0
 }
 
-fn rra(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add RRA implementation
+/// Branches if a single bit of a zero page location is reset (BBR0-BBR7).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bbr(cpu: &mut Cpu) -> u8 {
+    let value = cpu.fetch();
+    branch(cpu, value & rockwell_bit(cpu.opcode) == 0);
     0
 }
 
-fn sax(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add SAX implementation
+/// Branches if a single bit of a zero page location is set (BBS0-BBS7).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn bbs(cpu: &mut Cpu) -> u8 {
+    let value = cpu.fetch();
+    branch(cpu, value & rockwell_bit(cpu.opcode) != 0);
     0
 }
 
-fn shx(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add SHX implementation
+// WDC instructions
+
+/// Waits for an interrupt (WAI).
+///
+/// The CPU stops fetching instructions until an IRQ or NMI is asserted. If
+/// interrupts are disabled when an IRQ arrives, execution resumes with the next
+/// instruction instead of entering the handler.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn wai(cpu: &mut Cpu) -> u8 {
+    cpu.state = CpuState::Waiting;
     0
 }
 
-fn shy(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add SHY implementation
+/// Stops the CPU (STP) until it is reset.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn stp(cpu: &mut Cpu) -> u8 {
+    cpu.state = CpuState::Stopped;
     0
 }
 
-fn slo(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add SLO implementation
+// WDC 65C816 instructions
+
+/// Clears the status bits that are set in the operand (REP).
+///
+/// In emulation mode the M and X bits don't exist, so the Break and Unused
+/// positions are left alone.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn rep(cpu: &mut Cpu) -> u8 {
+    let mut mask = cpu.fetch();
+    if cpu.emulation_mode() {
+        mask &= !(StatusFlags::Break.bits() | StatusFlags::Unused.bits());
+    }
+    cpu.p.set(cpu.p.get() & !mask);
     0
 }
 
-fn sre(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add SRE implementation
+/// Sets the status bits that are set in the operand (SEP).
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn sep(cpu: &mut Cpu) -> u8 {
+    let mask = cpu.fetch();
+    cpu.p.insert(mask);
     0
 }
 
-fn tas(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add TAS implementation
+/// Exchanges the B and A accumulators (XBA).
+///
+/// The Negative and Zero flags reflect the new value of A.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn xba(cpu: &mut Cpu) -> u8 {
+    let a = cpu.a.get();
+    cpu.a.set(cpu.b.get());
+    cpu.b.set(a);
+    cpu.set_zn_flags(cpu.a.get());
     0
 }
 
-fn xaa(_cpu: &mut Cpu) -> u8 {
-    // TODO: Add XAA implementation
+/// Exchanges the carry flag with the emulation flag (XCE).
+///
+/// Entering emulation mode forces the M and X bits back on, so the registers
+/// return to eight bits.
+///
+/// # Arguments
+///
+/// * `cpu` - A mutable reference to the `Cpu` struct.
+///
+/// # Returns
+///
+/// The number of extra cycles required to execute the instruction.
+fn xce(cpu: &mut Cpu) -> u8 {
+    let carry = cpu.get_flag(StatusFlags::Carry);
+    cpu.set_flag(StatusFlags::Carry, cpu.emulation);
+    cpu.emulation = carry;
+
+    if cpu.emulation {
+        cpu.p.insert(StatusFlags::Break.bits() | StatusFlags::Unused.bits());
+    }
     0
-}
\ No newline at end of file
+}
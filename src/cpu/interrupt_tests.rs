@@ -0,0 +1,159 @@
+//! Measures where an IRQ is taken, the way the `cli_latency` test ROMs do.
+//!
+//! A timer on the bus asserts IRQ a chosen number of cycles after the program
+//! starts. Sweeping that delay over a short program and reading the return
+//! address the interrupt pushed shows which instruction boundary each delay
+//! lands on. The NMOS 6502 samples the line before the last cycle of an
+//! instruction, a taken branch that stays on its page samples it before its
+//! second cycle instead, and CLI, SEI and PLP change the flag after the
+//! sample. Each sweep is run with `clock()` and with `tick()`, which must agree.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::bus::ram::Ram;
+use crate::bus::{BusDevice, MainBus, SharedBus};
+use crate::cpu::Cpu;
+
+/// Where the programs start.
+const ORIGIN: u16 = 0x0200;
+
+/// Where the IRQ handler starts.
+const HANDLER: u16 = 0x4000;
+
+/// The most cycles a program runs before the IRQ must have been taken.
+const MAX_CYCLES: u64 = 100;
+
+/// A timer that asserts IRQ once it has been ticked for a number of cycles.
+struct IrqTimer {
+    /// The cycles left before IRQ is asserted.
+    remaining: u64,
+}
+
+impl BusDevice for IrqTimer {
+    fn read(&self, _address: u16) -> u8 {
+        0
+    }
+
+    fn write(&mut self, _address: u16, _value: u8) {}
+
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {}
+
+    fn tick(&mut self, cycles: u64) {
+        self.remaining = self.remaining.saturating_sub(cycles);
+    }
+
+    fn irq(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn name(&self) -> String {
+        String::from("IRQ timer")
+    }
+
+    fn start_address(&self) -> u16 {
+        0xD000
+    }
+
+    fn end_address(&self) -> u16 {
+        0xD000
+    }
+}
+
+/// Runs a program until an IRQ is taken.
+///
+/// # Arguments
+///
+/// * `origin` - Where the program is placed and starts. NOPs follow it.
+/// * `program` - The program.
+/// * `p` - The status register to start with.
+/// * `delay` - The cycles after the start at which IRQ is asserted.
+/// * `cycle_stepped` - Whether to run with `tick()` rather than `clock()`.
+///
+/// # Returns
+///
+/// The return address the interrupt pushed.
+fn return_address(origin: u16, program: &[u8], p: u8, delay: u64, cycle_stepped: bool) -> u16 {
+    let mut bus = MainBus::new();
+    bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    bus.add_device_with_priority(Box::new(IrqTimer { remaining: delay }), 1);
+    let mut cpu = Cpu::new(SharedBus::new(bus));
+    for address in origin..origin + 0x40 {
+        cpu.write8(address, 0xEA);
+    }
+    for (offset, byte) in program.iter().enumerate() {
+        cpu.write8(origin + offset as u16, *byte);
+    }
+    cpu.write16(0xFFFE, HANDLER);
+    cpu.pc.set(origin);
+    cpu.p.set(p);
+    cpu.irq_inhibited = p & 0x04 != 0;
+    cpu.sp.set(0xFF);
+
+    for _ in 0..MAX_CYCLES {
+        match cycle_stepped {
+            true => cpu.tick(),
+            false => cpu.clock(),
+        };
+        if cpu.cycles == 0 && cpu.pc.get() == HANDLER {
+            return cpu.read16(0x01FE);
+        }
+    }
+    panic!("no IRQ was taken with a delay of {}", delay);
+}
+
+/// Sweeps the delay before IRQ is asserted over a program.
+///
+/// # Arguments
+///
+/// * `origin` - Where the program is placed and starts.
+/// * `program` - The program.
+/// * `p` - The status register to start with.
+/// * `expected` - The return address expected for each delay, starting at 1.
+fn sweep(origin: u16, program: &[u8], p: u8, expected: &[u16]) {
+    for cycle_stepped in [false, true] {
+        let addresses: Vec<u16> = (1..=expected.len() as u64)
+            .map(|delay| return_address(origin, program, p, delay, cycle_stepped))
+            .collect();
+        assert_eq!(addresses, expected, "returning from IRQs asserted 1, 2, 3... cycles in (tick: {})", cycle_stepped);
+    }
+}
+
+#[test]
+fn irq_must_be_asserted_before_the_last_cycle() {
+    // NOP, NOP, LDA $10 (3 cycles), NOP: an IRQ asserted in an instruction's
+    // last cycle waits for the end of the next one
+    sweep(ORIGIN, &[0xEA, 0xEA, 0xA5, 0x10], 0x20, &[0x0201, 0x0202, 0x0202, 0x0204, 0x0204, 0x0204, 0x0205]);
+}
+
+#[test]
+fn cli_sei_and_plp_change_the_flag_after_sampling() {
+    // CLI, NOP: the instruction after CLI always runs first
+    sweep(ORIGIN, &[0x58, 0xEA], 0x24, &[0x0202, 0x0202, 0x0202, 0x0203]);
+
+    // SEI, NOP: an IRQ asserted in time is still taken after SEI
+    sweep(ORIGIN, &[0x78, 0xEA, 0xEA], 0x20, &[0x0201]);
+
+    // LDA #$20, PHA, PLP, NOP: PLP clears I like CLI, ten cycles in
+    let mut expected = vec![0x0205; 10];
+    expected.push(0x0206);
+    sweep(ORIGIN, &[0xA9, 0x20, 0x48, 0x28, 0xEA], 0x24, &expected);
+}
+
+#[test]
+fn taken_branch_on_the_same_page_samples_early() {
+    // BNE +0, taken on its own page: IRQ must be asserted by its second cycle
+    sweep(ORIGIN, &[0xD0, 0x00], 0x20, &[0x0202, 0x0203, 0x0203, 0x0203, 0x0204]);
+
+    // BNE +2 across a page: IRQ can arrive up to the last cycle as usual
+    sweep(0x02FC, &[0xD0, 0x02], 0x20, &[0x0300, 0x0300, 0x0300, 0x0301]);
+
+    // BEQ +0, not taken: the same as any two cycle instruction
+    sweep(ORIGIN, &[0xF0, 0x00], 0x20, &[0x0202, 0x0203, 0x0203]);
+}
@@ -7,24 +7,76 @@
 
 mod addresses;
 mod addressing;
-mod instructions;
-
-use std::cell::RefCell;
-use std::fmt::Display;
-use std::ops::AddAssign;
-use std::rc::Rc;
+mod backtrace;
+mod breakpoint;
+mod builder;
+mod condition;
+#[cfg(test)]
+mod golden_tests;
+mod debug;
+#[cfg(test)]
+mod decimal_tests;
+#[cfg(test)]
+mod differential_tests;
+#[cfg(test)]
+mod interrupt_tests;
+mod history;
+pub(crate) mod instructions;
+mod model;
+#[cfg(test)]
+mod processor_tests;
+mod record;
+mod rewind;
+mod snapshot;
+mod state;
+mod stop;
+mod trace;
+mod watch;
+mod width;
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt::Display;
+use core::ops::{AddAssign, RangeInclusive, SubAssign};
 use bitflags::bitflags;
 
-use crate::bus::MainBus;
-use crate::cpu::addresses::RESET_VECTOR;
-use crate::cpu::addressing::AddressingMode;
-use crate::cpu::instructions::INSTRUCTION_LIST;
-use crate::register::{Register8, Register16};
+use crate::bus::{AccessKind, BusAccess, MainBus, SharedBus, SnapshotError};
+use crate::cpu::addresses::{NMI_VECTOR, RESET_VECTOR};
+use crate::cpu::backtrace::CallStack;
+use crate::cpu::instructions::Instruction;
+use crate::disasm::{self, DisassembledInstruction, SymbolTable};
+use crate::register::{Register8, Register16, StatusRegister};
+
+pub use crate::cpu::addressing::AddressingMode;
+pub use crate::cpu::backtrace::{CallFrame, CallKind};
+pub use crate::cpu::breakpoint::Breakpoint;
+pub use crate::cpu::builder::CpuBuilder;
+pub use crate::cpu::condition::{Condition, ConditionError};
+pub use crate::cpu::debug::{DebugLevel, DebugSink};
+pub use crate::cpu::history::{History, HistoryEntry};
+pub use crate::cpu::model::CpuModel;
+pub use crate::cpu::record::InstructionRecord;
+pub use crate::cpu::rewind::Rewinder;
+pub use crate::cpu::snapshot::{CpuSnapshot, MachineSnapshot};
+pub use crate::cpu::state::CpuState;
+pub use crate::cpu::stop::StopReason;
+pub use crate::cpu::trace::TraceMismatch;
+pub use crate::cpu::watch::{WatchHandler, WatchHit, WatchKind, Watchpoint};
+pub use crate::cpu::width::RegisterWidth;
 
 /// Represents the 6502 CPU core.
 pub struct Cpu {
-    /// A reference-counted, mutable, smart pointer to a `MainBus` object.
-    pub bus: Rc<RefCell<MainBus>>,
+    /// A shared handle to the `MainBus` object.
+    pub bus: SharedBus,
+
+    /// The bus while the CPU holds it for a run of instructions, and the
+    /// stand-in left in its place the rest of the time. See `with_bus_held()`.
+    held_bus: MainBus,
+
+    /// Whether the CPU is holding the bus, so accesses go to `held_bus`.
+    bus_held: bool,
 
     /// The accumulator register.
     pub a: Register8,
@@ -35,8 +87,11 @@ pub struct Cpu {
     /// The Y register.
     pub y: Register8,
 
+    /// The hidden high byte of the 65C816 accumulator, exchanged with A by XBA.
+    pub b: Register8,
+
     /// The processor status flags register.
-    pub p: Register8,
+    pub p: StatusRegister,
 
     /// The stack pointer register.
     pub sp: Register8,
@@ -47,6 +102,9 @@ pub struct Cpu {
     /// The number of CPU cycles remaining in the current instruction.
     pub cycles: u8,
 
+    /// The cycles the CPU has been clocked for since it was created. See `total_cycles()`.
+    total_cycles: u64,
+
     /// The absolute address as calculated by the instruction's address mode.
     address_absolute: u16,
 
@@ -62,20 +120,106 @@ pub struct Cpu {
     /// The current fetched data.
     fetched_data: u8,
 
+    /// The CPU variant being emulated, which selects the instruction set.
+    pub model: CpuModel,
+
     /// Whether illegal opcodes should be enabled.
     pub enable_illegal_opcodes: bool,
 
-    /// The current instruction string.
+    /// Whether read-modify-write instructions perform the NMOS dummy write of the
+    /// unmodified value before writing the result. CMOS models make the dummy
+    /// read of the address that they do instead.
+    pub rmw_dummy_write: bool,
+
+    /// Whether writes should be queued in `deferred_writes` instead of reaching the bus.
+    /// Used by `tick()` to place writes on the cycle they belong to.
+    defer_writes: bool,
+
+    /// Writes queued while executing an instruction in cycle-stepped mode.
+    deferred_writes: VecDeque<(u16, u8)>,
+
+    /// Whether the IRQ line is being held low. IRQ is level-sensitive, so it keeps
+    /// being serviced for as long as the line stays asserted.
+    irq_line: bool,
+
+    /// Whether the NMI line is being held low. Only the transition to low matters.
+    nmi_line: bool,
+
+    /// Whether a falling edge on the NMI line is waiting to be serviced.
+    nmi_pending: bool,
+
+    /// Whether IRQs are held off at the next instruction boundary: the Interrupt Disable
+    /// flag as seen by the last interrupt poll, or the IRQ line wasn't asserted when it was
+    /// sampled. CLI, SEI and PLP change the flag after the poll, so their effect on IRQs is
+    /// delayed by one instruction.
+    irq_inhibited: bool,
+
+    /// The cycles left in the instruction when the IRQ line is sampled for the next
+    /// boundary, or 0 if it isn't sampled. An IRQ asserted after that point waits for
+    /// the next instruction, as it does on the real chip.
+    irq_sample: u8,
+
+    /// Whether the CPU is running, or why it has stopped fetching instructions.
+    state: CpuState,
+
+    /// Whether bus accesses are being collected in `accesses` for `step()`.
+    record_accesses: bool,
+
+    /// The bus accesses made by the instruction being stepped.
+    accesses: RefCell<Vec<BusAccess>>,
+
+    /// The breakpoints, by the address at which execution stops before fetching an instruction.
+    breakpoints: BTreeMap<u16, Breakpoint>,
+
+    /// The breakpoint that was just reported, which is let through on the next
+    /// attempt so that execution can resume.
+    resume_at: Option<u16>,
+
+    /// The address ranges whose accesses stop execution.
+    watchpoints: Vec<Watchpoint>,
+
+    /// Called for every watchpoint hit; returns whether execution should stop.
+    watch_handler: RefCell<Option<WatchHandler>>,
+
+    /// The first watchpoint hit by the current instruction that should stop execution.
+    watch_hit: Cell<Option<WatchHit>>,
+
+    /// The address of the instruction or interrupt sequence being executed.
+    instruction_pc: u16,
+
+    /// The snapshots taken for rewinding, if rewinding is enabled.
+    rewinder: Option<Rewinder>,
+
+    /// The most recently executed instructions, if the history is enabled.
+    history: Option<History>,
+
+    /// The subroutine calls and interrupts that haven't returned yet.
+    call_stack: CallStack,
+
+    /// The 65C816 emulation flag (E). Exchanged with the carry flag by XCE, and
+    /// always set on other models.
+    emulation: bool,
+
+    /// The labels shown in place of addresses in debug output, which can also
+    /// be used to set breakpoints. An assembler's label file can be read into
+    /// a table with `crate::loaders::symbols`.
+    pub symbols: SymbolTable,
+
+    /// The disassembly of the current instruction. Only kept up to date while
+    /// debug output is enabled; `current_instruction()` disassembles it on
+    /// request otherwise.
     pub current_instruction_string: String,
 
-    /// Debug modes
-    /// 0: No debug
-    /// 1: Print CPU state after each instruction
-    /// 2: Print CPU state after each cycle
-    pub debug: usize,
+    /// How much the CPU reports about what it is executing. The output goes
+    /// through `tracing`, or to the sink set with `set_debug_sink()`.
+    pub debug: DebugLevel,
+
+    /// Where debug output goes instead of `tracing`, if anywhere.
+    debug_sink: Option<DebugSink>,
 }
 
 bitflags! {
+    /// The bits of the processor status register.
     pub struct StatusFlags: u8 {
         /// No flags set.
         const None = 0b0000_0000;
@@ -106,35 +250,54 @@ bitflags! {
     }
 }
 
+/// Gives the bus back when a run that holds it ends, even if it panics.
+struct HeldBus<'a> {
+    /// The CPU holding the bus.
+    cpu: &'a mut Cpu,
+}
+
+impl Drop for HeldBus<'_> {
+    fn drop(&mut self) {
+        self.cpu.release_bus();
+    }
+}
+
 impl Cpu {
     /// Creates a new instance of the `Cpu` struct.
     ///
     /// # Arguments
     ///
-    /// * `bus` - A reference-counted, mutable, smart pointer to a `MainBus` object.
+    /// * `bus` - A shared handle to a `MainBus` object.
     ///
     /// # Returns
     ///
     /// A new instance of the `Cpu` struct.
-    pub fn new(bus: Rc<RefCell<MainBus>>) -> Cpu {
+    pub fn new(bus: SharedBus) -> Cpu {
         // Create a new instance of the `Cpu` struct.
         Cpu {
             // Assign the `bus` argument to the `bus` field of the `Cpu` struct.
             bus,
+            // The bus is only held while instructions run
+            held_bus: MainBus::new(),
+            bus_held: false,
             // Create a new instance of the `Register8` struct and assign it to the `a` field of the `Cpu` struct.
             a: Register8::new(),
             // Create a new instance of the `Register8` struct and assign it to the `x` field of the `Cpu` struct.
             x: Register8::new(),
             // Create a new instance of the `Register8` struct and assign it to the `y` field of the `Cpu` struct.
             y: Register8::new(),
-            // Create a new instance of the `Register8` struct and assign it to the `p` field of the `Cpu` struct.
-            p: Register8::new(),
+            // Create a new instance of the `Register8` struct and assign it to the `b` field of the `Cpu` struct.
+            b: Register8::new(),
+            // Create a new instance of the `StatusRegister` struct and assign it to the `p` field of the `Cpu` struct.
+            p: StatusRegister::new(),
             // Create a new instance of the `Register8` struct and assign it to the `sp` field of the `Cpu` struct.
             sp: Register8::new(),
             // Create a new instance of the `Register16` struct and assign it to the `pc` field of the `Cpu` struct.
             pc: Register16::new(),
             // Set the `cycles` field of the `Cpu` struct to 0.
             cycles: 0,
+            // No time has passed yet
+            total_cycles: 0,
             // Set the `address_absolute` field of the `Cpu` struct to 0.
             address_absolute: 0,
             // Set the `address_relative` field of the `Cpu` struct to 0.
@@ -145,10 +308,45 @@ impl Cpu {
             opcode: 0,
             // Set the `fetched_data` field of the `Cpu` struct to 0.
             fetched_data: 0,
+            // Emulate the original NMOS 6502 by default.
+            model: CpuModel::default(),
             // Set the `enable_illegal_opcodes` field of the `Cpu` struct to false.
             enable_illegal_opcodes: false,
+            // Set the `rmw_dummy_write` field of the `Cpu` struct to false.
+            rmw_dummy_write: false,
+            // Writes go straight to the bus unless `tick()` says otherwise.
+            defer_writes: false,
+            deferred_writes: VecDeque::new(),
+            // No interrupts are pending on power-up.
+            irq_line: false,
+            nmi_line: false,
+            nmi_pending: false,
+            irq_inhibited: true,
+            irq_sample: 0,
+            // The CPU starts out running.
+            state: CpuState::Running,
+            // Bus accesses are only recorded while stepping
+            record_accesses: false,
+            accesses: RefCell::new(Vec::new()),
+            // No breakpoints are set initially
+            breakpoints: BTreeMap::new(),
+            resume_at: None,
+            // No watchpoints are set initially
+            watchpoints: Vec::new(),
+            watch_handler: RefCell::new(None),
+            watch_hit: Cell::new(None),
+            instruction_pc: 0,
+            // Rewinding is off until enabled
+            rewinder: None,
+            history: None,
+            call_stack: CallStack::default(),
+            // The 65C816 powers up in emulation mode.
+            emulation: true,
+            // No labels until the host loads some
+            symbols: SymbolTable::new(),
             current_instruction_string: String::new(),
-            debug: 0,
+            debug: DebugLevel::Off,
+            debug_sink: None,
         }
     }
 
@@ -161,16 +359,14 @@ impl Cpu {
     /// # Example
     ///
     /// ```
-    /// use std::rc::Rc;
-    /// use std::cell::RefCell;
-    /// use crate::bus::MainBus;
-    /// use crate::cpu::Cpu;
+    /// use butterflyrs::bus::{MainBus, SharedBus};
+    /// use butterflyrs::cpu::Cpu;
     ///
-    /// let mut cpu = Cpu::new(Rc::new(RefCell::new(MainBus::new())));
-    /// let bus = Rc::new(RefCell::new(MainBus::new()));
+    /// let mut cpu = Cpu::new(SharedBus::new(MainBus::new()));
+    /// let bus = SharedBus::new(MainBus::new());
     /// cpu.connect_bus(bus);
     /// ```
-    pub fn connect_bus(&mut self, bus: Rc<RefCell<MainBus>>) {
+    pub fn connect_bus(&mut self, bus: SharedBus) {
         // Connects the CPU to the main bus.
         self.bus = bus;
     }
@@ -198,6 +394,122 @@ impl Cpu {
 
         // Set the program counter to the reset vector address
         self.pc.set(self.read16(RESET_VECTOR));
+
+        // A reset is the only way out of a jammed or stopped state
+        self.state = CpuState::Running;
+
+        // Forget any NMI that hadn't been serviced yet; IRQs are masked again
+        self.nmi_pending = false;
+        self.irq_inhibited = true;
+        self.irq_sample = 0;
+
+        // A 65C816 always comes out of reset in emulation mode
+        self.emulation = true;
+
+        // Nothing that was called before the reset will return
+        self.call_stack.clear();
+    }
+
+    /// Returns whether the CPU has been halted by a KIL/JAM opcode.
+    ///
+    /// A jammed CPU ignores `clock()` until it is reset.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the CPU is jammed, `false` otherwise.
+    pub fn is_jammed(&self) -> bool {
+        self.state == CpuState::Jammed
+    }
+
+    /// Returns the execution state of the CPU.
+    ///
+    /// Hosts can use this to find out why the CPU isn't advancing: it may be
+    /// waiting for an interrupt after WAI, stopped by STP, or jammed by KIL.
+    ///
+    /// # Returns
+    ///
+    /// The current `CpuState`.
+    pub fn state(&self) -> CpuState {
+        self.state
+    }
+
+    /// Returns the number of cycles the CPU has been clocked for.
+    ///
+    /// The count only goes up: every cycle spent by `clock()`, `tick()` and
+    /// `step()` adds one, including the cycles spent waiting, stopped or
+    /// jammed, and a reset doesn't clear it. Restoring a save state puts back
+    /// the count it was saved with. At 1 MHz it would take over half a million
+    /// years to wrap.
+    ///
+    /// # Returns
+    ///
+    /// The total number of cycles.
+    pub fn total_cycles(&self) -> u64 {
+        self.total_cycles
+    }
+
+    /// Returns whether the CPU is in 6502 emulation mode.
+    ///
+    /// This is always `true` except on a 65C816 that has executed XCE with the
+    /// carry flag clear.
+    ///
+    /// # Returns
+    ///
+    /// The value of the emulation flag (E).
+    pub fn emulation_mode(&self) -> bool {
+        self.emulation
+    }
+
+    /// Returns the width of the accumulator.
+    ///
+    /// In 65C816 native mode this is selected by the M flag, which occupies the
+    /// position of the Unused flag. Everywhere else the accumulator is eight bits.
+    ///
+    /// # Returns
+    ///
+    /// The current `RegisterWidth` of A.
+    pub fn accumulator_width(&self) -> RegisterWidth {
+        if self.emulation || self.get_flag(StatusFlags::Unused) {
+            RegisterWidth::Eight
+        } else {
+            RegisterWidth::Sixteen
+        }
+    }
+
+    /// Returns the width of the X and Y registers.
+    ///
+    /// In 65C816 native mode this is selected by the X flag, which occupies the
+    /// position of the Break flag. Everywhere else the index registers are eight bits.
+    ///
+    /// # Returns
+    ///
+    /// The current `RegisterWidth` of X and Y.
+    pub fn index_width(&self) -> RegisterWidth {
+        if self.emulation || self.get_flag(StatusFlags::Break) {
+            RegisterWidth::Eight
+        } else {
+            RegisterWidth::Sixteen
+        }
+    }
+
+    /// Checks whether the CPU can make progress, waking it up from WAI when an
+    /// interrupt is pending.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the CPU is running, `false` if it is waiting, stopped or jammed.
+    fn is_running(&mut self) -> bool {
+        match self.state {
+            CpuState::Running => true,
+            CpuState::Waiting if self.nmi_pending || self.irq_asserted() => {
+                // Any interrupt wakes the CPU up. If IRQs are disabled, execution
+                // simply continues with the instruction after WAI.
+                self.state = CpuState::Running;
+                self.irq_inhibited = self.get_flag(StatusFlags::InterruptDisable);
+                true
+            }
+            _ => false,
+        }
     }
 
     /// Reads a single byte from the specified address on the bus.
@@ -210,9 +522,129 @@ impl Cpu {
     ///
     /// The byte read from the bus.
     fn read8(&self, address: u16) -> u8 {
-        // Borrow the bus to read from it.
-        // The borrow is released when the function returns.
-        self.bus.borrow().read(address)
+        let value = self.with_bus(|bus| bus.read(address));
+
+        self.observe(BusAccess { kind: AccessKind::Read, address, value });
+
+        value
+    }
+
+    /// Reads a single byte for the debugger or disassembler.
+    ///
+    /// The read is not recorded by `step()`, doesn't trigger watchpoints and
+    /// doesn't show up in the bus trace.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read from.
+    ///
+    /// # Returns
+    ///
+    /// The byte read from the bus, or 0 if the address is unmapped.
+    fn peek8(&self, address: u16) -> u8 {
+        self.with_bus(|bus| bus.peek(address))
+    }
+
+    /// Calls a closure with the bus, whether the CPU is holding it or not.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure to call.
+    ///
+    /// # Returns
+    ///
+    /// What the closure returned.
+    fn with_bus<R>(&self, f: impl FnOnce(&MainBus) -> R) -> R {
+        match self.bus_held {
+            true => f(&self.held_bus),
+            false => f(&self.bus.borrow()),
+        }
+    }
+
+    /// Calls a closure with the bus for writing, whether the CPU is holding it or not.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure to call.
+    ///
+    /// # Returns
+    ///
+    /// What the closure returned.
+    fn with_bus_mut<R>(&mut self, f: impl FnOnce(&mut MainBus) -> R) -> R {
+        match self.bus_held {
+            true => f(&mut self.held_bus),
+            false => f(&mut self.bus.borrow_mut()),
+        }
+    }
+
+    /// Holds the bus for as long as a closure runs, so the CPU can run many
+    /// instructions without borrowing the shared bus for every access.
+    ///
+    /// Each borrow is cheap, but there are several for every instruction, and
+    /// with the `sync` feature each one locks a `Mutex`. While the bus is held
+    /// it is moved out of `bus`, which holds a stand-in bus instead, and it is
+    /// given back when the closure returns or panics. Devices are handed the
+    /// real bus by `MainBus` as usual, and a device added to the stand-in is
+    /// moved onto the real bus when it is given back.
+    ///
+    /// The bus is only held while the CPU has the only handle to it. If it has
+    /// been cloned, such as for another thread, the CPU borrows it for every
+    /// access instead, so that accesses through the other handles reach the
+    /// real bus during the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure to call with the CPU. If the bus is already held,
+    ///   or can't be, it is simply called.
+    ///
+    /// # Returns
+    ///
+    /// What the closure returned.
+    pub(crate) fn with_bus_held<R>(&mut self, f: impl FnOnce(&mut Cpu) -> R) -> R {
+        if self.bus_held || self.bus.is_shared() {
+            return f(self);
+        }
+
+        core::mem::swap(&mut *self.bus.borrow_mut(), &mut self.held_bus);
+        self.bus_held = true;
+        let held = HeldBus { cpu: self };
+        f(&mut *held.cpu)
+    }
+
+    /// Gives the bus held by `with_bus_held()` back to `bus`, along with any
+    /// devices added to the stand-in in the meantime.
+    fn release_bus(&mut self) {
+        self.bus_held = false;
+        let mut bus = self.bus.borrow_mut();
+        core::mem::swap(&mut *bus, &mut self.held_bus);
+        bus.take_devices(&mut self.held_bus);
+    }
+
+    /// Passes a bus access made by the CPU to the step recorder and the watchpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `access` - The access that was made.
+    fn observe(&self, access: BusAccess) {
+        // Remember the access if an instruction is being stepped
+        if self.record_accesses {
+            self.accesses.borrow_mut().push(access);
+        }
+
+        if self.watchpoints.iter().any(|watchpoint| watchpoint.matches(&access)) {
+            let hit = WatchHit { pc: self.instruction_pc, access };
+
+            // Without a handler every hit stops execution
+            let stop = match self.watch_handler.borrow_mut().as_mut() {
+                Some(handler) => handler(hit),
+                None => true,
+            };
+
+            // Only the first hit of an instruction is reported
+            if stop && self.watch_hit.get().is_none() {
+                self.watch_hit.set(Some(hit));
+            }
+        }
     }
 
     /// Writes a single byte to the specified address on the bus.
@@ -222,9 +654,15 @@ impl Cpu {
     /// * `address` - The address to write to.
     /// * `value` - The byte value to write.
     fn write8(&mut self, address: u16, value: u8) {
-        // Borrow the bus as mutable to write to it.
-        // The borrow is released when the function returns.
-        self.bus.borrow_mut().write(address, value)
+        self.observe(BusAccess { kind: AccessKind::Write, address, value });
+
+        // In cycle-stepped mode the write is performed later by `tick()`
+        if self.defer_writes {
+            self.deferred_writes.push_back((address, value));
+            return;
+        }
+
+        self.with_bus_mut(|bus| bus.write(address, value))
     }
 
     /// Reads a 16-bit value from the specified address on the bus.
@@ -240,8 +678,8 @@ impl Cpu {
         // Read the low byte from the bus
         let low = self.read8(address) as u16;
 
-        // Read the high byte from the bus, offset by 1
-        let high = self.read8(address + 1) as u16;
+        // Read the high byte from the bus, offset by 1 and wrapping at the top of memory
+        let high = self.read8(address.wrapping_add(1)) as u16;
 
         // Combine the low and high bytes into a 16-bit value
         // by shifting the high byte 8 bits to the left and ORing it with the low byte
@@ -258,8 +696,8 @@ impl Cpu {
         // Write the low byte to the bus
         self.write8(address, (value & 0xFF) as u8);
 
-        // Write the high byte to the bus, offset by 1
-        self.write8(address + 1, ((value >> 8) & 0xFF) as u8);
+        // Write the high byte to the bus, offset by 1 and wrapping at the top of memory
+        self.write8(address.wrapping_add(1), ((value >> 8) & 0xFF) as u8);
     }
 
     /// Sets or removes a flag in the processor status register (`p`).
@@ -271,7 +709,7 @@ impl Cpu {
     fn set_flag(&mut self, flag: StatusFlags, value: bool) {
         // If the value is true, set the flag in the processor status register.
         if value {
-            self.p.set(flag.bits());
+            self.p.insert(flag.bits());
         }
         // If the value is false, remove the flag from the processor status register.
         else {
@@ -294,41 +732,36 @@ impl Cpu {
     }
 
     /// Increments the stack pointer (`sp`) by 1.
-    /// If the stack pointer reaches 0x00, it wraps around to 0xFF.
+    /// If the stack pointer is 0xFF, it wraps around to 0x00.
     fn increment_sp(&mut self) {
-        // Increment the stack pointer by 1
+        // Increment the stack pointer by 1, wrapping within page one
         self.sp.add_assign(1);
-
-        // Check if the stack pointer is 0x00
-        if self.sp.get() == 0x00 {
-            // If it is, wrap around to 0xFF
-            self.sp.set(0xFF);
-        }
     }
 
     /// Decrements the stack pointer (`sp`) by 1.
-    /// If the stack pointer reaches 0x00, it wraps around to 0xFF.
+    /// If the stack pointer is 0x00, it wraps around to 0xFF.
     ///
     /// # Examples
     ///
-    /// ```
-    /// use your_crate::cpu::Cpu;
-    ///
-    /// let mut cpu = Cpu::new();
-    /// cpu.sp.set(0x01);
+    /// ```ignore
+    /// let mut cpu = Cpu::new(SharedBus::new(MainBus::new()));
+    /// cpu.sp.set(0x00);
     /// cpu.decrement_sp();
     /// assert_eq!(cpu.sp.get(), 0xFF);
     /// ```
     fn decrement_sp(&mut self) {
-        // Decrement the stack pointer by 1
+        // Decrement the stack pointer by 1, wrapping within page one
         self.sp.sub_assign(1);
-
-        // If the stack pointer is 0xFF, wrap around to 0x00
-        if self.sp.get() == 0xFF {
-            self.sp.set(0x00);
-        }
     }
 
+    /// Enables or disables execution of undocumented opcodes.
+    ///
+    /// When disabled (the default), undocumented opcodes consume their operand
+    /// bytes and cycles but otherwise have no effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Whether undocumented opcodes should be executed.
     pub fn set_illegal_opcodes(&mut self, value: bool) {
         self.enable_illegal_opcodes = value;
     }
@@ -382,7 +815,8 @@ impl Cpu {
     ///
     /// The fetched byte.
     fn fetch(&mut self) -> u8 {
-        if self.address_mode != AddressingMode::Implied {
+        // Implied and accumulator operands were already latched by the addressing mode
+        if self.address_mode != AddressingMode::Implied && self.address_mode != AddressingMode::Accumulator {
             self.fetched_data = self.read8(self.address_absolute);
         }
         self.fetched_data
@@ -396,7 +830,7 @@ impl Cpu {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let mut cpu = Cpu::new();
     /// cpu.push(0x42);
     /// assert_eq!(cpu.read8(0x100 + cpu.sp.get() as u16), 0x42);
@@ -418,7 +852,7 @@ impl Cpu {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let mut cpu = Cpu::new();
     /// cpu.push_word(0x1234);
     /// assert_eq!(cpu.read8(0x100 + cpu.sp.get() as u16), 0x34);
@@ -441,7 +875,7 @@ impl Cpu {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let mut cpu = Cpu::new();
     /// cpu.push(0x42);
     /// assert_eq!(cpu.pop(), 0x42);
@@ -463,7 +897,7 @@ impl Cpu {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let mut cpu = Cpu::new();
     /// cpu.push_word(0x1234);
     /// assert_eq!(cpu.pop_word(), 0x1234);
@@ -480,76 +914,74 @@ impl Cpu {
         (hi << 8) | lo
     }
 
-    /// Executes an instruction.
+    /// Looks up the instruction for an opcode in the current model's instruction set.
     ///
     /// # Arguments
     ///
-    /// * `opcode` - The opcode of the instruction to execute.
+    /// * `opcode` - The opcode to look up.
     ///
     /// # Returns
     ///
-    /// The number of cycles the instruction took to execute.
-    fn execute_instruction(&mut self, opcode: u8) -> u8 {
-        let instruction = &INSTRUCTION_LIST[opcode as usize];
-        (instruction.function)(self)
+    /// The instruction table entry for the opcode.
+    fn instruction(&self, opcode: u8) -> &'static Instruction {
+        instructions::lookup(self.model, opcode)
     }
 
-    /// Returns a string representation of the operand based on the addressing mode.
+    /// Executes an instruction.
     ///
     /// # Arguments
     ///
-    /// * `mode` - The addressing mode of the operand.
-    /// * `address` - The address of the operand.
+    /// * `instruction` - The instruction table entry to execute.
     ///
     /// # Returns
     ///
-    /// A string representation of the operand.
-    fn get_operand_string(&mut self, mode: AddressingMode, address: u16) -> String {
-        match mode {
-            // No operand
-            AddressingMode::None => String::from(""),
-            // Implied operand
-            AddressingMode::Implied => String::from(""),
-            // Immediate operand
-            AddressingMode::Immediate => format!("#${:02X}", self.read8(address)),
-            // Zero page operand
-            AddressingMode::ZeroPage => format!("${:02X}", self.read8(address)),
-            // Zero page with X offset operand
-            AddressingMode::ZeroPageX => format!("${:02X},X", self.read8(address)),
-            // Zero page with Y offset operand
-            AddressingMode::ZeroPageY => format!("${:02X},Y", self.read8(address)),
-            // Relative operand
-            AddressingMode::Relative => format!("${:02X}", self.read8(address)),
-            // Absolute operand
-            AddressingMode::Absolute => format!("${:04X}", self.read16(address)),
-            // Absolute with X offset operand
-            AddressingMode::AbsoluteX => format!("${:04X},X", self.read16(address)),
-            // Absolute with Y offset operand
-            AddressingMode::AbsoluteY => format!("${:04X},Y", self.read16(address)),
-            // Indirect operand
-            AddressingMode::Indirect => format!("(${:04X})", self.read16(address)),
-            // Indexed indirect operand
-            AddressingMode::IndexedIndirect => format!("(${:02X},X)", self.read8(address)),
-            // Indirect indexed operand
-            AddressingMode::IndirectIndexed => format!("(${:02X}),Y", self.read8(address)),
+    /// The number of cycles the instruction took to execute.
+    fn execute_instruction(&mut self, instruction: &Instruction) -> u8 {
+        // Undocumented opcodes behave like a NOP of the same length unless they
+        // have been explicitly enabled. KIL always halts the CPU, just like the
+        // real hardware does.
+        if instruction.illegal && !self.enable_illegal_opcodes && !instruction.jam {
+            return 0;
         }
+
+        // A `match` on the opcode that calls the functions directly measured no
+        // faster on the tight loop in `benches/cpu.rs`, and would need one per
+        // instruction set, so the entry's function is called instead
+        (instruction.function)(self)
     }
 
     /// Disassembles the instruction at the specified address.
     ///
+    /// The instruction bytes are peeked, so disassembling doesn't disturb devices.
+    ///
     /// # Arguments
     ///
     /// * `from_pc` - The address of the instruction to disassemble.
     ///
     /// # Returns
     ///
-    /// The disassembled instruction.
-    fn disassemble_instruction_at(&mut self, from_pc: u16) -> String {
-        let opcode = self.read8(from_pc);
-        let instruction = &INSTRUCTION_LIST[opcode as usize];
-        let addr_mode = instructions::get_addr_mode(opcode);
-        let addr_str = self.get_operand_string(addr_mode, from_pc + 1);
-        format!("{} {}", instruction.name, addr_str)
+    /// The decoded instruction.
+    fn disassemble_instruction_at(&self, from_pc: u16) -> DisassembledInstruction {
+        let bytes = [0, 1, 2].map(|offset| self.peek8(from_pc.wrapping_add(offset)));
+
+        // Three bytes always hold a whole instruction
+        disasm::disassemble_one(self.model, &bytes, from_pc).expect("instructions are at most three bytes long")
+    }
+
+    /// Disassembles the instruction the CPU last started, on request.
+    ///
+    /// Nothing is disassembled as the CPU runs unless debug output is on,
+    /// since building the text for every instruction would slow it down.
+    /// Hosts that only sometimes need the text, such as a debugger's status
+    /// line, ask for it here instead. The bytes are peeked, so devices aren't
+    /// disturbed, and are read as they are now, so code that has modified
+    /// itself since shows its new form.
+    ///
+    /// # Returns
+    ///
+    /// The decoded instruction, with its address and bytes.
+    pub fn current_instruction(&self) -> DisassembledInstruction {
+        self.disassemble_instruction_at(self.instruction_pc)
     }
 
     /// Executes the given addressing mode.
@@ -586,7 +1018,7 @@ impl Cpu {
     /// The number of cycles required to execute the instruction.
     pub fn get_cycles(&self, opcode: u8) -> u8 {
         // Get the number of cycles required to execute the instruction from the instructions module.
-        instructions::get_cycles(opcode)
+        instructions::get_cycles(self.model, opcode)
     }
 
     /// Performs an interrupt by pushing the program counter and status flags to the stack,
@@ -595,34 +1027,99 @@ impl Cpu {
     /// # Arguments
     ///
     /// * `vector` - The address of the interrupt vector.
-    ///
-    /// # Returns
-    ///
-    /// None
-    fn do_interrupt(&mut self, vector: u16) {
+    /// * `brk` - Whether the interrupt was caused by a BRK instruction. The Break flag is
+    ///   only set in the pushed status byte for software interrupts.
+    fn do_interrupt(&mut self, vector: u16, brk: bool) {
         // Push the program counter to the stack
-        self.push_word(self.pc.get());
+        let return_address = self.pc.get();
+        self.push_word(return_address);
+
+        // Push the status flags with the Unused flag set, and the Break flag
+        // telling the handler whether this was a BRK or a hardware interrupt
+        let mut status = self.p.get() | StatusFlags::Unused.bits();
+        if brk {
+            status |= StatusFlags::Break.bits();
+        } else {
+            status &= !StatusFlags::Break.bits();
+        }
+        self.push(status);
 
-        // Clear the Break flag
-        self.set_flag(StatusFlags::Break, false);
+        // Set the Interrupt Disable flag so the handler isn't interrupted by another IRQ
+        self.set_flag(StatusFlags::InterruptDisable, true);
 
-        // Set the Unused flag
-        self.set_flag(StatusFlags::Unused, true);
+        // CMOS parts also clear decimal mode, so the handler starts in binary
+        if self.model.is_cmos() {
+            self.set_flag(StatusFlags::DecimalMode, false);
+        }
 
-        // Set the Break flag again
-        self.set_flag(StatusFlags::Break, true);
+        // Load the interrupt vector into the program counter
+        self.pc = Register16 { value: self.read16(vector) };
 
-        // Set the Interrupt Disable flag
-        self.set_flag(StatusFlags::InterruptDisable, true);
+        let kind = match (brk, vector) {
+            (true, _) => CallKind::Break,
+            (false, NMI_VECTOR) => CallKind::Nmi,
+            (false, _) => CallKind::Irq,
+        };
+        self.enter_call(kind, return_address);
+    }
 
-        // Push the status flags to the stack
-        self.push(self.p.get());
+    /// Records a subroutine call or interrupt on the call stack, once its return
+    /// address has been pushed and the program counter points at its target.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - How the call was made.
+    /// * `return_address` - Where execution carries on when it returns.
+    pub(crate) fn enter_call(&mut self, kind: CallKind, return_address: u16) {
+        self.call_stack.enter(CallFrame {
+            kind,
+            from: self.instruction_pc,
+            to: self.pc.get(),
+            return_address,
+            sp: self.sp.get(),
+        });
+    }
 
-        // Clear the Interrupt Disable flag
-        self.set_flag(StatusFlags::InterruptDisable, false);
+    /// Drops the calls that have returned from the call stack, after an RTS or RTI.
+    pub(crate) fn return_from_call(&mut self) {
+        self.call_stack.unwind(self.sp.get());
+    }
 
-        // Load the interrupt vector into the program counter
-        self.pc = Register16 { value: self.read16(vector) };
+    /// Returns the subroutine calls and interrupts that are in progress.
+    ///
+    /// JSR, BRK and interrupts are tracked as they happen. A frame is gone
+    /// once the stack pointer rises above its return address, however the
+    /// program returned, so stack tricks such as discarding a return address
+    /// or jumping with RTS don't confuse it.
+    ///
+    /// # Returns
+    ///
+    /// The frames, outermost first, so the last one is the subroutine or
+    /// interrupt handler being executed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use butterflyrs::cpu::Cpu;
+    /// # use butterflyrs::bus::{MainBus, SharedBus};
+    /// # let cpu = Cpu::new(SharedBus::new(MainBus::new()));
+    /// for frame in cpu.backtrace().iter().rev() {
+    ///     println!("{}", frame);
+    /// }
+    /// ```
+    pub fn backtrace(&self) -> &[CallFrame] {
+        self.call_stack.frames(self.sp.get())
+    }
+
+    /// Starts a hardware interrupt sequence through the given vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `vector` - The address of the interrupt vector.
+    fn hardware_interrupt(&mut self, vector: u16) {
+        // Accesses made by the sequence are attributed to the interrupted instruction
+        self.instruction_pc = self.pc.get();
+        self.do_interrupt(vector, false);
 
         // Set the number of cycles required to execute the interrupt
         self.cycles = 7;
@@ -630,31 +1127,134 @@ impl Cpu {
 
     /// Handles the IRQ (Interrupt Request) interrupt.
     ///
-    /// If the Interrupt Disable flag is not set, the function calls the `do_interrupt` method with the IRQ vector address.
+    /// If the Interrupt Disable flag is not set, the interrupt sequence is started with the IRQ vector address.
+    /// The interrupt is taken immediately, regardless of where the CPU is in the current instruction;
+    /// use `assert_irq()` to have `clock()` take it at the next instruction boundary instead.
     ///
     /// # Arguments
     ///
     /// * `&mut self` - The mutable reference to the `Cpu` struct.
-    #[allow(dead_code)]
     pub fn irq(&mut self) {
         // Check if the Interrupt Disable flag is not set
         if !self.get_flag(StatusFlags::InterruptDisable) {
-            // Call the `do_interrupt` method with the IRQ vector address
-            self.do_interrupt(addresses::IRQ_VECTOR);
+            // Start the interrupt sequence with the IRQ vector address
+            self.hardware_interrupt(addresses::IRQ_VECTOR);
         }
     }
 
     /// Handles the Non-Maskable Interrupt (NMI) interrupt.
     ///
-    /// This function calls the `do_interrupt` method with the NMI vector address.
+    /// This function starts the interrupt sequence with the NMI vector address.
+    /// The interrupt is taken immediately, regardless of where the CPU is in the current instruction;
+    /// use `trigger_nmi()` to have `clock()` take it at the next instruction boundary instead.
     ///
     /// # Arguments
     ///
     /// * `&mut self` - The mutable reference to the `Cpu` struct.
-    #[allow(dead_code)]
     pub fn nmi(&mut self) {
-        // Call the `do_interrupt` method with the NMI vector address
-        self.do_interrupt(addresses::NMI_VECTOR);
+        // Start the interrupt sequence with the NMI vector address
+        self.hardware_interrupt(addresses::NMI_VECTOR);
+    }
+
+    /// Asserts the IRQ line.
+    ///
+    /// The interrupt is taken at the next instruction boundary where the Interrupt
+    /// Disable flag is clear, and keeps being taken until `release_irq()` is called.
+    pub fn assert_irq(&mut self) {
+        self.set_irq_line(true);
+    }
+
+    /// Releases the IRQ line.
+    pub fn release_irq(&mut self) {
+        self.set_irq_line(false);
+    }
+
+    /// Drives the IRQ line.
+    ///
+    /// IRQ is level-sensitive: while the line is asserted an interrupt is taken at
+    /// every instruction boundary where interrupts are enabled, so a device holding
+    /// the line will interrupt again as soon as its handler returns with RTI.
+    ///
+    /// # Arguments
+    ///
+    /// * `asserted` - `true` to pull the line low, `false` to release it.
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    /// Returns whether the IRQ line is currently asserted by `set_irq_line()`.
+    ///
+    /// Devices on the bus can assert IRQ too, which this doesn't include. See
+    /// `irq_asserted()`.
+    pub fn irq_line(&self) -> bool {
+        self.irq_line
+    }
+
+    /// Returns whether anything is asserting IRQ.
+    ///
+    /// The 6502 has a single IRQ input, shared by everything that can
+    /// interrupt it. This samples it: the line driven by `set_irq_line()`,
+    /// and whatever the bus reports with `MainBus::irq()`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if an IRQ is being requested, whether or not interrupts are enabled.
+    pub fn irq_asserted(&self) -> bool {
+        self.irq_line || self.with_bus(MainBus::irq)
+    }
+
+    /// Drives the NMI line.
+    ///
+    /// NMI is edge-triggered: one interrupt is latched when the line goes from
+    /// released to asserted, and holding it asserted does not cause another one.
+    /// The line must be released and asserted again to trigger the next NMI.
+    ///
+    /// # Arguments
+    ///
+    /// * `asserted` - `true` to pull the line low, `false` to release it.
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        // Latch the falling edge
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    /// Returns whether the NMI line is currently asserted.
+    pub fn nmi_line(&self) -> bool {
+        self.nmi_line
+    }
+
+    /// Requests a non-maskable interrupt.
+    ///
+    /// This behaves like a short pulse on the NMI line: the interrupt is taken
+    /// once, at the next instruction boundary, and the line is left released.
+    pub fn trigger_nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    /// Services a pending interrupt, if any, at an instruction boundary.
+    ///
+    /// NMI has priority over IRQ. An IRQ is only taken if the Interrupt Disable flag
+    /// was clear when the previous instruction polled for interrupts.
+    ///
+    /// # Returns
+    ///
+    /// `true` if an interrupt sequence was started, `false` otherwise.
+    fn poll_interrupts(&mut self) -> bool {
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.hardware_interrupt(addresses::NMI_VECTOR);
+        } else if !self.irq_inhibited && self.irq_asserted() {
+            self.hardware_interrupt(addresses::IRQ_VECTOR);
+        } else {
+            return false;
+        }
+
+        // The handler's first instruction always runs before the next poll
+        self.irq_inhibited = true;
+        self.irq_sample = 0;
+        true
     }
 
     /// Returns the value of a specific register.
@@ -682,38 +1282,922 @@ impl Cpu {
     ///
     /// * `value` - The value to set the flags for.
     fn set_zn_flags(&mut self, value: u8) {
-        // Set the zero flag if the value is zero
-        self.set_flag(StatusFlags::Zero, value == 0);
-
-        // Set the negative flag if the most significant bit of the value is set
-        self.set_flag(StatusFlags::Negative, value & 0x80 != 0);
+        // Almost every instruction ends here, and the flags are kept apart, so this is two stores
+        self.p.set_zn(value);
     }
 
-    pub fn clock(&mut self) {
-        if self.cycles == 0 {
-            self.current_instruction_string = self.disassemble_instruction_at(self.pc.get());
-            match self.debug {
-                0 => (),
-                1 => println!("{}", self.current_instruction_string),
-                2 => {
-                    println!("{}", self.current_instruction_string);
-                    println!("CPU pre-execute state: {}", self);
-                }
-                _ => panic!("Invalid debug value: {}", self.debug),
-            }
-            self.opcode = self.read8(self.pc.get());
-            self.pc.add_assign(1);
-            self.cycles = self.get_cycles(self.opcode);
-            self.address_mode = instructions::get_addr_mode(self.opcode);
-            let cycles_address_mode = self.execute_addr_mode(self.address_mode);
-            let cycles_instruction = self.execute_instruction(self.opcode);
-            self.cycles += cycles_address_mode + cycles_instruction;
-            if self.debug > 1 {
-                println!("CPU post-execute state: {}", self);
-            }
+    /// Fetches, decodes and executes the instruction at the program counter.
+    ///
+    /// The whole instruction is executed at once and `cycles` is loaded with the
+    /// number of cycles it takes, so that the caller can burn them afterwards.
+    fn execute_next_instruction(&mut self) {
+        // Disassembling is expensive, so it is only done when it will be reported
+        if self.debug >= DebugLevel::Instruction {
+            let instruction = self.disassemble_instruction_at(self.pc.get());
+            self.current_instruction_string = instruction.to_string_with(&self.symbols);
+            self.debug_output(DebugLevel::Instruction, self.current_instruction_string.clone());
         }
-        self.cycles -= 1;
-    }
+        if self.debug >= DebugLevel::Cycle {
+            self.debug_output(DebugLevel::Cycle, format!("CPU pre-execute state: {}", self));
+        }
+        self.instruction_pc = self.pc.get();
+        self.opcode = self.read8(self.pc.get());
+        self.pc.add_assign(1);
+
+        // Look the instruction up once and take everything from that entry
+        let instruction = self.instruction(self.opcode);
+        if self.history.is_some() {
+            self.record_history(instruction);
+        }
+        self.cycles = instruction.cycles;
+        let interrupt_disable = self.get_flag(StatusFlags::InterruptDisable);
+        let cycles_address_mode = self.execute_addr_mode(instruction.mode);
+        let cycles_instruction = self.execute_instruction(instruction);
+
+        // Interrupts are polled before CLI, SEI and PLP update the flag, so the
+        // next boundary still sees the old value for those three
+        self.irq_inhibited = match self.opcode {
+            0x28 | 0x58 | 0x78 => interrupt_disable,
+            _ => self.get_flag(StatusFlags::InterruptDisable),
+        };
+
+        // An extra cycle is only taken when the addressing mode crossed a page
+        // boundary and the instruction is one that pays for it
+        self.cycles += cycles_address_mode & cycles_instruction;
+
+        // The IRQ line is sampled before the last cycle, except that a taken branch
+        // which stays on its page doesn't poll in its third cycle
+        self.irq_sample = match instruction.mode {
+            _ if self.irq_inhibited => 0,
+            AddressingMode::Relative if self.cycles == instruction.cycles + 1 => 2,
+            _ => 1,
+        };
+        if self.debug >= DebugLevel::Cycle {
+            self.debug_output(DebugLevel::Cycle, format!("CPU post-execute state: {}", self));
+        }
+    }
+
+    /// Sends a line of debug output to the sink, or reports it through `tracing`.
+    ///
+    /// Instructions are debug events and the CPU state around them trace
+    /// events, so the host's subscriber decides what is shown and where it goes.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The level that produced the line.
+    /// * `line` - The line.
+    fn debug_output(&mut self, level: DebugLevel, line: String) {
+        match &mut self.debug_sink {
+            Some(sink) => sink(level, &line),
+            None if level == DebugLevel::Instruction => tracing::debug!("{}", line),
+            None => tracing::trace!("{}", line),
+        }
+    }
+
+    /// Sends debug output to a function instead of `tracing`, such as a
+    /// debugger's log window.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Called with every line of debug output and the level that produced it.
+    pub fn set_debug_sink(&mut self, sink: DebugSink) {
+        self.debug_sink = Some(sink);
+    }
+
+    /// Writes debug output to a file, a pipe or anything else that takes bytes,
+    /// one line at a time. Write errors are ignored, so a closed pipe doesn't
+    /// stop the CPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where to write the output.
+    #[cfg(feature = "std")]
+    pub fn set_debug_writer<W: std::io::Write + Send + 'static>(&mut self, mut writer: W) {
+        self.set_debug_sink(alloc::boxed::Box::new(move |_, line| {
+            let _ = writeln!(writer, "{}", line);
+        }));
+    }
+
+    /// Sends debug output back to `tracing`.
+    pub fn clear_debug_sink(&mut self) {
+        self.debug_sink = None;
+    }
+
+    /// Sets a breakpoint at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the instruction to stop at.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the breakpoint was added, `false` if one was already set,
+    /// in which case it is left as it was.
+    pub fn add_breakpoint(&mut self, address: u16) -> bool {
+        if self.breakpoints.contains_key(&address) {
+            return false;
+        }
+        self.breakpoints.insert(address, Breakpoint::default());
+        true
+    }
+
+    /// Sets a breakpoint with a condition or a hit count at an address,
+    /// replacing any breakpoint already set there.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the instruction to stop at.
+    /// * `breakpoint` - When to stop there.
+    pub fn set_breakpoint(&mut self, address: u16, breakpoint: Breakpoint) {
+        self.breakpoints.insert(address, breakpoint);
+    }
+
+    /// Returns the breakpoint at an address, with its condition and the number of times it has been hit.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the breakpoint.
+    ///
+    /// # Returns
+    ///
+    /// The breakpoint, or `None` if none is set there.
+    pub fn breakpoint(&self, address: u16) -> Option<&Breakpoint> {
+        self.breakpoints.get(&address)
+    }
+
+    /// Sets a breakpoint at the address a label names.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the instruction to stop at, from `symbols`.
+    ///
+    /// # Returns
+    ///
+    /// `Some(true)` if the breakpoint was added, `Some(false)` if it was
+    /// already set, or `None` if no address has that label.
+    pub fn add_breakpoint_at_label(&mut self, label: &str) -> Option<bool> {
+        let address = self.symbols.address_of(label)?;
+        Some(self.add_breakpoint(address))
+    }
+
+    /// Removes the breakpoint at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the breakpoint.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a breakpoint was removed, `false` if none was set there.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    /// Adds a watchpoint over a range of addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to watch. Use `address..=address` for a single location.
+    /// * `kind` - The kind of access that triggers the watchpoint.
+    pub fn add_watchpoint(&mut self, range: RangeInclusive<u16>, kind: WatchKind) {
+        self.watchpoints.push(Watchpoint::new(range, kind));
+    }
+
+    /// Removes a watchpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses of the watchpoint, as it was added.
+    /// * `kind` - The kind of access of the watchpoint, as it was added.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the watchpoint was removed, `false` if it wasn't set.
+    pub fn remove_watchpoint(&mut self, range: RangeInclusive<u16>, kind: WatchKind) -> bool {
+        let watchpoint = Watchpoint::new(range, kind);
+        match self.watchpoints.iter().position(|w| *w == watchpoint) {
+            Some(index) => {
+                self.watchpoints.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all watchpoints.
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+    }
+
+    /// Returns the watchpoints that are set.
+    ///
+    /// # Returns
+    ///
+    /// A slice of the watchpoints, in the order they were added.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Sets a function to be called whenever a watchpoint is hit.
+    ///
+    /// The handler decides whether execution stops: returning `false` lets the
+    /// CPU carry on, which is useful for logging accesses. Without a handler,
+    /// every hit stops execution.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Called with each hit; returns `true` to stop.
+    pub fn set_watch_handler(&mut self, handler: WatchHandler) {
+        self.watch_handler = RefCell::new(Some(handler));
+    }
+
+    /// Removes the watchpoint handler, so that every hit stops execution again.
+    pub fn clear_watch_handler(&mut self) {
+        self.watch_handler = RefCell::new(None);
+    }
+
+    /// Removes all breakpoints.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Returns whether a breakpoint is set at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if there is a breakpoint at the address.
+    pub fn has_breakpoint(&self, address: u16) -> bool {
+        self.breakpoints.contains_key(&address)
+    }
+
+    /// Returns the addresses of all breakpoints in ascending order.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the breakpoint addresses.
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.keys().copied()
+    }
+
+    /// Checks for a breakpoint at the program counter before an instruction is fetched.
+    ///
+    /// A breakpoint is reported once. The next attempt to execute from the same
+    /// address goes ahead, so calling `clock()` or `step()` again resumes.
+    /// A conditional breakpoint is only hit while its condition holds, and
+    /// hits within its skip count are counted but don't stop execution.
+    ///
+    /// # Returns
+    ///
+    /// `Some(StopReason::Breakpoint)` if execution should stop, `None` otherwise.
+    fn check_breakpoint(&mut self) -> Option<StopReason> {
+        let pc = self.pc.get();
+
+        // Let the instruction through if its breakpoint was just reported
+        if self.resume_at.take() == Some(pc) {
+            return None;
+        }
+
+        let hit = match self.breakpoints.get(&pc) {
+            Some(breakpoint) => breakpoint.condition.as_ref().is_none_or(|condition| condition.is_true(self)),
+            None => false,
+        };
+        if !hit {
+            return None;
+        }
+
+        let breakpoint = self.breakpoints.get_mut(&pc).expect("the breakpoint was just found");
+        breakpoint.hits = breakpoint.hits.saturating_add(1);
+        if breakpoint.hits <= breakpoint.skip {
+            return None;
+        }
+
+        self.resume_at = Some(pc);
+        Some(StopReason::Breakpoint(pc))
+    }
+
+    /// Advances the CPU by one clock cycle.
+    ///
+    /// The whole instruction is executed on its first cycle and the remaining
+    /// cycles are spent idle. This is the fastest way to run the CPU, but devices
+    /// see every bus access of an instruction at once.
+    ///
+    /// # Returns
+    ///
+    /// `Some(StopReason)` if execution should stop. At a breakpoint the next
+    /// instruction hasn't been started and no cycle elapsed. At a watchpoint the
+    /// instruction that made the access was executed on this cycle. `None` otherwise.
+    pub fn clock(&mut self) -> Option<StopReason> {
+        if self.cycles == 0 {
+            // A waiting, stopped or jammed CPU doesn't fetch instructions, but time still passes
+            if !self.is_running() {
+                self.tick_devices(1);
+                self.total_cycles += 1;
+                return None;
+            }
+
+            // Stop before executing past a breakpoint
+            if let Some(reason) = self.check_breakpoint() {
+                return Some(reason);
+            }
+            self.record_rewind();
+
+            if !self.poll_interrupts() {
+                self.execute_next_instruction();
+            }
+
+            // The devices are advanced by the whole instruction at once, like its accesses
+            self.tick_instruction(self.cycles as u64);
+        }
+        self.cycles -= 1;
+        self.total_cycles += 1;
+        self.watch_hit.take().map(StopReason::Watchpoint)
+    }
+
+    /// Advances the CPU by one clock cycle, spreading the instruction's writes
+    /// over the cycles they happen on.
+    ///
+    /// Like `clock()`, the instruction is decoded and executed on its first
+    /// cycle, so opcode, operand and data reads all happen then. Writes, however,
+    /// are held back and performed one per cycle at the end of the instruction,
+    /// which is where the 6502 puts them: the final cycle for stores, the last two
+    /// cycles for read-modify-write instructions with `rmw_dummy_write` enabled
+    /// on NMOS models, and so on. Devices that are clocked alongside the CPU therefore observe
+    /// writes on their true cycle.
+    ///
+    /// JSR, BRK and interrupts are the exception: their stack pushes land at the
+    /// end of the instruction rather than in the middle of it.
+    ///
+    /// `tick()` and `clock()` can be mixed freely at instruction boundaries.
+    ///
+    /// # Returns
+    ///
+    /// `Some(StopReason)` if execution should stop, as for `clock()`.
+    pub fn tick(&mut self) -> Option<StopReason> {
+        if self.cycles == 0 {
+            // A waiting, stopped or jammed CPU doesn't fetch instructions, but time still passes
+            if !self.is_running() {
+                self.tick_devices(1);
+                self.total_cycles += 1;
+                return None;
+            }
+
+            // Stop before executing past a breakpoint
+            if let Some(reason) = self.check_breakpoint() {
+                return Some(reason);
+            }
+            self.record_rewind();
+
+            // Execute the instruction or interrupt sequence, queueing its writes
+            // instead of performing them
+            self.defer_writes = true;
+            if !self.poll_interrupts() {
+                self.execute_next_instruction();
+            }
+            self.defer_writes = false;
+        }
+
+        // Writes are right-aligned to the end of the instruction, one per cycle
+        while self.deferred_writes.len() >= self.cycles as usize {
+            match self.deferred_writes.pop_front() {
+                Some((address, value)) => self.write8(address, value),
+                None => break,
+            }
+        }
+        self.tick_devices(1);
+        self.cycles -= 1;
+        self.total_cycles += 1;
+        if self.irq_sample != 0 && self.cycles == self.irq_sample {
+            self.sample_irq();
+        }
+        self.watch_hit.take().map(StopReason::Watchpoint)
+    }
+
+    /// Advances the devices on the bus by a number of cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles that have elapsed.
+    fn tick_devices(&mut self, cycles: u64) {
+        self.with_bus_mut(|bus| bus.tick(cycles));
+    }
+
+    /// Advances the devices on the bus through a whole instruction, sampling the
+    /// IRQ line on the way at the point the instruction polls it.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The cycles the instruction takes, including any interrupt sequence before it.
+    fn tick_instruction(&mut self, cycles: u64) {
+        let after = (self.irq_sample as u64).min(cycles);
+        if after == 0 {
+            self.tick_devices(cycles);
+            return;
+        }
+        self.tick_devices(cycles - after);
+        self.sample_irq();
+        self.tick_devices(after);
+    }
+
+    /// Samples the IRQ line for the next instruction boundary. An IRQ that isn't
+    /// asserted by now isn't taken there, even if it is asserted in time for it.
+    fn sample_irq(&mut self) {
+        if !self.irq_asserted() {
+            self.irq_inhibited = true;
+        }
+        self.irq_sample = 0;
+    }
+
+    /// Clocks the CPU until the current instruction or interrupt sequence is complete.
+    ///
+    /// If the CPU is at an instruction boundary, the next instruction is started
+    /// first.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles that elapsed, which is 0 if the CPU is waiting,
+    /// stopped or jammed, or hit a breakpoint, and the reason to stop if there is one.
+    pub(crate) fn finish_instruction(&mut self) -> (u64, Option<StopReason>) {
+        let mut elapsed = 0;
+        let mut stop = None;
+
+        // Start the next instruction, unless there is nothing to run
+        if self.cycles == 0 {
+            if !self.is_running() {
+                return (0, None);
+            }
+            stop = self.clock();
+            if let Some(StopReason::Breakpoint(_)) = stop {
+                return (0, stop);
+            }
+            elapsed += 1;
+        }
+
+        // Burn the rest of its cycles
+        while self.cycles > 0 {
+            stop = stop.or(self.clock());
+            elapsed += 1;
+        }
+
+        (elapsed, stop)
+    }
+
+    /// Runs whole instructions until at least the given number of cycles have elapsed.
+    ///
+    /// Execution only stops at instruction boundaries, so the last instruction
+    /// may take the total past `cycles`. It also stops early at a breakpoint, after
+    /// an instruction that hit a watchpoint, or if the CPU is waiting, stopped or jammed.
+    ///
+    /// Unless the bus has been cloned, the CPU holds it for the whole run
+    /// rather than borrowing it for every access. See `with_bus_held()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles to run for.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles that actually elapsed.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> u64 {
+        self.with_bus_held(|cpu| {
+            let mut elapsed = 0;
+
+            while elapsed < cycles {
+                let (instruction_cycles, stop) = cpu.finish_instruction();
+                elapsed += instruction_cycles;
+                if instruction_cycles == 0 || stop.is_some() {
+                    break;
+                }
+            }
+
+            elapsed
+        })
+    }
+
+    /// Runs whole instructions until the predicate holds at an instruction boundary.
+    ///
+    /// The predicate is checked before each instruction, including the first,
+    /// so nothing is run if it already holds. Execution also stops at a
+    /// breakpoint, after an instruction that hit a watchpoint, or if the CPU is
+    /// waiting, stopped or jammed; otherwise it runs for as long as it takes.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - Called with the CPU before each instruction; returns `true` to stop.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles that elapsed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let cycles = cpu.run_until(|cpu| cpu.pc.get() == 0x8000);
+    /// ```
+    pub fn run_until<F: FnMut(&Cpu) -> bool>(&mut self, mut predicate: F) -> u64 {
+        let mut elapsed = 0;
+
+        // Get to an instruction boundary before checking the predicate
+        while self.cycles > 0 {
+            self.clock();
+            elapsed += 1;
+        }
+
+        while !predicate(self) {
+            let (instruction_cycles, stop) = self.finish_instruction();
+            elapsed += instruction_cycles;
+            if instruction_cycles == 0 || stop.is_some() {
+                break;
+            }
+        }
+
+        elapsed
+    }
+
+    /// Runs exactly one instruction to completion and reports what it did.
+    ///
+    /// Any instruction that `clock()` left partway through is finished first.
+    /// If an interrupt is pending it is serviced, and the record then describes
+    /// the first instruction of the handler.
+    ///
+    /// # Returns
+    ///
+    /// An `InstructionRecord` describing the instruction. If the CPU is waiting,
+    /// stopped or jammed, nothing is executed and the record has zero cycles.
+    /// The same goes for a breakpoint, which is reported in the record's `stop`
+    /// field; stepping again executes the instruction. A watchpoint hit is
+    /// reported there too, after the instruction has been executed.
+    pub fn step(&mut self) -> InstructionRecord {
+        // Burn the rest of the instruction in progress
+        while self.cycles > 0 {
+            self.clock();
+        }
+
+        let mut running = self.is_running();
+
+        // A breakpoint holds the instruction back
+        let mut stop = if running { self.check_breakpoint() } else { None };
+        running &= stop.is_none();
+        if running {
+            self.record_rewind();
+        }
+
+        self.accesses.borrow_mut().clear();
+        self.record_accesses = true;
+
+        // An interrupt sequence runs before the instruction, as it would in `clock()`
+        let mut cycles = 0;
+        let interrupt = running && self.poll_interrupts();
+        if interrupt {
+            cycles += self.cycles as u32;
+        }
+
+        // Read the instruction bytes for the record
+        let pc = self.pc.get();
+        let opcode = self.peek8(pc);
+        let instruction = self.instruction(opcode);
+        let operands = (1..=instruction.mode.operand_length()).map(|i| self.peek8(pc.wrapping_add(i))).collect();
+
+        if running {
+            self.execute_next_instruction();
+            cycles += self.cycles as u32;
+        }
+
+        self.record_accesses = false;
+        self.cycles = 0;
+        if cycles > 0 {
+            self.tick_instruction(cycles as u64);
+            self.total_cycles += cycles as u64;
+        }
+
+        // Report a watchpoint hit by the interrupt sequence or the instruction
+        if let Some(hit) = self.watch_hit.take() {
+            stop = Some(StopReason::Watchpoint(hit));
+        }
+
+        InstructionRecord {
+            pc,
+            opcode,
+            mnemonic: instruction.name,
+            operands,
+            mode: instruction.mode,
+            accesses: self.accesses.take(),
+            cycles,
+            interrupt,
+            stop,
+        }
+    }
+
+    /// Formats the instruction at the program counter and the registers as a line of nestest.log.
+    ///
+    /// The line shows the state before the instruction executes, in the columns
+    /// nestest.log uses: the address, the instruction bytes, the disassembly
+    /// (marked with `*` for undocumented opcodes), the registers and the cycle
+    /// count. Branch targets are shown as absolute addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles that have elapsed, shown in the CYC column.
+    ///
+    /// # Returns
+    ///
+    /// The trace line, without a line ending.
+    pub fn nestest_line(&self, cycles: u64) -> String {
+        let pc = self.pc.get();
+        let instruction = self.disassemble_instruction_at(pc);
+
+        // The opcode and operand bytes
+        let bytes: Vec<String> = core::iter::once(&instruction.opcode)
+            .chain(&instruction.operands)
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+
+        format!(
+            "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            pc,
+            bytes.join(" "),
+            if instruction.illegal { '*' } else { ' ' },
+            instruction.to_string(),
+            self.a.get(),
+            self.x.get(),
+            self.y.get(),
+            self.p.get(),
+            self.sp.get(),
+            cycles
+        )
+    }
+
+    /// Runs the CPU alongside a reference log in nestest format, stopping at the first divergence.
+    ///
+    /// Before each instruction, the CPU's trace line is compared with the next
+    /// line of the log. The address, instruction bytes, registers and cycle count
+    /// are compared; the disassembly and anything after the registers, such as
+    /// the PPU position, are not. Empty lines are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - The contents of the reference log.
+    /// * `cycles` - The cycle count at the first line of the log, which is 7 for nestest.log.
+    ///
+    /// # Returns
+    ///
+    /// The number of lines that matched if the whole log did, or a `TraceMismatch`
+    /// describing the first line that didn't.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// cpu.pc.set(0xC000);
+    /// cpu.compare_nestest(&std::fs::read_to_string("nestest.log")?, 7)?;
+    /// ```
+    pub fn compare_nestest(&mut self, reference: &str, mut cycles: u64) -> Result<usize, TraceMismatch> {
+        let mut matched = 0;
+
+        for (index, expected) in reference.lines().enumerate() {
+            let expected = expected.trim_end();
+            if expected.is_empty() {
+                continue;
+            }
+
+            let actual = self.nestest_line(cycles);
+            if let Some(field) = trace::first_difference(&actual, expected) {
+                return Err(TraceMismatch {
+                    line: index + 1,
+                    field,
+                    expected: String::from(expected),
+                    actual,
+                });
+            }
+
+            matched += 1;
+            cycles += self.step().cycles as u64;
+        }
+
+        Ok(matched)
+    }
+}
+
+impl Cpu {
+    /// Saves the registers and internal state of the CPU.
+    ///
+    /// Debugger state, such as breakpoints and watchpoints, isn't included.
+    ///
+    /// # Returns
+    ///
+    /// A `CpuSnapshot` that can be restored with `load_state()`.
+    pub fn save_state(&self) -> CpuSnapshot {
+        CpuSnapshot {
+            model: self.model,
+            a: self.a.get(),
+            x: self.x.get(),
+            y: self.y.get(),
+            b: self.b.get(),
+            p: self.p.get(),
+            sp: self.sp.get(),
+            pc: self.pc.get(),
+            cycles: self.cycles,
+            total_cycles: self.total_cycles,
+            address_absolute: self.address_absolute,
+            address_relative: self.address_relative,
+            address_mode: self.address_mode,
+            opcode: self.opcode,
+            fetched_data: self.fetched_data,
+            enable_illegal_opcodes: self.enable_illegal_opcodes,
+            rmw_dummy_write: self.rmw_dummy_write,
+            defer_writes: self.defer_writes,
+            deferred_writes: self.deferred_writes.iter().copied().collect(),
+            irq_line: self.irq_line,
+            nmi_line: self.nmi_line,
+            nmi_pending: self.nmi_pending,
+            irq_inhibited: self.irq_inhibited,
+            irq_sample: self.irq_sample,
+            state: self.state,
+            emulation: self.emulation,
+            instruction_pc: self.instruction_pc,
+        }
+    }
+
+    /// Restores a state saved by `save_state()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The saved state.
+    pub fn load_state(&mut self, snapshot: &CpuSnapshot) {
+        self.model = snapshot.model;
+        self.a.set(snapshot.a);
+        self.x.set(snapshot.x);
+        self.y.set(snapshot.y);
+        self.b.set(snapshot.b);
+        self.p.set(snapshot.p);
+        self.sp.set(snapshot.sp);
+        self.pc.set(snapshot.pc);
+        self.cycles = snapshot.cycles;
+        self.total_cycles = snapshot.total_cycles;
+        self.address_absolute = snapshot.address_absolute;
+        self.address_relative = snapshot.address_relative;
+        self.address_mode = snapshot.address_mode;
+        self.opcode = snapshot.opcode;
+        self.fetched_data = snapshot.fetched_data;
+        self.enable_illegal_opcodes = snapshot.enable_illegal_opcodes;
+        self.rmw_dummy_write = snapshot.rmw_dummy_write;
+        self.defer_writes = snapshot.defer_writes;
+        self.deferred_writes = snapshot.deferred_writes.iter().copied().collect();
+        self.irq_line = snapshot.irq_line;
+        self.nmi_line = snapshot.nmi_line;
+        self.nmi_pending = snapshot.nmi_pending;
+        self.irq_inhibited = snapshot.irq_inhibited;
+        self.irq_sample = snapshot.irq_sample;
+        self.state = snapshot.state;
+        self.emulation = snapshot.emulation;
+        self.instruction_pc = snapshot.instruction_pc;
+
+        // Any breakpoint that was reported belongs to the old state, and so do the calls
+        self.resume_at = None;
+        self.call_stack.clear();
+        self.watch_hit.set(None);
+    }
+
+    /// Saves the whole machine: the CPU and every device on its bus.
+    ///
+    /// # Returns
+    ///
+    /// A `MachineSnapshot` that can be restored with `restore()`.
+    pub fn snapshot(&self) -> MachineSnapshot {
+        MachineSnapshot { cpu: self.save_state(), bus: self.with_bus(MainBus::save_state) }
+    }
+
+    /// Starts taking snapshots so that execution can be rewound with `rewind()`.
+    ///
+    /// Any snapshots already taken are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The number of instructions between snapshots.
+    /// * `capacity` - The maximum number of snapshots kept; older ones are dropped.
+    pub fn enable_rewind(&mut self, interval: usize, capacity: usize) {
+        self.rewinder = Some(Rewinder::new(interval, capacity));
+    }
+
+    /// Stops taking snapshots and drops the ones already taken.
+    pub fn disable_rewind(&mut self) {
+        self.rewinder = None;
+    }
+
+    /// Returns the rewind snapshots, if rewinding is enabled.
+    pub fn rewinder(&self) -> Option<&Rewinder> {
+        self.rewinder.as_ref()
+    }
+
+    /// Steps execution backwards to an earlier snapshot.
+    ///
+    /// The CPU is put back at the start of the instruction the snapshot was
+    /// taken at, with the bus as it was then. Snapshots newer than that one are
+    /// dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - The number of snapshots to go back. 1 is the newest snapshot.
+    ///
+    /// # Returns
+    ///
+    /// The number of snapshots gone back, which is 0 if rewinding is disabled or
+    /// nothing has been recorded, or an error if the bus has been rewired since.
+    pub fn rewind(&mut self, steps: usize) -> Result<usize, SnapshotError> {
+        // Take the rewinder out so the CPU can be restored from its snapshot
+        let Some(mut rewinder) = self.rewinder.take() else {
+            return Ok(0);
+        };
+        let result = match rewinder.rewind(steps) {
+            Some((steps, snapshot)) => self.restore(snapshot).map(|_| steps),
+            None => Ok(0),
+        };
+        self.rewinder = Some(rewinder);
+        result
+    }
+
+    /// Keeps a history of the most recently executed instructions, so the path
+    /// that led to a jam or a crash can be inspected afterwards.
+    ///
+    /// Any history already kept is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of instructions kept; older ones are dropped.
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history = Some(History::new(capacity));
+    }
+
+    /// Stops keeping a history and drops the one already kept.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Returns the most recently executed instructions, if the history is enabled.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// cpu.enable_history(64);
+    /// cpu.run_until(|cpu| cpu.is_jammed());
+    /// print!("{}", cpu.history().unwrap());
+    /// ```
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    /// Adds the instruction about to be executed to the history.
+    ///
+    /// # Arguments
+    ///
+    /// * `instruction` - The instruction, whose opcode has been fetched but whose operands haven't.
+    fn record_history(&mut self, instruction: &Instruction) {
+        let pc = self.instruction_pc;
+        let entry = HistoryEntry {
+            pc,
+            opcode: self.opcode,
+            operands: [self.peek8(pc.wrapping_add(1)), self.peek8(pc.wrapping_add(2))],
+            mnemonic: instruction.name,
+            mode: instruction.mode,
+            illegal: instruction.illegal,
+            a: self.a.get(),
+            x: self.x.get(),
+            y: self.y.get(),
+            p: self.p.get(),
+            sp: self.sp.get(),
+        };
+        if let Some(history) = &mut self.history {
+            history.push(entry);
+        }
+    }
+
+    /// Lets the rewinder take a snapshot at the start of an instruction.
+    fn record_rewind(&mut self) {
+        if let Some(mut rewinder) = self.rewinder.take() {
+            rewinder.record(|| self.snapshot());
+            self.rewinder = Some(rewinder);
+        }
+    }
+
+    /// Restores a machine saved by `snapshot()`.
+    ///
+    /// The bus is restored first; if its devices don't match the snapshot,
+    /// neither the bus nor the CPU is changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The saved machine.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the machine was restored, or the reason it couldn't be.
+    pub fn restore(&mut self, snapshot: &MachineSnapshot) -> Result<(), SnapshotError> {
+        self.with_bus_mut(|bus| bus.load_state(&snapshot.bus))?;
+        self.load_state(&snapshot.cpu);
+        Ok(())
+    }
 }
 
 impl Display for Cpu {
@@ -726,7 +2210,7 @@ impl Display for Cpu {
     /// # Returns
     ///
     /// The formatted CPU state.
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         // Format the CPU state into the formatter
         write!(
             f,
@@ -739,4 +2223,1326 @@ impl Display for Cpu {
             self.pc.get()
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    use crate::bus::{MainBus, SharedBus};
+    use crate::bus::keyboard::{Keyboard, IRQ_ENABLE};
+    use crate::bus::ram::Ram;
+    use crate::bus::sound::ToneGenerator;
+    use crate::bus::{AccessKind, BusAccess};
+    use crate::cpu::instructions;
+    use crate::cpu::{
+        AddressingMode, Breakpoint, CallKind, Condition, Cpu, CpuModel, CpuState, DebugLevel, RegisterWidth,
+        StatusFlags, StopReason, WatchHit, WatchKind,
+    };
+
+    /// Creates a CPU backed by 64K of RAM with `program` loaded at $0200.
+    fn setup(program: &[u8]) -> Cpu {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        bus.add_device(Box::new(Ram::new(0x8000, 0xFFFF)));
+        let mut cpu = Cpu::new(SharedBus::new(bus));
+        for (offset, byte) in program.iter().enumerate() {
+            cpu.write8(0x0200 + offset as u16, *byte);
+        }
+        cpu.pc.set(0x0200);
+        cpu
+    }
+
+    #[test]
+    fn zn_flags_come_from_the_result_alone() {
+        let mut cpu = setup(&[]);
+        for value in 0..=255u8 {
+            // The other flags are all set, and must stay set
+            cpu.p.set(0xFF);
+            cpu.set_zn_flags(value);
+            assert_eq!(cpu.get_flag(StatusFlags::Zero), value == 0);
+            assert_eq!(cpu.get_flag(StatusFlags::Negative), value >= 0x80);
+            assert_eq!(cpu.p.get() | 0x82, 0xFF);
+        }
+    }
+
+    #[test]
+    fn tick_performs_store_on_final_cycle() {
+        // STA $1234
+        let mut cpu = setup(&[0x8D, 0x34, 0x12]);
+        cpu.a.set(0x42);
+
+        for _ in 0..3 {
+            cpu.tick();
+            assert_eq!(cpu.read8(0x1234), 0x00);
+        }
+        cpu.tick();
+
+        assert_eq!(cpu.read8(0x1234), 0x42);
+        assert_eq!(cpu.cycles, 0);
+    }
+
+    #[test]
+    fn tick_places_rmw_dummy_write_before_final_write() {
+        // INC $10
+        let mut cpu = setup(&[0xE6, 0x10]);
+        cpu.rmw_dummy_write = true;
+        cpu.write8(0x0010, 0x07);
+
+        for _ in 0..3 {
+            cpu.tick();
+        }
+        assert_eq!(cpu.deferred_writes.len(), 2);
+        cpu.tick();
+        assert_eq!(cpu.read8(0x0010), 0x07);
+        cpu.tick();
+
+        assert_eq!(cpu.read8(0x0010), 0x08);
+        assert!(cpu.deferred_writes.is_empty());
+    }
+
+    #[test]
+    fn cmos_rmw_dummy_access_is_a_read() {
+        // INC $10
+        let mut cpu = setup(&[0xE6, 0x10]);
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.rmw_dummy_write = true;
+        cpu.write8(0x0010, 0x07);
+
+        let accesses: Vec<(AccessKind, u16)> =
+            cpu.step().accesses.iter().map(|access| (access.kind, access.address)).collect();
+        assert_eq!(
+            accesses,
+            [
+                (AccessKind::Read, 0x0200),
+                (AccessKind::Read, 0x0201),
+                (AccessKind::Read, 0x0010),
+                (AccessKind::Read, 0x0010),
+                (AccessKind::Write, 0x0010),
+            ]
+        );
+        assert_eq!(cpu.read8(0x0010), 0x08);
+    }
+
+    /// Clocks the CPU until the current instruction or interrupt sequence has finished.
+    fn run_instruction(cpu: &mut Cpu) {
+        cpu.clock();
+        while cpu.cycles > 0 {
+            cpu.clock();
+        }
+    }
+
+    #[test]
+    fn nmi_takes_priority_over_irq() {
+        // NOP
+        let mut cpu = setup(&[0xEA]);
+        cpu.write16(0xFFFA, 0x3000);
+        cpu.write16(0xFFFE, 0x4000);
+        cpu.p.set(0x20);
+        cpu.irq_inhibited = false;
+        cpu.sp.set(0xFF);
+
+        cpu.assert_irq();
+        cpu.trigger_nmi();
+        run_instruction(&mut cpu);
+
+        assert_eq!(cpu.pc.get(), 0x3000);
+        assert_eq!(cpu.sp.get(), 0xFC);
+        // The pushed status has the Break flag clear
+        assert_eq!(cpu.read8(0x01FD), 0x20);
+        assert_eq!(cpu.read16(0x01FE), 0x0200);
+    }
+
+    #[test]
+    fn devices_are_ticked_as_cycles_pass() {
+        // A tone generator renders a sample a cycle at this rate, which counts the ticks
+        let mut cpu = setup(&[0xEA, 0xEA, 0xEA]);
+        cpu.bus.borrow_mut().add_device_with_priority(Box::new(ToneGenerator::new(0xD400, 1_000_000, 1_000_000)), 1);
+        let ticked = |cpu: &Cpu| cpu.bus.borrow_mut().device_of_type_mut::<ToneGenerator>().unwrap().take_samples().len();
+
+        // Clocking ticks the devices by the whole instruction as it starts
+        cpu.clock();
+        assert_eq!(ticked(&cpu), 2);
+        cpu.clock();
+        assert_eq!(ticked(&cpu), 0);
+
+        cpu.step();
+        assert_eq!(ticked(&cpu), 2);
+
+        // Cycle stepping ticks them every cycle
+        cpu.tick();
+        assert_eq!(ticked(&cpu), 1);
+        cpu.tick();
+        assert_eq!(ticked(&cpu), 1);
+
+        // Time passes for the devices while the CPU waits for an interrupt
+        cpu.state = CpuState::Waiting;
+        cpu.clock();
+        cpu.tick();
+        assert_eq!(ticked(&cpu), 2);
+    }
+
+    #[test]
+    fn held_bus_is_given_back_when_a_run_panics() {
+        let mut cpu = setup(&[0xEA]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cpu.with_bus_held(|_| panic!("the run failed"));
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(cpu.bus.borrow().devices().len(), 2);
+        assert_eq!(cpu.read8(0x0200), 0xEA);
+    }
+
+    #[test]
+    fn devices_added_while_the_bus_is_held_are_kept() {
+        let mut cpu = setup(&[0xEA]);
+        cpu.with_bus_held(|cpu| {
+            cpu.bus.borrow_mut().add_device_with_priority(Box::new(Ram::new(0x0200, 0x02FF)), 1);
+            cpu.run_for_cycles(2);
+        });
+
+        // The new RAM decodes ahead of the old, which still holds the NOP
+        let bus = cpu.bus.borrow();
+        assert_eq!(bus.devices().len(), 3);
+        assert_eq!(bus.device_index_at(0x0200), Some(0));
+        assert_eq!(bus.read(0x0200), 0x00);
+    }
+
+    #[test]
+    fn words_wrap_at_the_top_of_memory() {
+        let mut cpu = setup(&[]);
+        cpu.write16(0xFFFF, 0x1234);
+
+        assert_eq!(cpu.read8(0xFFFF), 0x34);
+        assert_eq!(cpu.read8(0x0000), 0x12);
+        assert_eq!(cpu.read16(0xFFFF), 0x1234);
+    }
+
+    #[test]
+    fn total_cycles_count_every_cycle_clocked() {
+        // NOP, NOP, NOP, NOP
+        let mut cpu = setup(&[0xEA, 0xEA, 0xEA, 0xEA]);
+        cpu.add_breakpoint(0x0203);
+
+        cpu.clock();
+        assert_eq!(cpu.total_cycles(), 1);
+        // Stepping finishes the NOP in progress, then runs another
+        cpu.step();
+        assert_eq!(cpu.total_cycles(), 4);
+        cpu.tick();
+        cpu.tick();
+        assert_eq!(cpu.total_cycles(), 6);
+
+        // A breakpoint stops the CPU before any time passes
+        assert_eq!(cpu.clock(), Some(StopReason::Breakpoint(0x0203)));
+        assert_eq!(cpu.total_cycles(), 6);
+
+        // Time still passes while the CPU waits, and a reset doesn't turn the clock back
+        cpu.state = CpuState::Waiting;
+        cpu.clock();
+        cpu.tick();
+        cpu.reset();
+        assert_eq!(cpu.total_cycles(), 8);
+    }
+
+    #[test]
+    fn devices_share_the_irq_line() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        bus.add_device_with_priority(Box::new(Keyboard::new(0xD010)), 1);
+        let timer = bus.interrupts.line("Timer");
+
+        // NOP, NOP
+        let mut cpu = Cpu::new(SharedBus::new(bus));
+        cpu.write8(0x0200, 0xEA);
+        cpu.write8(0x0201, 0xEA);
+        cpu.write16(0xFFFE, 0x4000);
+        let restart = |cpu: &mut Cpu| {
+            cpu.pc.set(0x0200);
+            cpu.p.set(0x20);
+            cpu.irq_inhibited = false;
+            cpu.sp.set(0xFF);
+        };
+
+        // An IRQ line from the interrupt controller
+        restart(&mut cpu);
+        timer.assert();
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x4000);
+
+        restart(&mut cpu);
+        timer.release();
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0201);
+
+        // A device on the bus
+        restart(&mut cpu);
+        cpu.write8(0xD011, IRQ_ENABLE);
+        cpu.bus.borrow_mut().device_of_type_mut::<Keyboard>().unwrap().push_key(b'A');
+        assert!(cpu.irq_asserted() && !cpu.irq_line());
+        assert_eq!(cpu.bus.borrow().irq_sources(), ["Keyboard"]);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x4000);
+
+        // Reading the key releases the line
+        cpu.read8(0xD010);
+        assert!(!cpu.irq_asserted());
+    }
+
+    #[test]
+    fn irq_is_delayed_by_one_instruction_after_cli() {
+        // CLI, NOP, NOP
+        let mut cpu = setup(&[0x58, 0xEA, 0xEA]);
+        cpu.write16(0xFFFE, 0x4000);
+        cpu.p.set(0x24);
+        cpu.sp.set(0xFF);
+        cpu.assert_irq();
+
+        // CLI
+        run_instruction(&mut cpu);
+        // The IRQ is still masked at this boundary, so the first NOP runs
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0202);
+
+        // Now the IRQ is taken
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x4000);
+        assert_eq!(cpu.read16(0x01FE), 0x0202);
+    }
+
+    #[test]
+    fn irq_is_ignored_while_interrupts_are_disabled() {
+        // NOP, NOP
+        let mut cpu = setup(&[0xEA, 0xEA]);
+        cpu.write16(0xFFFE, 0x4000);
+        cpu.p.set(0x24);
+        cpu.assert_irq();
+
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+
+        assert_eq!(cpu.pc.get(), 0x0202);
+    }
+
+    #[test]
+    fn held_irq_fires_again_after_rti() {
+        // NOP, NOP
+        let mut cpu = setup(&[0xEA, 0xEA]);
+        // RTI at the handler
+        cpu.write8(0x4000, 0x40);
+        cpu.write16(0xFFFE, 0x4000);
+        cpu.p.set(0x20);
+        cpu.irq_inhibited = false;
+        cpu.sp.set(0xFF);
+        cpu.set_irq_line(true);
+
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x4000);
+
+        // RTI restores the cleared Interrupt Disable flag
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0200);
+        assert_eq!(cpu.sp.get(), 0xFF);
+
+        // The line is still held, so the interrupt is taken again
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x4000);
+
+        // Once released, the main program continues
+        cpu.set_irq_line(false);
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0201);
+    }
+
+    #[test]
+    fn brk_and_rti_skip_the_padding_byte() {
+        // BRK, padding, PHP, PLP
+        let mut cpu = setup(&[0x00, 0xFF, 0x08, 0x28]);
+        // RTI at the handler
+        cpu.write8(0x4000, 0x40);
+        cpu.write16(0xFFFE, 0x4000);
+        cpu.p.set(0x20);
+        cpu.sp.set(0xFF);
+
+        // BRK pushes the address after the padding byte and the status with B set
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x4000);
+        assert_eq!(cpu.read16(0x01FE), 0x0202);
+        assert_eq!(cpu.read8(0x01FD), 0x30);
+        assert!(cpu.get_flag(StatusFlags::InterruptDisable));
+
+        // RTI restores both
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0202);
+        assert_eq!(cpu.p.get(), 0x20);
+        assert_eq!(cpu.sp.get(), 0xFF);
+
+        // PHP pushes B set too, and PLP leaves B clear whatever it pulls
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.read8(0x01FF), 0x30);
+        cpu.write8(0x01FF, 0xFF);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.p.get(), 0xEF);
+        assert_eq!(cpu.sp.get(), 0xFF);
+    }
+
+    #[test]
+    fn nmi_fires_once_per_falling_edge() {
+        // NOP, NOP, NOP
+        let mut cpu = setup(&[0xEA, 0xEA, 0xEA]);
+        // NOP, NOP at the handler
+        cpu.write8(0x3000, 0xEA);
+        cpu.write8(0x3001, 0xEA);
+        cpu.write16(0xFFFA, 0x3000);
+        cpu.sp.set(0xFF);
+
+        cpu.set_nmi_line(true);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x3000);
+
+        // Holding the line does not trigger another NMI
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x3001);
+
+        // Releasing and asserting it again does
+        cpu.set_nmi_line(false);
+        cpu.set_nmi_line(true);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x3000);
+        assert_eq!(cpu.sp.get(), 0xF9);
+    }
+
+    #[test]
+    fn branches_test_their_flag_and_cost_extra_when_taken() {
+        let branches = [
+            (0x10, StatusFlags::Negative, false),
+            (0x30, StatusFlags::Negative, true),
+            (0x50, StatusFlags::Overflow, false),
+            (0x70, StatusFlags::Overflow, true),
+            (0x90, StatusFlags::Carry, false),
+            (0xB0, StatusFlags::Carry, true),
+            (0xD0, StatusFlags::Zero, false),
+            (0xF0, StatusFlags::Zero, true),
+        ];
+        for (opcode, flag, taken_when) in branches {
+            for value in [false, true] {
+                let mut cpu = setup(&[opcode, 0x10]);
+                cpu.p.set(if value { 0x20 | flag.bits() } else { 0x20 });
+                let taken = value == taken_when;
+                assert_eq!(cpu.step().cycles, if taken { 3 } else { 2 }, "opcode {:02X}", opcode);
+                assert_eq!(cpu.pc.get(), if taken { 0x0212 } else { 0x0202 }, "opcode {:02X}", opcode);
+            }
+        }
+
+        // BNE -4 from $0200 lands on the page before, which costs one more
+        let mut cpu = setup(&[0xD0, 0xFC]);
+        assert_eq!(cpu.step().cycles, 4);
+        assert_eq!(cpu.pc.get(), 0x01FE);
+    }
+
+    #[test]
+    fn rockwell_bit_instructions_on_65c02() {
+        // SMB3 $10, BBS3 $10,+2, RMB3 $10, BBR3 $10,-5
+        let mut cpu = setup(&[0xB7, 0x10, 0xBF, 0x10, 0x02, 0x37, 0x10, 0x3F, 0x10, 0xFB]);
+        cpu.model = CpuModel::Wdc65C02;
+
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.read8(0x0010), 0x08);
+
+        // The bit is set, so the branch skips over RMB3 to BBR3
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0207);
+
+        // The bit is still set, so BBR3 falls through
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x020A);
+    }
+
+    #[test]
+    fn rockwell_opcodes_are_undocumented_on_nmos() {
+        // SMB0 $10 is SAX $10 on the NMOS 6502, which is disabled by default
+        let mut cpu = setup(&[0x87, 0x10]);
+        cpu.write8(0x0010, 0x01);
+
+        run_instruction(&mut cpu);
+
+        assert_eq!(cpu.read8(0x0010), 0x01);
+        assert_eq!(cpu.pc.get(), 0x0202);
+    }
+
+    #[test]
+    fn wai_waits_for_an_interrupt() {
+        // WAI, NOP
+        let mut cpu = setup(&[0xCB, 0xEA]);
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.p.set(0x24);
+
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.state(), CpuState::Waiting);
+        for _ in 0..10 {
+            cpu.clock();
+        }
+        assert_eq!(cpu.pc.get(), 0x0201);
+
+        // With interrupts disabled, an IRQ only wakes the CPU up
+        cpu.assert_irq();
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.state(), CpuState::Running);
+        assert_eq!(cpu.pc.get(), 0x0202);
+    }
+
+    #[test]
+    fn stp_stops_until_reset() {
+        // STP
+        let mut cpu = setup(&[0xDB]);
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.write16(0xFFFC, 0x0200);
+
+        run_instruction(&mut cpu);
+        cpu.trigger_nmi();
+        for _ in 0..10 {
+            cpu.clock();
+        }
+        assert_eq!(cpu.state(), CpuState::Stopped);
+        assert_eq!(cpu.pc.get(), 0x0201);
+
+        cpu.reset();
+        assert_eq!(cpu.state(), CpuState::Running);
+    }
+
+    #[test]
+    fn adc_and_sbc_set_carry_and_overflow() {
+        // CLC, LDA #$50, ADC #$50, SEC, SBC #$B0
+        let mut cpu = setup(&[0x18, 0xA9, 0x50, 0x69, 0x50, 0x38, 0xE9, 0xB0]);
+
+        // Two positive numbers that add up to a negative one overflow
+        for _ in 0..3 {
+            run_instruction(&mut cpu);
+        }
+        assert_eq!(cpu.a.get(), 0xA0);
+        assert!(cpu.get_flag(StatusFlags::Overflow));
+        assert!(!cpu.get_flag(StatusFlags::Carry));
+        assert!(cpu.get_flag(StatusFlags::Negative));
+
+        // Subtracting a larger number borrows, but two negatives can't overflow
+        for _ in 0..2 {
+            run_instruction(&mut cpu);
+        }
+        assert_eq!(cpu.a.get(), 0xF0);
+        assert!(!cpu.get_flag(StatusFlags::Overflow));
+        assert!(!cpu.get_flag(StatusFlags::Carry));
+        assert!(cpu.get_flag(StatusFlags::Negative));
+    }
+
+    #[test]
+    fn adc_honours_decimal_mode_on_nmos() {
+        // SED, CLC, LDA #$19, ADC #$28
+        let mut cpu = setup(&[0xF8, 0x18, 0xA9, 0x19, 0x69, 0x28]);
+
+        for _ in 0..4 {
+            run_instruction(&mut cpu);
+        }
+
+        assert_eq!(cpu.a.get(), 0x47);
+    }
+
+    #[test]
+    fn cmos_decimal_flags_come_from_the_bcd_result() {
+        // SED, CLC, LDA #$99, ADC #$01
+        for (model, n, z) in [(CpuModel::Nmos6502, true, false), (CpuModel::Wdc65C02, false, true)] {
+            let mut cpu = setup(&[0xF8, 0x18, 0xA9, 0x99, 0x69, 0x01]);
+            cpu.model = model;
+
+            for _ in 0..4 {
+                run_instruction(&mut cpu);
+            }
+            assert_eq!(cpu.a.get(), 0x00, "{}", model);
+            assert!(cpu.get_flag(StatusFlags::Carry), "{}", model);
+            assert_eq!(cpu.get_flag(StatusFlags::Negative), n, "{}", model);
+            assert_eq!(cpu.get_flag(StatusFlags::Zero), z, "{}", model);
+        }
+    }
+
+    #[test]
+    fn decimal_mode_costs_an_extra_cycle_on_the_65c02() {
+        // SED, ADC #$01, SBC #$01, CLD, ADC #$01
+        for (model, cycles) in [(CpuModel::Nmos6502, [2, 2, 2, 2]), (CpuModel::Wdc65C02, [3, 3, 2, 2])] {
+            let mut cpu = setup(&[0xF8, 0x69, 0x01, 0xE9, 0x01, 0xD8, 0x69, 0x01]);
+            cpu.model = model;
+            run_instruction(&mut cpu);
+
+            let taken = [(); 4].map(|_| {
+                let start = cpu.total_cycles();
+                run_instruction(&mut cpu);
+                cpu.total_cycles() - start
+            });
+            assert_eq!(taken, cycles, "{}", model);
+        }
+    }
+
+    #[test]
+    fn cmos_interrupts_clear_decimal_mode() {
+        // SED, BRK
+        for (model, decimal) in [(CpuModel::Nmos6502, true), (CpuModel::Wdc65C02, false)] {
+            let mut cpu = setup(&[0xF8, 0x00]);
+            cpu.model = model;
+            cpu.write16(0xFFFE, 0x0300);
+
+            run_instruction(&mut cpu);
+            run_instruction(&mut cpu);
+            assert_eq!(cpu.pc.get(), 0x0300, "{}", model);
+            assert_eq!(cpu.get_flag(StatusFlags::DecimalMode), decimal, "{}", model);
+            // The pushed status still has decimal mode set
+            assert_ne!(cpu.read8(0x0100 | cpu.sp.get().wrapping_add(1) as u16) & 0x08, 0, "{}", model);
+        }
+    }
+
+    #[test]
+    fn ricoh_2a03_ignores_decimal_mode() {
+        // SED, CLC, LDA #$19, ADC #$28, SEC, SBC #$01
+        let mut cpu = setup(&[0xF8, 0x18, 0xA9, 0x19, 0x69, 0x28, 0x38, 0xE9, 0x01]);
+        cpu.model = CpuModel::Ricoh2A03;
+
+        for _ in 0..4 {
+            run_instruction(&mut cpu);
+        }
+        assert_eq!(cpu.a.get(), 0x41);
+        assert!(cpu.get_flag(StatusFlags::DecimalMode));
+
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.a.get(), 0x40);
+    }
+
+    #[test]
+    fn xce_switches_65c816_between_emulation_and_native_mode() {
+        // CLC, XCE, REP #$30, SEC, XCE
+        let mut cpu = setup(&[0x18, 0xFB, 0xC2, 0x30, 0x38, 0xFB]);
+        cpu.model = CpuModel::Wdc65C816;
+        assert!(cpu.emulation_mode());
+
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+        assert!(!cpu.emulation_mode());
+        assert!(cpu.get_flag(StatusFlags::Carry));
+
+        // In native mode REP can widen the accumulator and index registers
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.accumulator_width(), RegisterWidth::Sixteen);
+        assert_eq!(cpu.index_width(), RegisterWidth::Sixteen);
+
+        // Returning to emulation mode forces them back to eight bits
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+        assert!(cpu.emulation_mode());
+        assert!(!cpu.get_flag(StatusFlags::Carry));
+        assert_eq!(cpu.accumulator_width(), RegisterWidth::Eight);
+        assert_eq!(cpu.index_width(), RegisterWidth::Eight);
+    }
+
+    #[test]
+    fn rep_cannot_clear_m_and_x_in_emulation_mode() {
+        // SEP #$39, REP #$39
+        let mut cpu = setup(&[0xE2, 0x39, 0xC2, 0x39]);
+        cpu.model = CpuModel::Wdc65C816;
+
+        run_instruction(&mut cpu);
+        assert!(cpu.get_flag(StatusFlags::Carry));
+        assert!(cpu.get_flag(StatusFlags::DecimalMode));
+
+        run_instruction(&mut cpu);
+        assert!(!cpu.get_flag(StatusFlags::Carry));
+        assert!(!cpu.get_flag(StatusFlags::DecimalMode));
+        assert!(cpu.get_flag(StatusFlags::Unused));
+        assert!(cpu.get_flag(StatusFlags::Break));
+        assert_eq!(cpu.accumulator_width(), RegisterWidth::Eight);
+    }
+
+    #[test]
+    fn xba_exchanges_accumulators() {
+        // LDA #$12, XBA, LDA #$00, XBA
+        let mut cpu = setup(&[0xA9, 0x12, 0xEB, 0xA9, 0x00, 0xEB]);
+        cpu.model = CpuModel::Wdc65C816;
+
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.b.get(), 0x12);
+        assert!(cpu.get_flag(StatusFlags::Zero));
+
+        run_instruction(&mut cpu);
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.a.get(), 0x12);
+        assert_eq!(cpu.b.get(), 0x00);
+        assert!(!cpu.get_flag(StatusFlags::Zero));
+    }
+
+    #[test]
+    fn pha_and_pla_round_trip_through_the_stack() {
+        // LDA #$80, PHA, LDA #$00, PLA
+        let mut cpu = setup(&[0xA9, 0x80, 0x48, 0xA9, 0x00, 0x68]);
+        cpu.sp.set(0xFF);
+
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.step().cycles, 3);
+        assert_eq!(cpu.read8(0x01FF), 0x80);
+        assert_eq!(cpu.sp.get(), 0xFE);
+
+        // PLA sets the flags from the value it pulls
+        run_instruction(&mut cpu);
+        assert!(cpu.get_flag(StatusFlags::Zero));
+        assert_eq!(cpu.step().cycles, 4);
+        assert_eq!(cpu.a.get(), 0x80);
+        assert_eq!(cpu.sp.get(), 0xFF);
+        assert!(!cpu.get_flag(StatusFlags::Zero));
+        assert!(cpu.get_flag(StatusFlags::Negative));
+    }
+
+    #[test]
+    fn cmos_instructions_on_65c02() {
+        // LDA #$80, STA ($10), STZ $20, PHX, BRA +1, (skipped byte), PLY
+        let mut cpu = setup(&[0xA9, 0x80, 0x92, 0x10, 0x64, 0x20, 0xDA, 0x80, 0x01, 0xEA, 0x7A]);
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.sp.set(0xFF);
+        cpu.x.set(0x42);
+        cpu.write8(0x0010, 0x00);
+        cpu.write8(0x0011, 0x30);
+        cpu.write8(0x0020, 0xFF);
+
+        for _ in 0..6 {
+            run_instruction(&mut cpu);
+        }
+
+        assert_eq!(cpu.read8(0x3000), 0x80);
+        assert_eq!(cpu.read8(0x0020), 0x00);
+        assert_eq!(cpu.y.get(), 0x42);
+        assert_eq!(cpu.pc.get(), 0x020B);
+    }
+
+    #[test]
+    fn only_indexed_nops_take_the_page_crossing_cycle() {
+        // NOP, NOP $10FF,X, NOP $1000,X
+        let mut cpu = setup(&[0xEA, 0x1C, 0xFF, 0x10, 0x1C, 0x00, 0x10]);
+        cpu.enable_illegal_opcodes = true;
+        cpu.x.set(0x01);
+
+        assert_eq!(cpu.step().cycles, 2);
+        assert_eq!(cpu.step().cycles, 5);
+        assert_eq!(cpu.step().cycles, 4);
+    }
+
+    #[test]
+    fn stable_undocumented_opcodes_on_nmos() {
+        // The code, A, X and P before it, and A, X and P after it
+        let cases: [([u8; 2], [u8; 3], [u8; 3]); 9] = [
+            // ANC, and its duplicate: N is copied into C
+            ([0x0B, 0x80], [0xFF, 0x00, 0x20], [0x80, 0x00, 0xA1]),
+            ([0x2B, 0x7F], [0x80, 0x00, 0x21], [0x00, 0x00, 0x22]),
+            // ALR #$03: AND, then LSR A
+            ([0x4B, 0x03], [0xFF, 0x00, 0x20], [0x01, 0x00, 0x21]),
+            // ARR #$FF: C is bit 6 and V is bit 6 ^ bit 5 of the result
+            ([0x6B, 0xFF], [0x80, 0x00, 0x20], [0x40, 0x00, 0x61]),
+            ([0x6B, 0xFF], [0xFF, 0x00, 0x21], [0xFF, 0x00, 0xA1]),
+            // ARR #$FF in decimal mode: both nibbles of $33 are corrected
+            ([0x6B, 0xFF], [0x66, 0x00, 0x28], [0x99, 0x00, 0x69]),
+            // AXS: (A & X) - imm into X, with C set as CMP would and D ignored
+            ([0xCB, 0x10], [0xF0, 0x3F, 0x20], [0xF0, 0x20, 0x21]),
+            ([0xCB, 0x40], [0xF0, 0x3F, 0x21], [0xF0, 0xF0, 0xA0]),
+            ([0xCB, 0x30], [0xF0, 0x3F, 0x28], [0xF0, 0x00, 0x2B]),
+        ];
+        for (code, [a, x, p], expected) in cases {
+            let mut cpu = setup(&code);
+            cpu.enable_illegal_opcodes = true;
+            cpu.a.set(a);
+            cpu.x.set(x);
+            cpu.p.set(p);
+
+            assert_eq!(cpu.step().cycles, 2, "{:02X?}", code);
+            assert_eq!(
+                [cpu.a.get(), cpu.x.get(), cpu.p.get()],
+                expected,
+                "{:02X?} with A={:02X} X={:02X} P={:02X}",
+                code,
+                a,
+                x,
+                p
+            );
+        }
+
+        // LAS $10FF,Y: (memory & S) into A, X and S, with a cycle for crossing a page
+        let mut cpu = setup(&[0xBB, 0xFF, 0x10]);
+        cpu.enable_illegal_opcodes = true;
+        cpu.write8(0x1100, 0xF0);
+        cpu.y.set(0x01);
+        cpu.sp.set(0x7F);
+        cpu.p.set(0x20);
+
+        assert_eq!(cpu.step().cycles, 5);
+        assert_eq!((cpu.a.get(), cpu.x.get(), cpu.sp.get(), cpu.p.get()), (0x70, 0x70, 0x70, 0x20));
+    }
+
+    #[test]
+    fn undocumented_nmos_opcodes_are_nops_on_65c02() {
+        // SLO (zp,X) on NMOS but a one-byte NOP on the 65C02, then LDA #$01
+        let mut cpu = setup(&[0x03, 0xA9, 0x01]);
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.enable_illegal_opcodes = true;
+
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.pc.get(), 0x0201);
+
+        run_instruction(&mut cpu);
+        assert_eq!(cpu.a.get(), 0x01);
+    }
+
+    #[test]
+    fn unemulated_65c816_opcodes_are_undocumented() {
+        let cpu = setup(&[]);
+
+        assert!(!instructions::get_illegal(CpuModel::Wdc65C02, 0x42));
+        assert!(instructions::get_illegal(CpuModel::Wdc65C816, 0x42));
+        assert!(!instructions::get_illegal(CpuModel::Wdc65C816, 0xEA));
+        assert_eq!(cpu.instruction(0x07).name, "SLO");
+    }
+
+    #[test]
+    fn step_records_one_instruction() {
+        // LDA #$42, STA $1234
+        let mut cpu = setup(&[0xA9, 0x42, 0x8D, 0x34, 0x12]);
+
+        let record = cpu.step();
+        assert_eq!(record.pc, 0x0200);
+        assert_eq!(record.mnemonic, "LDA");
+        assert_eq!(record.operands, vec![0x42]);
+        assert_eq!(record.cycles, 2);
+
+        let record = cpu.step();
+        assert_eq!(record.opcode, 0x8D);
+        assert_eq!(record.mode, AddressingMode::Absolute);
+        assert_eq!(record.operands, vec![0x34, 0x12]);
+        assert_eq!(record.cycles, 4);
+        assert_eq!(
+            record.accesses,
+            vec![
+                BusAccess { kind: AccessKind::Read, address: 0x0202, value: 0x8D },
+                BusAccess { kind: AccessKind::Read, address: 0x0203, value: 0x34 },
+                BusAccess { kind: AccessKind::Read, address: 0x0204, value: 0x12 },
+                BusAccess { kind: AccessKind::Write, address: 0x1234, value: 0x42 },
+            ]
+        );
+        assert_eq!(cpu.pc.get(), 0x0205);
+        assert_eq!(cpu.cycles, 0);
+    }
+
+    #[test]
+    fn step_includes_interrupt_sequence() {
+        // NOP, with an NMI handler at $0300 starting with INX
+        let mut cpu = setup(&[0xEA]);
+        cpu.write16(0xFFFA, 0x0300);
+        cpu.write8(0x0300, 0xE8);
+        cpu.sp.set(0xFF);
+        cpu.trigger_nmi();
+
+        let record = cpu.step();
+
+        assert!(record.interrupt);
+        assert_eq!(record.pc, 0x0300);
+        assert_eq!(record.mnemonic, "INX");
+        assert_eq!(record.cycles, 9);
+        assert_eq!(cpu.x.get(), 0x01);
+    }
+
+    #[test]
+    fn step_does_nothing_when_stopped() {
+        // STP
+        let mut cpu = setup(&[0xDB, 0xEA]);
+        cpu.model = CpuModel::Wdc65C02;
+        cpu.step();
+
+        let record = cpu.step();
+
+        assert_eq!(record.cycles, 0);
+        assert!(record.accesses.is_empty());
+        assert_eq!(cpu.pc.get(), 0x0201);
+    }
+
+    #[test]
+    fn run_for_cycles_stops_at_instruction_boundary() {
+        // LDA #$01, STA $1234, LDA #$02
+        let mut cpu = setup(&[0xA9, 0x01, 0x8D, 0x34, 0x12, 0xA9, 0x02]);
+
+        // Three cycles end in the middle of STA, so it runs to completion
+        let elapsed = cpu.run_for_cycles(3);
+
+        assert_eq!(elapsed, 6);
+        assert_eq!(cpu.cycles, 0);
+        assert_eq!(cpu.pc.get(), 0x0205);
+    }
+
+    #[test]
+    fn run_until_reaches_target_pc() {
+        // LDX #$03, DEX, BNE -3, BRK
+        let mut cpu = setup(&[0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0x00]);
+
+        let elapsed = cpu.run_until(|cpu| cpu.pc.get() == 0x0205);
+
+        // LDX, then DEX/BNE three times with two taken branches
+        assert_eq!(elapsed, 2 + 3 * 2 + 2 * 3 + 2);
+        assert_eq!(cpu.x.get(), 0x00);
+        assert_eq!(cpu.pc.get(), 0x0205);
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_instructions() {
+        // LDX #$03; loop: DEX; BNE loop; KIL
+        let mut cpu = setup(&[0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0x02]);
+        cpu.enable_illegal_opcodes = true;
+        cpu.enable_history(4);
+
+        cpu.run_until(|cpu| cpu.is_jammed());
+
+        let history = cpu.history().unwrap();
+        let path: Vec<(u16, &str, u8)> = history.iter().map(|entry| (entry.pc, entry.mnemonic, entry.x)).collect();
+        assert_eq!(path, [(0x0203, "BNE", 0x01), (0x0202, "DEX", 0x01), (0x0203, "BNE", 0x00), (0x0205, "KIL", 0x00)]);
+        assert_eq!(history.last().unwrap().to_string(), "0205  KIL            A:00 X:00 Y:00 P:02 SP:00");
+    }
+
+    #[test]
+    fn history_is_off_by_default() {
+        let mut cpu = setup(&[0xEA]);
+        cpu.step();
+
+        assert!(cpu.history().is_none());
+    }
+
+    #[test]
+    fn run_until_stops_when_cpu_jams() {
+        // KIL
+        let mut cpu = setup(&[0x02]);
+
+        cpu.run_until(|_| false);
+
+        assert!(cpu.is_jammed());
+    }
+
+    #[test]
+    fn only_kil_jams() {
+        for model in [CpuModel::Nmos6502, CpuModel::Wdc65C02, CpuModel::Ricoh2A03, CpuModel::Wdc65C816] {
+            for opcode in 0..=255 {
+                let instruction = instructions::lookup(model, opcode);
+                assert_eq!(instruction.jam, instruction.name == "KIL", "{} on the {}", instruction.name, model);
+            }
+        }
+    }
+
+    #[test]
+    fn clock_stops_at_breakpoint_and_resumes() {
+        // LDA #$01, LDA #$02
+        let mut cpu = setup(&[0xA9, 0x01, 0xA9, 0x02]);
+        cpu.add_breakpoint(0x0202);
+
+        assert_eq!(cpu.clock(), None);
+        assert_eq!(cpu.clock(), None);
+        assert_eq!(cpu.clock(), Some(StopReason::Breakpoint(0x0202)));
+        assert_eq!(cpu.a.get(), 0x01);
+
+        // Clocking again executes the instruction at the breakpoint
+        assert_eq!(cpu.clock(), None);
+        assert_eq!(cpu.a.get(), 0x02);
+    }
+
+    #[test]
+    fn step_and_run_until_report_breakpoints() {
+        // INX, JMP $0200
+        let mut cpu = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+        cpu.add_breakpoint(0x0201);
+
+        cpu.run_until(|_| false);
+        assert_eq!(cpu.pc.get(), 0x0201);
+        assert_eq!(cpu.x.get(), 0x01);
+
+        let record = cpu.step();
+        assert_eq!(record.stop, None);
+        assert_eq!(record.mnemonic, "JMP");
+
+        cpu.step();
+        let record = cpu.step();
+        assert_eq!(record.stop, Some(StopReason::Breakpoint(0x0201)));
+        assert_eq!(record.cycles, 0);
+        assert_eq!(cpu.x.get(), 0x02);
+
+        assert!(cpu.remove_breakpoint(0x0201));
+        assert_eq!(cpu.breakpoints().count(), 0);
+    }
+
+    #[test]
+    fn disassembling_is_on_request_and_leaves_devices_alone() {
+        // A NOP just before a keyboard, so reading past it would take a key
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x0202)));
+        bus.add_device(Box::new(Keyboard::new(0x0203)));
+        let mut cpu = Cpu::new(SharedBus::new(bus));
+        cpu.write8(0x0202, 0xEA);
+        cpu.bus.borrow_mut().device_of_type_mut::<Keyboard>().unwrap().push_key(b'A');
+
+        // Without debug output the text isn't built
+        cpu.pc.set(0x0202);
+        cpu.step();
+        assert_eq!(cpu.current_instruction_string, "");
+        assert_eq!(cpu.current_instruction().to_string(), "NOP");
+
+        cpu.debug = DebugLevel::Instruction;
+        cpu.pc.set(0x0202);
+        cpu.step();
+        assert_eq!(cpu.current_instruction_string, "NOP");
+        assert_eq!(cpu.bus.borrow().device_of_type::<Keyboard>().unwrap().pending(), 1);
+    }
+
+    #[test]
+    fn debug_output_goes_to_the_sink() {
+        // NOP, NOP
+        let mut cpu = setup(&[0xEA, 0xEA]);
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = lines.clone();
+        cpu.set_debug_sink(Box::new(move |level, line| sink.lock().unwrap().push((level, line.to_string()))));
+
+        cpu.debug = DebugLevel::Cycle;
+        cpu.step();
+        cpu.debug = DebugLevel::Instruction;
+        cpu.step();
+
+        let lines = lines.lock().unwrap();
+        let levels: Vec<DebugLevel> = lines.iter().map(|(level, _)| *level).collect();
+        assert_eq!(levels, [DebugLevel::Instruction, DebugLevel::Cycle, DebugLevel::Cycle, DebugLevel::Instruction]);
+        assert_eq!(lines[0].1, "NOP");
+        assert_eq!(lines[1].1, "CPU pre-execute state: a: 00, x: 00, y: 00, p: 00, sp: 00, pc: 0200");
+        assert_eq!(lines[2].1, "CPU post-execute state: a: 00, x: 00, y: 00, p: 00, sp: 00, pc: 0201");
+    }
+
+    #[test]
+    fn breakpoints_and_traces_use_labels() {
+        // JSR $0210
+        let mut cpu = setup(&[0x20, 0x10, 0x02]);
+        cpu.symbols.insert(0x0210, "print_char");
+        cpu.debug = DebugLevel::Instruction;
+
+        assert_eq!(cpu.add_breakpoint_at_label("print_char"), Some(true));
+        assert_eq!(cpu.add_breakpoint_at_label("missing"), None);
+
+        let record = cpu.step();
+        assert_eq!(cpu.current_instruction_string, "JSR print_char");
+        assert_eq!(record.stop, None);
+        assert_eq!(cpu.step().stop, Some(StopReason::Breakpoint(0x0210)));
+    }
+
+    #[test]
+    fn conditional_breakpoints_stop_on_the_chosen_hit() {
+        // loop: INX, JMP loop
+        let mut cpu = setup(&[0xE8, 0x4C, 0x00, 0x02]);
+        let condition = Condition::parse("X > 3", &cpu.symbols).unwrap();
+        cpu.set_breakpoint(0x0200, Breakpoint { condition: Some(condition), skip: 1, ..Breakpoint::default() });
+
+        // The condition first holds with X at 4, and that hit is skipped
+        cpu.run_until(|_| false);
+        assert_eq!(cpu.x.get(), 0x05);
+        assert_eq!(cpu.breakpoint(0x0200).unwrap().hits, 2);
+
+        // Every later hit stops
+        cpu.run_until(|_| false);
+        assert_eq!(cpu.x.get(), 0x06);
+        assert!(!cpu.add_breakpoint(0x0200));
+        assert!(cpu.breakpoint(0x0200).unwrap().condition.is_some());
+    }
+
+    #[test]
+    fn write_watchpoint_stops_after_instruction() {
+        // LDA $10, STA $20, LDA #$01
+        let mut cpu = setup(&[0xA5, 0x10, 0x85, 0x20, 0xA9, 0x01]);
+        cpu.write8(0x0010, 0x42);
+        cpu.add_watchpoint(0x0020..=0x002F, WatchKind::Write);
+
+        let hit = WatchHit {
+            pc: 0x0202,
+            access: BusAccess { kind: AccessKind::Write, address: 0x0020, value: 0x42 },
+        };
+
+        // The read of $10 doesn't trigger the write watchpoint
+        let elapsed = cpu.run_until(|_| false);
+        assert_eq!(elapsed, 6);
+        assert_eq!(cpu.pc.get(), 0x0204);
+
+        cpu.pc.set(0x0202);
+        let record = cpu.step();
+        assert_eq!(record.stop, Some(StopReason::Watchpoint(hit)));
+    }
+
+    #[test]
+    fn watch_handler_can_let_execution_continue() {
+        // LDA $10, LDA $10, KIL
+        let mut cpu = setup(&[0xA5, 0x10, 0xA5, 0x10, 0x02]);
+        cpu.add_watchpoint(0x0010..=0x0010, WatchKind::Read);
+
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let log = hits.clone();
+        cpu.set_watch_handler(Box::new(move |hit| {
+            log.lock().unwrap().push(hit.pc);
+            false
+        }));
+
+        cpu.run_until(|_| false);
+
+        assert!(cpu.is_jammed());
+        assert_eq!(*hits.lock().unwrap(), vec![0x0200, 0x0202]);
+    }
+
+    #[test]
+    fn restore_rewinds_cpu_and_memory_mid_instruction() {
+        // INC $10, LDX #$05
+        let mut cpu = setup(&[0xE6, 0x10, 0xA2, 0x05]);
+        cpu.write8(0x0010, 0x07);
+        cpu.tick();
+        cpu.tick();
+        let snapshot = cpu.snapshot();
+
+        for _ in 0..5 {
+            cpu.tick();
+        }
+        assert_eq!(cpu.read8(0x0010), 0x08);
+        assert_eq!(cpu.x.get(), 0x05);
+
+        cpu.restore(&snapshot).unwrap();
+        assert_eq!(cpu.read8(0x0010), 0x07);
+        assert_eq!(cpu.x.get(), 0x00);
+        assert_eq!(cpu.cycles, snapshot.cpu.cycles);
+
+        // The instruction picks up where it left off, queued write included
+        for _ in 0..5 {
+            cpu.tick();
+        }
+        assert_eq!(cpu.read8(0x0010), 0x08);
+        assert_eq!(cpu.x.get(), 0x05);
+        assert_eq!(cpu.pc.get(), 0x0204);
+    }
+
+    #[test]
+    fn restore_leaves_machine_alone_on_mismatch() {
+        let mut cpu = setup(&[0xEA]);
+        let mut snapshot = cpu.snapshot();
+        snapshot.bus.devices.pop();
+        snapshot.cpu.a = 0x42;
+
+        assert!(cpu.restore(&snapshot).is_err());
+        assert_eq!(cpu.a.get(), 0x00);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshot_round_trips_through_serde() {
+        let mut cpu = setup(&[0xA9, 0x42, 0x85, 0x10]);
+        cpu.run_for_cycles(5);
+        let snapshot = cpu.snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: crate::cpu::MachineSnapshot = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[test]
+    fn rewind_steps_back_through_snapshots() {
+        // INX, repeated
+        let mut cpu = setup(&[0xE8; 16]);
+        cpu.enable_rewind(2, 3);
+
+        for _ in 0..10 {
+            cpu.step();
+        }
+        assert_eq!(cpu.x.get(), 10);
+
+        // Snapshots were taken before instructions 0, 2, 4, 6 and 8; only the last 3 are kept
+        assert_eq!(cpu.rewinder().unwrap().len(), 3);
+        assert_eq!(cpu.rewind(1), Ok(1));
+        assert_eq!(cpu.x.get(), 8);
+        assert_eq!(cpu.pc.get(), 0x0208);
+
+        assert_eq!(cpu.rewind(5), Ok(3));
+        assert_eq!(cpu.x.get(), 4);
+        assert_eq!(cpu.rewinder().unwrap().len(), 1);
+
+        // Execution carries on, and snapshots resume after the interval
+        cpu.run_for_cycles(4);
+        assert_eq!(cpu.x.get(), 6);
+        assert_eq!(cpu.rewinder().unwrap().len(), 1);
+        cpu.step();
+        assert_eq!(cpu.rewinder().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rewind_restores_memory() {
+        // INC $10, INC $10
+        let mut cpu = setup(&[0xE6, 0x10, 0xE6, 0x10]);
+        cpu.enable_rewind(1, 8);
+
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.read8(0x0010), 0x02);
+
+        assert_eq!(cpu.rewind(2), Ok(2));
+        assert_eq!(cpu.read8(0x0010), 0x00);
+        assert_eq!(cpu.pc.get(), 0x0200);
+
+        cpu.disable_rewind();
+        assert_eq!(cpu.rewind(1), Ok(0));
+    }
+
+    #[test]
+    fn jsr_and_rts_round_trip() {
+        // JSR $0210 ... $0210: RTS
+        let mut cpu = setup(&[0x20, 0x10, 0x02]);
+        cpu.write8(0x0210, 0x60);
+        cpu.sp.set(0xFF);
+
+        assert_eq!(cpu.step().cycles, 6);
+        assert_eq!(cpu.pc.get(), 0x0210);
+        assert_eq!(cpu.read8(0x01FF), 0x02);
+        assert_eq!(cpu.read8(0x01FE), 0x02);
+
+        assert_eq!(cpu.step().cycles, 6);
+        assert_eq!(cpu.pc.get(), 0x0203);
+        assert_eq!(cpu.sp.get(), 0xFF);
+    }
+
+    #[test]
+    fn jsr_and_rts_wrap_around_the_stack_page() {
+        // $0300: JSR $0210 ... $0210: RTS
+        let mut cpu = setup(&[]);
+        for (offset, byte) in [0x20, 0x10, 0x02].into_iter().enumerate() {
+            cpu.write8(0x0300 + offset as u16, byte);
+        }
+        cpu.write8(0x0210, 0x60);
+        cpu.pc.set(0x0300);
+        cpu.sp.set(0x00);
+
+        // The high byte goes to the bottom of the page and the low byte to the top
+        cpu.step();
+        assert_eq!(cpu.read8(0x0100), 0x03);
+        assert_eq!(cpu.read8(0x01FF), 0x02);
+        assert_eq!(cpu.sp.get(), 0xFE);
+
+        cpu.step();
+        assert_eq!(cpu.pc.get(), 0x0303);
+        assert_eq!(cpu.sp.get(), 0x00);
+    }
+
+    #[test]
+    fn backtrace_follows_calls_and_stack_tricks() {
+        // JSR $0210 ... $0210: JSR $0220, RTS ... $0220: PLA, PLA, BRK
+        let mut program = vec![0xEA; 0x23];
+        program[0x00..0x03].copy_from_slice(&[0x20, 0x10, 0x02]);
+        program[0x10..0x14].copy_from_slice(&[0x20, 0x20, 0x02, 0x60]);
+        program[0x20..0x23].copy_from_slice(&[0x68, 0x68, 0x00]);
+        let mut cpu = setup(&program);
+        cpu.write16(0xFFFE, 0x0300);
+
+        cpu.step();
+        cpu.step();
+        let frames: Vec<(CallKind, u16, u16)> = cpu.backtrace().iter().map(|f| (f.kind, f.from, f.to)).collect();
+        assert_eq!(frames, [(CallKind::Subroutine, 0x0200, 0x0210), (CallKind::Subroutine, 0x0210, 0x0220)]);
+        assert_eq!(cpu.backtrace()[1].return_address, 0x0213);
+
+        // Pulling the return address drops the inner call, and BRK enters a handler in its place
+        cpu.step();
+        assert_eq!(cpu.backtrace().len(), 1);
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.backtrace().last().unwrap().to_string(), "BRK 0300 from 0222");
+        assert_eq!(cpu.backtrace().len(), 2);
+
+        cpu.reset();
+        assert!(cpu.backtrace().is_empty());
+
+        // Calls that wrap the stack pointer replace the frames instead of piling up behind them
+        let mut cpu = setup(&[0x20, 0x06, 0x02, 0x4C, 0x00, 0x02, 0x60]);
+        cpu.sp.set(0x00);
+        for _ in 0..28 {
+            cpu.step();
+        }
+        assert_eq!(cpu.backtrace().len(), 1);
+    }
+
+    #[test]
+    fn nestest_line_matches_nestest_columns() {
+        // JMP $C5F5, BCS -2
+        let mut cpu = setup(&[0x4C, 0xF5, 0xC5, 0xB0, 0xFE]);
+        cpu.sp.set(0xFD);
+        cpu.p.set(0x24);
+
+        assert_eq!(
+            cpu.nestest_line(7),
+            "0200  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:7"
+        );
+        cpu.pc.set(0x0203);
+        assert_eq!(
+            cpu.nestest_line(10),
+            "0203  B0 FE     BCS $0203                       A:00 X:00 Y:00 P:24 SP:FD CYC:10"
+        );
+    }
+
+    #[test]
+    fn compare_nestest_stops_at_first_divergence() {
+        // LDX #$05, INX, INX
+        let mut cpu = setup(&[0xA2, 0x05, 0xE8, 0xE8]);
+        cpu.sp.set(0xFD);
+        cpu.p.set(0x24);
+        let reference = "\
+0200  A2 05     LDX #$05                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+0202  E8        INX                             A:00 X:05 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9
+0203  E8        INX                             A:00 X:06 Y:00 P:24 SP:FD PPU:  0, 33 CYC:12
+";
+
+        let mismatch = cpu.compare_nestest(reference, 7).unwrap_err();
+
+        assert_eq!(mismatch.line, 3);
+        assert_eq!(mismatch.field, "CYC");
+        assert_eq!(cpu.pc.get(), 0x0203);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn writes_from_another_thread_reach_the_bus_during_a_run() {
+        // loop: LDA $10, BEQ loop, KIL
+        let mut cpu = setup(&[0xA5, 0x10, 0xF0, 0xFC, 0x02]);
+        let bus = cpu.bus.clone();
+
+        let worker = std::thread::spawn(move || {
+            cpu.run_for_cycles(100_000_000);
+            cpu.state()
+        });
+
+        // Let the run get going, then release the loop from this thread
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        bus.borrow_mut().write(0x0010, 0x01);
+
+        assert_eq!(worker.join().unwrap(), CpuState::Jammed);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn machine_runs_on_another_thread() {
+        // LDX #$05, KIL
+        let mut cpu = setup(&[0xA2, 0x05, 0x02]);
+        let bus = cpu.bus.clone();
+
+        let worker = std::thread::spawn(move || {
+            cpu.run_until(|_| false);
+            cpu.x.get()
+        });
+
+        assert_eq!(worker.join().unwrap(), 0x05);
+        assert_eq!(bus.borrow().read(0x0200), 0xA2);
+    }
+}
@@ -0,0 +1,57 @@
+use core::fmt::Display;
+
+/// The CPU variant being emulated.
+///
+/// The variants share the NMOS instruction set as a base; each model then adds
+/// or changes the opcodes that differ on that chip.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CpuModel {
+    /// The original NMOS 6502, including its undocumented opcodes.
+    #[default]
+    Nmos6502,
+
+    /// The WDC W65C02S. It adds the CMOS instructions and addressing modes
+    /// (BRA, STZ, TSB, TRB, PHX, PLX, PHY, PLY, `(zp)` and `(abs,X)`), the
+    /// Rockwell bit manipulation instructions (BBR, BBS, RMB and SMB) and WDC's
+    /// WAI and STP. Opcodes that were undocumented on the NMOS part are NOPs.
+    Wdc65C02,
+
+    /// The Ricoh 2A03/2A07 used in the NES and Famicom. It is an NMOS 6502 whose
+    /// decimal mode has been disconnected: the D flag can still be set and
+    /// cleared, but ADC and SBC always operate in binary.
+    Ricoh2A03,
+
+    /// The WDC W65C816S running in emulation mode. Only the groundwork is in
+    /// place: XCE, REP, SEP and XBA on top of the 65C02 instruction set (less
+    /// the Rockwell instructions), and the E, M and X flags that select register
+    /// widths. Opcodes that are new on the 65C816 but not emulated yet are
+    /// treated as undocumented. Native mode can be entered
+    /// and its widths are reported, but instructions still operate on eight-bit
+    /// registers, and the new 65C816 addressing modes are not implemented yet.
+    Wdc65C816,
+}
+
+impl CpuModel {
+    /// Returns whether ADC and SBC honour the decimal mode flag on this model.
+    pub fn has_decimal_mode(&self) -> bool {
+        !matches!(self, CpuModel::Ricoh2A03)
+    }
+
+    /// Returns whether this is a CMOS part, which fixes the NMOS indirect jump
+    /// bug and has the 65C02 instruction set.
+    pub fn is_cmos(&self) -> bool {
+        matches!(self, CpuModel::Wdc65C02 | CpuModel::Wdc65C816)
+    }
+}
+
+impl Display for CpuModel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CpuModel::Nmos6502 => write!(f, "NMOS 6502"),
+            CpuModel::Wdc65C02 => write!(f, "WDC 65C02"),
+            CpuModel::Ricoh2A03 => write!(f, "Ricoh 2A03"),
+            CpuModel::Wdc65C816 => write!(f, "WDC 65C816"),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Runs the SingleStepTests 65x02 vectors against the CPU core.
+//!
+//! Each vector gives the registers and memory before a single instruction, and
+//! the registers, memory and cycle-by-cycle bus activity expected after it. The
+//! vectors live in their own repository (https://github.com/SingleStepTests/65x02),
+//! so these tests are ignored by default. Point `PROCESSOR_TESTS` at a checkout
+//! to run them:
+//!
+//! ```text
+//! PROCESSOR_TESTS=/path/to/65x02 cargo test processor_tests -- --ignored --nocapture
+//! ```
+//!
+//! `PROCESSOR_TESTS_OPCODES` narrows a run down to a comma-separated list of
+//! opcodes in hex, such as `20,60`. Without it every opcode is run, except the
+//! unstable undocumented NMOS opcodes in `UNSTABLE_OPCODES`, which the CPU
+//! doesn't emulate.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::path::Path;
+use std::{env, fs, println};
+
+use serde_json::Value;
+
+use crate::bus::ram::Ram;
+use crate::bus::{AccessKind, BusAccess, MainBus, SharedBus};
+use crate::cpu::{CpuBuilder, CpuModel};
+
+/// The registers and memory at the start or end of a test.
+struct State {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+impl State {
+    /// Reads the `initial` or `final` object of a test.
+    fn parse(value: &Value) -> State {
+        let ram = value["ram"]
+            .as_array()
+            .expect("ram should be an array")
+            .iter()
+            .map(|entry| (number(&entry[0]) as u16, number(&entry[1]) as u8))
+            .collect();
+
+        State {
+            pc: number(&value["pc"]) as u16,
+            s: number(&value["s"]) as u8,
+            a: number(&value["a"]) as u8,
+            x: number(&value["x"]) as u8,
+            y: number(&value["y"]) as u8,
+            p: number(&value["p"]) as u8,
+            ram,
+        }
+    }
+}
+
+/// A single test: one instruction and everything it is expected to do.
+struct TestCase {
+    name: String,
+    initial: State,
+    expected: State,
+    cycles: Vec<BusAccess>,
+}
+
+impl TestCase {
+    /// Reads a test from its JSON object.
+    fn parse(value: &Value) -> TestCase {
+        let cycles = value["cycles"]
+            .as_array()
+            .expect("cycles should be an array")
+            .iter()
+            .map(|cycle| BusAccess {
+                kind: match cycle[2].as_str() {
+                    Some("read") => AccessKind::Read,
+                    Some("write") => AccessKind::Write,
+                    other => panic!("unknown bus activity {:?}", other),
+                },
+                address: number(&cycle[0]) as u16,
+                value: number(&cycle[1]) as u8,
+            })
+            .collect();
+
+        TestCase {
+            name: value["name"].as_str().unwrap_or_default().into(),
+            initial: State::parse(&value["initial"]),
+            expected: State::parse(&value["final"]),
+            cycles,
+        }
+    }
+}
+
+/// Reads a number out of a test, which is always a non-negative integer.
+fn number(value: &Value) -> u64 {
+    value.as_u64().unwrap_or_else(|| panic!("expected a number, found {}", value))
+}
+
+/// The undocumented NMOS opcodes whose results differ from chip to chip: XAA,
+/// AHX, TAS, SHY, SHX, AHX again and LXA.
+const UNSTABLE_OPCODES: [u8; 7] = [0x8B, 0x93, 0x9B, 0x9C, 0x9E, 0x9F, 0xAB];
+
+/// The opcodes selected by `PROCESSOR_TESTS_OPCODES`, or all of them but the unstable ones.
+///
+/// # Arguments
+///
+/// * `model` - The CPU model being tested.
+fn selected_opcodes(model: CpuModel) -> Vec<u8> {
+    match env::var("PROCESSOR_TESTS_OPCODES") {
+        Ok(list) => list
+            .split(',')
+            .map(|opcode| u8::from_str_radix(opcode.trim(), 16).expect("opcodes should be in hex"))
+            .collect(),
+        Err(_) => (0..=255).filter(|opcode| model.is_cmos() || !UNSTABLE_OPCODES.contains(opcode)).collect(),
+    }
+}
+
+/// Runs a single test on a fresh CPU.
+///
+/// # Arguments
+///
+/// * `bus` - A bus with 64K of RAM.
+/// * `model` - The CPU model the tests were recorded on.
+/// * `case` - The test to run.
+///
+/// # Returns
+///
+/// `Ok(())` if the CPU ended up where the test expects, or a description of the first difference.
+fn run_case(bus: &SharedBus, model: CpuModel, case: &TestCase) -> Result<(), String> {
+    // The NMOS tests cover the illegal opcodes, and the dummy write the NMOS
+    // parts make during read-modify-write instructions
+    let nmos = matches!(model, CpuModel::Nmos6502 | CpuModel::Ricoh2A03);
+    let mut cpu = CpuBuilder::new()
+        .bus(bus.clone())
+        .model(model)
+        .illegal_opcodes(nmos)
+        .rmw_dummy_write(nmos)
+        .build();
+
+    // Set up the registers and memory
+    cpu.pc.set(case.initial.pc);
+    cpu.sp.set(case.initial.s);
+    cpu.a.set(case.initial.a);
+    cpu.x.set(case.initial.x);
+    cpu.y.set(case.initial.y);
+    cpu.p.set(case.initial.p);
+    for &(address, value) in &case.initial.ram {
+        bus.borrow_mut().write(address, value);
+    }
+
+    let record = cpu.step();
+
+    // Compare the registers
+    let registers = [
+        ("PC", cpu.pc.get(), case.expected.pc),
+        ("S", cpu.sp.get() as u16, case.expected.s as u16),
+        ("A", cpu.a.get() as u16, case.expected.a as u16),
+        ("X", cpu.x.get() as u16, case.expected.x as u16),
+        ("Y", cpu.y.get() as u16, case.expected.y as u16),
+        ("P", cpu.p.get() as u16, case.expected.p as u16),
+    ];
+    for (name, actual, expected) in registers {
+        if actual != expected {
+            return Err(format!("{} is {:02X}, expected {:02X}", name, actual, expected));
+        }
+    }
+
+    // Compare the memory the test cares about
+    for &(address, expected) in &case.expected.ram {
+        let actual = bus.borrow().read(address);
+        if actual != expected {
+            return Err(format!("${:04X} is {:02X}, expected {:02X}", address, actual, expected));
+        }
+    }
+
+    // Compare the bus activity, one cycle at a time
+    if record.cycles as usize != case.cycles.len() {
+        return Err(format!("took {} cycles, expected {}", record.cycles, case.cycles.len()));
+    }
+    for (cycle, (actual, expected)) in record.accesses.iter().zip(&case.cycles).enumerate() {
+        if actual != expected {
+            return Err(format!("cycle {} was {}, expected {}", cycle + 1, actual, expected));
+        }
+    }
+    if record.accesses.len() != case.cycles.len() {
+        return Err(format!(
+            "made {} bus accesses, expected {}",
+            record.accesses.len(),
+            case.cycles.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Runs every test for a CPU model and fails if any of them don't pass.
+///
+/// # Arguments
+///
+/// * `directory` - The model's directory in the 65x02 repository.
+/// * `model` - The CPU model the tests were recorded on.
+fn run_suite(directory: &str, model: CpuModel) {
+    let root = env::var_os("PROCESSOR_TESTS").expect("PROCESSOR_TESTS should point at a checkout of SingleStepTests/65x02");
+    let directory = Path::new(&root).join(directory).join("v1");
+
+    let mut bus = MainBus::new();
+    bus.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    let bus = SharedBus::new(bus);
+
+    let mut passed = 0;
+    let mut failed_opcodes = Vec::new();
+    for opcode in selected_opcodes(model) {
+        let path = directory.join(format!("{:02x}.json", opcode));
+        let text = fs::read_to_string(&path).unwrap_or_else(|error| panic!("couldn't read {}: {}", path.display(), error));
+        let cases: Value = serde_json::from_str(&text).unwrap_or_else(|error| panic!("couldn't parse {}: {}", path.display(), error));
+
+        // Report the first failure for each opcode rather than every one of them
+        let mut failed = 0;
+        let mut first_failure = None;
+        for case in cases.as_array().expect("a test file should hold an array") {
+            let case = TestCase::parse(case);
+            match run_case(&bus, model, &case) {
+                Ok(()) => passed += 1,
+                Err(message) => {
+                    failed += 1;
+                    first_failure.get_or_insert(format!("[{}] {}", case.name, message));
+                }
+            }
+        }
+
+        if let Some(failure) = first_failure {
+            println!("{:02X}: {} failed, first {}", opcode, failed, failure);
+            failed_opcodes.push(opcode);
+        }
+    }
+
+    println!("{}: {} tests passed, {} opcodes failed", model, passed, failed_opcodes.len());
+    assert!(failed_opcodes.is_empty(), "failing opcodes: {:02X?}", failed_opcodes);
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests/65x02 vectors, see the module documentation"]
+fn nmos_6502() {
+    run_suite("6502", CpuModel::Nmos6502);
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests/65x02 vectors, see the module documentation"]
+fn ricoh_2a03() {
+    run_suite("nes6502", CpuModel::Ricoh2A03);
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests/65x02 vectors, see the module documentation"]
+fn wdc_65c02() {
+    run_suite("wdc65c02", CpuModel::Wdc65C02);
+}
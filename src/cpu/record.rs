@@ -0,0 +1,37 @@
+use alloc::vec::Vec;
+use crate::bus::BusAccess;
+use crate::cpu::{AddressingMode, StopReason};
+
+/// A description of one instruction executed by `Cpu::step()`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InstructionRecord {
+    /// The address the instruction was fetched from.
+    pub pc: u16,
+
+    /// The opcode of the instruction.
+    pub opcode: u8,
+
+    /// The mnemonic of the instruction, such as "LDA".
+    pub mnemonic: &'static str,
+
+    /// The operand bytes that follow the opcode.
+    pub operands: Vec<u8>,
+
+    /// The addressing mode of the instruction.
+    pub mode: AddressingMode,
+
+    /// Every bus access the CPU made, in order, starting with the opcode fetch.
+    pub accesses: Vec<BusAccess>,
+
+    /// The number of cycles consumed. This is 0 if the CPU was waiting, stopped
+    /// or jammed, or hit a breakpoint, in which case nothing was executed.
+    pub cycles: u32,
+
+    /// Whether an interrupt was serviced before the instruction. If so, the
+    /// instruction is the first one of the handler, and the interrupt
+    /// sequence's accesses and cycles are included in the record.
+    pub interrupt: bool,
+
+    /// Why the instruction wasn't executed, if it was held back by the debugger.
+    pub stop: Option<StopReason>,
+}
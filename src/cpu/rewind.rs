@@ -0,0 +1,115 @@
+use alloc::collections::VecDeque;
+
+use crate::cpu::MachineSnapshot;
+
+/// Keeps a ring buffer of machine snapshots so execution can be stepped backwards.
+///
+/// A snapshot is taken at the start of every `interval`th instruction. Once
+/// `capacity` snapshots are held, the oldest one is dropped to make room.
+pub struct Rewinder {
+    /// The number of instructions between snapshots.
+    interval: usize,
+
+    /// The maximum number of snapshots held.
+    capacity: usize,
+
+    /// The number of instructions left until the next snapshot.
+    countdown: usize,
+
+    /// The snapshots, oldest first.
+    snapshots: VecDeque<MachineSnapshot>,
+}
+
+impl Rewinder {
+    /// Creates a new, empty `Rewinder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The number of instructions between snapshots. 0 is treated as 1.
+    /// * `capacity` - The maximum number of snapshots held. 0 is treated as 1.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Rewinder` struct, which takes its first snapshot
+    /// at the next instruction.
+    pub fn new(interval: usize, capacity: usize) -> Rewinder {
+        let capacity = capacity.max(1);
+        Rewinder {
+            interval: interval.max(1),
+            capacity,
+            countdown: 0,
+            snapshots: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of instructions between snapshots.
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Returns the maximum number of snapshots held.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of snapshots held.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns whether no snapshots are held.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Drops every snapshot. The next instruction is snapshotted.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.countdown = 0;
+    }
+
+    /// Counts an instruction about to be executed, and takes a snapshot if one is due.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - Takes the snapshot. Only called when one is due, since
+    ///   saving the whole machine isn't free.
+    pub fn record<F: FnOnce() -> MachineSnapshot>(&mut self, snapshot: F) {
+        if self.countdown == 0 {
+            if self.snapshots.len() == self.capacity {
+                self.snapshots.pop_front();
+            }
+            self.snapshots.push_back(snapshot());
+            self.countdown = self.interval;
+        }
+        self.countdown -= 1;
+    }
+
+    /// Steps back through the snapshots.
+    ///
+    /// The newest `steps - 1` snapshots are dropped, and the one before them is
+    /// returned. It stays in the buffer, so it can be rewound to again.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - The number of snapshots to go back. 1 is the newest snapshot;
+    ///   anything past the oldest snapshot stops at it.
+    ///
+    /// # Returns
+    ///
+    /// The number of snapshots actually gone back and the snapshot to restore,
+    /// or `None` if `steps` is 0 or there are no snapshots.
+    pub fn rewind(&mut self, steps: usize) -> Option<(usize, &MachineSnapshot)> {
+        if steps == 0 || self.snapshots.is_empty() {
+            return None;
+        }
+
+        let steps = steps.min(self.snapshots.len());
+        self.snapshots.truncate(self.snapshots.len() - (steps - 1));
+
+        // The restored snapshot stands in for the next one that would be taken
+        self.countdown = self.interval;
+
+        self.snapshots.back().map(|snapshot| (steps, snapshot))
+    }
+}
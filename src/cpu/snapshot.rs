@@ -0,0 +1,104 @@
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::bus::BusSnapshot;
+use crate::cpu::{AddressingMode, CpuModel, CpuState};
+
+/// The saved state of the CPU: its registers and the internal latches of the
+/// instruction in progress.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuSnapshot {
+    /// The CPU variant the state was saved from.
+    pub model: CpuModel,
+
+    /// The accumulator.
+    pub a: u8,
+
+    /// The X index register.
+    pub x: u8,
+
+    /// The Y index register.
+    pub y: u8,
+
+    /// The hidden high byte of the 65C816 accumulator.
+    pub b: u8,
+
+    /// The status register.
+    pub p: u8,
+
+    /// The stack pointer.
+    pub sp: u8,
+
+    /// The program counter.
+    pub pc: u16,
+
+    /// The cycles left in the current instruction.
+    pub cycles: u8,
+
+    /// The cycles the CPU had been clocked for.
+    pub total_cycles: u64,
+
+    /// The effective address latched by the addressing mode.
+    pub address_absolute: u16,
+
+    /// The branch offset latched by the relative addressing mode.
+    pub address_relative: u16,
+
+    /// The addressing mode of the current instruction.
+    pub address_mode: AddressingMode,
+
+    /// The opcode of the current instruction.
+    pub opcode: u8,
+
+    /// The operand fetched by the current instruction.
+    pub fetched_data: u8,
+
+    /// Whether the undocumented NMOS opcodes are enabled.
+    pub enable_illegal_opcodes: bool,
+
+    /// Whether read-modify-write instructions perform the dummy write.
+    pub rmw_dummy_write: bool,
+
+    /// Whether writes are being queued to land cycle by cycle.
+    pub defer_writes: bool,
+
+    /// The queued writes that haven't reached the bus yet.
+    pub deferred_writes: Vec<(u16, u8)>,
+
+    /// Whether the IRQ line is asserted.
+    pub irq_line: bool,
+
+    /// Whether the NMI line is asserted.
+    pub nmi_line: bool,
+
+    /// Whether an NMI edge is waiting to be serviced.
+    pub nmi_pending: bool,
+
+    /// Whether IRQs are held off for one more instruction.
+    pub irq_inhibited: bool,
+
+    /// The cycles left in the instruction when the IRQ line is sampled, or 0.
+    pub irq_sample: u8,
+
+    /// Whether the CPU is running, waiting, stopped or jammed.
+    pub state: CpuState,
+
+    /// The 65C816 emulation flag (E).
+    pub emulation: bool,
+
+    /// The address of the instruction or interrupt sequence being executed.
+    pub instruction_pc: u16,
+}
+
+/// The saved state of a whole machine: the CPU and everything on its bus.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MachineSnapshot {
+    /// The state of the CPU.
+    pub cpu: CpuSnapshot,
+
+    /// The state of the bus and its devices.
+    pub bus: BusSnapshot,
+}
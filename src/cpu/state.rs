@@ -0,0 +1,33 @@
+use core::fmt::Display;
+
+/// The execution state of the CPU.
+///
+/// Anything other than `Running` means `clock()` is not fetching instructions,
+/// and the variant tells the host why.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CpuState {
+    /// The CPU is executing instructions normally.
+    #[default]
+    Running,
+
+    /// The CPU executed WAI and is idle until an IRQ or NMI arrives.
+    Waiting,
+
+    /// The CPU executed STP and is idle until it is reset.
+    Stopped,
+
+    /// The CPU executed a KIL/JAM opcode and is locked up until it is reset.
+    Jammed,
+}
+
+impl Display for CpuState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CpuState::Running => write!(f, "Running"),
+            CpuState::Waiting => write!(f, "Waiting"),
+            CpuState::Stopped => write!(f, "Stopped"),
+            CpuState::Jammed => write!(f, "Jammed"),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use core::fmt::Display;
+
+use crate::cpu::WatchHit;
+
+/// Why the CPU stopped short of executing an instruction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StopReason {
+    /// The program counter reached a breakpoint at the given address. The
+    /// instruction there has not been executed yet.
+    Breakpoint(u16),
+
+    /// The CPU accessed a watched address. The instruction that made the access
+    /// has been executed.
+    Watchpoint(WatchHit),
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StopReason::Breakpoint(address) => write!(f, "Breakpoint at {:04X}", address),
+            StopReason::Watchpoint(hit) => write!(f, "Watchpoint: {}", hit),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// The first point at which a trace differs from a reference log.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceMismatch {
+    /// The line of the reference log that differs, counting from 1.
+    pub line: usize,
+
+    /// The field that differs, such as "PC", "A" or "CYC".
+    pub field: &'static str,
+
+    /// The line from the reference log.
+    pub expected: String,
+
+    /// The line the CPU produced.
+    pub actual: String,
+}
+
+impl Display for TraceMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Line {}: {} differs", self.line, self.field)?;
+        writeln!(f, "Expected: {}", self.expected)?;
+        write!(f, "Actual:   {}", self.actual)
+    }
+}
+
+/// Splits a nestest-format line into the fields that are compared.
+///
+/// The disassembly isn't compared, since nestest.log annotates operands with the
+/// values they point at and uses its own names for the undocumented opcodes.
+/// Anything after the registers, such as the PPU position, is ignored too.
+///
+/// # Arguments
+///
+/// * `line` - A line in nestest format.
+///
+/// # Returns
+///
+/// The name and value of each field found on the line.
+fn fields(line: &str) -> Vec<(&'static str, &str)> {
+    let mut fields = Vec::new();
+
+    // The address and instruction bytes are in fixed columns
+    if let Some(pc) = line.get(0..4) {
+        fields.push(("PC", pc));
+    }
+    if let Some(bytes) = line.get(6..14) {
+        fields.push(("Bytes", bytes.trim()));
+    }
+
+    // The registers are labelled, so they are found wherever they are
+    for (name, label) in [("A", "A:"), ("X", "X:"), ("Y", "Y:"), ("P", "P:"), ("SP", "SP:"), ("CYC", "CYC:")] {
+        if let Some(value) = line.split_whitespace().find_map(|token| token.strip_prefix(label)) {
+            fields.push((name, value));
+        }
+    }
+
+    fields
+}
+
+/// Finds the first field that differs between two nestest-format lines.
+///
+/// Fields missing from the reference line aren't compared, so logs without a
+/// cycle count can still be checked.
+///
+/// # Arguments
+///
+/// * `actual` - The line the CPU produced.
+/// * `expected` - The line from the reference log.
+///
+/// # Returns
+///
+/// The name of the first field that differs, or `None` if the lines match.
+pub(crate) fn first_difference(actual: &str, expected: &str) -> Option<&'static str> {
+    let actual = fields(actual);
+    fields(expected).into_iter().find_map(|(name, value)| {
+        let matches = actual
+            .iter()
+            .any(|&(actual_name, actual_value)| actual_name == name && actual_value.eq_ignore_ascii_case(value));
+        (!matches).then_some(name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::trace::first_difference;
+
+    const LINE: &str = "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7";
+
+    #[test]
+    fn ignores_disassembly_and_ppu() {
+        let actual = "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:7";
+
+        assert_eq!(first_difference(actual, LINE), None);
+    }
+
+    #[test]
+    fn reports_first_differing_field() {
+        let actual = "C000  4C F5 C5  JMP $C5F5                       A:00 X:01 Y:00 P:25 SP:FD CYC:7";
+
+        assert_eq!(first_difference(actual, LINE), Some("X"));
+    }
+}
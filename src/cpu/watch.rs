@@ -0,0 +1,80 @@
+use alloc::boxed::Box;
+use core::fmt::Display;
+use core::ops::RangeInclusive;
+
+use crate::bus::{AccessKind, BusAccess};
+
+/// The kind of access a watchpoint triggers on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WatchKind {
+    /// Trigger when the CPU reads a watched address.
+    Read,
+
+    /// Trigger when the CPU writes a watched address.
+    Write,
+
+    /// Trigger on both reads and writes.
+    ReadWrite,
+}
+
+/// A range of addresses whose accesses by the CPU stop execution.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Watchpoint {
+    /// The addresses being watched.
+    pub range: RangeInclusive<u16>,
+
+    /// The kind of access that triggers the watchpoint.
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    /// Creates a watchpoint over a range of addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to watch.
+    /// * `kind` - The kind of access that triggers the watchpoint.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Watchpoint` struct.
+    pub fn new(range: RangeInclusive<u16>, kind: WatchKind) -> Watchpoint {
+        Watchpoint { range, kind }
+    }
+
+    /// Returns whether a bus access triggers this watchpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `access` - The access made by the CPU.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the access is of a watched kind and falls in the range.
+    pub fn matches(&self, access: &BusAccess) -> bool {
+        let kind = matches!(
+            (self.kind, access.kind),
+            (WatchKind::ReadWrite, _) | (WatchKind::Read, AccessKind::Read) | (WatchKind::Write, AccessKind::Write)
+        );
+        kind && self.range.contains(&access.address)
+    }
+}
+
+/// A function called for every watchpoint hit, returning whether execution should stop.
+pub type WatchHandler = Box<dyn FnMut(WatchHit) -> bool + Send>;
+
+/// A watchpoint that was triggered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct WatchHit {
+    /// The address of the instruction that made the access.
+    pub pc: u16,
+
+    /// The access that triggered the watchpoint, including the value and whether it was a read or a write.
+    pub access: BusAccess,
+}
+
+impl Display for WatchHit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} at PC {:04X}", self.access, self.pc)
+    }
+}
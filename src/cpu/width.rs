@@ -0,0 +1,24 @@
+use core::fmt::Display;
+
+/// The width of a register on CPUs whose registers can be resized.
+///
+/// Only the 65C816 in native mode can widen its registers; every other model,
+/// and the 65C816 in emulation mode, always uses eight-bit registers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum RegisterWidth {
+    /// The register holds eight bits.
+    #[default]
+    Eight,
+
+    /// The register holds sixteen bits.
+    Sixteen,
+}
+
+impl Display for RegisterWidth {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RegisterWidth::Eight => write!(f, "8-bit"),
+            RegisterWidth::Sixteen => write!(f, "16-bit"),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+/// The assembler syntax disassembly is written in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Dialect {
+    /// A readable listing with addresses and instruction bytes, not meant to be reassembled.
+    #[default]
+    Plain,
+
+    /// The syntax of ca65, from the cc65 suite (`.org`, `.byte`, `label:`).
+    Ca65,
+
+    /// The syntax of the ACME cross-assembler (`* =`, `!byte`, bare labels).
+    Acme,
+}
+
+impl Dialect {
+    /// Returns the line that places the following code at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address the code is assembled at.
+    ///
+    /// # Returns
+    ///
+    /// The directive, or `None` for the plain dialect, which shows addresses on every line instead.
+    pub fn origin(&self, address: u16) -> Option<String> {
+        match self {
+            Dialect::Plain => None,
+            Dialect::Ca65 => Some(format!(".org ${:04X}", address)),
+            Dialect::Acme => Some(format!("* = ${:04X}", address)),
+        }
+    }
+
+    /// Returns the directive that emits raw bytes.
+    pub fn byte_directive(&self) -> &'static str {
+        match self {
+            Dialect::Plain | Dialect::Ca65 => ".byte",
+            Dialect::Acme => "!byte",
+        }
+    }
+
+    /// Returns how a label is defined at the current address.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The name of the label.
+    pub fn label(&self, label: &str) -> String {
+        match self {
+            Dialect::Plain | Dialect::Ca65 => format!("{}:", label),
+            Dialect::Acme => String::from(label),
+        }
+    }
+
+    /// Marks an operand so the assembler keeps it absolute even though it fits in page zero.
+    ///
+    /// Without this, assemblers would pick the shorter zero page encoding and the
+    /// output would no longer match the original bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `mnemonic` - The instruction's mnemonic.
+    /// * `operand` - The operand as it would otherwise be written.
+    ///
+    /// # Returns
+    ///
+    /// The mnemonic and operand to write.
+    pub fn force_absolute(&self, mnemonic: &str, operand: &str) -> (String, String) {
+        match self {
+            Dialect::Plain => (String::from(mnemonic), String::from(operand)),
+            Dialect::Ca65 => (String::from(mnemonic), format!("a:{}", operand)),
+            Dialect::Acme => (format!("{}+2", mnemonic), String::from(operand)),
+        }
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Dialect::Plain => write!(f, "Plain"),
+            Dialect::Ca65 => write!(f, "ca65"),
+            Dialect::Acme => write!(f, "ACME"),
+        }
+    }
+}
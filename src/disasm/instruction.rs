@@ -0,0 +1,157 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use crate::cpu::AddressingMode;
+use crate::disasm::SymbolTable;
+
+/// One instruction decoded by the disassembler.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DisassembledInstruction {
+    /// The address of the opcode.
+    pub address: u16,
+
+    /// The opcode of the instruction.
+    pub opcode: u8,
+
+    /// The mnemonic of the instruction, such as "LDA".
+    pub mnemonic: &'static str,
+
+    /// The addressing mode of the instruction.
+    pub mode: AddressingMode,
+
+    /// The operand bytes that follow the opcode.
+    pub operands: Vec<u8>,
+
+    /// The length of the instruction in bytes, including the opcode.
+    pub length: u16,
+
+    /// The base number of cycles the instruction takes, before any penalty for
+    /// crossing a page or taking a branch.
+    pub cycles: u8,
+
+    /// Whether the opcode is undocumented.
+    pub illegal: bool,
+}
+
+impl DisassembledInstruction {
+    /// Returns the operand as it is written in assembly language.
+    ///
+    /// Branch offsets are shown as the address they branch to.
+    ///
+    /// # Returns
+    ///
+    /// The operand, such as "#$12" or "($1234),Y", or an empty string if the instruction has none.
+    pub fn operand_string(&self) -> String {
+        self.render_operand(None)
+    }
+
+    /// Returns the operand with addresses replaced by their labels.
+    ///
+    /// Immediate values are never replaced, since they aren't addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbols` - The labels to use.
+    ///
+    /// # Returns
+    ///
+    /// The operand, such as "print_char" or "(ptr),Y", or an empty string if the instruction has none.
+    pub fn operand_string_with(&self, symbols: &SymbolTable) -> String {
+        self.render_operand(Some(symbols))
+    }
+
+    /// Returns the whole instruction with addresses replaced by their labels.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbols` - The labels to use.
+    ///
+    /// # Returns
+    ///
+    /// The instruction, such as "JSR print_char".
+    pub fn to_string_with(&self, symbols: &SymbolTable) -> String {
+        join(self.mnemonic, &self.operand_string_with(symbols))
+    }
+
+    /// Returns where the instruction transfers control to, if it is a branch, JMP or JSR.
+    ///
+    /// Indirect jumps aren't included, since their target depends on memory.
+    ///
+    /// # Returns
+    ///
+    /// The address execution continues at when the branch is taken, or `None`.
+    pub fn target(&self) -> Option<u16> {
+        match self.mode {
+            AddressingMode::Relative => Some(self.branch_target(self.byte(0))),
+            AddressingMode::ZeroPageRelative => Some(self.branch_target(self.byte(1))),
+            AddressingMode::Absolute if matches!(self.mnemonic, "JMP" | "JSR") => Some(self.word()),
+            _ => None,
+        }
+    }
+
+    /// Returns an operand byte, or zero if it is missing.
+    fn byte(&self, index: usize) -> u8 {
+        self.operands.get(index).copied().unwrap_or(0)
+    }
+
+    /// Returns the first two operand bytes as a little-endian word.
+    fn word(&self) -> u16 {
+        (self.byte(1) as u16) << 8 | self.byte(0) as u16
+    }
+
+    /// Returns the address a branch offset leads to, which is relative to the end of the instruction.
+    fn branch_target(&self, offset: u8) -> u16 {
+        self.address
+            .wrapping_add(self.length)
+            .wrapping_add_signed(offset as i8 as i16)
+    }
+
+    /// Formats the operand, naming addresses from the symbol table if one is given.
+    fn render_operand(&self, symbols: Option<&SymbolTable>) -> String {
+        // An address is shown as its label, or in hex with as many digits as the operand has
+        let name = |address: u16, zero_page: bool| match symbols.and_then(|symbols| symbols.get(address)) {
+            Some(label) => String::from(label),
+            None if zero_page => format!("${:02X}", address),
+            None => format!("${:04X}", address),
+        };
+        let zero_page = name(self.byte(0) as u16, true);
+        let absolute = name(self.word(), false);
+
+        match self.mode {
+            AddressingMode::None | AddressingMode::Implied => String::new(),
+            AddressingMode::Accumulator => String::from("A"),
+            AddressingMode::Immediate => format!("#${:02X}", self.byte(0)),
+            AddressingMode::ZeroPage => zero_page,
+            AddressingMode::ZeroPageX => format!("{},X", zero_page),
+            AddressingMode::ZeroPageY => format!("{},Y", zero_page),
+            AddressingMode::Relative => name(self.branch_target(self.byte(0)), false),
+            AddressingMode::Absolute => absolute,
+            AddressingMode::AbsoluteX => format!("{},X", absolute),
+            AddressingMode::AbsoluteY => format!("{},Y", absolute),
+            AddressingMode::Indirect => format!("({})", absolute),
+            AddressingMode::AbsoluteIndexedIndirect => format!("({},X)", absolute),
+            AddressingMode::IndexedIndirect => format!("({},X)", zero_page),
+            AddressingMode::IndirectIndexed => format!("({}),Y", zero_page),
+            AddressingMode::ZeroPageIndirect => format!("({})", zero_page),
+            AddressingMode::ZeroPageRelative => {
+                format!("{},{}", zero_page, name(self.branch_target(self.byte(1)), false))
+            }
+        }
+    }
+}
+
+/// Joins a mnemonic and an operand, leaving out the space if there is no operand.
+fn join(mnemonic: &str, operand: &str) -> String {
+    if operand.is_empty() {
+        String::from(mnemonic)
+    } else {
+        format!("{} {}", mnemonic, operand)
+    }
+}
+
+impl Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", join(self.mnemonic, &self.operand_string()))
+    }
+}
@@ -0,0 +1,351 @@
+#![warn(missing_docs)]
+//! A disassembler that works on plain bytes.
+//!
+//! Unlike the CPU's own debug output, it doesn't need a bus or a CPU, so it can
+//! be used on ROM images, assembler output and test fixtures.
+
+mod dialect;
+mod instruction;
+mod options;
+mod symbols;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::cpu::instructions;
+use crate::cpu::{AddressingMode, CpuModel};
+
+pub use crate::disasm::dialect::Dialect;
+pub use crate::disasm::instruction::DisassembledInstruction;
+pub use crate::disasm::options::FormatOptions;
+pub use crate::disasm::symbols::SymbolTable;
+
+/// Decodes the instruction at the start of a slice.
+///
+/// # Arguments
+///
+/// * `model` - The CPU model whose instruction set is used.
+/// * `bytes` - The bytes to decode, starting with an opcode.
+/// * `address` - The address of the first byte, used to resolve branch targets.
+///
+/// # Returns
+///
+/// The decoded instruction, or `None` if the slice is empty or ends partway through the instruction.
+pub fn disassemble_one(model: CpuModel, bytes: &[u8], address: u16) -> Option<DisassembledInstruction> {
+    let (&opcode, rest) = bytes.split_first()?;
+    let instruction = instructions::lookup(model, opcode);
+    let operand_length = instruction.mode.operand_length();
+    let operands = rest.get(..operand_length as usize)?;
+
+    Some(DisassembledInstruction {
+        address,
+        opcode,
+        mnemonic: instruction.name,
+        mode: instruction.mode,
+        operands: operands.to_vec(),
+        length: operand_length + 1,
+        cycles: instruction.cycles,
+        illegal: instruction.illegal,
+    })
+}
+
+/// Disassembles a block of NMOS 6502 code.
+///
+/// # Arguments
+///
+/// * `bytes` - The code to disassemble.
+/// * `origin` - The address the first byte is loaded at.
+///
+/// # Returns
+///
+/// The instructions in order. An instruction cut off by the end of the slice is left out.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::disasm::disassemble;
+///
+/// let listing = disassemble(&[0xA9, 0x01, 0xD0, 0xFC], 0x0200);
+/// assert_eq!(listing[0].to_string(), "LDA #$01");
+/// assert_eq!(listing[1].to_string(), "BNE $0200");
+/// ```
+pub fn disassemble(bytes: &[u8], origin: u16) -> Vec<DisassembledInstruction> {
+    disassemble_model(CpuModel::Nmos6502, bytes, origin)
+}
+
+/// Disassembles a block of code for a specific CPU model.
+///
+/// # Arguments
+///
+/// * `model` - The CPU model whose instruction set is used.
+/// * `bytes` - The code to disassemble.
+/// * `origin` - The address the first byte is loaded at.
+///
+/// # Returns
+///
+/// The instructions in order. An instruction cut off by the end of the slice is left out.
+pub fn disassemble_model(model: CpuModel, bytes: &[u8], origin: u16) -> Vec<DisassembledInstruction> {
+    let mut listing = Vec::new();
+    let mut offset = 0;
+
+    while let Some(instruction) = disassemble_one(model, &bytes[offset..], origin.wrapping_add(offset as u16)) {
+        offset += instruction.length as usize;
+        listing.push(instruction);
+    }
+
+    listing
+}
+
+/// Adds labels for the branch, JMP and JSR targets that fall inside a disassembled range.
+///
+/// Targets that already have a label keep it. New labels are named after their
+/// address, such as "L0204".
+///
+/// # Arguments
+///
+/// * `listing` - The disassembled instructions, in address order.
+/// * `symbols` - The symbol table to add the labels to.
+///
+/// # Returns
+///
+/// The number of labels that were added.
+pub fn label_targets(listing: &[DisassembledInstruction], symbols: &mut SymbolTable) -> usize {
+    let mut added = 0;
+
+    for target in listing.iter().filter_map(DisassembledInstruction::target) {
+        // Only label targets that are the start of an instruction in the listing
+        let in_range = listing.iter().any(|instruction| instruction.address == target);
+        if in_range && symbols.get(target).is_none() {
+            symbols.insert(target, format!("L{:04X}", target));
+            added += 1;
+        }
+    }
+
+    added
+}
+
+/// Writes disassembled instructions out as source text.
+///
+/// Labels for addresses that aren't the start of an instruction in the listing
+/// are defined as constants at the top. In the ca65 and ACME dialects, an
+/// origin directive is written before the first instruction and after any gap,
+/// so the output can be fed straight back into the assembler.
+///
+/// # Arguments
+///
+/// * `listing` - The disassembled instructions, in address order.
+/// * `symbols` - The labels to define and to use in operands.
+/// * `options` - The dialect and other settings.
+///
+/// # Returns
+///
+/// The source text, one line per label, directive or instruction.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::disasm::{disassemble, format_listing, Dialect, FormatOptions, SymbolTable};
+///
+/// let listing = disassemble(&[0xA9, 0x01], 0x0200);
+/// let options = FormatOptions { dialect: Dialect::Acme, ..FormatOptions::default() };
+/// let source = format_listing(&listing, &SymbolTable::new(), &options);
+/// assert_eq!(source, "        * = $0200\n        LDA #$01\n");
+/// ```
+pub fn format_listing(listing: &[DisassembledInstruction], symbols: &SymbolTable, options: &FormatOptions) -> String {
+    let dialect = options.dialect;
+    let mut source = String::new();
+
+    // Labels that don't land on an instruction become constants
+    for (address, label) in symbols.iter() {
+        if !listing.iter().any(|instruction| instruction.address == address) {
+            let _ = writeln!(source, "{} = ${:04X}", label, address);
+        }
+    }
+
+    let mut next_address = None;
+    for instruction in listing {
+        // Start a new block of code if this instruction doesn't follow on from the last
+        if next_address != Some(instruction.address) {
+            if let Some(origin) = dialect.origin(instruction.address) {
+                let _ = writeln!(source, "        {}", origin);
+            }
+        }
+        next_address = Some(instruction.address.wrapping_add(instruction.length));
+
+        if let Some(label) = symbols.get(instruction.address) {
+            let _ = writeln!(source, "{}", dialect.label(label));
+        }
+
+        let bytes: Vec<String> = core::iter::once(&instruction.opcode)
+            .chain(&instruction.operands)
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+
+        // Assemblers don't agree on names for undocumented opcodes, so those are
+        // written as bytes, along with the CMOS NOPs that skip operands
+        let as_bytes = instruction.illegal || (instruction.mnemonic == "NOP" && !instruction.operands.is_empty());
+        let text = if options.illegal_as_bytes && as_bytes {
+            let values: Vec<String> = bytes.iter().map(|byte| format!("${}", byte)).collect();
+            format!("{} {}", dialect.byte_directive(), values.join(", "))
+        } else {
+            let operand = instruction.operand_string_with(symbols);
+
+            // An absolute operand in page zero would be shortened by the assembler
+            let absolute = matches!(
+                instruction.mode,
+                AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY
+            );
+            let (mnemonic, operand) = if absolute && instruction.operands.get(1) == Some(&0) {
+                dialect.force_absolute(instruction.mnemonic, &operand)
+            } else {
+                (String::from(instruction.mnemonic), operand)
+            };
+
+            if operand.is_empty() {
+                mnemonic
+            } else {
+                format!("{} {}", mnemonic, operand)
+            }
+        };
+
+        let _ = match dialect {
+            Dialect::Plain => writeln!(source, "{:04X}  {:<8}  {}", instruction.address, bytes.join(" "), text),
+            _ if options.comment_addresses => {
+                writeln!(source, "        {:<24}; {:04X}  {}", text, instruction.address, bytes.join(" "))
+            }
+            _ => writeln!(source, "        {}", text),
+        };
+    }
+
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use crate::cpu::{AddressingMode, CpuModel};
+    use crate::disasm::{
+        disassemble, disassemble_model, format_listing, label_targets, Dialect, FormatOptions, SymbolTable,
+    };
+
+    #[test]
+    fn decodes_lengths_and_cycles() {
+        // LDA $1234,X, INX, JMP ($0300)
+        let listing = disassemble(&[0xBD, 0x34, 0x12, 0xE8, 0x6C, 0x00, 0x03], 0x8000);
+
+        assert_eq!(listing.len(), 3);
+        assert_eq!(listing[0].address, 0x8000);
+        assert_eq!(listing[0].mode, AddressingMode::AbsoluteX);
+        assert_eq!(listing[0].operands, [0x34, 0x12]);
+        assert_eq!(listing[0].cycles, 4);
+        assert_eq!(listing[1].address, 0x8003);
+        assert_eq!(listing[1].to_string(), "INX");
+        assert_eq!(listing[2].to_string(), "JMP ($0300)");
+    }
+
+    #[test]
+    fn uses_the_model_instruction_set() {
+        // $A7 is the undocumented LAX on the NMOS part and SMB2 on the 65C02
+        let listing = disassemble(&[0xA7, 0x10], 0);
+        assert_eq!(listing[0].to_string(), "LAX $10");
+        assert!(listing[0].illegal);
+
+        let listing = disassemble_model(CpuModel::Wdc65C02, &[0xA7, 0x10], 0);
+        assert_eq!(listing[0].to_string(), "SMB2 $10");
+        assert!(!listing[0].illegal);
+    }
+
+    #[test]
+    fn leaves_out_truncated_instruction() {
+        // NOP, then LDA with only one of its operand bytes
+        let listing = disassemble(&[0xEA, 0xAD, 0x34], 0);
+
+        assert_eq!(listing.len(), 1);
+    }
+
+    #[test]
+    fn renders_operands_as_labels() {
+        // JSR $FDED, LDA ($10),Y, LDA #$10
+        let listing = disassemble(&[0x20, 0xED, 0xFD, 0xB1, 0x10, 0xA9, 0x10], 0x0200);
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0xFDED, "print_char");
+        symbols.insert(0x0010, "ptr");
+
+        assert_eq!(listing[0].to_string_with(&symbols), "JSR print_char");
+        assert_eq!(listing[1].to_string_with(&symbols), "LDA (ptr),Y");
+        assert_eq!(listing[2].to_string_with(&symbols), "LDA #$10");
+    }
+
+    #[test]
+    fn labels_targets_inside_range() {
+        // loop: DEX, BNE loop, JMP $0200, JMP $C000
+        let listing = disassemble(&[0xCA, 0xD0, 0xFD, 0x4C, 0x00, 0x02, 0x4C, 0x00, 0xC0], 0x0200);
+        let mut symbols = SymbolTable::new();
+
+        assert_eq!(label_targets(&listing, &mut symbols), 1);
+        assert_eq!(symbols.get(0x0200), Some("L0200"));
+        assert_eq!(listing[1].to_string_with(&symbols), "BNE L0200");
+        assert_eq!(listing[3].to_string_with(&symbols), "JMP $C000");
+    }
+
+    /// Disassembles a program that exercises labels, page zero operands and an undocumented opcode.
+    fn format_sample(dialect: Dialect) -> alloc::string::String {
+        // loop: LDA $0010, JSR $FDED, LAX $10, BNE loop
+        let listing = disassemble(&[0xAD, 0x10, 0x00, 0x20, 0xED, 0xFD, 0xA7, 0x10, 0xD0, 0xF6], 0x0200);
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0xFDED, "print_char");
+        label_targets(&listing, &mut symbols);
+
+        let options = FormatOptions { dialect, ..FormatOptions::default() };
+        format_listing(&listing, &symbols, &options)
+    }
+
+    #[test]
+    fn formats_ca65_source() {
+        assert_eq!(
+            format_sample(Dialect::Ca65),
+            "\
+print_char = $FDED
+        .org $0200
+L0200:
+        LDA a:$0010
+        JSR print_char
+        .byte $A7, $10
+        BNE L0200
+"
+        );
+    }
+
+    #[test]
+    fn formats_acme_source() {
+        assert_eq!(
+            format_sample(Dialect::Acme),
+            "\
+print_char = $FDED
+        * = $0200
+L0200
+        LDA+2 $0010
+        JSR print_char
+        !byte $A7, $10
+        BNE L0200
+"
+        );
+    }
+
+    #[test]
+    fn formats_plain_listing() {
+        assert_eq!(
+            format_sample(Dialect::Plain),
+            "\
+print_char = $FDED
+L0200:
+0200  AD 10 00  LDA $0010
+0203  20 ED FD  JSR print_char
+0206  A7 10     .byte $A7, $10
+0208  D0 F6     BNE L0200
+"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use crate::disasm::Dialect;
+
+/// Settings for turning disassembly into source text with `format_listing()`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FormatOptions {
+    /// The assembler syntax to write.
+    pub dialect: Dialect,
+
+    /// Whether to write undocumented opcodes, and NOPs that take operands, as
+    /// raw bytes. Assemblers disagree on their mnemonics, so this keeps the
+    /// output assembling to the same bytes.
+    pub illegal_as_bytes: bool,
+
+    /// Whether to add a comment with the address and bytes of each instruction.
+    /// The plain dialect always shows them.
+    pub comment_addresses: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            dialect: Dialect::Plain,
+            illegal_as_bytes: true,
+            comment_addresses: false,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// Names for addresses, used to show labels in place of raw operands.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SymbolTable {
+    /// The label for each named address.
+    labels: BTreeMap<u16, String>,
+}
+
+impl SymbolTable {
+    /// Creates an empty symbol table.
+    ///
+    /// # Returns
+    ///
+    /// A new `SymbolTable` with no labels.
+    pub fn new() -> SymbolTable {
+        SymbolTable { labels: BTreeMap::new() }
+    }
+
+    /// Names an address, replacing any label it already had.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to name.
+    /// * `label` - The label to show for it.
+    ///
+    /// # Returns
+    ///
+    /// The label the address had before, if any.
+    pub fn insert(&mut self, address: u16, label: impl Into<String>) -> Option<String> {
+        self.labels.insert(address, label.into())
+    }
+
+    /// Removes the label for an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address whose label is removed.
+    ///
+    /// # Returns
+    ///
+    /// The label that was removed, if there was one.
+    pub fn remove(&mut self, address: u16) -> Option<String> {
+        self.labels.remove(&address)
+    }
+
+    /// Returns the label for an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to look up.
+    ///
+    /// # Returns
+    ///
+    /// The label, or `None` if the address has no name.
+    pub fn get(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// Finds the address a label names.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label to look up.
+    ///
+    /// # Returns
+    ///
+    /// The address, or `None` if no address has that label.
+    pub fn address_of(&self, label: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find_map(|(&address, name)| (name == label).then_some(address))
+    }
+
+    /// Returns the number of labels in the table.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns whether the table has no labels.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Iterates over the labels in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.labels.iter().map(|(&address, label)| (address, label.as_str()))
+    }
+}
@@ -0,0 +1,501 @@
+use alloc::boxed::Box;
+use core::fmt::Display;
+use core::ops::ControlFlow;
+
+use crate::bus::{BusDevice, MainBus, SharedBus};
+use crate::cpu::{Cpu, CpuState, InstructionRecord, WatchHit};
+#[cfg(feature = "std")]
+use crate::pacing::{Benchmark, Pacer};
+
+/// The number of batches `run_realtime()` splits each emulated second into.
+#[cfg(feature = "std")]
+const BATCHES_PER_SECOND: u64 = 1_000;
+
+/// The number of instructions `benchmark()` runs between looking at the clock.
+#[cfg(feature = "std")]
+const INSTRUCTIONS_PER_CHECK: u64 = 1_024;
+
+/// Why `Emulator::run()` returned.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StopReason {
+    /// The program counter reached a breakpoint at the given address. The
+    /// instruction there has not been executed yet.
+    Breakpoint(u16),
+
+    /// The CPU accessed a watched address. The instruction that made the access
+    /// has been executed.
+    Watchpoint(WatchHit),
+
+    /// The CPU executed STP at the given address, and is idle until it is reset.
+    Stopped(u16),
+
+    /// The CPU executed a KIL/JAM opcode at the given address, and is locked up until it is reset.
+    Jammed(u16),
+
+    /// The host asked to stop, such as when the user pressed a key to break in.
+    Interrupted,
+}
+
+impl From<crate::cpu::StopReason> for StopReason {
+    fn from(reason: crate::cpu::StopReason) -> StopReason {
+        match reason {
+            crate::cpu::StopReason::Breakpoint(address) => StopReason::Breakpoint(address),
+            crate::cpu::StopReason::Watchpoint(hit) => StopReason::Watchpoint(hit),
+        }
+    }
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StopReason::Breakpoint(address) => write!(f, "Breakpoint at {:04X}", address),
+            StopReason::Watchpoint(hit) => write!(f, "Watchpoint: {}", hit),
+            StopReason::Stopped(address) => write!(f, "Stopped at {:04X}", address),
+            StopReason::Jammed(address) => write!(f, "Jammed at {:04X}", address),
+            StopReason::Interrupted => write!(f, "Interrupted"),
+        }
+    }
+}
+
+/// A complete machine: a CPU and the bus it is connected to.
+///
+/// This is the front-end facing entry point. The CPU is still reachable for
+/// anything the emulator doesn't wrap, and its bus is the emulator's bus.
+pub struct Emulator {
+    /// The CPU, connected to the emulator's bus.
+    pub cpu: Cpu,
+}
+
+impl Emulator {
+    /// Creates an emulator with an NMOS 6502 and an empty bus.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Emulator` struct.
+    pub fn new() -> Emulator {
+        Emulator::from_cpu(Cpu::new(SharedBus::new(MainBus::new())))
+    }
+
+    /// Creates an emulator around an already configured CPU, such as one made
+    /// by `CpuBuilder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The CPU, connected to the bus the machine should use.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Emulator` struct.
+    pub fn from_cpu(cpu: Cpu) -> Emulator {
+        Emulator { cpu }
+    }
+
+    /// Returns the bus the CPU is connected to.
+    pub fn bus(&self) -> &SharedBus {
+        &self.cpu.bus
+    }
+
+    /// Connects a device to the bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to connect.
+    pub fn add_device(&mut self, device: Box<dyn BusDevice>) {
+        self.cpu.bus.borrow_mut().add_device(device);
+    }
+
+    /// Copies bytes onto the bus, as a debugger or loader would.
+    ///
+    /// The writes aren't traced or watched, and bytes that land on unmapped
+    /// addresses are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the first byte. Later bytes wrap around
+    ///   the top of memory.
+    /// * `data` - The bytes to copy.
+    pub fn load(&mut self, address: u16, data: &[u8]) {
+        let mut bus = self.cpu.bus.borrow_mut();
+        for (offset, byte) in data.iter().enumerate() {
+            let _ = bus.try_write(address.wrapping_add(offset as u16), *byte);
+        }
+    }
+
+    /// Reads a byte from the bus without tracing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read.
+    ///
+    /// # Returns
+    ///
+    /// The byte at `address`, or 0 if nothing is mapped there.
+    pub fn read(&self, address: u16) -> u8 {
+        self.cpu.bus.borrow().try_read(address).unwrap_or(0)
+    }
+
+    /// Writes a byte to the bus without tracing it. Writes to unmapped
+    /// addresses are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write.
+    /// * `value` - The byte to write.
+    pub fn write(&mut self, address: u16, value: u8) {
+        let _ = self.cpu.bus.borrow_mut().try_write(address, value);
+    }
+
+    /// Reads a byte for a debugger, without triggering any device side
+    /// effects. See `MainBus::peek()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to read.
+    ///
+    /// # Returns
+    ///
+    /// The byte at `address`, or 0 if nothing is mapped there.
+    pub fn peek(&self, address: u16) -> u8 {
+        self.cpu.bus.borrow().peek(address)
+    }
+
+    /// Writes a byte for a debugger, without triggering any device side
+    /// effects. See `MainBus::poke()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to write.
+    /// * `value` - The byte to write.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.cpu.bus.borrow_mut().poke(address, value);
+    }
+
+    /// Asserts the reset line, resetting the devices and then the CPU.
+    ///
+    /// Devices go back to their register defaults but memory keeps its
+    /// contents, so a program loaded beforehand still runs. The CPU loads the
+    /// program counter from the reset vector once the devices have reset.
+    pub fn reset(&mut self) {
+        self.cpu.bus.borrow_mut().reset();
+        self.cpu.reset();
+    }
+
+    /// Switches the machine on, filling RAM with its power-on pattern and
+    /// then resetting the CPU.
+    pub fn power_on(&mut self) {
+        self.cpu.bus.borrow_mut().power_on();
+        self.cpu.reset();
+    }
+
+    /// Executes one instruction. See `Cpu::step()`.
+    ///
+    /// # Returns
+    ///
+    /// A record of the instruction.
+    pub fn step(&mut self) -> InstructionRecord {
+        self.cpu.step()
+    }
+
+    /// Runs whole instructions for at least the given number of cycles. See
+    /// `Cpu::run_for_cycles()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles to run for.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles that actually elapsed.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> u64 {
+        self.cpu.run_for_cycles(cycles)
+    }
+
+    /// Runs the machine until something stops it, handing control to the host between slices.
+    ///
+    /// This is the main loop for a front end. The CPU runs whole instructions,
+    /// ticking the devices as it goes, and after every `slice` cycles the
+    /// host gets to poll its own I/O: feed keys to a keyboard, show what a
+    /// display has drawn, pace the machine to real time or check whether the
+    /// user wants to break in. While the CPU is waiting for an interrupt, time
+    /// still passes for the devices that might raise one. As with
+    /// `Cpu::run_for_cycles()`, the CPU holds the bus during each slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `slice` - The number of cycles to run between calls to `host`. The
+    ///   last instruction of a slice may take it a few cycles over.
+    /// * `host` - Called with the machine and the cycles that elapsed after
+    ///   every slice, including the last one before the machine stops.
+    ///   Returning `ControlFlow::Break(())` stops the machine.
+    ///
+    /// # Returns
+    ///
+    /// Why the machine stopped: a breakpoint or watchpoint, the CPU stopping
+    /// or jamming, or the host breaking in.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::ops::ControlFlow;
+    /// use butterflyrs::bus::ram::Ram;
+    /// use butterflyrs::emulator::{Emulator, StopReason};
+    ///
+    /// let mut emulator = Emulator::new();
+    /// emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    ///
+    /// // An endless loop, which the host breaks out of after a second at 1 MHz
+    /// emulator.load(0x0200, &[0x4C, 0x00, 0x02]);
+    /// emulator.cpu.pc.set(0x0200);
+    /// let mut total = 0;
+    /// let reason = emulator.run(1_000, |_, elapsed| {
+    ///     total += elapsed;
+    ///     if total < 1_000_000 { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+    /// });
+    /// assert_eq!(reason, StopReason::Interrupted);
+    /// ```
+    pub fn run<F>(&mut self, slice: u64, mut host: F) -> StopReason
+    where
+        F: FnMut(&mut Emulator, u64) -> ControlFlow<()>,
+    {
+        let slice = slice.max(1);
+
+        loop {
+            // The CPU holds the bus for the slice, and gives it back for the host
+            let (elapsed, stop) = self.cpu.with_bus_held(|cpu| {
+                let mut elapsed = 0;
+                let mut stop = None;
+                while elapsed < slice && stop.is_none() {
+                    let (cycles, reason) = cpu.finish_instruction();
+                    elapsed += cycles;
+                    stop = reason.map(StopReason::from);
+                    if cycles > 0 || stop.is_some() {
+                        continue;
+                    }
+
+                    let pc = cpu.pc.get();
+                    match cpu.state() {
+                        // Clocking a waiting CPU ticks the devices, and runs the handler once one interrupts
+                        CpuState::Waiting | CpuState::Running => {
+                            cpu.clock();
+                            elapsed += 1;
+                        }
+                        CpuState::Stopped => stop = Some(StopReason::Stopped(pc)),
+                        CpuState::Jammed => stop = Some(StopReason::Jammed(pc)),
+                    }
+                }
+                (elapsed, stop)
+            });
+
+            let flow = host(self, elapsed);
+            if let Some(reason) = stop {
+                return reason;
+            }
+            if flow.is_break() {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+
+    /// Runs at the speed of a real machine, until the CPU stops.
+    ///
+    /// Cycles are run a millisecond's worth at a time with `run()`, and paced
+    /// with a `Pacer`, so devices such as the Blink8 change at a speed a
+    /// person can follow.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The clock frequency to run at in Hz, such as 1_000_000 for 1 MHz.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles that elapsed before the CPU hit a breakpoint or
+    /// watchpoint, or stopped or jammed.
+    #[cfg(feature = "std")]
+    pub fn run_realtime(&mut self, frequency: u64) -> u64 {
+        let mut pacer = Pacer::new(frequency);
+        let mut total = 0;
+        self.run(frequency / BATCHES_PER_SECOND, |_, elapsed| {
+            total += elapsed;
+            pacer.pace(elapsed);
+            ControlFlow::Continue(())
+        });
+        total
+    }
+
+    /// Runs as fast as possible for a while and measures how fast that was.
+    ///
+    /// The workload is whatever the machine is running. Stopping early at a
+    /// breakpoint or watchpoint, or because the CPU stopped or jammed, ends
+    /// the measurement there.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The wall-clock time to run for.
+    ///
+    /// # Returns
+    ///
+    /// The cycles and instructions run, and the time they took.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use butterflyrs::emulator::Emulator;
+    ///
+    /// let mut emulator = Emulator::new();
+    /// println!("{}", emulator.benchmark(Duration::from_secs(5)));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn benchmark(&mut self, duration: std::time::Duration) -> Benchmark {
+        let start = std::time::Instant::now();
+        let (mut cycles, mut instructions) = (0, 0);
+
+        'run: while start.elapsed() < duration {
+            for _ in 0..INSTRUCTIONS_PER_CHECK {
+                // Asking for a single cycle runs exactly one instruction
+                let elapsed = self.cpu.run_for_cycles(1);
+                if elapsed > 0 {
+                    cycles += elapsed;
+                    instructions += 1;
+                } else if self.cpu.state() == CpuState::Waiting {
+                    self.cpu.clock();
+                    cycles += 1;
+                } else {
+                    break 'run;
+                }
+            }
+        }
+
+        Benchmark { cycles, instructions, elapsed: start.elapsed() }
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Emulator {
+        Emulator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    use core::ops::ControlFlow;
+
+    use crate::bus::blink8::Blink8;
+    use crate::bus::ram::Ram;
+    use crate::cpu::Breakpoint;
+    use crate::emulator::{Emulator, StopReason};
+
+    #[test]
+    fn loads_and_runs_a_program() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        emulator.add_device(Box::new(Ram::new(0x8000, 0xFFFF)));
+
+        // Reset vector at $0400: LDA #$42, STA $10
+        emulator.load(0xFFFC, &[0x00, 0x04]);
+        emulator.load(0x0400, &[0xA9, 0x42, 0x85, 0x10]);
+        emulator.reset();
+
+        assert_eq!(emulator.step().cycles, 2);
+        assert_eq!(emulator.run_for_cycles(3), 3);
+        assert_eq!(emulator.read(0x0010), 0x42);
+        assert_eq!(emulator.cpu.pc.get(), 0x0404);
+    }
+
+    #[test]
+    fn reset_reaches_devices_but_keeps_memory() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::with_fill(0x0000, 0x7FFF, 0xFF)));
+        emulator.add_device(Box::new(Blink8::new()));
+        emulator.load(0x0010, &[0x42]);
+        emulator.write(0x8002, 0xFF);
+
+        emulator.reset();
+        assert_eq!(emulator.read(0x0010), 0x42);
+        // Blink8 is disabled again
+        assert_eq!(emulator.cpu.bus.borrow().device_at(0x8000).unwrap().save(), [0]);
+
+        emulator.power_on();
+        assert_eq!(emulator.read(0x0010), 0xFF);
+    }
+
+    #[test]
+    fn run_stops_for_breakpoints_jams_and_the_host() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+
+        // LDX #$00, DEX, BNE -3, KIL
+        emulator.load(0x0200, &[0xA2, 0x00, 0xCA, 0xD0, 0xFD, 0x02]);
+        emulator.cpu.pc.set(0x0200);
+
+        // The host sees every cycle, and breaks in after the fourth slice
+        let mut slices = Vec::new();
+        let reason = emulator.run(100, |_, elapsed| {
+            slices.push(elapsed);
+            if slices.len() < 4 { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+        });
+        assert_eq!(reason, StopReason::Interrupted);
+        assert!(slices.iter().all(|&elapsed| (100..105).contains(&elapsed)));
+
+        emulator.cpu.set_breakpoint(0x0205, Breakpoint::default());
+        assert_eq!(emulator.run(100, |_, _| ControlFlow::Continue(())), StopReason::Breakpoint(0x0205));
+        assert_eq!(emulator.run(100, |_, _| ControlFlow::Continue(())), StopReason::Jammed(0x0205));
+        assert_eq!(StopReason::Jammed(0x0205).to_string(), "Jammed at 0205");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn run_realtime_takes_as_long_as_the_real_machine() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+
+        // LDX #$00, DEX, BNE -3, KIL: 1,283 cycles with the KIL, which take 12.8 ms at 100 kHz
+        emulator.load(0x0200, &[0xA2, 0x00, 0xCA, 0xD0, 0xFD, 0x02]);
+        emulator.cpu.pc.set(0x0200);
+
+        let start = std::time::Instant::now();
+        assert_eq!(emulator.run_realtime(100_000), 1_283);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(12));
+        assert!(emulator.cpu.is_jammed());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn benchmark_counts_cycles_and_instructions() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+
+        // The same loop, which jams long before the time is up
+        emulator.load(0x0200, &[0xA2, 0x00, 0xCA, 0xD0, 0xFD, 0x02]);
+        emulator.cpu.pc.set(0x0200);
+
+        let report = emulator.benchmark(std::time::Duration::from_secs(10));
+        assert_eq!((report.cycles, report.instructions), (1_283, 514));
+        assert!(report.elapsed < std::time::Duration::from_secs(10));
+        assert!(report.cycles_per_second() > report.instructions_per_second());
+    }
+
+    #[test]
+    fn blink8_output_goes_to_its_handler() {
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let log = shown.clone();
+        let mut blink8 = Blink8::new();
+        blink8.set_output(Box::new(move |value| log.lock().unwrap().push(value)));
+
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        emulator.add_device(Box::new(blink8));
+
+        // Nothing is shown until the device is enabled
+        emulator.write(0x8000, 0x01);
+        emulator.write(0x8002, 0xFF);
+        emulator.write(0x8000, 0xA5);
+
+        assert_eq!(*shown.lock().unwrap(), [0xA5]);
+    }
+}
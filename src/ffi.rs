@@ -0,0 +1,375 @@
+//! C bindings for the emulator core.
+//!
+//! Every function takes a `ButterflyCpu` handle created by one of the
+//! `butterfly_cpu_new` functions and released by `butterfly_cpu_free`. The
+//! header for these bindings, `include/butterfly.h`, is generated by cbindgen.
+//! Building with the `ffi` feature generates it into `OUT_DIR`, and the tests
+//! check that the copy in `include` is up to date. After changing the
+//! bindings, regenerate that copy with:
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/butterfly.h
+//! ```
+//!
+//! The crate is an rlib by default so that it still builds for `no_std`
+//! targets; build the shared library for C with:
+//!
+//! ```text
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! ```
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::ffi::c_void;
+
+use crate::bus::ram::Ram;
+use crate::bus::{BusDevice, MainBus, SharedBus};
+use crate::cpu::{Cpu, CpuModel};
+
+/// Called to read a byte from a host-provided bus.
+pub type ButterflyReadFn = extern "C" fn(user_data: *mut c_void, address: u16) -> u8;
+
+/// Called to write a byte to a host-provided bus.
+pub type ButterflyWriteFn = extern "C" fn(user_data: *mut c_void, address: u16, value: u8);
+
+/// The CPU variants that can be created through the C bindings.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ButterflyModel {
+    /// The original NMOS 6502.
+    Nmos6502,
+    /// The WDC 65C02.
+    Wdc65C02,
+    /// The Ricoh 2A03 used in the NES.
+    Ricoh2A03,
+    /// The WDC 65C816.
+    Wdc65C816,
+}
+
+impl From<ButterflyModel> for CpuModel {
+    fn from(model: ButterflyModel) -> CpuModel {
+        match model {
+            ButterflyModel::Nmos6502 => CpuModel::Nmos6502,
+            ButterflyModel::Wdc65C02 => CpuModel::Wdc65C02,
+            ButterflyModel::Ricoh2A03 => CpuModel::Ricoh2A03,
+            ButterflyModel::Wdc65C816 => CpuModel::Wdc65C816,
+        }
+    }
+}
+
+/// The registers that can be read and written through the C bindings.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ButterflyRegister {
+    /// The accumulator.
+    A,
+    /// The X index register.
+    X,
+    /// The Y index register.
+    Y,
+    /// The processor status register.
+    P,
+    /// The stack pointer.
+    Sp,
+    /// The program counter.
+    Pc,
+}
+
+/// An opaque handle to a CPU and its bus.
+pub struct ButterflyCpu {
+    cpu: Cpu,
+}
+
+/// A device that forwards every access covering the whole address space to
+/// host callbacks.
+struct CallbackDevice {
+    read: ButterflyReadFn,
+    write: ButterflyWriteFn,
+    user_data: *mut c_void,
+}
+
+// The host promises, through `butterfly_cpu_new_with_bus`, that the callbacks
+// and their user data may be used from whichever thread drives the CPU.
+unsafe impl Send for CallbackDevice {}
+
+impl BusDevice for CallbackDevice {
+    fn read(&self, address: u16) -> u8 {
+        (self.read)(self.user_data, address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        (self.write)(self.user_data, address, value)
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {}
+
+    fn name(&self) -> String {
+        String::from("Host bus")
+    }
+
+    fn start_address(&self) -> u16 {
+        0x0000
+    }
+
+    fn end_address(&self) -> u16 {
+        0xFFFF
+    }
+}
+
+/// Wraps a bus in a new CPU handle.
+///
+/// # Arguments
+///
+/// * `model` - The CPU variant to emulate.
+/// * `bus` - The bus to connect the CPU to.
+///
+/// # Returns
+///
+/// An owned pointer to the handle.
+fn new_handle(model: ButterflyModel, bus: MainBus) -> *mut ButterflyCpu {
+    let mut cpu = Cpu::new(SharedBus::new(bus));
+    cpu.model = model.into();
+    Box::into_raw(Box::new(ButterflyCpu { cpu }))
+}
+
+/// Creates a CPU backed by 64K of RAM.
+///
+/// The CPU isn't reset; load a program and call `butterfly_cpu_reset` first.
+///
+/// # Arguments
+///
+/// * `model` - The CPU variant to emulate.
+///
+/// # Returns
+///
+/// A handle to release with `butterfly_cpu_free`.
+#[no_mangle]
+pub extern "C" fn butterfly_cpu_new(model: ButterflyModel) -> *mut ButterflyCpu {
+    let mut bus = MainBus::new();
+    bus.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+    bus.add_device(Box::new(Ram::new(0x8000, 0xFFFF)));
+    new_handle(model, bus)
+}
+
+/// Creates a CPU whose whole address space is handled by host callbacks.
+///
+/// # Arguments
+///
+/// * `model` - The CPU variant to emulate.
+/// * `read` - Called for every read, with `user_data` and the address.
+/// * `write` - Called for every write, with `user_data`, the address and the value.
+/// * `user_data` - Passed through to the callbacks untouched.
+///
+/// # Returns
+///
+/// A handle to release with `butterfly_cpu_free`.
+///
+/// # Safety
+///
+/// `user_data` must remain valid for as long as the handle exists, and the
+/// callbacks must be safe to call from whichever thread drives the CPU.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_new_with_bus(
+    model: ButterflyModel,
+    read: ButterflyReadFn,
+    write: ButterflyWriteFn,
+    user_data: *mut c_void,
+) -> *mut ButterflyCpu {
+    let mut bus = MainBus::new();
+    bus.add_device(Box::new(CallbackDevice { read, write, user_data }));
+    new_handle(model, bus)
+}
+
+/// Releases a CPU handle. Passing null does nothing.
+///
+/// # Safety
+///
+/// `cpu` must be null or a handle returned by a `butterfly_cpu_new` function
+/// that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_free(cpu: *mut ButterflyCpu) {
+    if !cpu.is_null() {
+        drop(Box::from_raw(cpu));
+    }
+}
+
+/// Resets the CPU, loading the program counter from the reset vector.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_reset(cpu: *mut ButterflyCpu) {
+    (*cpu).cpu.reset();
+}
+
+/// Executes one instruction, servicing a pending interrupt first.
+///
+/// # Returns
+///
+/// The number of cycles taken, which is 0 if the CPU is waiting, stopped or jammed.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_step(cpu: *mut ButterflyCpu) -> u32 {
+    (*cpu).cpu.step().cycles
+}
+
+/// Reads a byte from the bus for a debugger, without side effects.
+///
+/// The device's `peek()` is used, so reading an I/O register doesn't change it.
+///
+/// # Returns
+///
+/// The byte at `address`, or 0 if nothing is mapped there.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_read(cpu: *const ButterflyCpu, address: u16) -> u8 {
+    (*cpu).cpu.bus.borrow().peek(address)
+}
+
+/// Writes a byte to the bus. Writes to unmapped addresses are dropped.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_write(cpu: *mut ButterflyCpu, address: u16, value: u8) {
+    let _ = (*cpu).cpu.bus.borrow_mut().try_write(address, value);
+}
+
+/// Reads a register.
+///
+/// # Returns
+///
+/// The register's value, widened to 16 bits for the 8-bit registers.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_get_register(cpu: *const ButterflyCpu, register: ButterflyRegister) -> u16 {
+    let cpu = &(*cpu).cpu;
+    match register {
+        ButterflyRegister::A => cpu.a.get() as u16,
+        ButterflyRegister::X => cpu.x.get() as u16,
+        ButterflyRegister::Y => cpu.y.get() as u16,
+        ButterflyRegister::P => cpu.p.get() as u16,
+        ButterflyRegister::Sp => cpu.sp.get() as u16,
+        ButterflyRegister::Pc => cpu.pc.get(),
+    }
+}
+
+/// Sets a register.
+///
+/// # Returns
+///
+/// `false` if `value` doesn't fit in an 8-bit register, in which case the
+/// register is left alone.
+///
+/// # Safety
+///
+/// `cpu` must be a live handle returned by a `butterfly_cpu_new` function.
+#[no_mangle]
+pub unsafe extern "C" fn butterfly_cpu_set_register(
+    cpu: *mut ButterflyCpu,
+    register: ButterflyRegister,
+    value: u16,
+) -> bool {
+    let cpu = &mut (*cpu).cpu;
+    if register == ButterflyRegister::Pc {
+        cpu.pc.set(value);
+        return true;
+    }
+
+    let Ok(value) = u8::try_from(value) else {
+        return false;
+    };
+    match register {
+        ButterflyRegister::A => cpu.a.set(value),
+        ButterflyRegister::X => cpu.x.set(value),
+        ButterflyRegister::Y => cpu.y.set(value),
+        ButterflyRegister::P => cpu.p.set(value),
+        ButterflyRegister::Sp => cpu.sp.set(value),
+        ButterflyRegister::Pc => unreachable!(),
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::c_void;
+
+    use crate::ffi::*;
+
+    extern "C" fn read_memory(user_data: *mut c_void, address: u16) -> u8 {
+        let memory = unsafe { &*(user_data as *const [u8; 0x10000]) };
+        memory[address as usize]
+    }
+
+    extern "C" fn write_memory(user_data: *mut c_void, address: u16, value: u8) {
+        let memory = unsafe { &mut *(user_data as *mut [u8; 0x10000]) };
+        memory[address as usize] = value;
+    }
+
+    #[test]
+    fn steps_a_program_in_ram() {
+        unsafe {
+            let cpu = butterfly_cpu_new(ButterflyModel::Nmos6502);
+
+            // LDA #$42, STA $10
+            for (offset, byte) in [0xA9, 0x42, 0x85, 0x10].iter().enumerate() {
+                butterfly_cpu_write(cpu, 0x0200 + offset as u16, *byte);
+            }
+            assert!(butterfly_cpu_set_register(cpu, ButterflyRegister::Pc, 0x0200));
+
+            assert_eq!(butterfly_cpu_step(cpu), 2);
+            assert_eq!(butterfly_cpu_step(cpu), 3);
+            assert_eq!(butterfly_cpu_get_register(cpu, ButterflyRegister::A), 0x42);
+            assert_eq!(butterfly_cpu_read(cpu, 0x0010), 0x42);
+
+            assert!(!butterfly_cpu_set_register(cpu, ButterflyRegister::X, 0x100));
+            assert_eq!(butterfly_cpu_get_register(cpu, ButterflyRegister::X), 0x00);
+
+            butterfly_cpu_free(cpu);
+        }
+    }
+
+    #[test]
+    fn host_callbacks_serve_the_bus() {
+        let mut memory = Box::new([0u8; 0x10000]);
+        // Reset vector at $0300, LDX #$07, STX $20
+        memory[0xFFFC] = 0x00;
+        memory[0xFFFD] = 0x03;
+        memory[0x0300..0x0304].copy_from_slice(&[0xA2, 0x07, 0x86, 0x20]);
+        let user_data = memory.as_mut_ptr() as *mut c_void;
+
+        unsafe {
+            let cpu = butterfly_cpu_new_with_bus(ButterflyModel::Wdc65C02, read_memory, write_memory, user_data);
+            butterfly_cpu_reset(cpu);
+            assert_eq!(butterfly_cpu_get_register(cpu, ButterflyRegister::Pc), 0x0300);
+
+            butterfly_cpu_step(cpu);
+            butterfly_cpu_step(cpu);
+            butterfly_cpu_free(cpu);
+        }
+
+        assert_eq!(memory[0x0020], 0x07);
+    }
+
+    #[test]
+    fn checked_in_header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/butterfly.h"));
+        let checked_in = include_str!("../include/butterfly.h");
+        assert!(generated == checked_in, "include/butterfly.h is stale; regenerate it with cbindgen");
+    }
+}
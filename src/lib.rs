@@ -0,0 +1,31 @@
+//! An experiment in 6502 system emulation.
+//!
+//! The CPU core, its registers and the bus only need `alloc`, so the crate is
+//! `no_std` unless the default `std` feature is enabled. Without `std`, debug
+//! output and the devices' console messages are compiled out.
+
+#![no_std]
+
+#[macro_use]
+extern crate alloc;
+
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+pub mod asm;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod bus;
+pub mod cpu;
+pub mod disasm;
+pub mod emulator;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod loaders;
+pub mod machines;
+pub mod monitor;
+#[cfg(feature = "std")]
+pub mod pacing;
+pub mod register;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,196 @@
+#[cfg(feature = "std")]
+use alloc::string::ToString;
+
+use crate::bus::MainBus;
+use crate::loaders::{crc32, LoadError};
+
+/// The address of the reset vector.
+const RESET_VECTOR: u16 = 0xFFFC;
+
+/// How a raw binary is placed in the device it is loaded into.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BinaryOptions {
+    /// The byte to fill the rest of the device with before the image is
+    /// loaded, such as 0xFF for erased EPROM, or `None` to leave it as it is.
+    pub fill: Option<u8>,
+
+    /// Whether to point the reset vector at the origin once the image is
+    /// loaded, for images that don't have vectors of their own. This
+    /// overwrites the vector if the image covers it.
+    pub set_reset_vector: bool,
+
+    /// The CRC-32 the image must have, or `None` to load any image. Set this
+    /// when a machine needs a particular version of its firmware.
+    pub expected_crc32: Option<u32>,
+}
+
+/// Loads a raw binary into the devices on a bus.
+///
+/// The whole image must fit in the device that decodes the origin, so an
+/// image that is too big for its ROM is caught instead of spilling into
+/// whatever is mapped after it. The bytes are written with
+/// `MainBus::poke()`, as a debugger would, so they reach ROM too.
+///
+/// # Arguments
+///
+/// * `bus` - The bus to load into.
+/// * `data` - The image.
+/// * `origin` - The address of the first byte of the image.
+/// * `options` - How to place the image.
+///
+/// # Returns
+///
+/// `Ok(())` if the image was loaded, `LoadError::Crc32Mismatch` if it isn't
+/// the image that was expected, `LoadError::Unmapped` if nothing is mapped
+/// at the origin or the reset vector, or `LoadError::OutOfRange` if the
+/// image runs past the end of the device. Nothing is loaded on an error.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::bus::rom::Rom;
+/// use butterflyrs::bus::MainBus;
+/// use butterflyrs::loaders::{load_bytes, BinaryOptions};
+///
+/// let mut bus = MainBus::new();
+/// bus.add_device(Box::new(Rom::new(0xF000, 0xFFFF)));
+///
+/// let options = BinaryOptions { fill: Some(0xFF), set_reset_vector: true, ..BinaryOptions::default() };
+/// load_bytes(&mut bus, &[0x4C, 0x00, 0xF0], 0xF000, options).unwrap();
+/// assert_eq!(bus.peek(0xF003), 0xFF);
+/// assert_eq!((bus.peek(0xFFFC), bus.peek(0xFFFD)), (0x00, 0xF0));
+/// ```
+pub fn load_bytes(bus: &mut MainBus, data: &[u8], origin: u16, options: BinaryOptions) -> Result<(), LoadError> {
+    if let Some(expected) = options.expected_crc32 {
+        let found = crc32(data);
+        if found != expected {
+            return Err(LoadError::Crc32Mismatch { expected, found });
+        }
+    }
+
+    let device = bus.device_at(origin).ok_or(LoadError::Unmapped { address: origin })?;
+    let (start, end) = (device.start_address(), device.end_address());
+    if origin as u32 + data.len() as u32 > end as u32 + 1 {
+        return Err(LoadError::OutOfRange { address: end as u32 + 1 });
+    }
+
+    if options.set_reset_vector {
+        let vector = [RESET_VECTOR, RESET_VECTOR + 1];
+        if let Some(address) = vector.into_iter().find(|&address| bus.device_at(address).is_none()) {
+            return Err(LoadError::Unmapped { address });
+        }
+    }
+
+    if let Some(fill) = options.fill {
+        for address in start..=end {
+            bus.poke(address, fill);
+        }
+    }
+    for (offset, &byte) in data.iter().enumerate() {
+        bus.poke(origin + offset as u16, byte);
+    }
+
+    if options.set_reset_vector {
+        let [low, high] = origin.to_le_bytes();
+        bus.poke(RESET_VECTOR, low);
+        bus.poke(RESET_VECTOR + 1, high);
+    }
+    Ok(())
+}
+
+/// Loads a raw binary file into the devices on a bus, leaving the rest of
+/// the device and the reset vector as they are.
+///
+/// # Arguments
+///
+/// * `bus` - The bus to load into.
+/// * `path` - The file to load.
+/// * `origin` - The address of the first byte of the file.
+///
+/// # Returns
+///
+/// `Ok(())` if the file was loaded, `LoadError::Io` if it couldn't be read,
+/// or an error from `load_bytes()`.
+#[cfg(feature = "std")]
+pub fn load_binary(bus: &mut MainBus, path: impl AsRef<std::path::Path>, origin: u16) -> Result<(), LoadError> {
+    load_binary_with(bus, path, origin, BinaryOptions::default())
+}
+
+/// Loads a raw binary file into the devices on a bus.
+///
+/// # Arguments
+///
+/// * `bus` - The bus to load into.
+/// * `path` - The file to load.
+/// * `origin` - The address of the first byte of the file.
+/// * `options` - How to place the image.
+///
+/// # Returns
+///
+/// `Ok(())` if the file was loaded, `LoadError::Io` if it couldn't be read,
+/// or an error from `load_bytes()`.
+#[cfg(feature = "std")]
+pub fn load_binary_with(
+    bus: &mut MainBus,
+    path: impl AsRef<std::path::Path>,
+    origin: u16,
+    options: BinaryOptions,
+) -> Result<(), LoadError> {
+    let data = std::fs::read(path).map_err(|error| LoadError::Io { message: error.to_string() })?;
+    load_bytes(bus, &data, origin, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::bus::ram::Ram;
+    use crate::bus::rom::Rom;
+    use crate::bus::MainBus;
+    use crate::loaders::binary::{load_bytes, BinaryOptions};
+    use crate::loaders::LoadError;
+
+    #[test]
+    fn loads_images_that_fit_their_device() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        bus.add_device(Box::new(Rom::new(0xC000, 0xFFFF)));
+
+        // An image that would run from RAM into the gap after it is rejected
+        let (image, defaults) = ([0xEA; 0x10], BinaryOptions::default());
+        assert_eq!(load_bytes(&mut bus, &image, 0x7FF8, defaults), Err(LoadError::OutOfRange { address: 0x8000 }));
+        assert_eq!(bus.peek(0x7FF8), 0x00);
+        assert_eq!(load_bytes(&mut bus, &image, 0x9000, defaults), Err(LoadError::Unmapped { address: 0x9000 }));
+
+        // Without a fill the rest of the device is untouched
+        load_bytes(&mut bus, &image, 0x0200, defaults).unwrap();
+        assert_eq!((bus.peek(0x020F), bus.peek(0x0210)), (0xEA, 0x00));
+
+        // A short ROM image padded as erased EPROM and started at its origin
+        let options = BinaryOptions { fill: Some(0xFF), set_reset_vector: true, ..defaults };
+        load_bytes(&mut bus, &image, 0xE000, options).unwrap();
+        assert_eq!((bus.peek(0xC000), bus.peek(0xE000), bus.peek(0xE010)), (0xFF, 0xEA, 0xFF));
+        assert_eq!((bus.peek(0xFFFC), bus.peek(0xFFFD), bus.peek(0xFFFE)), (0x00, 0xE0, 0xFF));
+
+        // The reset vector must be mapped to be set
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        assert_eq!(load_bytes(&mut bus, &image, 0x0200, options), Err(LoadError::Unmapped { address: 0xFFFC }));
+    }
+
+    #[test]
+    fn checks_the_crc_before_loading() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Rom::new(0xF000, 0xFFFF)));
+        let image = *b"123456789";
+
+        let wrong = BinaryOptions { expected_crc32: Some(0x12345678), ..BinaryOptions::default() };
+        let error = LoadError::Crc32Mismatch { expected: 0x12345678, found: 0xCBF43926 };
+        assert_eq!(load_bytes(&mut bus, &image, 0xF000, wrong), Err(error));
+        assert_eq!(bus.peek(0xF000), 0x00);
+
+        let right = BinaryOptions { expected_crc32: Some(0xCBF43926), ..BinaryOptions::default() };
+        load_bytes(&mut bus, &image, 0xF000, right).unwrap();
+        assert_eq!(bus.peek(0xF000), b'1');
+    }
+}
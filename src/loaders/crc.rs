@@ -0,0 +1,43 @@
+/// The CRC-32 lookup table, one entry for each value of a byte.
+const TABLE: [u32; 256] = table();
+
+/// Builds the lookup table for the reflected IEEE 802.3 polynomial.
+const fn table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut value = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 != 0 { 0xEDB88320 ^ (value >> 1) } else { value >> 1 };
+            bit += 1;
+        }
+        table[index] = value;
+        index += 1;
+    }
+    table
+}
+
+/// Computes the CRC-32 of some data.
+///
+/// This is the CRC used by zip, PNG and most ROM databases, so the result can
+/// be compared with the checksums published for known firmware.
+///
+/// # Arguments
+///
+/// * `data` - The data to check.
+///
+/// # Returns
+///
+/// The checksum.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::loaders::crc32;
+///
+/// assert_eq!(crc32(b"123456789"), 0xCBF43926);
+/// ```
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
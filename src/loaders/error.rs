@@ -0,0 +1,111 @@
+use alloc::string::String;
+use core::fmt::Display;
+
+/// Why a program image couldn't be loaded.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LoadError {
+    /// A line of a text file, such as an Intel HEX or label file, isn't a well-formed record.
+    InvalidRecord {
+        /// The line the record is on, counting from 1.
+        line: usize,
+    },
+
+    /// A record's checksum doesn't match its contents.
+    Checksum {
+        /// The line the record is on, counting from 1.
+        line: usize,
+
+        /// The checksum the contents add up to.
+        expected: u8,
+
+        /// The checksum in the record.
+        found: u8,
+    },
+
+    /// A record has a type that has no meaning for a 6502.
+    UnsupportedRecord {
+        /// The line the record is on, counting from 1.
+        line: usize,
+
+        /// The record type.
+        record_type: u8,
+    },
+
+    /// An Intel HEX file ended without an end-of-file record, so it may have been cut short.
+    MissingEnd,
+
+    /// Part of the image lies outside the 64K address space, or outside the
+    /// device it is being loaded into.
+    OutOfRange {
+        /// The address of the first byte that doesn't fit.
+        address: u32,
+    },
+
+    /// Part of the image would be loaded where no device decodes the address.
+    Unmapped {
+        /// The first address that isn't mapped.
+        address: u16,
+    },
+
+    /// The file doesn't start with the header its format needs.
+    InvalidHeader,
+
+    /// The file is shorter than its header says.
+    Truncated {
+        /// The size the header says the file is.
+        expected: usize,
+
+        /// The size of the file.
+        found: usize,
+    },
+
+    /// A cartridge image uses a mapper that isn't supported.
+    UnsupportedMapper {
+        /// The mapper number.
+        mapper: u16,
+    },
+
+    /// An image doesn't have the CRC-32 it was expected to have, so it isn't
+    /// the firmware the machine was set up for, or it is corrupt.
+    Crc32Mismatch {
+        /// The CRC-32 the image should have.
+        expected: u32,
+
+        /// The CRC-32 of the image.
+        found: u32,
+    },
+
+    /// The image file couldn't be read.
+    Io {
+        /// Why the file couldn't be read.
+        message: String,
+    },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LoadError::InvalidRecord { line } => write!(f, "Line {}: Not a valid record", line),
+            LoadError::Checksum { line, expected, found } => {
+                write!(f, "Line {}: Checksum is {:02X}, but should be {:02X}", line, found, expected)
+            }
+            LoadError::UnsupportedRecord { line, record_type } => {
+                write!(f, "Line {}: Unsupported record type {:02X}", line, record_type)
+            }
+            LoadError::MissingEnd => write!(f, "No end-of-file record"),
+            LoadError::OutOfRange { address } => write!(f, "Address out of range: {:X}", address),
+            LoadError::Unmapped { address } => write!(f, "Nothing is mapped at {:04X}", address),
+            LoadError::InvalidHeader => write!(f, "Not a valid header"),
+            LoadError::Truncated { expected, found } => {
+                write!(f, "The file is {} bytes, but should be at least {}", found, expected)
+            }
+            LoadError::UnsupportedMapper { mapper } => write!(f, "Unsupported mapper {}", mapper),
+            LoadError::Crc32Mismatch { expected, found } => {
+                write!(f, "CRC-32 is {:08X}, but should be {:08X}", found, expected)
+            }
+            LoadError::Io { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl core::error::Error for LoadError {}
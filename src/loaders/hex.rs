@@ -0,0 +1,272 @@
+use alloc::vec::Vec;
+
+use crate::bus::rom::Rom;
+use crate::bus::MainBus;
+use crate::loaders::LoadError;
+
+/// A run of bytes at consecutive addresses.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    /// The address of the first byte.
+    pub address: u16,
+
+    /// The bytes.
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    /// Returns the address one past the last byte, as a `u32` so a segment ending at $FFFF doesn't wrap.
+    fn end(&self) -> u32 {
+        self.address as u32 + self.data.len() as u32
+    }
+}
+
+/// The contents of an Intel HEX file.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct HexImage {
+    /// The data records, with records that follow on from each other joined
+    /// into one segment, in the order they appear in the file.
+    pub segments: Vec<Segment>,
+
+    /// The start address from a start address record, if the file has one.
+    pub start_address: Option<u16>,
+}
+
+impl HexImage {
+    /// Loads the image into the devices on a bus.
+    ///
+    /// The bytes are written with `MainBus::poke()`, as a debugger would, so
+    /// they reach ROM too.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus to load into.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the image was loaded, or `LoadError::Unmapped` if part of
+    /// it has no device to go to, in which case nothing is loaded.
+    pub fn load(&self, bus: &mut MainBus) -> Result<(), LoadError> {
+        for segment in &self.segments {
+            let mut addresses = (0..segment.data.len()).map(|offset| segment.address + offset as u16);
+            if let Some(address) = addresses.find(|&address| bus.device_at(address).is_none()) {
+                return Err(LoadError::Unmapped { address });
+            }
+        }
+
+        for segment in &self.segments {
+            for (offset, &byte) in segment.data.iter().enumerate() {
+                bus.poke(segment.address + offset as u16, byte);
+            }
+        }
+        Ok(())
+    }
+
+    /// Programs the image into a ROM.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM, which must cover every byte of the image.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the image was programmed, or `LoadError::OutOfRange` if
+    /// part of it is outside the ROM, in which case nothing is programmed.
+    pub fn program(&self, rom: &mut Rom) -> Result<(), LoadError> {
+        for segment in &self.segments {
+            if segment.address < rom.start {
+                return Err(LoadError::OutOfRange { address: segment.address as u32 });
+            }
+            if segment.end() > rom.end as u32 + 1 {
+                return Err(LoadError::OutOfRange { address: rom.end as u32 + 1 });
+            }
+        }
+
+        // An image shorter than the ROM is padded with erased bytes first
+        rom.data.resize((rom.end - rom.start) as usize + 1, 0xFF);
+        for segment in &self.segments {
+            let offset = (segment.address - rom.start) as usize;
+            rom.data[offset..offset + segment.data.len()].copy_from_slice(&segment.data);
+        }
+        Ok(())
+    }
+}
+
+/// Parses an Intel HEX file.
+///
+/// Data, end-of-file, extended segment address and extended linear address
+/// records are understood, as are both kinds of start address record. Every
+/// address must be within the 6502's 64K. Blank lines are skipped, and
+/// anything after the end-of-file record is ignored.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the file.
+///
+/// # Returns
+///
+/// The image, or the first error in the file.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::loaders::hex;
+///
+/// let image = hex::parse(":03020000A9018BC6\n:00000001FF\n").unwrap();
+/// assert_eq!(image.segments[0].address, 0x0200);
+/// assert_eq!(image.segments[0].data, [0xA9, 0x01, 0x8B]);
+/// ```
+pub fn parse(text: &str) -> Result<HexImage, LoadError> {
+    let mut image = HexImage::default();
+    let mut base = 0u32;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let record = parse_record(line).ok_or(LoadError::InvalidRecord { line: line_number })?;
+
+        // The checksum makes every byte of the record add up to zero
+        let (checksum, contents) = record.split_last().expect("records have at least five bytes");
+        let expected = contents.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+        if *checksum != expected {
+            return Err(LoadError::Checksum { line: line_number, expected, found: *checksum });
+        }
+
+        let offset = u16::from_be_bytes([record[1], record[2]]) as u32;
+        let record_type = record[3];
+        let data = &contents[4..];
+
+        match (record_type, data.len()) {
+            (0x00, _) => add_data(&mut image, base + offset, data)?,
+            (0x01, _) => return Ok(image),
+            (0x02, 2) => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+            (0x04, 2) => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16,
+            (0x03, 4) | (0x05, 4) => {
+                let [high, low] = [u16::from_be_bytes([data[0], data[1]]), u16::from_be_bytes([data[2], data[3]])];
+                let address = match record_type {
+                    // A segment and offset, or a linear address
+                    0x03 => high as u32 * 16 + low as u32,
+                    _ => (high as u32) << 16 | low as u32,
+                };
+                image.start_address = Some(u16::try_from(address).map_err(|_| LoadError::OutOfRange { address })?);
+            }
+            (0x02..=0x05, _) => return Err(LoadError::InvalidRecord { line: line_number }),
+            _ => return Err(LoadError::UnsupportedRecord { line: line_number, record_type }),
+        }
+    }
+
+    Err(LoadError::MissingEnd)
+}
+
+/// Decodes the bytes of a record from its line.
+///
+/// # Arguments
+///
+/// * `line` - The line, without surrounding whitespace.
+///
+/// # Returns
+///
+/// The bytes after the colon, or `None` if the line isn't a record or its
+/// length doesn't match its byte count.
+fn parse_record(line: &str) -> Option<Vec<u8>> {
+    let digits = line.strip_prefix(':')?;
+    if digits.len() % 2 != 0 || !digits.is_ascii() {
+        return None;
+    }
+
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).ok())
+        .collect::<Option<_>>()?;
+
+    // The byte count, address, type and checksum surround the data
+    (bytes.len() >= 5 && bytes.len() == bytes[0] as usize + 5).then_some(bytes)
+}
+
+/// Adds the bytes of a data record to the image.
+///
+/// # Arguments
+///
+/// * `image` - The image so far.
+/// * `address` - The address of the first byte, with the extended address applied.
+/// * `data` - The bytes.
+///
+/// # Returns
+///
+/// `Ok(())`, or `LoadError::OutOfRange` if the bytes run past $FFFF.
+fn add_data(image: &mut HexImage, address: u32, data: &[u8]) -> Result<(), LoadError> {
+    // An extended address near the top of the 32-bit range would overflow a u32 sum
+    if address as u64 + data.len() as u64 > 0x10000 {
+        return Err(LoadError::OutOfRange { address: address.max(0x10000) });
+    }
+
+    // Records usually follow on from each other, so they are joined into one segment
+    match image.segments.last_mut() {
+        Some(segment) if segment.end() == address => segment.data.extend_from_slice(data),
+        _ => image.segments.push(Segment { address: address as u16, data: data.to_vec() }),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::bus::ram::Ram;
+    use crate::bus::rom::Rom;
+    use crate::bus::MainBus;
+    use crate::loaders::hex::{parse, Segment};
+    use crate::loaders::LoadError;
+
+    #[test]
+    fn joins_records_into_segments() {
+        let text = "\
+:02C00000EAEA6A
+:01C00200EA53
+:02FFFC0000C043
+:0400000500000200F5
+:00000001FF
+";
+        let image = parse(text).unwrap();
+        assert_eq!(
+            image.segments,
+            [
+                Segment { address: 0xC000, data: vec![0xEA, 0xEA, 0xEA] },
+                Segment { address: 0xFFFC, data: vec![0x00, 0xC0] }
+            ]
+        );
+        assert_eq!(image.start_address, Some(0x0200));
+
+        // Into a ROM, which is padded with erased bytes, and onto the bus
+        let mut rom = Rom::new(0xC000, 0xFFFF);
+        rom.data.clear();
+        image.program(&mut rom).unwrap();
+        assert_eq!((rom.data[2], rom.data[3], rom.data[0x3FFD]), (0xEA, 0xFF, 0xC0));
+
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0xC000, 0xCFFF)));
+        assert_eq!(image.load(&mut bus), Err(LoadError::Unmapped { address: 0xFFFC }));
+        bus.add_device(Box::new(Rom::new(0xF000, 0xFFFF)));
+        image.load(&mut bus).unwrap();
+        assert_eq!((bus.read(0xC002), bus.read(0xFFFD)), (0xEA, 0xC0));
+
+        assert_eq!(image.program(&mut Rom::new(0xE000, 0xFFFF)), Err(LoadError::OutOfRange { address: 0xC000 }));
+    }
+
+    #[test]
+    fn reports_bad_records() {
+        let checksum = LoadError::Checksum { line: 1, expected: 0x6A, found: 0x6B };
+        assert_eq!(parse(":02C00000EAEA6B\n:00000001FF"), Err(checksum));
+        assert_eq!(parse("\n02C00000EAEA8A"), Err(LoadError::InvalidRecord { line: 2 }));
+        assert_eq!(parse(":03C00000EAEA8A"), Err(LoadError::InvalidRecord { line: 1 }));
+        assert_eq!(parse(":00000006FA"), Err(LoadError::UnsupportedRecord { line: 1, record_type: 0x06 }));
+        assert_eq!(parse(":020000040001F9\n:01000000EA15"), Err(LoadError::OutOfRange { address: 0x10000 }));
+        let top = ":02000004FFFFFC\n:10FFF000EAEAEAEAEAEAEAEAEAEAEAEAEAEAEAEA61";
+        assert_eq!(parse(top), Err(LoadError::OutOfRange { address: 0xFFFFFFF0 }));
+        assert_eq!(parse(":02C00000EAEA6A"), Err(LoadError::MissingEnd));
+    }
+}
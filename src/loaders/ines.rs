@@ -0,0 +1,202 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::bus::rom::Rom;
+use crate::bus::MainBus;
+use crate::loaders::LoadError;
+
+/// The size of the iNES header.
+const HEADER_SIZE: usize = 16;
+
+/// The size of the trainer that some images have between the header and PRG ROM.
+const TRAINER_SIZE: usize = 512;
+
+/// The size of a PRG ROM bank.
+pub const PRG_BANK_SIZE: usize = 0x4000;
+
+/// The size of a CHR ROM bank.
+pub const CHR_BANK_SIZE: usize = 0x2000;
+
+/// The mapper number of NROM, the board with no bank switching.
+pub const NROM: u16 = 0;
+
+/// The contents of an iNES (.nes) file.
+///
+/// Both the original iNES format and NES 2.0 are understood, although only
+/// the parts that matter to a CPU-only machine are kept.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InesImage {
+    /// The program ROM, which the CPU runs.
+    pub prg_rom: Vec<u8>,
+
+    /// The character ROM, which only the PPU sees. Empty for boards with CHR RAM.
+    pub chr_rom: Vec<u8>,
+
+    /// The trainer, if the image has one. The NES loads it at $7000.
+    pub trainer: Option<Vec<u8>>,
+
+    /// The mapper number, which identifies the board the cartridge was built on.
+    pub mapper: u16,
+
+    /// Whether the nametables are mirrored vertically instead of horizontally.
+    pub vertical_mirroring: bool,
+
+    /// Whether the cartridge has battery-backed RAM at $6000.
+    pub battery: bool,
+}
+
+impl InesImage {
+    /// Returns the number of 16K PRG ROM banks.
+    pub fn prg_banks(&self) -> usize {
+        self.prg_rom.len() / PRG_BANK_SIZE
+    }
+
+    /// Returns the number of 8K CHR ROM banks.
+    pub fn chr_banks(&self) -> usize {
+        self.chr_rom.len() / CHR_BANK_SIZE
+    }
+
+    /// Maps the PRG ROM into a bus at $8000-$FFFF, as the cartridge would be.
+    ///
+    /// Only NROM is supported. A 16K PRG ROM is mirrored into both halves, so
+    /// the vectors at the top of the bank are where the CPU expects them. The
+    /// rest of the NES, such as its 2K of RAM at $0000, is left to the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus to add the ROM to.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the ROM was added, `LoadError::UnsupportedMapper` if the
+    /// cartridge uses any other board, or `LoadError::OutOfRange` if the PRG
+    /// ROM is too big for NROM.
+    pub fn map_prg(&self, bus: &mut MainBus) -> Result<(), LoadError> {
+        if self.mapper != NROM {
+            return Err(LoadError::UnsupportedMapper { mapper: self.mapper });
+        }
+        if self.prg_rom.len() > 2 * PRG_BANK_SIZE {
+            return Err(LoadError::OutOfRange { address: 0x8000 + self.prg_rom.len() as u32 });
+        }
+
+        let mut rom = Rom::new(0x8000, 0xFFFF);
+        rom.data = self.prg_rom.repeat(2 * PRG_BANK_SIZE / self.prg_rom.len());
+        bus.add_device(Box::new(rom));
+        Ok(())
+    }
+}
+
+/// Parses an iNES (.nes) file.
+///
+/// # Arguments
+///
+/// * `data` - The contents of the file.
+///
+/// # Returns
+///
+/// The image, `LoadError::InvalidHeader` if the file doesn't start with an
+/// iNES header or has no PRG ROM, or `LoadError::Truncated` if it is shorter
+/// than its header says.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::loaders::ines;
+///
+/// let mut file = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+/// file.resize(16 + 0x4000 + 0x2000, 0xEA);
+///
+/// let image = ines::parse(&file).unwrap();
+/// assert_eq!((image.prg_banks(), image.chr_banks(), image.mapper), (1, 1, ines::NROM));
+/// assert!(image.vertical_mirroring);
+/// ```
+pub fn parse(data: &[u8]) -> Result<InesImage, LoadError> {
+    let header = data.get(..HEADER_SIZE).ok_or(LoadError::InvalidHeader)?;
+    if header[..4] != *b"NES\x1A" {
+        return Err(LoadError::InvalidHeader);
+    }
+
+    let (flags6, flags7) = (header[6], header[7]);
+    let nes2 = flags7 & 0x0C == 0x08;
+
+    // NES 2.0 adds high bits to the mapper number and ROM sizes. Old iNES
+    // dumps often have junk in the bytes after flags 6, so the high nibble of
+    // the mapper is only trusted when they are clear.
+    let (mapper, prg_units, chr_units) = if nes2 {
+        // Sizes given as an exponent and multiplier are only used for odd-sized ROMs
+        if header[9] & 0x0F == 0x0F || header[9] & 0xF0 == 0xF0 {
+            return Err(LoadError::InvalidHeader);
+        }
+        let mapper = (flags6 >> 4) as u16 | (flags7 & 0xF0) as u16 | ((header[8] & 0x0F) as u16) << 8;
+        let prg_units = header[4] as usize | ((header[9] & 0x0F) as usize) << 8;
+        let chr_units = header[5] as usize | ((header[9] >> 4) as usize) << 8;
+        (mapper, prg_units, chr_units)
+    } else if header[12..].iter().all(|&byte| byte == 0) {
+        ((flags6 >> 4 | flags7 & 0xF0) as u16, header[4] as usize, header[5] as usize)
+    } else {
+        ((flags6 >> 4) as u16, header[4] as usize, header[5] as usize)
+    };
+    if prg_units == 0 {
+        return Err(LoadError::InvalidHeader);
+    }
+
+    let trainer_size = if flags6 & 0x04 != 0 { TRAINER_SIZE } else { 0 };
+    let prg_size = prg_units * PRG_BANK_SIZE;
+    let chr_size = chr_units * CHR_BANK_SIZE;
+    let expected = HEADER_SIZE + trainer_size + prg_size + chr_size;
+    if data.len() < expected {
+        return Err(LoadError::Truncated { expected, found: data.len() });
+    }
+
+    let (trainer, rest) = data[HEADER_SIZE..].split_at(trainer_size);
+    let (prg_rom, rest) = rest.split_at(prg_size);
+    Ok(InesImage {
+        prg_rom: prg_rom.to_vec(),
+        chr_rom: rest[..chr_size].to_vec(),
+        trainer: (trainer_size > 0).then(|| trainer.to_vec()),
+        mapper,
+        vertical_mirroring: flags6 & 0x01 != 0,
+        battery: flags6 & 0x02 != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+
+    use crate::bus::ram::Ram;
+    use crate::bus::MainBus;
+    use crate::loaders::ines::{parse, PRG_BANK_SIZE};
+    use crate::loaders::LoadError;
+
+    /// Builds an iNES file with 16K of PRG ROM whose reset vector points at $C000.
+    fn nrom_file(flags6: u8) -> Vec<u8> {
+        let mut file = vec![b'N', b'E', b'S', 0x1A, 1, 0, flags6, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut prg = vec![0xEA; PRG_BANK_SIZE];
+        prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+        file.extend(prg);
+        file
+    }
+
+    #[test]
+    fn maps_nrom_into_the_top_half_of_memory() {
+        let image = parse(&nrom_file(0x02)).unwrap();
+        assert_eq!((image.prg_banks(), image.chr_banks(), image.battery, image.trainer.is_none()), (1, 0, true, true));
+
+        // 16K of PRG ROM appears in both halves, with the vectors at the top
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x07FF)));
+        image.map_prg(&mut bus).unwrap();
+        assert_eq!((bus.read(0xFFFC), bus.read(0xFFFD)), (0x00, 0xC0));
+        assert_eq!((bus.read(0x8000), bus.read(0xBFFD)), (0xEA, 0xC0));
+
+        // Mapper 1 in the high nibble of flags 6
+        let image = parse(&nrom_file(0x10)).unwrap();
+        assert_eq!(image.map_prg(&mut MainBus::new()), Err(LoadError::UnsupportedMapper { mapper: 1 }));
+
+        let file = nrom_file(0x00);
+        assert_eq!(parse(&file[..1000]), Err(LoadError::Truncated { expected: 16 + PRG_BANK_SIZE, found: 1000 }));
+        assert_eq!(parse(b"NES"), Err(LoadError::InvalidHeader));
+    }
+}
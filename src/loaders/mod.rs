@@ -0,0 +1,25 @@
+//! Loaders for program images in the formats assemblers and other tools produce.
+//!
+//! Each loader parses an image into its parts, which can then be loaded into
+//! the bus, as a debugger would, or programmed into a `Rom` before it is
+//! added to the bus. Raw binaries have no parts to parse, and are loaded
+//! straight into the bus with `load_binary()` or `load_bytes()`. The
+//! `symbols` module reads the label files assemblers write alongside them.
+
+pub mod binary;
+mod crc;
+mod error;
+pub mod hex;
+pub mod ines;
+pub mod prg;
+#[cfg(feature = "std")]
+mod reload;
+pub mod symbols;
+
+#[cfg(feature = "std")]
+pub use crate::loaders::binary::{load_binary, load_binary_with};
+pub use crate::loaders::binary::{load_bytes, BinaryOptions};
+pub use crate::loaders::crc::crc32;
+pub use crate::loaders::error::LoadError;
+#[cfg(feature = "std")]
+pub use crate::loaders::reload::RomWatcher;
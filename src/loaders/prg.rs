@@ -0,0 +1,93 @@
+#[cfg(feature = "std")]
+use alloc::string::ToString;
+
+use crate::bus::MainBus;
+use crate::loaders::hex::Segment;
+use crate::loaders::{load_bytes, BinaryOptions, LoadError};
+
+/// Parses a Commodore .prg file, which is the program preceded by the
+/// little-endian address it loads at.
+///
+/// # Arguments
+///
+/// * `data` - The contents of the file.
+///
+/// # Returns
+///
+/// The program and its load address, or `LoadError::Truncated` if the file
+/// is too short to have a load address.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::loaders::prg;
+///
+/// let program = prg::parse(&[0x01, 0x08, 0x0B, 0x08]).unwrap();
+/// assert_eq!(program.address, 0x0801);
+/// assert_eq!(program.data, [0x0B, 0x08]);
+/// ```
+pub fn parse(data: &[u8]) -> Result<Segment, LoadError> {
+    match data {
+        [low, high, program @ ..] => Ok(Segment { address: u16::from_le_bytes([*low, *high]), data: program.to_vec() }),
+        _ => Err(LoadError::Truncated { expected: 2, found: data.len() }),
+    }
+}
+
+/// Loads a .prg file into the devices on a bus at its load address.
+///
+/// The whole program must fit in the device that decodes its load address,
+/// as with `load_bytes()`.
+///
+/// # Arguments
+///
+/// * `bus` - The bus to load into.
+/// * `data` - The contents of the file.
+///
+/// # Returns
+///
+/// The load address, which is usually where the program starts, or the
+/// error from parsing or loading it. Nothing is loaded on an error.
+pub fn load(bus: &mut MainBus, data: &[u8]) -> Result<u16, LoadError> {
+    let program = parse(data)?;
+    load_bytes(bus, &program.data, program.address, BinaryOptions::default())?;
+    Ok(program.address)
+}
+
+/// Loads a .prg file from disk into the devices on a bus at its load address.
+///
+/// # Arguments
+///
+/// * `bus` - The bus to load into.
+/// * `path` - The file to load.
+///
+/// # Returns
+///
+/// The load address, `LoadError::Io` if the file couldn't be read, or an
+/// error from `load()`.
+#[cfg(feature = "std")]
+pub fn load_file(bus: &mut MainBus, path: impl AsRef<std::path::Path>) -> Result<u16, LoadError> {
+    let data = std::fs::read(path).map_err(|error| LoadError::Io { message: error.to_string() })?;
+    load(bus, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::bus::ram::Ram;
+    use crate::bus::MainBus;
+    use crate::loaders::prg::load;
+    use crate::loaders::LoadError;
+
+    #[test]
+    fn loads_at_the_address_in_the_header() {
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Ram::new(0x0000, 0x9FFF)));
+
+        // A program for $C000, where the C64 has 4K of free RAM
+        assert_eq!(load(&mut bus, &[0x00, 0xC0, 0xEA]), Err(LoadError::Unmapped { address: 0xC000 }));
+        assert_eq!(load(&mut bus, &[0x01, 0x08, 0xA9, 0x01, 0x60]), Ok(0x0801));
+        assert_eq!((bus.peek(0x0801), bus.peek(0x0802), bus.peek(0x0803)), (0xA9, 0x01, 0x60));
+        assert_eq!(load(&mut bus, &[0x01]), Err(LoadError::Truncated { expected: 2, found: 1 }));
+    }
+}
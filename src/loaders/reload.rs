@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::bus::MainBus;
+use crate::loaders::{load_binary_with, BinaryOptions, LoadError};
+
+/// Reprograms a ROM from its image file whenever the file changes.
+///
+/// This shortens the loop of assembling firmware, running it and changing it:
+/// the host polls the watcher as the machine runs, and a new build of the
+/// image is loaded as soon as it is written. The file is checked by its
+/// modification time, so polling is cheap enough to do many times a second.
+///
+/// The image is loaded with `load_binary_with()`, which pokes it into the
+/// ROM. RAM is left alone, so the host decides whether to reset the machine
+/// afterwards or let the running program carry on into the new code.
+///
+/// # Example
+///
+/// ```no_run
+/// use butterflyrs::bus::rom::Rom;
+/// use butterflyrs::emulator::Emulator;
+/// use butterflyrs::loaders::{BinaryOptions, RomWatcher};
+///
+/// let mut emulator = Emulator::new();
+/// emulator.add_device(Box::new(Rom::new(0xC000, 0xFFFF)));
+///
+/// let mut watcher = RomWatcher::new("firmware.bin", 0xC000, BinaryOptions::default());
+/// watcher.reload(&mut emulator.cpu.bus.borrow_mut()).unwrap();
+/// emulator.reset();
+/// loop {
+///     emulator.run_for_cycles(10_000);
+///     if watcher.poll(&mut emulator.cpu.bus.borrow_mut()) == Ok(true) {
+///         emulator.reset();
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RomWatcher {
+    /// The image file.
+    path: PathBuf,
+
+    /// Where the image is loaded.
+    origin: u16,
+
+    /// How the image is placed in the ROM.
+    options: BinaryOptions,
+
+    /// The modification time of the file when it was last loaded, or `None`
+    /// if it hasn't been loaded or had no modification time then.
+    modified: Option<SystemTime>,
+}
+
+impl RomWatcher {
+    /// Creates a watcher for an image that hasn't been loaded yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The image file.
+    /// * `origin` - The address of the first byte of the image.
+    /// * `options` - How to place the image, as for `load_binary_with()`.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `RomWatcher` struct. The first `poll()` loads the image.
+    pub fn new(path: impl AsRef<Path>, origin: u16, options: BinaryOptions) -> RomWatcher {
+        RomWatcher {
+            path: path.as_ref().to_path_buf(),
+            origin,
+            options,
+            modified: None,
+        }
+    }
+
+    /// Returns the image file being watched.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the file has changed since it was last loaded.
+    ///
+    /// A file that can't be read, such as while an assembler is replacing it,
+    /// doesn't count as changed until it can be read again.
+    pub fn has_changed(&self) -> bool {
+        match modified(&self.path) {
+            Some(modified) => self.modified != Some(modified),
+            None => false,
+        }
+    }
+
+    /// Loads the image now, whether or not it has changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus with the ROM on it.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the image was loaded, or the error from `load_binary_with()`.
+    /// The change is seen either way, so a broken image is reported once and
+    /// loaded again when it is next written.
+    pub fn reload(&mut self, bus: &mut MainBus) -> Result<(), LoadError> {
+        self.modified = modified(&self.path);
+        load_binary_with(bus, &self.path, self.origin, self.options)
+    }
+
+    /// Loads the image if the file has changed since it was last loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus with the ROM on it.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` if the image was loaded, `Ok(false)` if the file hasn't
+    /// changed, or the error from `load_binary_with()`.
+    pub fn poll(&mut self, bus: &mut MainBus) -> Result<bool, LoadError> {
+        if !self.has_changed() {
+            return Ok(false);
+        }
+        self.reload(bus).map(|_| true)
+    }
+}
+
+/// Returns the modification time of a file, or `None` if it can't be found.
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    use crate::bus::rom::Rom;
+    use crate::bus::MainBus;
+    use crate::loaders::{BinaryOptions, RomWatcher};
+
+    #[test]
+    fn reloads_the_image_when_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("butterflyrs-reload-{}.bin", std::process::id()));
+        std::fs::write(&path, [0x01, 0x02]).unwrap();
+
+        let mut bus = MainBus::new();
+        bus.add_device(Box::new(Rom::new(0xC000, 0xFFFF)));
+        let mut watcher = RomWatcher::new(&path, 0xC000, BinaryOptions::default());
+        assert_eq!(watcher.poll(&mut bus), Ok(true));
+        assert_eq!(watcher.poll(&mut bus), Ok(false));
+        assert_eq!(bus.peek(0xC001), 0x02);
+
+        // The time is set by hand, since a quick rewrite can keep the same one
+        std::fs::write(&path, [0x03, 0x04]).unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        drop(file);
+        assert!(watcher.has_changed());
+        assert_eq!(watcher.poll(&mut bus), Ok(true));
+        assert_eq!((bus.peek(0xC000), bus.peek(0xC001)), (0x03, 0x04));
+
+        // A missing file isn't a change, but reloading it is an error
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.poll(&mut bus), Ok(false));
+        assert!(watcher.reload(&mut bus).is_err());
+    }
+}
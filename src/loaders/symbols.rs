@@ -0,0 +1,149 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::disasm::SymbolTable;
+use crate::loaders::LoadError;
+
+/// Parses a VICE label file, as written by the VICE monitor or by ld65's `-Ln` option.
+///
+/// Each line is an `al` command naming one address, such as `al C:C000 .reset`
+/// or `al 00C000 .reset`. The memory space prefix and the dot before the
+/// label are optional. Blank lines and lines starting with `;` are skipped.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the file.
+///
+/// # Returns
+///
+/// The labels, or `LoadError::InvalidRecord` for the first line that isn't
+/// an `al` command with an address in the 6502's 64K.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::loaders::symbols;
+///
+/// let symbols = symbols::parse_vice("al C:C000 .reset\nal 00C003 .loop\n").unwrap();
+/// assert_eq!(symbols.get(0xC000), Some("reset"));
+/// assert_eq!(symbols.address_of("loop"), Some(0xC003));
+/// ```
+pub fn parse_vice(text: &str) -> Result<SymbolTable, LoadError> {
+    let mut symbols = SymbolTable::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let invalid = LoadError::InvalidRecord { line: index + 1 };
+        let (address, label) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["al", address, label] => (address, label),
+            _ => return Err(invalid),
+        };
+
+        // The memory space is only ever the CPU's for a 6502
+        let address = address.strip_prefix("C:").unwrap_or(address);
+        let address = u32::from_str_radix(address, 16).ok().and_then(|address| u16::try_from(address).ok());
+        let label = label.strip_prefix('.').unwrap_or(label);
+        match address {
+            Some(address) if !label.is_empty() => {
+                symbols.insert(address, label);
+            }
+            _ => return Err(invalid),
+        }
+    }
+
+    Ok(symbols)
+}
+
+/// Parses a ca65 debug information file, as written by ld65's `--dbgfile` option.
+///
+/// Only the `sym` lines are used, and only for labels: constants defined
+/// with `=` aren't addresses, and cheap local labels starting with `@`
+/// would hide the labels they are local to.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the file.
+///
+/// # Returns
+///
+/// The labels, or `LoadError::InvalidRecord` for the first `sym` line
+/// without a name or with a value outside the 6502's 64K.
+///
+/// # Example
+///
+/// ```
+/// use butterflyrs::loaders::symbols;
+///
+/// let text = "version\tmajor=2,minor=0\n\
+///             sym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=1,val=0xC000,seg=0,type=lab\n";
+/// let symbols = symbols::parse_ca65(text).unwrap();
+/// assert_eq!(symbols.get(0xC000), Some("reset"));
+/// ```
+pub fn parse_ca65(text: &str) -> Result<SymbolTable, LoadError> {
+    let mut symbols = SymbolTable::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let Some(attributes) = line.strip_prefix("sym").filter(|rest| rest.starts_with(char::is_whitespace)) else {
+            continue;
+        };
+
+        // Attributes are comma-separated key=value pairs, and names are quoted
+        let (mut name, mut value, mut kind) = (None, None, None);
+        for attribute in attributes.trim().split(',') {
+            match attribute.split_once('=') {
+                Some(("name", text)) => name = text.strip_prefix('"').and_then(|text| text.strip_suffix('"')),
+                Some(("val", text)) => value = Some(text),
+                Some(("type", text)) => kind = Some(text),
+                _ => {}
+            }
+        }
+        if kind != Some("lab") {
+            continue;
+        }
+
+        let address = value
+            .and_then(|value| value.strip_prefix("0x"))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .and_then(|address| u16::try_from(address).ok());
+        let (Some(name), Some(address)) = (name.filter(|name| !name.is_empty()), address) else {
+            return Err(LoadError::InvalidRecord { line: index + 1 });
+        };
+        if !name.starts_with('@') {
+            symbols.insert(address, String::from(name));
+        }
+    }
+
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::loaders::symbols::{parse_ca65, parse_vice};
+    use crate::loaders::LoadError;
+
+    #[test]
+    fn reads_labels_from_both_formats() {
+        let symbols = parse_vice("; ld65 label file\n\nal 00D010 .KBD\nal C:FF00 RESET\n").unwrap();
+        assert_eq!(symbols.iter().collect::<Vec<_>>(), [(0xD010, "KBD"), (0xFF00, "RESET")]);
+        assert_eq!(parse_vice("al 00D010 .KBD\nbreak 1234\n"), Err(LoadError::InvalidRecord { line: 2 }));
+        assert_eq!(parse_vice("al 10000 .high\n"), Err(LoadError::InvalidRecord { line: 1 }));
+
+        // Constants and cheap locals are left out
+        let text = "\
+version\tmajor=2,minor=0
+sym\tid=0,name=\"RESET\",addrsize=absolute,scope=0,def=4,ref=9,val=0xFF00,seg=1,type=lab
+sym\tid=1,name=\"@loop\",addrsize=absolute,scope=0,def=5,val=0xFF02,seg=1,type=lab,parent=0
+sym\tid=2,name=\"COUNT\",addrsize=zeropage,scope=0,def=1,val=0x5,type=equ
+sym\tid=3,name=\"KBD\",addrsize=absolute,scope=0,def=2,val=0xD010,type=lab
+";
+        let symbols = parse_ca65(text).unwrap();
+        assert_eq!(symbols.iter().collect::<Vec<_>>(), [(0xD010, "KBD"), (0xFF00, "RESET")]);
+        assert_eq!(parse_ca65("sym\tid=0,name=\"X\",type=lab\n"), Err(LoadError::InvalidRecord { line: 1 }));
+    }
+}
@@ -0,0 +1,193 @@
+//! Ready-made machines, laid out like well-known 6502 computers.
+//!
+//! Each preset is an `Emulator` constructor that builds the computer's bus
+//! around its firmware, so classic ROM images run without working out the
+//! memory map by hand. The devices are the crate's own, so the host reaches
+//! them the usual way, for example with `MainBus::device_of_type_mut()`.
+
+use alloc::boxed::Box;
+
+use crate::bus::pia::Pia;
+use crate::bus::ram::Ram;
+use crate::bus::rom::Rom;
+use crate::bus::serial::Acia;
+use crate::bus::via::Via;
+use crate::emulator::Emulator;
+use crate::loaders::{load_bytes, BinaryOptions, LoadError};
+
+/// The address of the 6522 VIA on Ben Eater's breadboard computer.
+pub const BEN_EATER_VIA: u16 = 0x6000;
+
+/// The address of the 6551 ACIA on Ben Eater's breadboard computer.
+pub const BEN_EATER_ACIA: u16 = 0x5000;
+
+/// The first address of the ROM on Ben Eater's breadboard computer.
+pub const BEN_EATER_ROM: u16 = 0x8000;
+
+/// The address of the PIA that runs the Apple 1's keyboard and display.
+pub const APPLE1_PIA: u16 = 0xD010;
+
+/// The first address of the Apple 1's monitor ROM.
+pub const APPLE1_ROM: u16 = 0xFF00;
+
+/// The first address of the KIM-1's monitor ROM.
+pub const KIM1_ROM: u16 = 0x1800;
+
+/// The last address of the KIM-1's monitor ROM.
+const KIM1_ROM_END: u16 = 0x1FFF;
+
+/// The KIM-1 only decodes the low 13 address lines, so its ROM also answers
+/// at the top of memory, where the CPU looks for its vectors.
+const KIM1_VECTOR_MIRROR: u16 = 0xF800;
+
+impl Emulator {
+    /// Creates Ben Eater's breadboard 6502 computer.
+    ///
+    /// The 62256 RAM chip is 32K, but the address decoding only maps its lower
+    /// 16K, at `$0000-$3FFF`. The ACIA is at `$5000`, the VIA at `$6000` and
+    /// the 28C256 EEPROM fills `$8000-$FFFF`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The EEPROM image, up to 32K. A shorter image is placed at the
+    ///   top of the ROM, so its last bytes are the vectors.
+    ///
+    /// # Returns
+    ///
+    /// The machine, switched on, or `LoadError::OutOfRange` if the image doesn't fit.
+    pub fn ben_eater(rom: &[u8]) -> Result<Emulator, LoadError> {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0x3FFF)));
+        emulator.add_device(Box::new(Acia::new(BEN_EATER_ACIA)));
+        emulator.add_device(Box::new(Via::new(BEN_EATER_VIA)));
+        emulator.add_device(Box::new(Rom::new(BEN_EATER_ROM, 0xFFFF)));
+        load_rom(&mut emulator, rom, BEN_EATER_ROM, 0xFFFF)?;
+        emulator.power_on();
+        Ok(emulator)
+    }
+
+    /// Creates an Apple 1.
+    ///
+    /// There is 4K of RAM at `$0000-$0FFF` and another 4K at `$E000-$EFFF`,
+    /// where Integer BASIC is loaded. The keyboard and display are a `Pia` at
+    /// `$D010`, and the monitor ROM, Woz Monitor, is at `$FF00-$FFFF`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The monitor ROM image, up to 256 bytes.
+    ///
+    /// # Returns
+    ///
+    /// The machine, switched on, or `LoadError::OutOfRange` if the image doesn't fit.
+    pub fn apple1(rom: &[u8]) -> Result<Emulator, LoadError> {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0x0FFF)));
+        emulator.add_device(Box::new(Pia::new(APPLE1_PIA)));
+        emulator.add_device(Box::new(Ram::new(0xE000, 0xEFFF)));
+        emulator.add_device(Box::new(Rom::new(APPLE1_ROM, 0xFFFF)));
+        load_rom(&mut emulator, rom, APPLE1_ROM, 0xFFFF)?;
+        emulator.power_on();
+        Ok(emulator)
+    }
+
+    /// Creates a MOS KIM-1.
+    ///
+    /// There is 1K of RAM at `$0000-$03FF`, and the monitor ROM is at
+    /// `$1800-$1FFF` with a copy at `$F800-$FFFF` for the vectors. The two
+    /// 6530 RRIOTs' I/O and timers at `$1700-$177F` and their RAM at
+    /// `$1780-$17FF` are all plain RAM, so the monitor runs but the keypad,
+    /// LED display and teletype aren't connected.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The monitor ROM image, up to 2K.
+    ///
+    /// # Returns
+    ///
+    /// The machine, switched on, or `LoadError::OutOfRange` if the image doesn't fit.
+    pub fn kim1(rom: &[u8]) -> Result<Emulator, LoadError> {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0x03FF)));
+        emulator.add_device(Box::new(Ram::new(0x1700, 0x17FF)));
+        emulator.add_device(Box::new(Rom::new(KIM1_ROM, KIM1_ROM_END)));
+        emulator.add_device(Box::new(Rom::new(KIM1_VECTOR_MIRROR, 0xFFFF)));
+        load_rom(&mut emulator, rom, KIM1_ROM, KIM1_ROM_END)?;
+        load_rom(&mut emulator, rom, KIM1_VECTOR_MIRROR, 0xFFFF)?;
+        emulator.power_on();
+        Ok(emulator)
+    }
+}
+
+/// Loads a ROM image so that it ends at the end of the ROM, filling the rest with `$FF`.
+///
+/// # Arguments
+///
+/// * `emulator` - The machine, with a ROM at `start-end`.
+/// * `rom` - The image.
+/// * `start` - The first address of the ROM.
+/// * `end` - The last address of the ROM.
+///
+/// # Returns
+///
+/// `Ok(())`, or `LoadError::OutOfRange` with the first address past the ROM
+/// if the image is bigger than it.
+fn load_rom(emulator: &mut Emulator, rom: &[u8], start: u16, end: u16) -> Result<(), LoadError> {
+    let size = (end - start) as usize + 1;
+    if rom.len() > size {
+        return Err(LoadError::OutOfRange { address: end as u32 + 1 });
+    }
+
+    let origin = (end as usize + 1 - rom.len()) as u16;
+    let options = BinaryOptions { fill: Some(0xFF), ..BinaryOptions::default() };
+    load_bytes(&mut emulator.cpu.bus.borrow_mut(), rom, origin, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::bus::pia::Pia;
+    use crate::bus::via::Via;
+    use crate::emulator::Emulator;
+    use crate::loaders::LoadError;
+
+    #[test]
+    fn apple1_echoes_keys_through_its_pia() {
+        // Sets up the PIA as the Woz Monitor does, then echoes every key
+        let mut rom = vec![
+            0xA0, 0x7F, // LDY #$7F
+            0x8C, 0x12, 0xD0, // STY DSP
+            0xA9, 0xA7, // LDA #$A7
+            0x8D, 0x11, 0xD0, // STA KBDCR
+            0x8D, 0x13, 0xD0, // STA DSPCR
+            0xAD, 0x11, 0xD0, // LDA KBDCR
+            0x10, 0xFB, // BPL $FF0D
+            0xAD, 0x10, 0xD0, // LDA KBD
+            0x8D, 0x12, 0xD0, // STA DSP
+            0x4C, 0x0D, 0xFF, // JMP $FF0D
+        ];
+        rom.resize(0xFA, 0xEA);
+        rom.extend([0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF]);
+
+        let mut emulator = Emulator::apple1(&rom).unwrap();
+        assert_eq!(emulator.cpu.pc.get(), 0xFF00);
+        emulator.bus().borrow_mut().device_of_type_mut::<Pia>().unwrap().push_str("HI\r");
+        emulator.run_for_cycles(500);
+        assert_eq!(emulator.bus().borrow_mut().device_of_type_mut::<Pia>().unwrap().take_output(), b"HI\r");
+    }
+
+    #[test]
+    fn places_short_images_at_the_top_of_the_rom() {
+        // Just the vectors, pointing the reset at $8000
+        let emulator = Emulator::ben_eater(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]).unwrap();
+        assert_eq!(emulator.cpu.pc.get(), 0x8000);
+        assert_eq!(emulator.peek(0x8000), 0xFF);
+        assert!(emulator.bus().borrow().device_of_type::<Via>().is_some());
+
+        // The KIM-1 finds its vectors through the mirror
+        let mut rom: Vec<u8> = vec![0xEA; 0x800];
+        rom[0x7FC..0x7FE].copy_from_slice(&[0x22, 0x1C]);
+        assert_eq!(Emulator::kim1(&rom).unwrap().cpu.pc.get(), 0x1C22);
+        assert_eq!(Emulator::apple1(&[0xEA; 0x101]).err(), Some(LoadError::OutOfRange { address: 0x10000 }));
+    }
+}
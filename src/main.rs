@@ -1,53 +1,724 @@
-use std::cell::RefCell;
-use std::io::Read;
-use std::rc::Rc;
-use crate::bus::{BusDevice, MainBus};
-use crate::bus::blink8::Blink8;
-use crate::bus::ram::Ram;
-use crate::bus::rom::Rom;
-use crate::cpu::Cpu;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::process::ExitCode;
+use std::io::{IsTerminal, Read};
+use std::sync::mpsc::Receiver;
+use butterflyrs::asm::assemble;
+use butterflyrs::bus::blink8::Blink8;
+use butterflyrs::bus::exit::ExitPort;
+use butterflyrs::bus::keyboard::Keyboard;
+use butterflyrs::bus::pia::Pia;
+use butterflyrs::bus::ram::Ram;
+use butterflyrs::bus::rom::Rom;
+use butterflyrs::bus::serial::{Acia, QueueLink};
+use butterflyrs::cpu::{CpuBuilder, DebugLevel};
+use butterflyrs::emulator::{Emulator, StopReason};
+use butterflyrs::loaders::{crc32, symbols, BinaryOptions, LoadError, RomWatcher};
+use butterflyrs::monitor::Monitor;
+use butterflyrs::pacing::Pacer;
 
-mod cpu;
-mod bus;
-mod register;
+const USAGE: &str = "\
+Usage:
+    butterflyrs [options]                    Run a ROM, the blink demo by default
+    butterflyrs debug [options]              Debug a ROM in the monitor (type help at the prompt)
+    butterflyrs batch [options]              Run a ROM until it writes its exit code
+    butterflyrs asm <source> [options]       Assemble a source file
+    butterflyrs screen <program> [address]   Run a program with a 32x32 screen at $0200
 
+Options for running or debugging a ROM:
+    --rom <file>          The binary to load into the ROM at $C000-$FFFF (default: demos/blink.bin)
+    --machine <name>      Run the ROM on a ben-eater, apple1 or kim1 instead, at the top of its ROM
+    --origin <address>    Where to load it (default: $C000)
+    --fill <byte>         Fill the rest of the ROM first, such as FF for erased EPROM
+    --reset-vector        Point the reset vector at the origin
+    --crc32 <checksum>    Refuse to run the binary unless it has this CRC-32
+    --symbols <file>      Name addresses from a VICE label file, or a ca65 .dbg file
+    --watch               Reload the ROM whenever its file changes, with --speed or in the debugger
+    --reset-on-reload     Reset the machine after reloading the ROM
 
-struct Emulator {
-    cpu: Cpu,
-    bus: MainBus,
+The debugger also takes a reload command, which reloads the ROM at once.
+
+Options for running a ROM, as well as the ones above:
+    --speed <hz>           The clock speed to run at (default: 1000000)
+    --benchmark <seconds>  Run flat out for this long and report how fast that was
+    --console              Type into the machine's ACIA, PIA or keyboard, and show what it prints
+
+A ROM runs until the CPU stops or jams, or until Enter is pressed. With
+--console, the terminal is in raw mode and Ctrl-] breaks in instead.
+
+Options for batch, as well as the ones above:
+    --exit-port <address>  Where the ROM writes its exit code (default: $BFFF)
+    --max-cycles <count>   Give up after this many cycles
+
+The batch command exits with the code the ROM writes to the exit port. It
+exits with 1 if the ROM times out or the CPU jams or stops first.
+
+Options for asm:
+    -o <file>    Where to write the binary (default: the source with a .bin extension)
+    -l <file>    Write a listing of addresses, bytes and source lines, or - for stdout
+
+The screen command loads a binary at the address, $0600 by default, and
+starts running it there. An Intel HEX file (.hex) is loaded at the addresses
+in it, and starts at the address, its start address record or its first byte.
+A Commodore program (.prg) is loaded at the address in its header, and starts
+at the address or its first byte.
+It needs the window feature.";
+
+fn main() -> ExitCode {
+    // The devices report through tracing, so show their info events and warnings on the console
+    tracing_subscriber::fmt().without_time().with_target(false).init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None => run_demo(&args),
+        Some(option) if option.starts_with("--") => run_demo(&args),
+        Some("debug") => run_monitor(&args[1..]),
+        Some("batch") => match run_batch(&args[1..]) {
+            Ok(code) => return ExitCode::from(code),
+            Err(message) => Err(message),
+        },
+        Some("asm") => assemble_file(&args[1..]),
+        Some("screen") => run_screen(&args[1..]),
+        Some(_) => Err(String::from(USAGE)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
 }
 
-impl Emulator {
-    fn new() -> Emulator {
-        Emulator {
-            cpu: Cpu::new(Rc::new(RefCell::new(MainBus::new()))),
-            bus: MainBus::new(),
+/// The clock speed to run a ROM at without `--speed`, in Hz.
+const DEFAULT_SPEED: u64 = 1_000_000;
+
+/// How many times a second a running machine checks the terminal and, with `--watch`, the ROM's file.
+const SLICES_PER_SECOND: u64 = 100;
+
+/// Builds the demo machine, with RAM, a Blink8 and a ROM loaded from a binary.
+///
+/// # Arguments
+///
+/// * `args` - The options for loading the ROM.
+///
+/// # Returns
+///
+/// The machine, reset and ready to run, and a watcher that reloads its ROM,
+/// or a message to print otherwise. Only the demo machine's ROM can be
+/// reloaded, so there is no watcher with `--machine`.
+fn build_machine(args: &[String]) -> Result<(Emulator, Option<RomWatcher>), String> {
+    let mut path = String::from("demos/blink.bin");
+    let mut origin = 0xC000;
+    let mut options = BinaryOptions::default();
+    let mut symbols_path = None;
+    let mut machine = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rom" => path = args.next().ok_or(USAGE)?.clone(),
+            "--origin" => origin = parse_address(args.next().ok_or(USAGE)?)?,
+            "--fill" => {
+                let fill = args.next().ok_or(USAGE)?;
+                options.fill = Some(u8::from_str_radix(fill, 16).map_err(|_| format!("Not a byte: {}", fill))?);
+            }
+            "--reset-vector" => options.set_reset_vector = true,
+            "--crc32" => {
+                let crc = args.next().ok_or(USAGE)?;
+                let crc = u32::from_str_radix(crc, 16).map_err(|_| format!("Not a CRC-32: {}", crc))?;
+                options.expected_crc32 = Some(crc);
+            }
+            "--symbols" => symbols_path = Some(args.next().ok_or(USAGE)?.clone()),
+            "--machine" => machine = Some(args.next().ok_or(USAGE)?.clone()),
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+
+    let (mut emulator, watcher) = match machine.as_deref() {
+        None => {
+            let mut emulator = Emulator::from_cpu(CpuBuilder::new().debug(DebugLevel::Off).build());
+
+            let ram_device = Ram::new(0x0000, 0x7FFF);
+            emulator.add_device(Box::new(ram_device));
+
+            let blink8_device = Blink8::new();
+            emulator.add_device(Box::new(blink8_device));
+
+            let rom_device = Rom::new(0xC000, 0xFFFF);
+            emulator.add_device(Box::new(rom_device));
+            let mut watcher = RomWatcher::new(&path, origin, options);
+            watcher.reload(&mut emulator.cpu.bus.borrow_mut()).map_err(|error| format!("{}: {}", path, error))?;
+            (emulator, Some(watcher))
+        }
+        Some(name) => {
+            let preset = match name {
+                "ben-eater" => Emulator::ben_eater,
+                "apple1" => Emulator::apple1,
+                "kim1" => Emulator::kim1,
+                _ => return Err(format!("Unknown machine: {}", name)),
+            };
+            let image = std::fs::read(&path).map_err(|error| format!("{}: {}", path, error))?;
+            if let Some(expected) = options.expected_crc32 {
+                let found = crc32(&image);
+                if found != expected {
+                    return Err(format!("{}: {}", path, LoadError::Crc32Mismatch { expected, found }));
+                }
+            }
+            (preset(&image).map_err(|error| format!("{}: {}", path, error))?, None)
         }
+    };
+
+    if let Some(path) = symbols_path {
+        let text = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path, error))?;
+        let table = if path.to_ascii_lowercase().ends_with(".dbg") {
+            symbols::parse_ca65(&text)
+        } else {
+            symbols::parse_vice(&text)
+        };
+        emulator.cpu.symbols = table.map_err(|error| format!("{}: {}", path, error))?;
     }
+
+    emulator.reset();
+    Ok((emulator, watcher))
 }
 
-fn main() {
-    let mut emulator = Emulator::new();
+/// Reprograms the ROM from its file, and resets the machine if asked to.
+///
+/// # Arguments
+///
+/// * `emulator` - The machine.
+/// * `watcher` - The watcher for the ROM's file.
+/// * `reset` - Whether to reset the machine once the ROM is reloaded.
+/// * `force` - Whether to reload the file even if it hasn't changed.
+///
+/// # Returns
+///
+/// Whether the ROM was reloaded, or a message to print if the file couldn't be loaded.
+fn reload_rom(emulator: &mut Emulator, watcher: &mut RomWatcher, reset: bool, force: bool) -> Result<bool, String> {
+    let reloaded = match force {
+        true => watcher.reload(&mut emulator.cpu.bus.borrow_mut()).map(|_| true),
+        false => watcher.poll(&mut emulator.cpu.bus.borrow_mut()),
+    };
+    let reloaded = reloaded.map_err(|error| format!("{}: {}", watcher.path().display(), error))?;
+    if reloaded && reset {
+        emulator.reset();
+    }
+    Ok(reloaded)
+}
+
+/// The key that breaks into a machine whose console is on the terminal, as in telnet.
+const BREAK_KEY: u8 = 0x1D;
+
+/// The user's terminal, while a machine runs.
+///
+/// Keys are read from stdin on a thread of their own, so the machine never
+/// waits for them. Normally stdin is left as it is, and pressing Enter breaks
+/// into the machine. With `--console` the terminal is put in raw mode, every
+/// key goes to the machine's ACIA, PIA or keyboard, and what the machine
+/// displays is printed, so programs such as the Woz Monitor or EhBASIC can be
+/// used. Ctrl-] breaks in then, and Ctrl-C goes to the machine.
+struct Terminal {
+    /// The bytes read from stdin, in the order they were typed.
+    keys: Receiver<u8>,
 
-    let ram_device = Ram::new(0x0000, 0x7FFF);
-    emulator.bus.add_device(Box::new(ram_device));
+    /// Whether keys go to the machine, rather than breaking into it.
+    console: bool,
 
-    let blink8_device = Blink8::new();
-    emulator.bus.add_device(Box::new(blink8_device));
+    /// The terminal settings to put back, if raw mode was switched on.
+    saved_mode: Option<String>,
+}
+
+impl Terminal {
+    /// Starts reading keys, and puts the terminal in raw mode for a console.
+    ///
+    /// # Arguments
+    ///
+    /// * `emulator` - The machine.
+    /// * `console` - Whether keys go to the machine, for `--console`.
+    ///
+    /// # Returns
+    ///
+    /// The terminal, or a message to print if the machine has nothing to type into.
+    fn new(emulator: &Emulator, console: bool) -> Result<Terminal, String> {
+        if console {
+            let bus = emulator.bus().borrow();
+            let has_device = bus.device_of_type::<Acia>().is_some()
+                || bus.device_of_type::<Pia>().is_some()
+                || bus.device_of_type::<Keyboard>().is_some();
+            if !has_device {
+                return Err(String::from("--console needs a machine with an ACIA, PIA or keyboard"));
+            }
+        }
 
-    let mut rom_device = Rom::new(0xC000, 0xFFFF);
-    let mut file = std::fs::File::open("demos/blink.bin").unwrap();
-    let mut data = Vec::new();
-    file.read_to_end(&mut data).unwrap();
-    rom_device.data = data;
-    emulator.bus.add_device(Box::new(rom_device));
+        // Without a terminal, such as when input is piped in, there is no mode to change
+        let saved_mode = match console && std::io::stdin().is_terminal() {
+            true => stty(&["-g"]).filter(|_| stty(&["raw", "-echo"]).is_some()),
+            false => None,
+        };
 
-    emulator.cpu.connect_bus(Rc::new(RefCell::new(emulator.bus)));
-    emulator.cpu.debug = 0;
-    emulator.cpu.reset();
+        let (sender, keys) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 256];
+            let mut stdin = std::io::stdin();
+            while let Ok(length @ 1..) = stdin.read(&mut buffer) {
+                if buffer[..length].iter().any(|&key| sender.send(key).is_err()) {
+                    break;
+                }
+            }
+        });
 
-    // Clock the CPU a few times just to make sure it works
-    for _ in 0..100 {
-        emulator.cpu.clock();
+        Ok(Terminal { keys, console, saved_mode })
     }
+
+    /// Passes keys to the machine and prints what it has displayed, or checks
+    /// whether the user has broken in.
+    ///
+    /// # Arguments
+    ///
+    /// * `emulator` - The machine.
+    ///
+    /// # Returns
+    ///
+    /// `ControlFlow::Break(())` if the user has broken in, for `Emulator::run()`.
+    fn poll(&mut self, emulator: &mut Emulator) -> ControlFlow<()> {
+        // Without a console, Enter is the only key that gets through
+        if !self.console {
+            return match self.keys.try_recv() {
+                Ok(_) => ControlFlow::Break(()),
+                Err(_) => ControlFlow::Continue(()),
+            };
+        }
+
+        let mut bus = emulator.bus().borrow_mut();
+        while let Ok(key) = self.keys.try_recv() {
+            if key == BREAK_KEY {
+                return ControlFlow::Break(());
+            }
+            if let Some(link) = bus.device_of_type_mut::<Acia>().and_then(Acia::link_mut::<QueueLink>) {
+                link.input.push_back(key);
+            } else if let Some(pia) = bus.device_of_type_mut::<Pia>() {
+                // The Apple 1 only has upper case, and rubs out with an underscore
+                pia.push_key(if key == 0x7F { b'_' } else { key.to_ascii_uppercase() });
+            } else if let Some(keyboard) = bus.device_of_type_mut::<Keyboard>() {
+                keyboard.push_key(key);
+            }
+        }
+
+        let output = if let Some(link) = bus.device_of_type_mut::<Acia>().and_then(Acia::link_mut::<QueueLink>) {
+            std::mem::take(&mut link.output)
+        } else if let Some(pia) = bus.device_of_type_mut::<Pia>() {
+            // The Apple 1 ends lines with a carriage return alone
+            let mut output = Vec::new();
+            for character in pia.take_output() {
+                output.push(character);
+                if character == b'\r' {
+                    output.push(b'\n');
+                }
+            }
+            output
+        } else {
+            Vec::new()
+        };
+        if !output.is_empty() {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(&output).and_then(|_| stdout.flush());
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if let Some(mode) = self.saved_mode.take() {
+            stty(&[&mode]);
+        }
+    }
+}
+
+/// Changes the settings of the terminal on stdin with `stty`.
+///
+/// # Arguments
+///
+/// * `args` - The arguments for `stty`.
+///
+/// # Returns
+///
+/// What `stty` printed, without the newline, or `None` if it failed.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new("stty").args(args).stdin(std::process::Stdio::inherit()).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok().filter(|_| output.status.success())?;
+    Some(text.trim_end().to_string())
+}
+
+/// Reloads the ROM if its file has changed, saying so or printing why it couldn't.
+///
+/// # Arguments
+///
+/// * `emulator` - The machine.
+/// * `watcher` - The watcher for the ROM's file.
+/// * `reset` - Whether to reset the machine once the ROM is reloaded.
+///
+/// # Returns
+///
+/// Whether the ROM was reloaded.
+fn report_reload(emulator: &mut Emulator, watcher: &mut RomWatcher, reset: bool) -> bool {
+    match reload_rom(emulator, watcher, reset, false) {
+        // Lines end with a carriage return as well, as the terminal may be in raw mode
+        Ok(true) => {
+            print!("Reloaded {}\r\n", watcher.path().display());
+            true
+        }
+        Ok(false) => false,
+        // A broken build is reported, and the machine carries on with the last good one
+        Err(message) => {
+            eprint!("{}\r\n", message);
+            false
+        }
+    }
+}
+
+/// Runs the machine at a given speed until the user breaks in, reloading
+/// the ROM whenever its file changes.
+///
+/// When the CPU stops, it waits for the ROM to change instead of returning,
+/// so a fixed build can be tried straight away.
+///
+/// # Arguments
+///
+/// * `emulator` - The machine.
+/// * `watcher` - The watcher for the ROM's file.
+/// * `frequency` - The clock speed in Hz.
+/// * `reset` - Whether to reset the machine after reloading the ROM.
+/// * `terminal` - The user's terminal.
+fn run_watched(
+    emulator: &mut Emulator,
+    watcher: &mut RomWatcher,
+    frequency: u64,
+    reset: bool,
+    terminal: &mut Terminal,
+) {
+    let mut pacer = Pacer::new(frequency);
+
+    loop {
+        let reason = emulator.run(frequency / SLICES_PER_SECOND, |emulator, elapsed| {
+            pacer.pace(elapsed);
+            report_reload(emulator, watcher, reset);
+            terminal.poll(emulator)
+        });
+        print!("{}\r\n", reason);
+        if reason == StopReason::Interrupted {
+            return;
+        }
+
+        while !report_reload(emulator, watcher, reset) {
+            if terminal.poll(emulator).is_break() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1_000 / SLICES_PER_SECOND));
+        }
+    }
+}
+
+/// Runs a ROM at a given speed until it stops or the user breaks in, or flat
+/// out to benchmark it, the blink demo unless another binary is given.
+///
+/// # Arguments
+///
+/// * `args` - The options.
+///
+/// # Returns
+///
+/// `Ok(())` once the ROM has run, or a message to print otherwise.
+fn run_demo(args: &[String]) -> Result<(), String> {
+    let mut speed = None;
+    let mut benchmark = None;
+    let mut watch = false;
+    let mut reset = false;
+    let mut console = false;
+    let mut machine_args = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--watch" => watch = true,
+            "--reset-on-reload" => reset = true,
+            "--console" => console = true,
+            "--speed" => {
+                let hz = args.next().ok_or(USAGE)?;
+                speed = Some(hz.parse::<u64>().ok().filter(|&hz| hz > 0).ok_or(format!("Not a speed: {}", hz))?);
+            }
+            "--benchmark" => {
+                let seconds = args.next().ok_or(USAGE)?;
+                let seconds = seconds.parse::<f64>().ok().filter(|seconds| seconds.is_finite() && *seconds > 0.0);
+                benchmark = Some(std::time::Duration::from_secs_f64(seconds.ok_or(USAGE)?));
+            }
+            _ => machine_args.push(arg.clone()),
+        }
+    }
+
+    let (mut emulator, watcher) = build_machine(&machine_args)?;
+
+    match (speed, benchmark) {
+        (Some(_), Some(_)) => return Err(String::from(USAGE)),
+        (None, Some(duration)) => println!("{}", emulator.benchmark(duration)),
+        (speed, None) if watch => {
+            let mut watcher = watcher.ok_or("Only the demo machine's ROM can be reloaded")?;
+            let mut terminal = Terminal::new(&emulator, console)?;
+            run_watched(&mut emulator, &mut watcher, speed.unwrap_or(DEFAULT_SPEED), reset, &mut terminal);
+        }
+        (speed, None) => {
+            let hz = speed.unwrap_or(DEFAULT_SPEED);
+            let mut pacer = Pacer::new(hz);
+            let mut terminal = Terminal::new(&emulator, console)?;
+            let reason = emulator.run(hz / SLICES_PER_SECOND, |emulator, elapsed| {
+                pacer.pace(elapsed);
+                terminal.poll(emulator)
+            });
+            drop(terminal);
+            println!("{}", reason);
+        }
+    }
+    Ok(())
+}
+
+/// Debugs a ROM in the monitor, reading commands from the terminal.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after `debug`.
+///
+/// # Returns
+///
+/// `Ok(())` once the user quits or input ends, or a message to print otherwise.
+fn run_monitor(args: &[String]) -> Result<(), String> {
+    let mut watch = false;
+    let mut reset = false;
+    let mut machine_args = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--watch" => watch = true,
+            "--reset-on-reload" => reset = true,
+            _ => machine_args.push(arg.clone()),
+        }
+    }
+
+    let (mut emulator, mut watcher) = build_machine(&machine_args)?;
+    if watch && watcher.is_none() {
+        return Err(String::from("Only the demo machine's ROM can be reloaded"));
+    }
+    let mut monitor = Monitor::new();
+    print!("{}", monitor.execute(&mut emulator, "r")?);
+
+    let stdin = std::io::stdin();
+    while !monitor.is_finished() {
+        print!("> ");
+        std::io::stdout().flush().map_err(|error| error.to_string())?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line).map_err(|error| error.to_string())? == 0 {
+            break;
+        }
+
+        // The file is checked before each command, so the command sees the new ROM
+        let force = line.trim() == "reload";
+        if let Some(watcher) = watcher.as_mut().filter(|_| watch || force) {
+            match reload_rom(&mut emulator, watcher, reset, force) {
+                Ok(true) => println!("Reloaded {}", watcher.path().display()),
+                Ok(false) => {}
+                Err(message) => println!("{}", message),
+            }
+        }
+        if force {
+            if watcher.is_none() {
+                println!("Only the demo machine's ROM can be reloaded");
+            }
+            continue;
+        }
+
+        match monitor.execute(&mut emulator, &line) {
+            Ok(text) => print!("{}", text),
+            Err(message) => println!("{}", message),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a ROM without a user until it writes its exit code to the exit port.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after `batch`.
+///
+/// # Returns
+///
+/// The exit code the ROM wrote, or a message to print if it didn't write
+/// one in time or the CPU jammed, stopped or hit a watchpoint first.
+fn run_batch(args: &[String]) -> Result<u8, String> {
+    let mut exit_port = 0xBFFF;
+    let mut max_cycles = None;
+    let mut machine_args = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--exit-port" => exit_port = parse_address(args.next().ok_or(USAGE)?)?,
+            "--max-cycles" => {
+                let count = args.next().ok_or(USAGE)?;
+                max_cycles = Some(count.parse::<u64>().map_err(|_| format!("Not a count: {}", count))?);
+            }
+            _ => machine_args.push(arg.clone()),
+        }
+    }
+
+    // The port is decoded ahead of anything it overlaps
+    let (mut emulator, _) = build_machine(&machine_args)?;
+    emulator.cpu.bus.borrow_mut().add_device_with_priority(Box::new(ExitPort::new(exit_port)), 1);
+
+    // One instruction at a time, so nothing runs after the exit code is written
+    let mut elapsed = 0;
+    let mut code = None;
+    let reason = emulator.run(1, |emulator, cycles| {
+        elapsed += cycles;
+        code = emulator.cpu.bus.borrow().device_of_type::<ExitPort>().and_then(ExitPort::exit_code);
+        match code.is_some() || max_cycles.is_some_and(|max| elapsed >= max) {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        }
+    });
+
+    match (code, reason) {
+        (Some(code), _) => Ok(code),
+        (None, StopReason::Interrupted) => Err(format!("Timed out after {} cycles", elapsed)),
+        (None, reason) => Err(reason.to_string()),
+    }
+}
+
+/// Assembles a source file into a binary, and optionally a listing.
+///
+/// The binary runs from the lowest assembled address to the highest, with
+/// zeros anywhere nothing was assembled.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after `asm`.
+///
+/// # Returns
+///
+/// `Ok(())` if the files were written, or a message to print otherwise.
+fn assemble_file(args: &[String]) -> Result<(), String> {
+    let mut source_path = None;
+    let mut output_path = None;
+    let mut listing_path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output_path = Some(args.next().ok_or(USAGE)?.clone()),
+            "-l" => listing_path = Some(args.next().ok_or(USAGE)?.clone()),
+            _ if source_path.is_none() => source_path = Some(arg.clone()),
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+    let source_path = source_path.ok_or(USAGE)?;
+    let output_path = output_path.unwrap_or_else(|| {
+        std::path::Path::new(&source_path)
+            .with_extension("bin")
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    let source = std::fs::read_to_string(&source_path).map_err(|error| format!("{}: {}", source_path, error))?;
+    let assembly = assemble(&source).map_err(|error| format!("{}:{}: {}", source_path, error.line, error.kind))?;
+
+    let (_, image) = assembly.image(0x00);
+    std::fs::write(&output_path, image).map_err(|error| format!("{}: {}", output_path, error))?;
+
+    match listing_path.as_deref() {
+        Some("-") => print!("{}", assembly.listing),
+        Some(path) => {
+            std::fs::write(path, assembly.listing.to_string()).map_err(|error| format!("{}: {}", path, error))?
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Parses an address written in hex, with or without a `$` or `0x` prefix.
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")).unwrap_or(text);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Not an address: {}", text))
+}
+
+/// Runs a program in a window showing a 32x32 framebuffer at $0200, like easy6502.
+///
+/// The CPU runs at about 1 MHz, and the window is redrawn 60 times a second
+/// until it is closed or Escape is pressed.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after `screen`.
+///
+/// # Returns
+///
+/// `Ok(())` once the window is closed, or a message to print otherwise.
+#[cfg(feature = "window")]
+fn run_screen(args: &[String]) -> Result<(), String> {
+    use butterflyrs::bus::framebuffer::Framebuffer;
+    use butterflyrs::loaders::{hex, prg};
+    use minifb::{Key, Scale, Window, WindowOptions};
+
+    /// The cycles in a 60th of a second at 1 MHz.
+    const CYCLES_PER_FRAME: u64 = 1_000_000 / 60;
+
+    let (path, address) = match args {
+        [path] => (path, None),
+        [path, address] => (path, Some(parse_address(address)?)),
+        _ => return Err(String::from(USAGE)),
+    };
+    let program = std::fs::read(path).map_err(|error| format!("{}: {}", path, error))?;
+
+    // The screen is decoded ahead of the RAM it sits in
+    let mut emulator = Emulator::from_cpu(CpuBuilder::new().debug(DebugLevel::Off).build());
+    emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+    emulator.cpu.bus.borrow_mut().add_device_with_priority(Box::new(Framebuffer::new(0x0200, 32, 32)), 1);
+
+    let extension = std::path::Path::new(path).extension().map(|extension| extension.to_ascii_lowercase());
+    let start = if extension.as_ref().is_some_and(|extension| extension == "hex") {
+        let text = String::from_utf8(program).map_err(|_| format!("{}: Not a text file", path))?;
+        let image = hex::parse(&text).map_err(|error| format!("{}: {}", path, error))?;
+        image.load(&mut emulator.cpu.bus.borrow_mut()).map_err(|error| format!("{}: {}", path, error))?;
+        address.or(image.start_address).or(image.segments.first().map(|segment| segment.address)).unwrap_or(0x0000)
+    } else if extension.as_ref().is_some_and(|extension| extension == "prg") {
+        let load_address = prg::load(&mut emulator.cpu.bus.borrow_mut(), &program)
+            .map_err(|error| format!("{}: {}", path, error))?;
+        address.unwrap_or(load_address)
+    } else {
+        let address = address.unwrap_or(0x0600);
+        emulator.load(address, &program);
+        address
+    };
+    emulator.cpu.pc.set(start);
+
+    let options = WindowOptions { scale: Scale::X16, ..WindowOptions::default() };
+    let mut window = Window::new("butterflyrs", 32, 32, options).map_err(|error| error.to_string())?;
+    window.set_target_fps(60);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        emulator.run_for_cycles(CYCLES_PER_FRAME);
+
+        let pixels = emulator.cpu.bus.borrow().device_of_type::<Framebuffer>().map(Framebuffer::to_rgb32);
+        let pixels = pixels.expect("the framebuffer is on the bus");
+        window.update_with_buffer(&pixels, 32, 32).map_err(|error| error.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "window"))]
+fn run_screen(_args: &[String]) -> Result<(), String> {
+    Err(String::from("The screen command needs butterflyrs to be built with the window feature"))
 }
@@ -0,0 +1,615 @@
+//! A machine code monitor for debugging programs as they run.
+//!
+//! The monitor takes one command at a time and returns the text to show, so
+//! a front end only has to supply the prompt. The `debug` command of the
+//! butterflyrs binary puts one on the terminal:
+//!
+//! ```
+//! use butterflyrs::emulator::Emulator;
+//! use butterflyrs::bus::ram::Ram;
+//! use butterflyrs::monitor::Monitor;
+//!
+//! let mut emulator = Emulator::new();
+//! emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+//!
+//! let mut monitor = Monitor::new();
+//! monitor.execute(&mut emulator, "w 0200 A9 42").unwrap();
+//! assert_eq!(monitor.execute(&mut emulator, "d 0200 1").unwrap(), "0200  A9 42     LDA #$42\n");
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::asm;
+use crate::cpu::{Breakpoint, Condition, Cpu};
+use crate::disasm;
+use crate::emulator::Emulator;
+
+/// The commands the monitor understands, as shown by `help`.
+pub const HELP: &str = "\
+s [count]              Step one or more instructions
+c [cycles]             Continue until a breakpoint, or for at most a number of cycles
+r                      Show the registers
+d [address] [count]    Disassemble, from the program counter by default
+m <address> [L<count>] Show memory, such as: m 0200 L16
+m <address> <byte>...  Write bytes to memory
+w <address> <byte>...  Write bytes to memory
+f <start> <end> <byte>...
+                       Fill memory from start to end with a repeating pattern of bytes
+a <address> <line>     Assemble a line of source to an address, such as: a 0200 LDA #$10
+b [address] [count] [if <condition>]
+                       Set a breakpoint, or list them without an address. It
+                       stops on the count'th hit, and only hits while the
+                       condition holds, such as: b loop 5 if A == $10 && X > 3
+bd <address>           Delete a breakpoint
+bt                     Show the subroutine calls and interrupts in progress, innermost first
+e <expression>         Evaluate an expression
+watch [expression]     Show an expression after every step and continue, or list them
+wd <number>            Delete a watch expression
+reset                  Reset the machine
+q                      Quit
+
+Addresses and bytes are in hex, with or without a $ prefix, and addresses can
+also be labels. Counts are in decimal. An empty line repeats a step or continue.
+Expressions use the registers A, X, Y, S, P and PC, the flags N, V, B, D, I,
+Z and C, labels, numbers in decimal or hex with a $ prefix, [address] for a
+byte of memory, and the operators of C.
+";
+
+/// The number of instructions `d` shows when no count is given.
+const DISASSEMBLY_LINES: u16 = 10;
+
+/// The number of bytes `m` shows when no count is given.
+const MEMORY_BYTES: u16 = 64;
+
+/// An interactive debugger for an emulator.
+///
+/// Each command is run with `execute()`. Stepping and continuing go through
+/// `Cpu::step()`, so breakpoints and watchpoints set through the library stop
+/// the monitor too.
+#[derive(Debug, Default)]
+pub struct Monitor {
+    /// The last step or continue command, which an empty line repeats.
+    repeat: String,
+
+    /// Whether the user has asked to quit.
+    finished: bool,
+
+    /// The expressions shown after every step and continue, in the order they were added.
+    watches: Vec<Condition>,
+}
+
+impl Monitor {
+    /// Creates a monitor.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Monitor` struct.
+    pub fn new() -> Monitor {
+        Monitor::default()
+    }
+
+    /// Returns whether the user has asked to quit with `q`.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Runs a command.
+    ///
+    /// # Arguments
+    ///
+    /// * `emulator` - The machine being debugged.
+    /// * `line` - The command, as typed.
+    ///
+    /// # Returns
+    ///
+    /// The text to show, a line at a time with each line ending in a newline,
+    /// or a message saying why the command couldn't be run.
+    pub fn execute(&mut self, emulator: &mut Emulator, line: &str) -> Result<String, String> {
+        let line = match line.trim() {
+            "" => self.repeat.clone(),
+            line => String::from(line),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let cpu = &mut emulator.cpu;
+
+        self.repeat = match words.first() {
+            Some(&"s" | &"c") => line.clone(),
+            _ => String::new(),
+        };
+
+        match words[..] {
+            [] => Ok(String::new()),
+            ["s", ..] => {
+                let count = words.get(1).map_or(Ok(1), |count| parse_count(count))?;
+                Ok(step(cpu, count) + &self.show_watches(cpu))
+            }
+            ["c", ..] => {
+                let limit = words.get(1).map(|cycles| parse_count(cycles)).transpose()?;
+                Ok(resume(cpu, limit) + &self.show_watches(cpu))
+            }
+            ["r"] => Ok(registers(cpu)),
+            ["d", ..] => {
+                let address = words.get(1).map_or(Ok(cpu.pc.get()), |address| parse_address(cpu, address))?;
+                let count = words.get(2).map_or(Ok(DISASSEMBLY_LINES as u64), |count| parse_count(count))?;
+                Ok(disassemble(cpu, address, count))
+            }
+            ["m", address] => {
+                let address = parse_address(cpu, address)?;
+                Ok(memory(emulator, address, MEMORY_BYTES as u64))
+            }
+            ["m", address, length] if length.starts_with(['L', 'l']) => {
+                let address = parse_address(cpu, address)?;
+                Ok(memory(emulator, address, parse_count(&length[1..])?))
+            }
+            ["m" | "w", address, ref bytes @ ..] if !bytes.is_empty() => {
+                let address = parse_address(cpu, address)?;
+                let bytes = parse_bytes(bytes)?;
+                for (offset, byte) in bytes.into_iter().enumerate() {
+                    emulator.poke(address.wrapping_add(offset as u16), byte);
+                }
+                Ok(String::new())
+            }
+            ["f", start, end, ref pattern @ ..] if !pattern.is_empty() => {
+                let (start, end) = (parse_address(cpu, start)?, parse_address(cpu, end)?);
+                if end < start {
+                    return Err(format!("The end {:04X} is before the start {:04X}", end, start));
+                }
+                let pattern = parse_bytes(pattern)?;
+                for (address, byte) in (start..=end).zip(pattern.iter().cycle()) {
+                    emulator.poke(address, *byte);
+                }
+                Ok(String::new())
+            }
+            ["a", address, _, ..] => {
+                let address = parse_address(cpu, address)?;
+                let bytes = assemble_line(cpu, address, rest(rest(&line)))?;
+                for (offset, byte) in bytes.into_iter().enumerate() {
+                    emulator.poke(address.wrapping_add(offset as u16), byte);
+                }
+                Ok(disassemble(&emulator.cpu, address, 1))
+            }
+            ["b"] => Ok(cpu.breakpoints().map(|address| describe_breakpoint(cpu, address)).collect()),
+            ["b", address, ..] => {
+                let address = parse_address(cpu, address)?;
+                let (line, condition) = match line.split_once(" if ") {
+                    Some((line, condition)) => (line, Some(parse_condition(cpu, condition)?)),
+                    None => (line.as_str(), None),
+                };
+                let count = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    [_, _] => 1,
+                    [_, _, text] => match parse_count(text)? {
+                        0 => return Err(format!("Not a count: {}", text)),
+                        count => count,
+                    },
+                    _ => return Err(String::from("Usage: b [address] [count] [if <condition>]")),
+                };
+
+                if cpu.has_breakpoint(address) {
+                    return Err(format!("There is already a breakpoint at {}", name(cpu, address)));
+                }
+                let skip = u32::try_from(count - 1).unwrap_or(u32::MAX);
+                cpu.set_breakpoint(address, Breakpoint { condition, skip, ..Breakpoint::default() });
+                Ok(format!("Breakpoint set at {}\n", name(cpu, address)))
+            }
+            ["bd", address] => {
+                let address = parse_address(cpu, address)?;
+                match cpu.remove_breakpoint(address) {
+                    true => Ok(String::new()),
+                    false => Err(format!("There is no breakpoint at {}", name(cpu, address))),
+                }
+            }
+            ["bt"] => Ok(cpu
+                .backtrace()
+                .iter()
+                .rev()
+                .map(|frame| format!("{} {} from {}\n", frame.kind, name(cpu, frame.to), name(cpu, frame.from)))
+                .collect()),
+            ["e", ..] => {
+                let condition = parse_condition(cpu, rest(&line))?;
+                let value = condition.evaluate(cpu);
+                Ok(format!("${:X} ({})\n", value, value))
+            }
+            ["watch"] => Ok(self.show_watches(cpu)),
+            ["watch", ..] => {
+                self.watches.push(parse_condition(cpu, rest(&line))?);
+                Ok(self.show_watches(cpu))
+            }
+            ["wd", number] => match parse_count(number)? {
+                number @ 1.. if number as usize <= self.watches.len() => {
+                    self.watches.remove(number as usize - 1);
+                    Ok(String::new())
+                }
+                _ => Err(format!("There is no watch expression {}", number)),
+            },
+            ["reset"] => {
+                emulator.reset();
+                Ok(registers(&emulator.cpu))
+            }
+            ["q"] => {
+                self.finished = true;
+                Ok(String::new())
+            }
+            ["help" | "h" | "?"] => Ok(String::from(HELP)),
+            _ => Err(format!("Unknown command: {} (try help)", line)),
+        }
+    }
+}
+
+impl Monitor {
+    /// Evaluates the watch expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The CPU to evaluate them against.
+    ///
+    /// # Returns
+    ///
+    /// A numbered line for each expression, with its value in hex and decimal.
+    fn show_watches(&self, cpu: &Cpu) -> String {
+        let mut text = String::new();
+        for (index, watch) in self.watches.iter().enumerate() {
+            let value = watch.evaluate(cpu);
+            let _ = writeln!(text, "{}: {} = ${:X} ({})", index + 1, watch, value, value);
+        }
+        text
+    }
+}
+
+/// Steps instructions, showing each one and then the registers.
+///
+/// # Arguments
+///
+/// * `cpu` - The CPU to step.
+/// * `count` - The number of instructions to step.
+///
+/// # Returns
+///
+/// The instructions executed, and why stepping stopped if it stopped early.
+fn step(cpu: &mut Cpu, count: u64) -> String {
+    let mut text = String::new();
+
+    for _ in 0..count {
+        let pc = cpu.pc.get();
+        let line = disassemble(cpu, pc, 1);
+        let record = cpu.step();
+
+        if let Some(stop) = record.stop {
+            let _ = writeln!(text, "{}", stop);
+            break;
+        }
+        if record.cycles == 0 {
+            let _ = writeln!(text, "The CPU is {}", cpu.state());
+            break;
+        }
+
+        // An interrupt runs the first instruction of its handler instead
+        text.push_str(&if record.interrupt { disassemble(cpu, record.pc, 1) } else { line });
+    }
+
+    text.push_str(&registers(cpu));
+    text
+}
+
+/// Runs until the CPU stops, or for at most a number of cycles.
+///
+/// # Arguments
+///
+/// * `cpu` - The CPU to run.
+/// * `limit` - The number of cycles to run for at most, or `None` to run until
+///   a breakpoint or watchpoint stops the CPU, or it jams, stops or waits.
+///
+/// # Returns
+///
+/// Why the CPU stopped, the next instruction and the registers.
+fn resume(cpu: &mut Cpu, limit: Option<u64>) -> String {
+    let mut text = String::new();
+    let mut elapsed = 0u64;
+
+    loop {
+        if limit.is_some_and(|limit| elapsed >= limit) {
+            let _ = writeln!(text, "Ran for {} cycles", elapsed);
+            break;
+        }
+
+        let record = cpu.step();
+        elapsed += record.cycles as u64;
+        if let Some(stop) = record.stop {
+            let _ = writeln!(text, "{}", stop);
+            break;
+        }
+        if record.cycles == 0 {
+            let _ = writeln!(text, "The CPU is {}", cpu.state());
+            break;
+        }
+    }
+
+    text.push_str(&disassemble(cpu, cpu.pc.get(), 1));
+    text.push_str(&registers(cpu));
+    text
+}
+
+/// Formats the registers, with the flags that are set in capitals.
+///
+/// # Arguments
+///
+/// * `cpu` - The CPU whose registers are shown.
+///
+/// # Returns
+///
+/// A line such as "PC=C000 A=00 X=00 Y=00 SP=FD P=24 nv-bdIzc".
+fn registers(cpu: &Cpu) -> String {
+    let p = cpu.p.get();
+    let flags: String = "NV-BDIZC"
+        .chars()
+        .enumerate()
+        .map(|(bit, letter)| match letter {
+            '-' => '-',
+            _ if p & (0x80 >> bit) != 0 => letter,
+            _ => letter.to_ascii_lowercase(),
+        })
+        .collect();
+
+    format!(
+        "PC={:04X} A={:02X} X={:02X} Y={:02X} SP={:02X} P={:02X} {}\n",
+        cpu.pc.get(),
+        cpu.a.get(),
+        cpu.x.get(),
+        cpu.y.get(),
+        cpu.sp.get(),
+        p,
+        flags
+    )
+}
+
+/// Disassembles instructions from memory, with a line for each label.
+///
+/// The bytes are peeked, so disassembling doesn't disturb devices.
+///
+/// # Arguments
+///
+/// * `cpu` - The CPU, whose model decides the instruction set and whose
+///   symbols name addresses.
+/// * `address` - The address of the first instruction.
+/// * `count` - The number of instructions to show.
+///
+/// # Returns
+///
+/// Each instruction as its address, bytes and source.
+fn disassemble(cpu: &Cpu, mut address: u16, count: u64) -> String {
+    let mut text = String::new();
+    let bus = cpu.bus.borrow();
+
+    for _ in 0..count {
+        let bytes = [0, 1, 2].map(|offset| bus.peek(address.wrapping_add(offset)));
+        let instruction =
+            disasm::disassemble_one(cpu.model, &bytes, address).expect("instructions are at most three bytes long");
+
+        if let Some(label) = cpu.symbols.get(address) {
+            let _ = writeln!(text, "{}:", label);
+        }
+        let bytes = &bytes[..instruction.length as usize];
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let source = instruction.to_string_with(&cpu.symbols);
+        let _ = writeln!(text, "{:04X}  {:<8}  {}", address, hex.join(" "), source);
+
+        address = address.wrapping_add(instruction.length);
+    }
+    text
+}
+
+/// Dumps memory as hex and ASCII, 16 bytes to a line.
+///
+/// # Arguments
+///
+/// * `emulator` - The machine whose memory is shown. Bytes are peeked, so
+///   devices aren't disturbed.
+/// * `address` - The address of the first byte.
+/// * `count` - The number of bytes to show.
+///
+/// # Returns
+///
+/// The lines of the dump.
+fn memory(emulator: &Emulator, address: u16, count: u64) -> String {
+    let mut text = String::new();
+    let addresses = (0..count.min(0x10000)).map(|offset| address.wrapping_add(offset as u16));
+    let bytes: Vec<u8> = addresses.map(|address| emulator.peek(address)).collect();
+
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| if (0x20..0x7F).contains(&byte) { byte as char } else { '.' })
+            .collect();
+        let _ = writeln!(text, "{:04X}  {:<47}  {}", address.wrapping_add(row as u16 * 16), hex.join(" "), ascii);
+    }
+    text
+}
+
+/// Names an address with its label, if it has one.
+fn name(cpu: &Cpu, address: u16) -> String {
+    match cpu.symbols.get(address) {
+        Some(label) => format!("{:04X} ({})", address, label),
+        None => format!("{:04X}", address),
+    }
+}
+
+/// Describes a breakpoint for the breakpoint list.
+fn describe_breakpoint(cpu: &Cpu, address: u16) -> String {
+    let mut text = name(cpu, address);
+    if let Some(breakpoint) = cpu.breakpoint(address) {
+        if let Some(condition) = &breakpoint.condition {
+            let _ = write!(text, " if {}", condition);
+        }
+        if breakpoint.skip > 0 {
+            let _ = write!(text, ", stops on hit {}", breakpoint.skip as u64 + 1);
+        }
+        if breakpoint.hits > 0 {
+            let _ = write!(text, ", hit {} times", breakpoint.hits);
+        }
+    }
+    text.push('\n');
+    text
+}
+
+/// Returns what follows the first word of a line.
+fn rest(line: &str) -> &str {
+    line.trim_start().split_once(char::is_whitespace).map_or("", |(_, rest)| rest.trim_start())
+}
+
+/// Assembles a line of source for an address.
+///
+/// The CPU's labels are defined as constants first, so the line can refer to
+/// them. Labels that aren't valid assembler symbols are left out.
+///
+/// # Arguments
+///
+/// * `cpu` - The CPU, whose model decides the instruction set.
+/// * `address` - The address the line is assembled for.
+/// * `line` - The source, such as an instruction or a `.byte` directive.
+///
+/// # Returns
+///
+/// The assembled bytes, or the assembler's error message.
+fn assemble_line(cpu: &Cpu, address: u16, line: &str) -> Result<Vec<u8>, String> {
+    let mut source = String::new();
+    for (value, label) in cpu.symbols.iter() {
+        let mut characters = label.chars();
+        let valid = characters.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && characters.all(|character| character.is_ascii_alphanumeric() || character == '_');
+        if valid {
+            let _ = writeln!(source, "{} = ${:04X}", label, value);
+        }
+    }
+    let _ = writeln!(source, ".org ${:04X}", address);
+    source.push_str(line);
+
+    // The line numbers of errors would count the constants, so only the error itself is shown
+    let assembly = asm::assemble_model(cpu.model, &source).map_err(|error| error.kind.to_string())?;
+    Ok(assembly.segments.into_iter().flat_map(|segment| segment.bytes).collect())
+}
+
+/// Parses an expression, which can use the CPU's labels.
+fn parse_condition(cpu: &Cpu, text: &str) -> Result<Condition, String> {
+    Condition::parse(text, &cpu.symbols).map_err(|error| format!("{}: {}", text.trim(), error))
+}
+
+/// Parses an address, given as a label or in hex.
+fn parse_address(cpu: &Cpu, text: &str) -> Result<u16, String> {
+    if let Some(address) = cpu.symbols.address_of(text) {
+        return Ok(address);
+    }
+    let digits = text.strip_prefix('$').unwrap_or(text);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Not an address or label: {}", text))
+}
+
+/// Parses a byte in hex.
+fn parse_byte(text: &str) -> Result<u8, String> {
+    let digits = text.strip_prefix('$').unwrap_or(text);
+    u8::from_str_radix(digits, 16).map_err(|_| format!("Not a byte: {}", text))
+}
+
+/// Parses bytes in hex.
+fn parse_bytes(texts: &[&str]) -> Result<Vec<u8>, String> {
+    texts.iter().map(|text| parse_byte(text)).collect()
+}
+
+/// Parses a count in decimal.
+fn parse_count(text: &str) -> Result<u64, String> {
+    text.parse().map_err(|_| format!("Not a count: {}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::bus::ram::Ram;
+    use crate::emulator::Emulator;
+    use crate::monitor::Monitor;
+
+    #[test]
+    fn steps_and_stops_at_breakpoints() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        emulator.cpu.symbols.insert(0x0203, "done");
+        let mut monitor = Monitor::new();
+
+        // LDA #$42, INX, NOP, at $0200 with the reset vector pointing at it
+        monitor.execute(&mut emulator, "w FFFC 00 02").unwrap();
+        monitor.execute(&mut emulator, "w $0200 A9 42 E8 EA").unwrap();
+        assert_eq!(monitor.execute(&mut emulator, "reset").unwrap(), "PC=0200 A=00 X=00 Y=00 SP=FD P=24 nv-bdIzc\n");
+
+        let text = monitor.execute(&mut emulator, "s").unwrap();
+        assert_eq!(text, "0200  A9 42     LDA #$42\nPC=0202 A=42 X=00 Y=00 SP=FD P=24 nv-bdIzc\n");
+
+        // An empty line steps again, and continuing stops at the labelled breakpoint
+        assert!(monitor.execute(&mut emulator, "").unwrap().starts_with("0202  E8        INX\n"));
+        assert_eq!(monitor.execute(&mut emulator, "b done").unwrap(), "Breakpoint set at 0203 (done)\n");
+        let text = monitor.execute(&mut emulator, "c").unwrap();
+        assert!(text.starts_with("Breakpoint at 0203\ndone:\n0203  EA        NOP\n"), "{}", text);
+
+        let dump = format!("0200  {:<47}  .B..\n", "A9 42 E8 EA");
+        assert_eq!(monitor.execute(&mut emulator, "m 0200 L4").unwrap(), dump);
+        assert!(monitor.execute(&mut emulator, "bd 1234").is_err());
+        assert_eq!(monitor.execute(&mut emulator, "bt").unwrap(), "");
+        assert!(monitor.execute(&mut emulator, "x").is_err());
+        monitor.execute(&mut emulator, "q").unwrap();
+        assert!(monitor.is_finished());
+    }
+
+    #[test]
+    fn breakpoints_take_conditions_and_expressions_can_be_watched() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        emulator.cpu.symbols.insert(0x0202, "loop");
+        let mut monitor = Monitor::new();
+
+        // LDX #$00, loop: INX, JMP loop
+        monitor.execute(&mut emulator, "w FFFC 00 02").unwrap();
+        monitor.execute(&mut emulator, "w 0200 A2 00 E8 4C 02 02").unwrap();
+        monitor.execute(&mut emulator, "reset").unwrap();
+
+        // X is above 1 at the loop for the first time at 2, so the third such hit is at 4
+        assert_eq!(monitor.execute(&mut emulator, "b loop 3 if X > 1").unwrap(), "Breakpoint set at 0202 (loop)\n");
+        assert_eq!(monitor.execute(&mut emulator, "watch X * 2").unwrap_err(), "X * 2: Unexpected *");
+        assert_eq!(monitor.execute(&mut emulator, "watch [loop] + X").unwrap(), "1: [loop] + X = $E8 (232)\n");
+        let text = monitor.execute(&mut emulator, "c").unwrap();
+        assert!(text.starts_with("Breakpoint at 0202\n"), "{}", text);
+        assert!(text.ends_with("1: [loop] + X = $EC (236)\n"), "{}", text);
+        assert_eq!(monitor.execute(&mut emulator, "e x == 4 && pc == loop").unwrap(), "$1 (1)\n");
+
+        let list = "0202 (loop) if X > 1, stops on hit 3, hit 3 times\n";
+        assert_eq!(monitor.execute(&mut emulator, "b").unwrap(), list);
+        assert!(monitor.execute(&mut emulator, "b 0300 0").is_err());
+        assert!(monitor.execute(&mut emulator, "wd 2").is_err());
+        monitor.execute(&mut emulator, "wd 1").unwrap();
+        assert_eq!(monitor.execute(&mut emulator, "watch").unwrap(), "");
+    }
+
+    #[test]
+    fn edits_fills_and_assembles_memory() {
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0xFFFF)));
+        emulator.cpu.symbols.insert(0x8000, "port");
+        let mut monitor = Monitor::new();
+
+        monitor.execute(&mut emulator, "f 0300 0307 AA 55").unwrap();
+        monitor.execute(&mut emulator, "m 0200 A9 10 8D 00 80").unwrap();
+
+        // A single byte is an edit too, whether or not it looks like a count
+        monitor.execute(&mut emulator, "m 0210 42").unwrap();
+        assert_eq!(emulator.peek(0x0210), 0x42);
+        assert!(monitor.execute(&mut emulator, "m 0210 Lx").is_err());
+        let dump = format!("0300  {:<47}  .U.U.U.U\n", "AA 55 AA 55 AA 55 AA 55");
+        assert_eq!(monitor.execute(&mut emulator, "m 0300 L8").unwrap(), dump);
+        let listing = "0200  A9 10     LDA #$10\n0202  8D 00 80  STA port\n";
+        assert_eq!(monitor.execute(&mut emulator, "d 0200 2").unwrap(), listing);
+
+        // Labels can be used in the line, which is assembled for its address
+        assert_eq!(monitor.execute(&mut emulator, "a 0205  inc  port").unwrap(), "0205  EE 00 80  INC port\n");
+        assert_eq!(monitor.execute(&mut emulator, "a 0208 bne $0205").unwrap(), "0208  D0 FB     BNE $0205\n");
+        assert_eq!(monitor.execute(&mut emulator, "a 0208 lda").unwrap_err(), "Invalid addressing mode for LDA");
+        assert!(monitor.execute(&mut emulator, "f 0307 0300 00").is_err());
+    }
+}
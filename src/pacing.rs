@@ -0,0 +1,142 @@
+//! Pacing emulation to real time, and measuring how fast it runs unpaced.
+//!
+//! The CPU runs as fast as the host allows, which makes a blinking LED or a
+//! serial terminal finish before anyone can see it. A [`Pacer`] slows a run
+//! loop down to the speed of the machine being emulated by sleeping between
+//! batches of cycles. Going the other way, a [`Benchmark`] reports how fast
+//! the emulator runs flat out, so changes that slow the core down show up.
+
+use core::fmt::Display;
+use std::time::{Duration, Instant};
+
+/// How far behind real time the pacer lets emulation fall before it gives up
+/// catching up. After the host has been busy or suspended, running flat out
+/// until emulation catches up would look like a burst of speed.
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+/// Keeps a run loop at the speed of the emulated clock.
+///
+/// The host runs a batch of cycles and passes the count to `pace()`, which
+/// sleeps until that many cycles should have taken at the emulated
+/// frequency. The deadline is worked out from when pacing started, rather
+/// than from the last sleep, so sleeping too long or too short on one batch
+/// is made up for on the next and the clock doesn't drift.
+///
+/// # Example
+///
+/// ```no_run
+/// use butterflyrs::emulator::Emulator;
+/// use butterflyrs::pacing::Pacer;
+///
+/// let mut emulator = Emulator::new();
+/// let mut pacer = Pacer::new(1_000_000);
+/// loop {
+///     let cycles = emulator.run_for_cycles(1_000);
+///     pacer.pace(cycles);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    /// The emulated clock frequency, in Hz.
+    frequency: u64,
+
+    /// When the current stretch of pacing started.
+    start: Instant,
+
+    /// The cycles run since `start`.
+    cycles: u64,
+}
+
+impl Pacer {
+    /// Creates a pacer that starts timing now.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The emulated clock frequency in Hz, such as 1_000_000 for 1 MHz.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `Pacer` struct.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is 0.
+    pub fn new(frequency: u64) -> Pacer {
+        assert!(frequency > 0, "the clock frequency must be above 0 Hz");
+        Pacer {
+            frequency,
+            start: Instant::now(),
+            cycles: 0,
+        }
+    }
+
+    /// Returns the emulated clock frequency in Hz.
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    /// Waits until the cycles run so far should have taken at the emulated frequency.
+    ///
+    /// If emulation has fallen more than a tenth of a second behind, the
+    /// pacer starts timing again from now instead of letting it race ahead.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of cycles run since the last call.
+    pub fn pace(&mut self, cycles: u64) {
+        self.cycles += cycles;
+
+        // Whole seconds and the remainder are worked out separately, so long runs don't overflow
+        let seconds = self.cycles / self.frequency;
+        let nanos = (self.cycles % self.frequency) * 1_000_000_000 / self.frequency;
+        let deadline = self.start + Duration::new(seconds, nanos as u32);
+
+        let now = Instant::now();
+        if deadline > now {
+            std::thread::sleep(deadline - now);
+        } else if now - deadline > MAX_LAG {
+            self.start = now;
+            self.cycles = 0;
+        }
+    }
+}
+
+/// How fast a machine ran flat out, as measured by `Emulator::benchmark()`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Benchmark {
+    /// The number of cycles run.
+    pub cycles: u64,
+
+    /// The number of instructions executed.
+    pub instructions: u64,
+
+    /// The wall-clock time the cycles took.
+    pub elapsed: Duration,
+}
+
+impl Benchmark {
+    /// Returns the emulated cycles run per second of wall-clock time, which is
+    /// the clock frequency in Hz the emulator could keep up with.
+    pub fn cycles_per_second(&self) -> f64 {
+        self.cycles as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    /// Returns the instructions executed per second of wall-clock time.
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+impl Display for Benchmark {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} cycles and {} instructions in {:.2} s: {:.2} MHz, {:.2} MIPS",
+            self.cycles,
+            self.instructions,
+            self.elapsed.as_secs_f64(),
+            self.cycles_per_second() / 1_000_000.0,
+            self.instructions_per_second() / 1_000_000.0
+        )
+    }
+}
@@ -1,6 +1,9 @@
-use std::ops::AddAssign;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
 
 /// Represents an 8-bit register.
+///
+/// All arithmetic on the register wraps around, as it does on the CPU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub struct Register8 {
     /// The value stored in the register.
     value: u8,
@@ -22,6 +25,11 @@ impl Register8 {
         self.value = value;
     }
 
+    /// Sets the given bits in the register, leaving the others untouched.
+    pub fn insert(&mut self, value: u8) {
+        self.value |= value;
+    }
+
     pub fn remove(&mut self, value: u8) {
         self.value &= !value;
     }
@@ -30,18 +38,60 @@ impl Register8 {
         self.value & value != 0
     }
 
-    pub fn sub_assign(&mut self, value: u8) {
-        self.value -= value;
+    /// Returns the register's value plus `value`, wrapping around at 0xFF.
+    pub fn wrapping_add(&self, value: u8) -> u8 {
+        self.value.wrapping_add(value)
+    }
+
+    /// Returns the register's value minus `value`, wrapping around at zero.
+    pub fn wrapping_sub(&self, value: u8) -> u8 {
+        self.value.wrapping_sub(value)
+    }
+
+    /// Returns the register's value plus a signed offset, wrapping around in
+    /// either direction.
+    pub fn wrapping_add_signed(&self, value: i8) -> u8 {
+        self.value.wrapping_add_signed(value)
+    }
+}
+
+impl Add<u8> for Register8 {
+    type Output = Register8;
+
+    /// Adds `rhs` to the register, wrapping around at 0xFF.
+    fn add(self, rhs: u8) -> Register8 {
+        Register8 { value: self.wrapping_add(rhs) }
+    }
+}
+
+impl Sub<u8> for Register8 {
+    type Output = Register8;
+
+    /// Subtracts `rhs` from the register, wrapping around at zero.
+    fn sub(self, rhs: u8) -> Register8 {
+        Register8 { value: self.wrapping_sub(rhs) }
     }
 }
 
 impl AddAssign<u8> for Register8 {
+    /// Adds `rhs` to the register, wrapping around at 0xFF.
     fn add_assign(&mut self, rhs: u8) {
-        self.value += rhs;
+        self.value = self.wrapping_add(rhs);
+    }
+}
+
+impl SubAssign<u8> for Register8 {
+    /// Subtracts `rhs` from the register, wrapping around at zero.
+    fn sub_assign(&mut self, rhs: u8) {
+        self.value = self.wrapping_sub(rhs);
     }
 }
 
 /// Represents a 16-bit register.
+///
+/// All arithmetic on the register wraps around, so the program counter rolls
+/// over from 0xFFFF to 0x0000 as it does on the CPU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub struct Register16 {
     pub value: u16,
 }
@@ -61,10 +111,238 @@ impl Register16 {
     pub fn set(&mut self, value: u16) {
         self.value = value;
     }
+
+    /// Returns the register's value plus `value`, wrapping around at 0xFFFF.
+    pub fn wrapping_add(&self, value: u16) -> u16 {
+        self.value.wrapping_add(value)
+    }
+
+    /// Returns the register's value minus `value`, wrapping around at zero.
+    pub fn wrapping_sub(&self, value: u16) -> u16 {
+        self.value.wrapping_sub(value)
+    }
+
+    /// Returns the register's value plus a signed offset, wrapping around in
+    /// either direction. Used to apply branch offsets to the program counter.
+    pub fn wrapping_add_signed(&self, value: i16) -> u16 {
+        self.value.wrapping_add_signed(value)
+    }
+}
+
+impl Add<u16> for Register16 {
+    type Output = Register16;
+
+    /// Adds `rhs` to the register, wrapping around at 0xFFFF.
+    fn add(self, rhs: u16) -> Register16 {
+        Register16 { value: self.wrapping_add(rhs) }
+    }
+}
+
+impl Sub<u16> for Register16 {
+    type Output = Register16;
+
+    /// Subtracts `rhs` from the register, wrapping around at zero.
+    fn sub(self, rhs: u16) -> Register16 {
+        Register16 { value: self.wrapping_sub(rhs) }
+    }
 }
 
 impl AddAssign<u16> for Register16 {
+    /// Adds `rhs` to the register, wrapping around at 0xFFFF.
     fn add_assign(&mut self, rhs: u16) {
-        self.value += rhs;
+        self.value = self.wrapping_add(rhs);
     }
-}
\ No newline at end of file
+}
+
+impl SubAssign<u16> for Register16 {
+    /// Subtracts `rhs` from the register, wrapping around at zero.
+    fn sub_assign(&mut self, rhs: u16) {
+        self.value = self.wrapping_sub(rhs);
+    }
+}
+
+/// Represents the processor status register.
+///
+/// The flags are kept apart rather than packed into a byte, since almost every
+/// instruction changes some of them. The byte is only put together when it is
+/// asked for, such as when PHP or an interrupt pushes it, and split up again
+/// when it is set, such as by PLP or RTI. Bits 4 and 5, which aren't flags on
+/// the 6502, are kept as they were set.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct StatusRegister {
+    /// The negative flag, bit 7.
+    negative: bool,
+
+    /// The overflow flag, bit 6.
+    overflow: bool,
+
+    /// Bits 4 and 5, in place.
+    other: u8,
+
+    /// The decimal mode flag, bit 3.
+    decimal: bool,
+
+    /// The interrupt disable flag, bit 2.
+    interrupt_disable: bool,
+
+    /// The zero flag, bit 1.
+    zero: bool,
+
+    /// The carry flag, bit 0.
+    carry: bool,
+}
+
+impl StatusRegister {
+    /// Creates a new instance of the `StatusRegister` struct with every bit clear.
+    pub fn new() -> StatusRegister {
+        StatusRegister::default()
+    }
+
+    /// Returns the register as a byte.
+    pub fn get(&self) -> u8 {
+        (self.negative as u8) << 7
+            | (self.overflow as u8) << 6
+            | self.other
+            | (self.decimal as u8) << 3
+            | (self.interrupt_disable as u8) << 2
+            | (self.zero as u8) << 1
+            | self.carry as u8
+    }
+
+    /// Sets the register from a byte.
+    pub fn set(&mut self, value: u8) {
+        self.negative = value & 0x80 != 0;
+        self.overflow = value & 0x40 != 0;
+        self.other = value & 0x30;
+        self.decimal = value & 0x08 != 0;
+        self.interrupt_disable = value & 0x04 != 0;
+        self.zero = value & 0x02 != 0;
+        self.carry = value & 0x01 != 0;
+    }
+
+    /// Sets the given bits in the register, leaving the others untouched.
+    pub fn insert(&mut self, value: u8) {
+        self.assign(value, true);
+    }
+
+    /// Clears the given bits in the register, leaving the others untouched.
+    pub fn remove(&mut self, value: u8) {
+        self.assign(value, false);
+    }
+
+    /// Returns whether any of the given bits are set.
+    pub fn contains(&self, value: u8) -> bool {
+        (value & 0x80 != 0 && self.negative)
+            || (value & 0x40 != 0 && self.overflow)
+            || value & self.other != 0
+            || (value & 0x08 != 0 && self.decimal)
+            || (value & 0x04 != 0 && self.interrupt_disable)
+            || (value & 0x02 != 0 && self.zero)
+            || (value & 0x01 != 0 && self.carry)
+    }
+
+    /// Sets the zero and negative flags from a result, as most instructions do.
+    pub fn set_zn(&mut self, value: u8) {
+        self.zero = value == 0;
+        self.negative = value & 0x80 != 0;
+    }
+
+    /// Sets or clears the given bits, leaving the others untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The bits to change.
+    /// * `value` - Whether to set them.
+    #[inline]
+    fn assign(&mut self, bits: u8, value: bool) {
+        if bits & 0x80 != 0 {
+            self.negative = value;
+        }
+        if bits & 0x40 != 0 {
+            self.overflow = value;
+        }
+        if bits & 0x30 != 0 {
+            self.other = match value {
+                true => self.other | (bits & 0x30),
+                false => self.other & !bits,
+            };
+        }
+        if bits & 0x08 != 0 {
+            self.decimal = value;
+        }
+        if bits & 0x04 != 0 {
+            self.interrupt_disable = value;
+        }
+        if bits & 0x02 != 0 {
+            self.zero = value;
+        }
+        if bits & 0x01 != 0 {
+            self.carry = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::register::{Register8, Register16, StatusRegister};
+
+    #[test]
+    fn register8_wraps_in_both_directions() {
+        let mut register = Register8::new();
+
+        register -= 1;
+        assert_eq!(register.get(), 0xFF);
+        register += 2;
+        assert_eq!(register.get(), 0x01);
+
+        assert_eq!((register + 0xFF).get(), 0x00);
+        assert_eq!((register - 2).get(), 0xFF);
+        assert_eq!(register.wrapping_add_signed(-2), 0xFF);
+    }
+
+    #[test]
+    fn program_counter_rolls_over() {
+        let mut pc = Register16::new();
+        pc.set(0xFFFF);
+
+        pc += 1;
+        assert_eq!(pc.get(), 0x0000);
+        pc -= 1;
+        assert_eq!(pc.get(), 0xFFFF);
+
+        assert_eq!((pc + 3).get(), 0x0002);
+        assert_eq!((pc - 0xFFFF).get(), 0x0000);
+    }
+
+    #[test]
+    fn branch_offsets_wrap_around_memory() {
+        let mut pc = Register16::new();
+        pc.set(0x0002);
+        assert_eq!(pc.wrapping_add_signed(-4), 0xFFFE);
+
+        pc.set(0xFFF0);
+        assert_eq!(pc.wrapping_add_signed(0x7F), 0x006F);
+    }
+
+    #[test]
+    fn status_register_splits_and_rebuilds_the_byte() {
+        let mut p = StatusRegister::new();
+        for value in 0..=255u8 {
+            p.set(value);
+            assert_eq!(p.get(), value);
+        }
+
+        p.set(0x24);
+        p.insert(0x81);
+        p.remove(0x04);
+        assert_eq!(p.get(), 0xA1);
+        assert!(p.contains(0x01));
+        assert!(p.contains(0x20));
+        assert!(!p.contains(0x42));
+
+        p.set_zn(0x00);
+        assert_eq!(p.get(), 0x23);
+        p.set_zn(0x80);
+        assert_eq!(p.get(), 0xA1);
+    }
+}
@@ -0,0 +1,152 @@
+//! WebAssembly bindings for running the emulator in a browser.
+//!
+//! The machine matches the demo binary: 32K of RAM at $0000, a Blink8 device
+//! at $8000 and a 16K ROM at $C000. Build it with
+//! `wasm-pack build --target web -- --features wasm`.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::bus::blink8::Blink8;
+use crate::bus::ram::Ram;
+use crate::bus::rom::Rom;
+use crate::emulator::Emulator;
+
+/// The size of the ROM image, which covers $C000-$FFFF.
+const ROM_SIZE: usize = 0x4000;
+
+/// A JavaScript callback that can be handed to a device.
+///
+/// Devices have to be `Send`, which a JavaScript function isn't. Browsers run
+/// the module on a single thread, so the callback never actually leaves it.
+struct JsCallback(Function);
+
+unsafe impl Send for JsCallback {}
+
+impl JsCallback {
+    /// Calls the function with one argument, ignoring anything it throws.
+    fn call(&self, value: u8) {
+        let _ = self.0.call1(&JsValue::NULL, &JsValue::from(value));
+    }
+}
+
+/// The emulator, as seen from JavaScript.
+#[wasm_bindgen(js_name = Emulator)]
+pub struct WasmEmulator {
+    emulator: Emulator,
+}
+
+#[wasm_bindgen(js_class = Emulator)]
+impl WasmEmulator {
+    /// Creates a machine running the given ROM image.
+    ///
+    /// The CPU isn't reset; call `reset()` to start it at the ROM's reset vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The 16K ROM image for $C000-$FFFF.
+    /// * `on_blink8` - Called with every value the Blink8 device displays.
+    ///
+    /// # Returns
+    ///
+    /// The machine, or an error if the ROM image isn't 16K.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: &[u8], on_blink8: Option<Function>) -> Result<WasmEmulator, JsError> {
+        if rom.len() != ROM_SIZE {
+            return Err(JsError::new("The ROM image must be exactly 16K"));
+        }
+
+        let mut blink8 = Blink8::new();
+        if let Some(callback) = on_blink8 {
+            let callback = JsCallback(callback);
+            blink8.set_output(Box::new(move |value| callback.call(value)));
+        }
+
+        let mut rom_device = Rom::new(0xC000, 0xFFFF);
+        rom_device.data = rom.to_vec();
+
+        let mut emulator = Emulator::new();
+        emulator.add_device(Box::new(Ram::new(0x0000, 0x7FFF)));
+        emulator.add_device(Box::new(blink8));
+        emulator.add_device(Box::new(rom_device));
+
+        Ok(WasmEmulator { emulator })
+    }
+
+    /// Resets the CPU, loading the program counter from the reset vector.
+    pub fn reset(&mut self) {
+        self.emulator.reset();
+    }
+
+    /// Executes one instruction.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles taken, which is 0 if the CPU is waiting, stopped or jammed.
+    pub fn step(&mut self) -> u32 {
+        self.emulator.step().cycles
+    }
+
+    /// Runs whole instructions for at least the given number of cycles.
+    ///
+    /// # Returns
+    ///
+    /// The number of cycles that actually elapsed, which is less than asked
+    /// for if the CPU jams or stops.
+    pub fn run(&mut self, cycles: u32) -> u32 {
+        self.emulator.run_for_cycles(cycles as u64) as u32
+    }
+
+    /// Reads a byte from the bus, or 0 if nothing is mapped there.
+    pub fn read(&self, address: u16) -> u8 {
+        self.emulator.read(address)
+    }
+
+    /// Writes a byte to the bus. Writes to the ROM or unmapped addresses are dropped.
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.emulator.write(address, value);
+    }
+
+    /// Reads a block of memory, such as a page for a memory viewer.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the first byte.
+    /// * `length` - The number of bytes to read, wrapping around the top of memory.
+    pub fn read_block(&self, address: u16, length: u16) -> Vec<u8> {
+        (0..length).map(|offset| self.emulator.read(address.wrapping_add(offset))).collect()
+    }
+
+    /// Returns the program counter.
+    pub fn pc(&self) -> u16 {
+        self.emulator.cpu.pc.get()
+    }
+
+    /// Returns the accumulator.
+    pub fn a(&self) -> u8 {
+        self.emulator.cpu.a.get()
+    }
+
+    /// Returns the X register.
+    pub fn x(&self) -> u8 {
+        self.emulator.cpu.x.get()
+    }
+
+    /// Returns the Y register.
+    pub fn y(&self) -> u8 {
+        self.emulator.cpu.y.get()
+    }
+
+    /// Returns the stack pointer.
+    pub fn sp(&self) -> u8 {
+        self.emulator.cpu.sp.get()
+    }
+
+    /// Returns the processor status register.
+    pub fn status(&self) -> u8 {
+        self.emulator.cpu.p.get()
+    }
+}